@@ -42,7 +42,10 @@ const config = {
   }
 } as const
 
-const client = MCPClient.fromDict(config as any)
+// The app's "mcp" feature flag; turned off, the agent only gets its local tools
+const mcpEnabled = process.env.AGI_FEATURE_MCP !== '0'
+const mcpServers = mcpEnabled ? config.mcpServers : {}
+const client = MCPClient.fromDict({ mcpServers } as any)
 
 function stringifyPreview(value: unknown, maxLen: number = 300): string {
   try {
@@ -551,7 +554,7 @@ app.post('/api/chat/stream', async (req, res) => {
     res.write(`data: ${JSON.stringify({ type: 'start' })}\n\n`)
 
     try {
-      console.log('[sidecar] MCP always enabled for all requests')
+      console.log(`[sidecar] MCP ${mcpEnabled ? 'enabled' : 'disabled by the mcp feature flag'}`)
       
       const { agent, systemPrompt: derivedSystemPrompt } = createAgent({ 
         apiKey: effectiveKey, 
@@ -705,7 +708,7 @@ app.listen(port, () => {
   console.log(`   GET  /api/health - Health check`) 
   console.log(`   GET  /api/tools - List available MCP tools`)
   try {
-    const servers = Object.keys((config as any).mcpServers || {})
+    const servers = Object.keys(mcpServers)
     console.log(`🧩 MCP servers configured: ${servers.join(', ') || '(none)'}`)
   } catch {}
 }) 
\ No newline at end of file
@@ -0,0 +1,3 @@
+def greet(name):
+    """AGI self-test code sample."""
+    return f"Hello, {name}!"
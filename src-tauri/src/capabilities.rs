@@ -0,0 +1,70 @@
+use serde::Serialize;
+use std::fs;
+use std::process::{Command, Stdio};
+
+use crate::embeddings;
+use crate::extractors::{self, ExtractorInfo};
+use crate::file_storage;
+use crate::transcription;
+
+/// Optional features available in this build and on this machine.
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    pub os: String,                    // "windows", "macos", "linux"
+    pub app_version: String,
+    pub ocr_engine: bool,              // tesseract found on PATH
+    pub media_transcription: bool,     // ffmpeg found on PATH to decode audio tracks
+    pub local_llm_model: bool,         // a GGUF model present in ./models
+    pub local_embeddings: bool,        // the configured embedding model present in ./models
+    pub loopback_capture: bool,        // system audio capture supported by the OS
+    pub extractors: Vec<ExtractorInfo>,
+}
+
+/// Probe the runtime environment. Never fails: missing pieces are reported as `false`.
+pub fn detect() -> Capabilities {
+    Capabilities {
+        os: std::env::consts::OS.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        ocr_engine: extractors::ocr::is_available(),
+        media_transcription: transcription::is_available(),
+        local_llm_model: local_model_present(),
+        local_embeddings: embeddings::local_model_available(),
+        loopback_capture: loopback_supported(),
+        extractors: extractors::registered(),
+    }
+}
+
+/// Returns true if `<bin> --version` runs successfully.
+pub fn binary_available(bin: &str) -> bool {
+    Command::new(bin)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+fn local_model_present() -> bool {
+    let models_dir = file_storage::project_root().join("models");
+    match fs::read_dir(&models_dir) {
+        Ok(entries) => entries.flatten().any(|e| {
+            e.path().extension().and_then(|ext| ext.to_str()) == Some("gguf")
+        }),
+        Err(_) => false,
+    }
+}
+
+fn loopback_supported() -> bool {
+    // WASAPI loopback on Windows and ScreenCaptureKit on macOS; Linux needs a PulseAudio/PipeWire monitor source
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        true
+    } else {
+        binary_available("pactl")
+    }
+}
+
+#[tauri::command]
+pub fn get_capabilities() -> Capabilities {
+    detect()
+}
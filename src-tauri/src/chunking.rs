@@ -0,0 +1,807 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use tiktoken_rs::CoreBPE;
+
+use crate::extractors;
+use crate::settings::{ChunkingSettings, ChunkingStrategy};
+
+/// Where a chunk came from in its document, for citations
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    pub pages: Option<(u32, u32)>,     // First and last PDF page the chunk covers
+    pub lines: (usize, usize),         // 1-based, inclusive line range in the extracted text
+    pub heading_path: Vec<String>,     // Enclosing Markdown headings, outermost first
+}
+
+impl Provenance {
+    /// Human-readable citation: "report.pdf, p. 12", "guide.md > Setup > Install"
+    /// or "main.rs, lines 10-42"
+    pub fn cite(&self, name: &str) -> String {
+        match self.pages {
+            Some((first, last)) if first == last => format!("{}, p. {}", name, first),
+            Some((first, last)) => format!("{}, pp. {}-{}", name, first, last),
+            None if !self.heading_path.is_empty() => {
+                format!("{} > {}", name, self.heading_path.join(" > "))
+            }
+            None if self.lines.0 == self.lines.1 => format!("{}, line {}", name, self.lines.0),
+            None => format!("{}, lines {}-{}", name, self.lines.0, self.lines.1),
+        }
+    }
+}
+
+/// A context chunk and the heading it falls under
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub text: String,
+    pub heading: Option<String>,       // Nearest preceding heading, e.g. "## Setup" or "[Page 3]"
+    pub tokens: usize,                 // Size of `text` in the measure it was chunked with
+    pub source: Provenance,
+}
+
+/// BPE tokenizer for a model name (e.g. "gpt-4o"); models tiktoken doesn't know,
+/// such as Claude, are approximated with cl100k_base
+pub fn tokenizer_for(model: Option<&str>) -> &'static CoreBPE {
+    model
+        .and_then(|m| tiktoken_rs::bpe_for_model(m).ok())
+        .unwrap_or_else(tiktoken_rs::cl100k_base_singleton)
+}
+
+/// Token count of `text` for a model
+pub fn count_tokens(model: Option<&str>, text: &str) -> usize {
+    tokenizer_for(model).encode_with_special_tokens(text).len()
+}
+
+/// A structural unit of a document
+#[derive(Debug, PartialEq)]
+enum Block<'a> {
+    Heading(&'a str),
+    Code(String),                      // Fenced block including its fences
+    Paragraph(String),
+}
+
+// "[Page 3]", or "[Page 3 of 10]"
+fn page_number(heading: &str) -> Option<u32> {
+    heading.strip_prefix("[Page ")?.strip_suffix(']')?.split_whitespace().next()?.parse().ok()
+}
+
+fn is_heading(line: &str) -> bool {
+    let t = line.trim();
+    let hashes = t.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&hashes) && t[hashes..].starts_with(' ')
+        || (t.starts_with("[Page ") && t.ends_with(']'))
+}
+
+fn fence(line: &str) -> Option<&'static str> {
+    let t = line.trim_start();
+    if t.starts_with("```") {
+        Some("```")
+    } else if t.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// Split text into headings, fenced code blocks and blank-line separated paragraphs,
+/// each with the 1-based line it starts on
+fn blocks(content: &str) -> Vec<(Block<'_>, usize)> {
+    let mut out = Vec::new();
+    let mut para: Vec<&str> = Vec::new();
+    let mut para_start = 0;
+    let mut lines = content.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+    fn flush<'a>(para: &mut Vec<&'a str>, start: usize, out: &mut Vec<(Block<'a>, usize)>) {
+        if !para.is_empty() {
+            out.push((Block::Paragraph(para.join("\n")), start));
+            para.clear();
+        }
+    }
+
+    while let Some((n, line)) = lines.next() {
+        if let Some(marker) = fence(line) {
+            flush(&mut para, para_start, &mut out);
+            let mut code = vec![line];
+            for (_, inner) in lines.by_ref() {
+                code.push(inner);
+                if inner.trim_start().starts_with(marker) {
+                    break;
+                }
+            }
+            out.push((Block::Code(code.join("\n")), n));
+        } else if is_heading(line) {
+            flush(&mut para, para_start, &mut out);
+            out.push((Block::Heading(line.trim()), n));
+        } else if line.trim().is_empty() {
+            flush(&mut para, para_start, &mut out);
+        } else {
+            if para.is_empty() {
+                para_start = n;
+            }
+            para.push(line);
+        }
+    }
+    flush(&mut para, para_start, &mut out);
+    out
+}
+
+/// Line range of each piece `split_block` cut from `text`, which starts on `first_line`.
+/// Pieces are located by their first word, searching forward from the previous piece.
+fn piece_lines(text: &str, pieces: &[(String, usize)], first_line: usize) -> Vec<(usize, usize)> {
+    let mut cursor = 0;
+    let starts: Vec<(usize, bool)> = pieces
+        .iter()
+        .map(|(piece, _)| {
+            let word = piece.split_whitespace().next().unwrap_or("");
+            let pos = text[cursor..].find(word).map_or(cursor, |p| cursor + p);
+            cursor = pos + word.len();
+            let before = &text[..pos];
+            let line_start = before.trim_end_matches([' ', '\t']).ends_with('\n');
+            (first_line + before.matches('\n').count(), line_start)
+        })
+        .collect();
+    let last_line = first_line + text.matches('\n').count();
+    (0..starts.len())
+        .map(|i| {
+            let end = match starts.get(i + 1) {
+                Some(&(next, true)) => next.saturating_sub(1),
+                Some(&(next, false)) => next,
+                None => last_line,
+            };
+            (starts[i].0, end.max(starts[i].0))
+        })
+        .collect()
+}
+
+
+/// Split prose after sentence-ending punctuation followed by a capital, digit or quote
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    for w in chars.windows(3) {
+        let [(_, end), (_, gap), (next_at, next)] = [w[0], w[1], w[2]];
+        if matches!(end, '.' | '!' | '?')
+            && gap.is_whitespace()
+            && (next.is_uppercase() || next.is_ascii_digit() || matches!(next, '"' | '(' | '['))
+        {
+            out.push(text[start..next_at].trim());
+            start = next_at;
+        }
+    }
+    out.push(text[start..].trim());
+    out.retain(|s| !s.is_empty());
+    out
+}
+
+/// Break an oversized block into pieces of at most `max` (as measured by `size`): code on
+/// lines, prose on sentences, and a single overlong sentence or line on words. Each unit is
+/// measured once; a piece's size is the sum of its units'.
+fn split_block(text: &str, is_code: bool, max: usize, size: &impl Fn(&str) -> usize) -> Vec<(String, usize)> {
+    let units: Vec<&str> = if is_code { text.lines().collect() } else { sentences(text) };
+    let joiner = if is_code { "\n" } else { " " };
+
+    let mut pieces = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for unit in units {
+        let n = size(unit);
+        if n > max {
+            if !current.is_empty() {
+                pieces.push((current.join(joiner), used));
+                current.clear();
+                used = 0;
+            }
+            pieces.extend(split_words(unit, max, size));
+            continue;
+        }
+        if used + n > max && !current.is_empty() {
+            pieces.push((current.join(joiner), used));
+            current.clear();
+            used = 0;
+        }
+        current.push(unit);
+        used += n;
+    }
+    if !current.is_empty() {
+        pieces.push((current.join(joiner), used));
+    }
+    pieces
+}
+
+/// Last resort for a single sentence or line larger than `max`
+fn split_words(unit: &str, max: usize, size: &impl Fn(&str) -> usize) -> Vec<(String, usize)> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut used = 0;
+    for word in unit.split_whitespace() {
+        let n = size(word).max(1);
+        if used + n > max && !current.is_empty() {
+            pieces.push((std::mem::take(&mut current), used));
+            used = 0;
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+        used += n;
+    }
+    if !current.is_empty() {
+        pieces.push((current, used));
+    }
+    pieces
+}
+
+/// `size`, remembering what it measured: the overlap is cut from sentences already measured
+/// while splitting, and words repeat
+fn memoized(size: impl Fn(&str) -> usize) -> impl Fn(&str) -> usize {
+    let sizes = RefCell::new(HashMap::<String, usize>::new());
+    move |text: &str| {
+        if let Some(&n) = sizes.borrow().get(text) {
+            return n;
+        }
+        let n = size(text);
+        sizes.borrow_mut().insert(text.to_string(), n);
+        n
+    }
+}
+
+/// Where the document is: the heading in effect, the Markdown heading path and the page
+#[derive(Clone, Default)]
+struct Position {
+    heading: Option<String>,
+    path: Vec<String>,
+    page: Option<u32>,
+}
+
+impl Position {
+    fn enter(&mut self, heading: &str) {
+        self.heading = Some(heading.to_string());
+        if let Some(page) = page_number(heading) {
+            self.page = Some(page);
+            return;
+        }
+        let level = heading.chars().take_while(|&c| c == '#').count();
+        // A "[Page ...]" marker without a page number; not part of the heading path
+        if level == 0 {
+            return;
+        }
+        self.path.truncate(level - 1);
+        self.path.push(heading[level..].trim().to_string());
+    }
+}
+
+/// The chunk being filled
+#[derive(Default)]
+struct Open {
+    parts: Vec<String>,
+    used: usize,
+    lines: Option<(usize, usize)>,
+    start: Position,                   // Position where the chunk began
+}
+
+impl Open {
+    /// Trailing sentences of the last part that fit in `overlap`, with their size and the line
+    /// they end on, to repeat at the start of the next chunk
+    fn tail(&self, overlap: usize, size: &impl Fn(&str) -> usize) -> Option<(String, usize, usize)> {
+        let last = self.parts.last()?;
+        let mut taken = Vec::new();
+        let mut used = 0;
+        for sentence in sentences(last).into_iter().rev() {
+            let n = size(sentence);
+            if used + n > overlap {
+                break;
+            }
+            taken.push(sentence);
+            used += n;
+        }
+        if taken.is_empty() {
+            return None;
+        }
+        taken.reverse();
+        Some((taken.join(" "), used, self.lines?.1))
+    }
+
+    fn push(&mut self, text: String, size: usize, lines: (usize, usize)) {
+        self.parts.push(text);
+        self.used += size;
+        self.lines = Some(match self.lines {
+            Some((first, _)) => (first, lines.1),
+            None => lines,
+        });
+    }
+
+    fn flush(&mut self, at: &Position, chunks: &mut Vec<Chunk>, size: &impl Fn(&str) -> usize) {
+        if self.parts.is_empty() {
+            return;
+        }
+        let text = self.parts.join("\n\n");
+        let first_page = self.start.page.or(at.page);
+        chunks.push(Chunk {
+            tokens: size(&text),
+            text,
+            heading: self.start.heading.clone(),
+            source: Provenance {
+                pages: first_page.map(|p| (p, at.page.unwrap_or(p))),
+                lines: self.lines.unwrap_or_default(),
+                heading_path: self.start.path.clone(),
+            },
+        });
+        self.parts.clear();
+        self.used = 0;
+        self.lines = None;
+    }
+}
+
+/// Pack a document into chunks of at most `max` (tokens, when `size` is a tokenizer count), splitting on headings, paragraphs and
+/// code blocks first and only breaking inside a block (on sentences) when it doesn't fit.
+/// A heading starts a new chunk once the current one is a quarter full, so sections stay whole
+/// without producing a chunk per heading. Each chunk records its pages, lines and heading path.
+/// When a section is cut mid-way, up to `overlap` worth of its last sentences open the next chunk.
+pub fn chunk_document(content: &str, max: usize, overlap: usize, size: impl Fn(&str) -> usize) -> Vec<Chunk> {
+    let max = max.max(1);
+    let min = max / 4;
+    let overlap = overlap.min(max - 1);
+    // Leave room for the carried-over sentences in front of each piece
+    let piece_max = max - overlap;
+    let size = memoized(size);
+
+    let mut chunks = Vec::new();
+    let mut at = Position::default();          // Where the document is
+    let mut open = Open::default();
+    let mut has_body = false;                   // Open chunk holds more than headings
+
+    for (block, line) in blocks(content) {
+        let (text, is_code) = match block {
+            Block::Heading(h) => {
+                if has_body && open.used >= min {
+                    open.flush(&at, &mut chunks, &size);
+                    has_body = false;
+                }
+                at.enter(h);
+                if !has_body {
+                    open.start = at.clone();
+                }
+                open.push(h.to_string(), size(h), (line, line));
+                continue;
+            }
+            Block::Code(text) => (text, true),
+            Block::Paragraph(text) => (text, false),
+        };
+
+        let pieces = split_block(&text, is_code, piece_max, &size);
+        let ranges = piece_lines(&text, &pieces, line);
+        for ((piece, n), lines) in pieces.into_iter().zip(ranges) {
+            // Headings stay with the text that follows them, even if that overflows slightly
+            if has_body && open.used + n > max {
+                let carry = if overlap > 0 { open.tail(overlap, &size) } else { None };
+                open.flush(&at, &mut chunks, &size);
+                open.start = at.clone();
+                if let Some((text, k, line)) = carry {
+                    if k + n <= max {
+                        open.push(text, k, (line, line));
+                    }
+                }
+            }
+            open.push(piece, n, lines);
+            has_body = true;
+        }
+    }
+    open.flush(&at, &mut chunks, &size);
+    chunks
+}
+
+/// Cut text into windows of at most `max`, each starting `overlap` before the previous one
+/// ended. Word sizes are summed, so with a tokenizer `size` a window can run slightly over.
+pub fn sliding_window(content: &str, max: usize, overlap: usize, size: impl Fn(&str) -> usize) -> Vec<Chunk> {
+    let max = max.max(1);
+    let overlap = overlap.min(max - 1);
+
+    // Position in effect on each line, for the citation of a window starting there
+    let mut at = Position::default();
+    let mut positions = Vec::new();
+    let mut line_starts = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if is_heading(line) {
+            at.enter(line.trim());
+        }
+        positions.push(at.clone());
+        line_starts.push(offset);
+        offset += line.len();
+    }
+    let line_of = |pos: usize| line_starts.partition_point(|&start| start <= pos).max(1);
+
+    let words: Vec<(usize, &str)> = content
+        .split_whitespace()
+        .map(|w| (w.as_ptr() as usize - content.as_ptr() as usize, w))
+        .collect();
+    let sizes: Vec<usize> = words.iter().map(|(_, w)| size(w).max(1)).collect();
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let mut end = start;
+        let mut used = 0;
+        while end < words.len() && (end == start || used + sizes[end] <= max) {
+            used += sizes[end];
+            end += 1;
+        }
+        let (first_at, _) = words[start];
+        let (last_at, last) = words[end - 1];
+        let text = content[first_at..last_at + last.len()].to_string();
+        let lines = (line_of(first_at), line_of(last_at));
+        let begin = &positions[lines.0 - 1];
+        chunks.push(Chunk {
+            tokens: size(&text),
+            text,
+            heading: begin.heading.clone(),
+            source: Provenance {
+                pages: begin.page.map(|p| (p, positions[lines.1 - 1].page.unwrap_or(p))),
+                lines,
+                heading_path: begin.path.clone(),
+            },
+        });
+        if end == words.len() {
+            break;
+        }
+        // Step back over up to `overlap` worth of words, always moving forward overall
+        let mut next = end;
+        let mut repeated = 0;
+        while next > start + 1 && repeated + sizes[next - 1] <= overlap {
+            next -= 1;
+            repeated += sizes[next];
+        }
+        start = next;
+    }
+    chunks
+}
+
+/// Comment, attribute and decorator lines directly above a definition belong to it
+fn is_preamble(line: &str) -> bool {
+    let line = line.trim_start();
+    ["//", "/*", "*", "#", "@", "--"].iter().any(|p| line.starts_with(p))
+}
+
+/// Lines `range` of the source as one chunk, without the blank lines around it
+fn code_chunk(source: &[&str], mut range: Range<usize>, heading: Option<String>, size: &impl Fn(&str) -> usize) -> Option<Chunk> {
+    while range.start < range.end && source[range.start].trim().is_empty() {
+        range.start += 1;
+    }
+    while range.end > range.start && source[range.end - 1].trim().is_empty() {
+        range.end -= 1;
+    }
+    if range.is_empty() {
+        return None;
+    }
+    let text = source[range.clone()].join("\n");
+    Some(Chunk {
+        tokens: size(&text),
+        text,
+        heading,
+        source: Provenance { pages: None, lines: (range.start + 1, range.end), heading_path: Vec::new() },
+    })
+}
+
+/// Code: the outline on its own, then the source cut at top-level definitions (together
+/// with the comments and attributes above them) and packed up to `max`, so a function or
+/// class is only split when it alone doesn't fit. Each chunk is headed by its first
+/// definition. None when the language has no grammar or defines nothing.
+fn chunk_code(content: &str, file_type: &str, max: usize, size: &impl Fn(&str) -> usize) -> Option<Vec<Chunk>> {
+    let offset = extractors::source_line_offset(file_type, content);
+    let lines: Vec<&str> = content.lines().collect();
+    let (outline, source) = lines.split_at(offset.min(lines.len()));
+    let definitions = extractors::top_level_definitions(file_type, &source.join("\n"))?;
+
+    // (first line index, definition) of each segment; a segment runs to the next one
+    let mut segments: Vec<(usize, Option<String>)> = Vec::new();
+    for (line, name) in definitions {
+        let floor = segments.last().map_or(0, |(start, _)| start + 1);
+        let mut first = line.saturating_sub(1).min(source.len());
+        if first < floor {
+            continue;
+        }
+        while first > floor && is_preamble(source[first - 1]) {
+            first -= 1;
+        }
+        segments.push((first, Some(name)));
+    }
+    if segments.is_empty() {
+        return None;
+    }
+    // Imports and other preamble ahead of the first definition
+    if segments[0].0 > 0 {
+        segments.insert(0, (0, None));
+    }
+
+    let max = max.max(1);
+    let mut chunks = Vec::new();
+    let outline = outline.join("\n");
+    if !outline.trim().is_empty() {
+        chunks.extend(chunk_document(&outline, max, 0, size).into_iter().map(|mut c| {
+            c.heading = Some("Outline".to_string());
+            c.source = Provenance { lines: (1, source.len().max(1)), ..Default::default() };
+            c
+        }));
+    }
+
+    let ends: Vec<usize> = segments.iter().skip(1).map(|(start, _)| *start).chain([source.len()]).collect();
+    let mut open: Option<(usize, Option<String>)> = None;
+    let mut used = 0;
+    for ((start, name), end) in segments.into_iter().zip(ends) {
+        let text = source[start..end].join("\n");
+        let n = size(&text);
+        if used + n > max {
+            if let Some((first, heading)) = open.take() {
+                chunks.extend(code_chunk(source, first..start, heading, size));
+            }
+            used = 0;
+        }
+        if n > max {
+            // A single definition larger than a chunk is split on lines
+            let pieces = split_block(&text, true, max, size);
+            let ranges = piece_lines(&text, &pieces, start + 1);
+            for ((piece, _), lines) in pieces.into_iter().zip(ranges) {
+                let piece = piece.trim_matches('\n').to_string();
+                if piece.trim().is_empty() {
+                    continue;
+                }
+                chunks.push(Chunk {
+                    tokens: size(&piece),
+                    text: piece,
+                    heading: name.clone(),
+                    source: Provenance { pages: None, lines, heading_path: Vec::new() },
+                });
+            }
+            continue;
+        }
+        let (_, heading) = open.get_or_insert((start, None));
+        if heading.is_none() {
+            *heading = name;
+        }
+        used += n;
+    }
+    if let Some((first, heading)) = open {
+        chunks.extend(code_chunk(source, first..source.len(), heading, size));
+    }
+    Some(chunks)
+}
+
+/// CSV/TSV profile: the column summary chunked like prose, then the sample rows in groups
+/// that fit `max`, each repeating the header row. None when there are no sample rows.
+fn chunk_table(content: &str, max: usize, size: &impl Fn(&str) -> usize) -> Option<Vec<Chunk>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let at = lines.iter().position(|l| l.starts_with("Sample rows ("))?;
+    let header = lines.get(at + 1)?;
+    let rows = &lines[at + 2..];
+    if rows.is_empty() {
+        return None;
+    }
+
+    let mut chunks = chunk_document(&lines[..at].join("\n"), max, 0, size);
+    let head = format!("{}\n{}", lines[at], header);
+    let budget = max.saturating_sub(size(&head)).max(1);
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    for (i, row) in rows.iter().enumerate() {
+        let n = size(row);
+        if i > start && used + n > budget {
+            groups.push(start..i);
+            start = i;
+            used = 0;
+        }
+        used += n;
+    }
+    groups.push(start..rows.len());
+
+    for group in groups {
+        let text = format!("{}\n{}", head, rows[group.clone()].join("\n"));
+        chunks.push(Chunk {
+            tokens: size(&text),
+            text,
+            heading: Some("Sample rows".to_string()),
+            source: Provenance {
+                pages: None,
+                lines: (at + 3 + group.start, at + 2 + group.end),
+                heading_path: vec!["Sample rows".to_string()],
+            },
+        });
+    }
+    Some(chunks)
+}
+
+/// Chunk a file's extracted content with the configured strategy, with line
+/// ranges referring to the file itself rather than to extractor additions such as a code outline.
+/// The structural strategy follows the file type: code is cut on top-level definitions,
+/// CSV profiles on row groups, and everything else on headings and paragraphs.
+pub fn chunk_file(
+    content: &str,
+    file_type: &str,
+    max: usize,
+    overlap: usize,
+    settings: &ChunkingSettings,
+    size: impl Fn(&str) -> usize,
+) -> Vec<Chunk> {
+    if settings.strategy == ChunkingStrategy::Structural {
+        let typed = match extractors::find(file_type).map(|e| e.name) {
+            Some("code") => chunk_code(content, file_type, max, &size),
+            Some("csv") => chunk_table(content, max, &size),
+            _ => None,
+        };
+        if let Some(chunks) = typed {
+            return chunks;
+        }
+    }
+
+    let offset = extractors::source_line_offset(file_type, content);
+    let mut chunks = match settings.strategy {
+        ChunkingStrategy::Structural => chunk_document(content, max, overlap, size),
+        ChunkingStrategy::SlidingWindow => sliding_window(content, max, overlap, size),
+    };
+    if offset > 0 {
+        for chunk in &mut chunks {
+            let (first, last) = chunk.source.lines;
+            chunk.source.lines = (first.saturating_sub(offset).max(1), last.saturating_sub(offset).max(1));
+        }
+    }
+    chunks
+}
+
+/// 64-bit SimHash over lowercase word trigrams. Texts that differ only in a few words
+/// (a signature with another name, a reflowed license header) land a few bits apart.
+pub fn simhash(text: &str) -> u64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let shingles: Vec<&[String]> = if words.len() < 3 { vec![&words[..]] } else { words.windows(3).collect() };
+
+    let mut weights = [0i32; 64];
+    for shingle in shingles {
+        // FNV-1a, stable across runs and builds
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in shingle.join(" ").bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (bit, &w)| if w > 0 { acc | 1 << bit } else { acc })
+}
+
+/// Whether two SimHashes are close enough to treat their texts as the same passage.
+/// Unrelated text differs in ~32 bits; passages filled from one template (two invoices)
+/// stay well above the threshold.
+pub fn near_duplicate(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_follow_headings_and_sentences() {
+        let doc = format!(
+            "# Intro\n{}\n\n## Code\n```\nfn main() {{}}\n```\n\n## Long\n{}",
+            "Short intro sentence here. ".repeat(3),
+            "This sentence has exactly six words. ".repeat(4)
+        );
+        let words = |t: &str| t.split_whitespace().count();
+        let chunks = chunk_document(&doc, 14, 0, words);
+
+        // Every chunk ends on a sentence or block boundary
+        for chunk in &chunks {
+            let last = chunk.text.trim_end();
+            assert!(last.ends_with('.') || last.ends_with("```"), "{:?}", last);
+        }
+        assert_eq!(chunks[0].heading.as_deref(), Some("# Intro"));
+        assert!(chunks.iter().any(|c| c.text.contains("## Code\n\n```\nfn main() {}\n```")));
+        let long: Vec<_> = chunks.iter().filter(|c| c.heading.as_deref() == Some("## Long")).collect();
+        assert_eq!(long.len(), 2);
+
+        let tokens = chunk_document(&doc, 20, 0, |t| count_tokens(Some("gpt-4o"), t));
+        assert!(tokens.iter().all(|c| c.tokens == count_tokens(Some("gpt-4o"), &c.text)));
+    }
+
+    #[test]
+    fn test_provenance_pages_lines_and_heading_path() {
+        let words = |t: &str| t.split_whitespace().count();
+
+        let pdf = "[Page 1]\nFirst page text.\n\n[Page 2]\nSecond page text.\n\n[Page 3]\nThird.";
+        let chunks = chunk_document(pdf, 100, 0, words);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].source.pages, Some((1, 3)));
+        assert_eq!(chunks[0].source.cite("report.pdf"), "report.pdf, pp. 1-3");
+        let chunks = chunk_document("[Page 3 of 10]\nText.\n\n[Page ii]\nMore.", 100, 0, words);
+        assert_eq!(chunks[0].source.pages, Some((3, 3)));
+        assert!(chunks[0].source.heading_path.is_empty());
+
+        let md = "# Guide\nIntro.\n\n## Setup\n### Install\nRun the installer now.\n\n## Usage\nUse it.";
+        let chunks = chunk_document(md, 4, 0, words);
+        let install = chunks.iter().find(|c| c.text.contains("installer")).unwrap();
+        assert_eq!(install.source.heading_path, vec!["Guide", "Setup", "Install"]);
+        assert_eq!(install.source.lines, (4, 6));
+        let usage = chunks.iter().find(|c| c.text.contains("Use it")).unwrap();
+        assert_eq!(usage.source.cite("guide.md"), "guide.md > Guide > Usage");
+
+        let code = "fn a() {\n}\n\nfn b() {\n}";
+        let chunks = chunk_document(code, 4, 0, words);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].source.cite("lib.rs"), "lib.rs, lines 4-5");
+    }
+
+    #[test]
+    fn test_overlap_and_sliding_window() {
+        let words = |t: &str| t.split_whitespace().count();
+        let prose = "One two three. Four five six. Seven eight nine. Ten eleven twelve.";
+
+        // A cut section repeats its last sentence in the next chunk
+        let chunks = chunk_document(prose, 6, 3, words);
+        assert_eq!(chunks[0].text, "One two three.\n\nFour five six.");
+        assert_eq!(chunks[1].text, "Four five six.\n\nSeven eight nine.");
+
+        let doc = "[Page 4]\na b c d\ne f g h";
+        let windows = sliding_window(doc, 4, 1, words);
+        let texts: Vec<&str> = windows.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["[Page 4]\na b", "b c d\ne", "e f g h"]);
+        assert_eq!(windows[1].source.lines, (2, 3));
+        assert_eq!(windows[2].source.pages, Some((4, 4)));
+    }
+
+    #[test]
+    fn test_simhash_near_duplicates() {
+        let footer = "This email and any attachments are confidential and intended solely for the \
+                      addressee. If you received it in error please notify the sender and delete it. \
+                      Acme Corp, 1 Main Street, Springfield";
+        let other = footer.replace("Acme Corp", "Acme Corporation");
+        assert!(near_duplicate(simhash(footer), simhash(&other)));
+        assert!(!near_duplicate(simhash(footer), simhash("Quarterly revenue grew 12% on strong cloud demand")));
+
+        let invoice = "Invoice INV-2024-0042 issued to Acme Corp for consulting services in April. \
+                       Amount due: $1,250.00. Payment due by 2024-05-01 via bank transfer to account 12345678.";
+        let next = invoice
+            .replace("0042", "0017")
+            .replace("April", "March")
+            .replace("1,250", "980")
+            .replace("05-01", "04-01");
+        assert!(!near_duplicate(simhash(invoice), simhash(&next)));
+    }
+
+    #[test]
+    fn test_code_and_table_strategies() {
+        let words = |s: &str| s.split_whitespace().count();
+        let settings = ChunkingSettings::default();
+
+        let source = "use std::fs;\n\n/// Reads a file\nfn a() {\n    x();\n}\n\nfn b() {}";
+        let content = format!("Outline:\nfn a (line 4)\nfn b (line 8)\n\n{}", source);
+        let chunks = chunk_file(&content, "rs", 12, 0, &settings, words);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].heading.as_deref(), Some("Outline"));
+        assert_eq!(chunks[1].text, "use std::fs;\n\n/// Reads a file\nfn a() {\n    x();\n}");
+        assert_eq!(chunks[1].heading.as_deref(), Some("fn a"));
+        assert_eq!(chunks[1].source.cite("lib.rs"), "lib.rs, lines 1-6");
+        assert_eq!(chunks[2].text, "fn b() {}");
+        assert_eq!(chunks[2].source.cite("lib.rs"), "lib.rs, line 8");
+
+        let rows: Vec<String> = (1..=6).map(|i| format!("{},item{}", i, i)).collect();
+        let content = format!(
+            "CSV: 6 rows x 2 columns\nColumns:\n- id (integer): 1 to 6\nSample rows (6 of 6):\nid,name\n{}",
+            rows.join("\n")
+        );
+        let chunks = chunk_file(&content, "csv", 9, 0, &settings, words);
+        let groups: Vec<&Chunk> = chunks.iter().filter(|c| c.heading.as_deref() == Some("Sample rows")).collect();
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|c| c.text.starts_with("Sample rows (6 of 6):\nid,name\n")));
+        assert!(groups[0].text.ends_with("3,item3") && groups[1].text.ends_with("6,item6"));
+        assert!(chunks[0].text.contains("Columns:"));
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// A clipboard tool: program and arguments to read, then to write (text on stdin)
+type Tool = (&'static str, &'static [&'static str], &'static str, &'static [&'static str]);
+
+/// Tools to try, in order, on this platform
+fn tools() -> Vec<Tool> {
+    if cfg!(target_os = "macos") {
+        vec![("pbpaste", &[], "pbcopy", &[])]
+    } else if cfg!(target_os = "windows") {
+        vec![(
+            "powershell",
+            &["-NoProfile", "-Command", "[Console]::OutputEncoding = [Text.Encoding]::UTF8; Get-Clipboard -Raw"],
+            "powershell",
+            &[
+                "-NoProfile",
+                "-Command",
+                "[Console]::InputEncoding = [Text.Encoding]::UTF8; Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+            ],
+        )]
+    } else {
+        // Wayland first when running under it; X11 sessions have xclip or xsel
+        let mut tools: Vec<Tool> = vec![
+            ("xclip", &["-selection", "clipboard", "-o"], "xclip", &["-selection", "clipboard", "-i"]),
+            ("xsel", &["--clipboard", "--output"], "xsel", &["--clipboard", "--input"]),
+        ];
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.insert(0, ("wl-paste", &["--no-newline"], "wl-copy", &[]));
+        }
+        tools
+    }
+}
+
+/// Text currently on the system clipboard
+pub fn read_text() -> Result<String> {
+    let mut last_error = anyhow!("No clipboard tool for this platform");
+    for (program, args, _, _) in tools() {
+        match Command::new(program).args(args).stderr(Stdio::null()).output() {
+            Ok(output) if output.status.success() => {
+                return String::from_utf8(output.stdout).context("Clipboard content isn't text");
+            }
+            Ok(output) => last_error = anyhow!("{} exited with {}", program, output.status),
+            Err(e) => last_error = anyhow!("{} not available: {}", program, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// Replace the clipboard content with `text`
+pub fn write_text(text: &str) -> Result<()> {
+    let mut last_error = anyhow!("No clipboard tool for this platform");
+    for (_, _, program, args) in tools() {
+        // xclip and xsel keep serving the selection in the background, so their output
+        // must not be captured or this would wait on them
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                last_error = anyhow!("{} not available: {}", program, e);
+                continue;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).with_context(|| format!("writing to {}", program))?;
+        }
+        let status = child.wait()?;
+        if status.success() {
+            return Ok(());
+        }
+        last_error = anyhow!("{} exited with {}", program, status);
+    }
+    Err(last_error)
+}
@@ -0,0 +1,388 @@
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use chrono::Utc;
+use reqwest::blocking::{Client, Response};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::{fs, path::Path, time::Duration};
+
+use super::{
+    compression, hmac_sha256, load_state, object_key, part_ranges, retry, save_state, send_parts, state_path, throttle,
+    uri_encode, xml_value, xml_values, StorageProvider, Transfer, UploadConfig,
+};
+
+const API_VERSION: &str = "2021-08-06";
+
+// How requests are authorized
+enum Auth {
+    Sas(String),                 // SAS token query string, without the leading '?'
+    SharedKey(Vec<u8>),          // decoded account key
+}
+
+// Progress of a block upload, saved next to the file as `<name>.upload`. Azure keeps
+// uncommitted blocks for a week, so the blob's name and which blocks went up are enough.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct BlockState {
+    name: String,                // blob name
+    size: u64,                   // of the scrubbed bytes being sent
+    sha256: String,              // of the same; a changed file can't reuse staged blocks
+    block_size: u64,
+    done: Vec<bool>,             // per block, set once it's staged
+}
+
+impl BlockState {
+    fn matches(&self, fresh: &BlockState) -> bool {
+        self.size == fresh.size
+            && self.sha256 == fresh.sha256
+            && self.block_size == fresh.block_size
+            && self.done.len() == fresh.done.len()
+    }
+}
+
+// Azure Blob Storage: small files as a single Put Blob, large ones as blocks staged in
+// parallel and committed with a block list
+pub(super) struct AzureProvider {
+    account: String,
+    container: String,
+    base: String,                // Blob service URL, without a trailing '/'
+    auth: Auth,
+}
+
+impl AzureProvider {
+    pub(super) fn new(cfg: &UploadConfig) -> Result<Self> {
+        let account = cfg.account.clone().ok_or_else(|| anyhow!("account is required for Azure"))?;
+        let container = cfg.bucket.clone().ok_or_else(|| anyhow!("bucket (the container) is required for Azure"))?;
+        // A SAS token is scoped and revocable, so it wins when both are set
+        let auth = match (&cfg.sas_token, &cfg.account_key) {
+            (Some(sas), _) => Auth::Sas(sas.trim_start_matches('?').to_string()),
+            (None, Some(key)) => Auth::SharedKey(
+                base64::engine::general_purpose::STANDARD
+                    .decode(key.trim())
+                    .map_err(|e| anyhow!("decoding account_key: {}", e))?,
+            ),
+            (None, None) => return Err(anyhow!("sas_token or account_key is required for Azure")),
+        };
+        let base = match &cfg.endpoint {
+            Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+            None => format!("https://{}.blob.core.windows.net", account),
+        };
+        Ok(Self { account, container, base, auth })
+    }
+
+    // Make a request on a blob; `query` holds the operation's own parameters (comp, blockid).
+    // The status is left for the caller to check.
+    #[allow(clippy::too_many_arguments)]
+    fn request(
+        &self,
+        client: &Client,
+        method: Method,
+        name: &str,
+        query: &[(&str, String)],
+        headers: &[(&str, String)],
+        body: Vec<u8>,
+        transfer: Option<&Transfer>,
+    ) -> Result<Response> {
+        // An empty name addresses the container itself
+        let path = if name.is_empty() {
+            format!("/{}", uri_encode(&self.container, true))
+        } else {
+            format!("/{}/{}", uri_encode(&self.container, true), uri_encode(name, false))
+        };
+        let mut params: Vec<String> = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, uri_encode(v, true)))
+            .collect();
+        if let Auth::Sas(sas) = &self.auth {
+            params.push(sas.clone());
+        }
+        let mut url = format!("{}{}", self.base, path);
+        if !params.is_empty() {
+            url = format!("{}?{}", url, params.join("&"));
+        }
+
+        let mut ms_headers = vec![
+            ("x-ms-date".to_string(), Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+            ("x-ms-version".to_string(), API_VERSION.to_string()),
+        ];
+        let mut content_type = String::new();
+        let mut builder = client.request(method.clone(), &url);
+        for (name, value) in headers {
+            if name.starts_with("x-ms-") {
+                ms_headers.push((name.to_string(), value.clone()));
+            } else if *name == "content-type" {
+                content_type = value.clone();
+            }
+            builder = builder.header(*name, value);
+        }
+        for (name, value) in &ms_headers {
+            builder = builder.header(name, value);
+        }
+        if let Auth::SharedKey(key) = &self.auth {
+            // The canonical resource names the path as the URL has it, after the account
+            let url_path = reqwest::Url::parse(&url)?.path().to_string();
+            let resource = format!("/{}{}", self.account, url_path);
+            let to_sign = string_to_sign(method.as_str(), body.len(), &content_type, &ms_headers, &resource, query);
+            let signature = base64::engine::general_purpose::STANDARD.encode(hmac_sha256(key, to_sign.as_bytes()));
+            builder = builder.header("authorization", format!("SharedKey {}:{}", self.account, signature));
+        }
+
+        // Blocks are far bigger than the JSON files
+        let r = throttle::body(builder, body, Duration::from_secs(300), transfer)
+            .send()
+            .context("calling Azure Blob Storage")?;
+        Ok(r)
+    }
+
+    fn put_blob(&self, client: &Client, name: &str, bytes: &[u8], transfer: Option<&Transfer>) -> Result<()> {
+        let mut headers = vec![
+            ("x-ms-blob-type", "BlockBlob".to_string()),
+            ("content-type", "application/json".to_string()),
+        ];
+        if let Some(encoding) = compression::content_encoding(name) {
+            headers.push(("x-ms-blob-content-encoding", encoding.to_string()));
+        }
+        self.request(client, Method::PUT, name, &[], &headers, bytes.to_vec(), transfer).and_then(check)?;
+        Ok(())
+    }
+
+    fn put_block(&self, client: &Client, name: &str, index: usize, bytes: &[u8], transfer: &Transfer) -> Result<()> {
+        let query = [("comp", "block".to_string()), ("blockid", block_id(index))];
+        self.request(client, Method::PUT, name, &query, &[], bytes.to_vec(), Some(transfer)).and_then(check)?;
+        Ok(())
+    }
+
+    // Commit the staged blocks, in order, as the blob's content
+    fn put_block_list(&self, client: &Client, name: &str, blocks: usize) -> Result<()> {
+        let latest: String = (0..blocks).map(|i| format!("<Latest>{}</Latest>", block_id(i))).collect();
+        let body = format!("<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>{}</BlockList>", latest);
+        let query = [("comp", "blocklist".to_string())];
+        let mut headers = vec![
+            ("x-ms-blob-content-type", "application/json".to_string()),
+            ("content-type", "application/xml".to_string()),
+        ];
+        if let Some(encoding) = compression::content_encoding(name) {
+            headers.push(("x-ms-blob-content-encoding", encoding.to_string()));
+        }
+        self.request(client, Method::PUT, name, &query, &headers, body.into_bytes(), None).and_then(check)?;
+        Ok(())
+    }
+
+    // Stage a large file's blocks several at a time, resuming a recorded upload when there is
+    // one, then commit them
+    fn upload_blocks(
+        &self,
+        client: &Client,
+        cfg: &UploadConfig,
+        path: &Path,
+        filename: &str,
+        bytes: &[u8],
+        transfer: &Transfer,
+    ) -> Result<String> {
+        let size = bytes.len() as u64;
+        let block_size = cfg.part_size(size);
+        let ranges = part_ranges(size, block_size);
+        let fresh = BlockState {
+            name: String::new(),
+            size,
+            sha256: format!("{:x}", Sha256::digest(bytes)),
+            block_size,
+            done: vec![false; ranges.len()],
+        };
+
+        let state_file = state_path(path);
+        let recorded = load_state::<BlockState>(&state_file).filter(|s| s.matches(&fresh));
+        let resumable = recorded.is_some();
+        let state = match recorded {
+            Some(state) => {
+                println!(
+                    "⬆️  resuming block upload of {}: {} of {} blocks already staged",
+                    filename,
+                    state.done.iter().filter(|d| **d).count(),
+                    state.done.len()
+                );
+                state
+            }
+            None => {
+                let state = BlockState { name: object_key(&cfg.device_prefix(), filename), ..fresh };
+                save_state(&state_file, &state)?;
+                println!("⬆️  block upload of {}: {} blocks", filename, ranges.len());
+                state
+            }
+        };
+
+        let pending: Vec<usize> = (0..ranges.len()).filter(|&i| !state.done[i]).collect();
+        transfer.resumed(size - pending.iter().map(|&i| ranges[i].len() as u64).sum::<u64>());
+        let name = state.name.clone();
+        let state = Mutex::new(state);
+        let staged_now = send_parts(
+            &pending,
+            cfg.part_concurrency.unwrap_or(4),
+            filename,
+            |i| self.put_block(client, &name, i, &bytes[ranges[i].clone()], transfer),
+            |i, ()| {
+                let mut state = state.lock().unwrap();
+                state.done[i] = true;
+                if let Err(e) = save_state(&state_file, &*state) {
+                    eprintln!("⚠️  failed to save upload progress of {}: {e:?}", filename);
+                }
+            },
+        );
+
+        let state = state.into_inner().unwrap();
+        if state.done.contains(&false) {
+            // Staged blocks are dropped after a week; a recorded upload that takes no block
+            // at all is forgotten so the next attempt starts a new one
+            if resumable && staged_now == 0 {
+                let _ = fs::remove_file(&state_file);
+            }
+            return Err(anyhow!("block upload of {} incomplete; will resume on the next attempt", filename));
+        }
+        if let Err(e) = retry(|| self.put_block_list(client, &state.name, state.done.len()), 3, 700) {
+            // Most likely the staged blocks expired; start over next time
+            let _ = fs::remove_file(&state_file);
+            return Err(e);
+        }
+        let _ = fs::remove_file(&state_file);
+        Ok(state.name)
+    }
+}
+
+impl StorageProvider for AzureProvider {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    fn upload(&self, client: &Client, cfg: &UploadConfig, path: &Path, filename: &str, bytes: &[u8], transfer: &Transfer) -> Result<String> {
+        let name = if bytes.len() as u64 > cfg.multipart_threshold() {
+            self.upload_blocks(client, cfg, path, filename, bytes, transfer)?
+        } else {
+            let name = object_key(&cfg.device_prefix(), filename);
+            retry(
+                || {
+                    transfer.confirmed(0);
+                    self.put_blob(client, &name, bytes, Some(transfer))
+                },
+                5,
+                700,
+            )?;
+            name
+        };
+        Ok(name)
+    }
+
+    fn delete(&self, client: &Client, _cfg: &UploadConfig, key: &str) -> Result<()> {
+        let r = self.request(client, Method::DELETE, key, &[], &[], Vec::new(), None)?;
+        if r.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(r).map(|_| ())
+    }
+
+    fn location(&self, _cfg: &UploadConfig, key: &str) -> String {
+        format!("{}/{}/{}", self.base, self.container, key)
+    }
+
+    fn list(&self, client: &Client, _cfg: &UploadConfig, prefix: &str) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut marker = String::new();
+        loop {
+            let mut query = vec![
+                ("restype", "container".to_string()),
+                ("comp", "list".to_string()),
+                ("prefix", prefix.to_string()),
+            ];
+            if !marker.is_empty() {
+                query.push(("marker", marker.clone()));
+            }
+            let xml = check(self.request(client, Method::GET, "", &query, &[], Vec::new(), None)?)?.text()?;
+            // <Name> appears once per blob; the container's own name is an attribute
+            names.extend(xml_values(&xml, "Name"));
+            marker = xml_value(&xml, "NextMarker").unwrap_or_default();
+            if marker.is_empty() {
+                return Ok(names);
+            }
+        }
+    }
+
+    fn download(&self, client: &Client, _cfg: &UploadConfig, key: &str) -> Result<Vec<u8>> {
+        let r = check(self.request(client, Method::GET, key, &[], &[], Vec::new(), None)?)?;
+        Ok(r.bytes()?.to_vec())
+    }
+
+    fn put(&self, client: &Client, _cfg: &UploadConfig, key: &str, bytes: &[u8]) -> Result<()> {
+        self.put_blob(client, key, bytes, None)
+    }
+}
+
+fn check(r: Response) -> Result<Response> {
+    if !r.status().is_success() {
+        let status = r.status();
+        return Err(anyhow!("Azure returned {}: {}", status, r.text().unwrap_or_default().trim()));
+    }
+    Ok(r)
+}
+
+// Block IDs must all have the same length within a blob
+fn block_id(index: usize) -> String {
+    base64::engine::general_purpose::STANDARD.encode(format!("block-{:06}", index))
+}
+
+// The Shared Key string to sign for a Blob service request
+fn string_to_sign(
+    method: &str,
+    content_length: usize,
+    content_type: &str,
+    ms_headers: &[(String, String)],
+    resource: &str,
+    query: &[(&str, String)],
+) -> String {
+    // Content-Length is left empty when zero
+    let length = if content_length == 0 { String::new() } else { content_length.to_string() };
+    let mut headers: Vec<String> = ms_headers
+        .iter()
+        .map(|(k, v)| format!("{}:{}\n", k.to_lowercase(), v.trim()))
+        .collect();
+    headers.sort();
+    let mut params: Vec<String> = query.iter().map(|(k, v)| format!("\n{}:{}", k.to_lowercase(), v)).collect();
+    params.sort();
+    format!(
+        "{}\n\n\n{}\n\n{}\n\n\n\n\n\n\n{}{}{}",
+        method,
+        length,
+        content_type,
+        headers.concat(),
+        resource,
+        params.concat()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_key_string_and_blocks() {
+        let ms_headers = vec![
+            ("x-ms-version".to_string(), API_VERSION.to_string()),
+            ("x-ms-date".to_string(), "Fri, 26 Jun 2015 23:39:12 GMT".to_string()),
+        ];
+        let query = [("comp", "block".to_string()), ("blockid", block_id(3))];
+        let to_sign = string_to_sign("PUT", 11, "", &ms_headers, "/myaccount/backups/uploads/a.json", &query);
+        let lines: Vec<&str> = to_sign.split('\n').collect();
+        // Verb, eleven standard headers, then the canonicalized x-ms headers and resource
+        assert_eq!(lines[0], "PUT");
+        assert_eq!(lines[3], "11");
+        assert_eq!(lines[12], "x-ms-date:Fri, 26 Jun 2015 23:39:12 GMT");
+        assert_eq!(lines[13], "x-ms-version:2021-08-06");
+        assert_eq!(lines[14], "/myaccount/backups/uploads/a.json");
+        assert_eq!(lines[15], format!("blockid:{}", block_id(3)));
+        assert_eq!(lines[16], "comp:block");
+        assert_eq!(lines.len(), 17);
+        // An empty body leaves Content-Length blank
+        assert_eq!(string_to_sign("PUT", 0, "", &ms_headers, "/a/b", &[]).split('\n').nth(3), Some(""));
+
+        assert_eq!(block_id(0).len(), block_id(999_999).len());
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+
+use super::UploadConfig;
+
+// Start of every zstd frame
+const MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+// Added to the object's file name, so the bucket shows what's compressed
+pub(super) const SUFFIX: &str = ".zst";
+// Good ratios on JSON without slowing down big exports much
+const LEVEL: i32 = 9;
+
+pub(super) fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// The Content-Encoding an object named `filename` is stored with
+pub(super) fn content_encoding(filename: &str) -> Option<&'static str> {
+    filename.ends_with(SUFFIX).then_some("zstd")
+}
+
+/// Compress an upload unless config.toml turns it off or it doesn't shrink by a tenth
+/// (e.g. mostly base64 of already-compressed media). Returns the bytes and the file name to
+/// upload them under.
+pub(super) fn pack(cfg: &UploadConfig, bytes: Vec<u8>, filename: &str) -> (Vec<u8>, String) {
+    if !cfg.compress.unwrap_or(true) {
+        return (bytes, filename.to_string());
+    }
+    match zstd::bulk::compress(&bytes, LEVEL) {
+        Ok(packed) if packed.len() < bytes.len() / 10 * 9 => (packed, format!("{}{}", filename, SUFFIX)),
+        Ok(_) => (bytes, filename.to_string()),
+        Err(e) => {
+            eprintln!("⚠️  not compressing {}: {}", filename, e);
+            (bytes, filename.to_string())
+        }
+    }
+}
+
+/// An object's content as it was before upload
+pub(super) fn unpack(bytes: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if !is_compressed(bytes) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    Ok(Cow::Owned(zstd::stream::decode_all(bytes).context("decompressing object")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let mut cfg: UploadConfig = toml::from_str("device_id = \"dev001\"\nwatch_dir = \"memory\"").unwrap();
+        let json = serde_json::to_vec(&serde_json::json!({
+            "messages": vec![serde_json::json!({ "role": "user", "content": "the same question again" }); 200]
+        }))
+        .unwrap();
+
+        let (packed, name) = pack(&cfg, json.clone(), "conversation_1.json");
+        assert_eq!(name, "conversation_1.json.zst");
+        assert_eq!(content_encoding(&name), Some("zstd"));
+        assert!(is_compressed(&packed) && packed.len() < json.len() / 10);
+        assert_eq!(unpack(&packed).unwrap().as_ref(), json.as_slice());
+        assert_eq!(unpack(&json).unwrap().as_ref(), json.as_slice());
+
+        // Random-looking content isn't worth it
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let (kept, name) = pack(&cfg, noise.clone(), "clip.json");
+        assert_eq!((kept, name.as_str()), (noise, "clip.json"));
+
+        cfg.compress = Some(false);
+        assert_eq!(pack(&cfg, json.clone(), "a.json").1, "a.json");
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+
+use super::progress::{Reporter, SyncStatus};
+use super::CloudUploader;
+
+// Sent to the background scan thread to wake it up
+pub(super) enum Control {
+    Resume,                      // scan right away
+    Stop,                        // end the scan and watcher threads
+}
+
+/// Switches the upload loops check before each file
+#[derive(Default)]
+pub(super) struct Gate {
+    paused: AtomicBool,
+    stopped: AtomicBool,
+}
+
+impl Gate {
+    pub(super) fn is_open(&self) -> bool {
+        !self.paused.load(Ordering::Relaxed) && !self.stopped.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+struct Running {
+    gate: Arc<Gate>,
+    control: Sender<Control>,
+}
+
+/// Managed state behind the cloud sync commands: the background uploader, while it runs
+pub struct CloudSync {
+    reporter: Reporter,
+    running: Mutex<Option<Running>>,
+}
+
+impl CloudSync {
+    /// Start the background uploader; when it can't start (no config.toml, missing
+    /// credentials) the state is still there, stopped, for a later resume
+    pub fn start(reporter: Reporter) -> Self {
+        let running = match launch(&reporter) {
+            Ok(running) => {
+                println!("Cloud background uploader started successfully");
+                Some(running)
+            }
+            Err(e) => {
+                eprintln!("Failed to start cloud uploader: {}", e);
+                reporter.set_state("stopped");
+                None
+            }
+        };
+        Self { reporter, running: Mutex::new(running) }
+    }
+
+    /// Hold uploads; the file being sent finishes first
+    pub fn pause(&self) -> Result<SyncStatus> {
+        let running = self.running.lock().unwrap();
+        let running = running.as_ref().ok_or_else(|| anyhow!("cloud uploader isn't running"))?;
+        running.gate.paused.store(true, Ordering::Relaxed);
+        self.reporter.set_state("paused");
+        Ok(self.reporter.status())
+    }
+
+    /// Lift a pause and scan now; starts the uploader again if it was stopped
+    pub fn resume(&self) -> Result<SyncStatus> {
+        let mut running = self.running.lock().unwrap();
+        if let Some(r) = running.as_ref() {
+            r.gate.paused.store(false, Ordering::Relaxed);
+            self.reporter.set_state("idle");
+            if r.control.send(Control::Resume).is_err() {
+                // The threads ended on their own; start over
+                *running = None;
+            }
+        }
+        if running.is_none() {
+            *running = Some(launch(&self.reporter)?);
+        }
+        Ok(self.reporter.status())
+    }
+
+    /// End the background uploader; the file being sent finishes first
+    pub fn stop(&self) -> Result<SyncStatus> {
+        if let Some(running) = self.running.lock().unwrap().take() {
+            running.gate.stopped.store(true, Ordering::Relaxed);
+            let _ = running.control.send(Control::Stop);
+        }
+        self.reporter.set_state("stopped");
+        Ok(self.reporter.status())
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.reporter.status()
+    }
+
+    /// For one-off scans, so they show up in the same status
+    pub fn reporter(&self) -> Reporter {
+        self.reporter.clone()
+    }
+
+    /// An uploader for a one-off scan that reports to the same status and holds off while
+    /// the background uploader is paused
+    pub fn uploader(&self) -> Result<CloudUploader> {
+        let uploader = CloudUploader::new(self.reporter())?;
+        match self.running.lock().unwrap().as_ref() {
+            Some(running) => Ok(CloudUploader { gate: running.gate.clone(), ..uploader }),
+            None => Ok(uploader),
+        }
+    }
+}
+
+fn launch(reporter: &Reporter) -> Result<Running> {
+    let gate = Arc::new(Gate::default());
+    let (control, rx) = channel();
+    CloudUploader::start_background_uploader(reporter.clone(), gate.clone(), rx)?;
+    reporter.set_state("idle");
+    Ok(Running { gate, control })
+}
+
+#[tauri::command]
+pub fn pause_cloud_sync(sync: tauri::State<'_, CloudSync>) -> Result<SyncStatus, String> {
+    sync.pause().map_err(|e| format!("Failed to pause cloud sync: {}", e))
+}
+
+#[tauri::command]
+pub fn resume_cloud_sync(sync: tauri::State<'_, CloudSync>) -> Result<SyncStatus, String> {
+    sync.resume().map_err(|e| format!("Failed to resume cloud sync: {}", e))
+}
+
+#[tauri::command]
+pub fn stop_cloud_sync(sync: tauri::State<'_, CloudSync>) -> Result<SyncStatus, String> {
+    sync.stop().map_err(|e| format!("Failed to stop cloud sync: {}", e))
+}
+
+#[tauri::command]
+pub fn get_cloud_sync_status(sync: tauri::State<'_, CloudSync>) -> SyncStatus {
+    sync.status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_controls_without_a_running_uploader() {
+        let sync = CloudSync { reporter: Reporter::new(|_| {}), running: Mutex::new(None) };
+        assert!(sync.pause().is_err());
+        assert_eq!(sync.stop().unwrap().state, "stopped");
+
+        // A pause that lands mid-file outlives the file
+        sync.reporter.queued(1);
+        let _transfer = sync.reporter.start("a.json", 10);
+        sync.reporter.set_state("paused");
+        sync.reporter.finish(true);
+        let status = sync.status();
+        assert_eq!((status.state, status.queued, status.uploaded), ("paused", 0, 1));
+    }
+}
@@ -0,0 +1,391 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+
+use super::{sigv4_presign, xml_value};
+use crate::keychain;
+
+// Keychain service the uploader's secrets are filed under
+const KEYCHAIN_SERVICE: &str = "agi-cloud-uploader";
+
+// Temporary credentials are renewed this long before they expire
+const REFRESH_MARGIN_MINS: i64 = 5;
+
+// AWS access keys; temporary ones come with a session token and an expiry
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+// Credentials resolved per profile, kept until shortly before they expire
+static CACHE: Mutex<Option<(String, AwsCredentials)>> = Mutex::new(None);
+
+/// A secret saved with `set_cloud_credentials`, if any. Keychain failures (no keychain
+/// tool, a locked keyring) count as nothing saved.
+pub(super) fn stored(name: &str) -> Option<String> {
+    match keychain::get(KEYCHAIN_SERVICE, name) {
+        Ok(secret) => secret,
+        Err(e) => {
+            eprintln!("⚠️  couldn't read {} from the keychain: {}", name, e);
+            None
+        }
+    }
+}
+
+// -------- shared config and credentials files --------
+
+type Section = HashMap<String, String>;
+
+// INI sections by name; keys are lowercased
+fn parse_ini(text: &str) -> HashMap<String, Section> {
+    let mut sections: HashMap<String, Section> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim().to_string();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+fn aws_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_default().join(".aws")
+}
+
+// ~/.aws/config and ~/.aws/credentials, or the files AWS_CONFIG_FILE and
+// AWS_SHARED_CREDENTIALS_FILE point at
+struct Profiles {
+    config: HashMap<String, Section>,
+    credentials: HashMap<String, Section>,
+}
+
+impl Profiles {
+    fn load() -> Self {
+        let read = |var: &str, default: &str| {
+            let path = std::env::var_os(var).map(PathBuf::from).unwrap_or_else(|| aws_dir().join(default));
+            parse_ini(&std::fs::read_to_string(path).unwrap_or_default())
+        };
+        Self { config: read("AWS_CONFIG_FILE", "config"), credentials: read("AWS_SHARED_CREDENTIALS_FILE", "credentials") }
+    }
+
+    // A profile's settings; the credentials file wins over the config file
+    fn profile(&self, name: &str) -> Section {
+        let config_name = if name == "default" { name.to_string() } else { format!("profile {}", name) };
+        let mut profile = self.config.get(&config_name).cloned().unwrap_or_default();
+        profile.extend(self.credentials.get(name).cloned().unwrap_or_default());
+        profile
+    }
+}
+
+/// Region a profile sets, if any
+pub(super) fn profile_region(name: &str) -> Option<String> {
+    Profiles::load().profile(name).remove("region")
+}
+
+// -------- resolving a profile --------
+
+fn http() -> Result<Client> {
+    Client::builder()
+        .timeout(std::time::Duration::from_secs(20))
+        .build()
+        .context("building http client")
+}
+
+/// Credentials for an AWS profile: static keys, a credential_process, an SSO role, or a role
+/// assumed from another profile
+pub(super) fn from_profile(name: &str) -> Result<AwsCredentials> {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((profile, creds)) = cache.as_ref() {
+        let fresh = creds.expires.is_none_or(|t| t - Duration::minutes(REFRESH_MARGIN_MINS) > Utc::now());
+        if profile == name && fresh {
+            return Ok(creds.clone());
+        }
+    }
+    let creds = resolve(&Profiles::load(), name, 0).with_context(|| format!("AWS profile {}", name))?;
+    *cache = Some((name.to_string(), creds.clone()));
+    Ok(creds)
+}
+
+fn resolve(profiles: &Profiles, name: &str, depth: usize) -> Result<AwsCredentials> {
+    if depth > 4 {
+        return Err(anyhow!("source_profile chain too long"));
+    }
+    let profile = profiles.profile(name);
+    if profile.is_empty() {
+        return Err(anyhow!("profile {} not found in {}", name, aws_dir().display()));
+    }
+    let get = |key: &str| profile.get(key).map(String::as_str);
+
+    if let Some(role_arn) = get("role_arn") {
+        if get("mfa_serial").is_some() {
+            return Err(anyhow!("roles that need MFA can't be assumed in the background"));
+        }
+        let source = get("source_profile").ok_or_else(|| anyhow!("role_arn needs a source_profile"))?;
+        // A profile may name itself as the source of its own static keys
+        let source = if source == name { static_keys(&profile)? } else { resolve(profiles, source, depth + 1)? };
+        return assume_role(&source, role_arn, &profile);
+    }
+    if let Ok(creds) = static_keys(&profile) {
+        return Ok(creds);
+    }
+    if get("sso_account_id").is_some() {
+        let session = get("sso_session").and_then(|s| profiles.config.get(&format!("sso-session {}", s)));
+        let setting = |key: &str| session.and_then(|s| s.get(key)).or_else(|| profile.get(key)).map(String::as_str);
+        return sso_role(
+            setting("sso_start_url").ok_or_else(|| anyhow!("no sso_start_url"))?,
+            setting("sso_region").ok_or_else(|| anyhow!("no sso_region"))?,
+            get("sso_account_id").unwrap_or_default(),
+            get("sso_role_name").ok_or_else(|| anyhow!("no sso_role_name"))?,
+        );
+    }
+    if let Some(command) = get("credential_process") {
+        return credential_process(command);
+    }
+    Err(anyhow!("profile {} has no credentials", name))
+}
+
+fn static_keys(profile: &Section) -> Result<AwsCredentials> {
+    match (profile.get("aws_access_key_id"), profile.get("aws_secret_access_key")) {
+        (Some(id), Some(secret)) => Ok(AwsCredentials {
+            access_key_id: id.clone(),
+            secret_access_key: secret.clone(),
+            session_token: profile.get("aws_session_token").cloned(),
+            expires: None,
+        }),
+        _ => Err(anyhow!("no static keys")),
+    }
+}
+
+// STS AssumeRole, signed with the source profile's credentials
+fn assume_role(source: &AwsCredentials, role_arn: &str, profile: &Section) -> Result<AwsCredentials> {
+    let region = profile.get("region").map(String::as_str);
+    let host = match region {
+        Some(region) => format!("sts.{}.amazonaws.com", region),
+        None => "sts.amazonaws.com".to_string(),
+    };
+    let mut query = vec![
+        ("Action", "AssumeRole".to_string()),
+        ("Version", "2011-06-15".to_string()),
+        ("RoleArn", role_arn.to_string()),
+        ("RoleSessionName", profile.get("role_session_name").cloned().unwrap_or_else(|| "agi-uploader".to_string())),
+    ];
+    if let Some(duration) = profile.get("duration_seconds") {
+        query.push(("DurationSeconds", duration.clone()));
+    }
+    if let Some(external_id) = profile.get("external_id") {
+        query.push(("ExternalId", external_id.clone()));
+    }
+    let url = sigv4_presign(source, region.unwrap_or("us-east-1"), "sts", "GET", "https", &host, "/", &query, Utc::now(), 300);
+    let r = http()?.get(url).send().context("calling STS AssumeRole")?;
+    let status = r.status();
+    let xml = r.text().unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!("STS AssumeRole returned {}: {}", status, xml.trim()));
+    }
+    let field = |tag: &str| xml_value(&xml, tag).ok_or_else(|| anyhow!("no {} in AssumeRole response", tag));
+    Ok(AwsCredentials {
+        access_key_id: field("AccessKeyId")?,
+        secret_access_key: field("SecretAccessKey")?,
+        session_token: Some(field("SessionToken")?),
+        expires: DateTime::parse_from_rfc3339(&field("Expiration")?).ok().map(|t| t.with_timezone(&Utc)),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SsoToken {
+    start_url: Option<String>,
+    access_token: Option<String>,
+    expires_at: Option<String>,
+}
+
+// The token `aws sso login` cached for a start URL, if it's still valid
+fn cached_sso_token(start_url: &str) -> Result<String> {
+    let dir = aws_dir().join("sso").join("cache");
+    for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+        let Ok(token) = std::fs::read_to_string(entry.path())
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Ok(serde_json::from_str::<SsoToken>(&text)?))
+        else {
+            continue;
+        };
+        // Older CLIs wrote "...UTC" rather than "...Z"
+        let expires = token.expires_at.as_deref().map(|t| t.replace("UTC", "Z"));
+        let valid = expires
+            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+            .is_some_and(|t| t > Utc::now());
+        if token.start_url.as_deref() == Some(start_url) && valid {
+            if let Some(access_token) = token.access_token {
+                return Ok(access_token);
+            }
+        }
+    }
+    Err(anyhow!("no valid SSO login for {}; run `aws sso login`", start_url))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RoleCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expiration: i64,             // milliseconds since the epoch
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RoleCredentialsResp {
+    role_credentials: RoleCredentials,
+}
+
+// Role credentials from IAM Identity Center (SSO), using the cached login
+fn sso_role(start_url: &str, sso_region: &str, account_id: &str, role_name: &str) -> Result<AwsCredentials> {
+    let token = cached_sso_token(start_url)?;
+    let url = format!(
+        "https://portal.sso.{}.amazonaws.com/federation/credentials?role_name={}&account_id={}",
+        sso_region,
+        urlencoding::encode(role_name),
+        urlencoding::encode(account_id)
+    );
+    let resp: RoleCredentialsResp = http()?
+        .get(url)
+        .header("x-amz-sso_bearer_token", token)
+        .send()
+        .context("calling SSO GetRoleCredentials")?
+        .error_for_status()
+        .context("non-200 from SSO GetRoleCredentials")?
+        .json()
+        .context("decoding SSO role credentials")?;
+    let role = resp.role_credentials;
+    Ok(AwsCredentials {
+        access_key_id: role.access_key_id,
+        secret_access_key: role.secret_access_key,
+        session_token: Some(role.session_token),
+        expires: Utc.timestamp_millis_opt(role.expiration).single(),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ProcessCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    expiration: Option<String>,
+}
+
+// Run a profile's credential_process and read the keys it prints
+fn credential_process(command: &str) -> Result<AwsCredentials> {
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .context("running credential_process")?;
+    if !output.status.success() {
+        return Err(anyhow!("credential_process exited with {}", output.status));
+    }
+    let creds: ProcessCredentials = serde_json::from_slice(&output.stdout).context("decoding credential_process output")?;
+    Ok(AwsCredentials {
+        access_key_id: creds.access_key_id,
+        secret_access_key: creds.secret_access_key,
+        session_token: creds.session_token,
+        expires: creds
+            .expiration
+            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.with_timezone(&Utc)),
+    })
+}
+
+// -------- storing secrets --------
+
+/// Secrets for one provider, to keep in the OS keychain instead of config.toml or the environment
+#[derive(Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum CloudCredentials {
+    S3 { access_key_id: String, secret_access_key: String },
+    Gcs { service_account: String },   // The service account key file's JSON
+    Azure { sas_token: Option<String>, account_key: Option<String> },
+}
+
+/// Save cloud upload secrets to the OS keychain (Keychain, Credential Manager, Secret Service).
+/// They're used when config.toml doesn't set them, from the next time the uploader starts.
+#[tauri::command]
+pub fn set_cloud_credentials(credentials: CloudCredentials) -> Result<(), String> {
+    let entries: Vec<(&str, Option<String>)> = match credentials {
+        CloudCredentials::S3 { access_key_id, secret_access_key } => {
+            if access_key_id.trim().is_empty() || secret_access_key.trim().is_empty() {
+                return Err("Both access_key_id and secret_access_key are required".to_string());
+            }
+            vec![("s3_access_key_id", Some(access_key_id)), ("s3_secret_access_key", Some(secret_access_key))]
+        }
+        CloudCredentials::Gcs { service_account } => {
+            let key: serde_json::Value =
+                serde_json::from_str(&service_account).map_err(|e| format!("Invalid service account key: {}", e))?;
+            if key.get("private_key").is_none() || key.get("client_email").is_none() {
+                return Err("The service account key needs private_key and client_email".to_string());
+            }
+            vec![("gcs_service_account", Some(service_account))]
+        }
+        CloudCredentials::Azure { sas_token, account_key } => {
+            if sas_token.is_none() && account_key.is_none() {
+                return Err("Either sas_token or account_key is required".to_string());
+            }
+            // Whichever isn't given is cleared, so a stale one can't take precedence
+            vec![("azure_sas_token", sas_token), ("azure_account_key", account_key)]
+        }
+    };
+    for (name, secret) in entries {
+        let saved = match secret {
+            Some(secret) => keychain::set(KEYCHAIN_SERVICE, name, secret.trim()),
+            None => keychain::delete(KEYCHAIN_SERVICE, name),
+        };
+        saved.map_err(|e| format!("Failed to save {} to the keychain: {}", name, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_from_shared_files() {
+        let profiles = Profiles {
+            config: parse_ini(
+                "[default]\nregion = us-west-2\n\n[profile sso]\nsso_session = corp\nsso_account_id = 123\nsso_role_name = Backup\n\
+                 [sso-session corp]\nsso_start_url = https://corp.awsapps.com/start\nsso_region = us-east-1\n\
+                 [profile deploy]\nrole_arn = arn:aws:iam::123:role/Deploy\nsource_profile = default\nmfa_serial = arn:mfa\n",
+            ),
+            credentials: parse_ini("# keys\n[default]\naws_access_key_id = AKIDEXAMPLE\naws_secret_access_key = secret\n"),
+        };
+        let default = profiles.profile("default");
+        assert_eq!(default["region"], "us-west-2");
+        let creds = resolve(&profiles, "default", 0).unwrap();
+        assert_eq!((creds.access_key_id.as_str(), creds.session_token), ("AKIDEXAMPLE", None));
+
+        assert_eq!(profiles.profile("sso")["sso_session"], "corp");
+        assert_eq!(profiles.config["sso-session corp"]["sso_region"], "us-east-1");
+        assert!(resolve(&profiles, "deploy", 0).unwrap_err().to_string().contains("MFA"));
+        assert!(resolve(&profiles, "missing", 0).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::{Client, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path, time::Duration};
+
+use super::{load_state, object_key, retry, save_state, state_path, throttle, StorageProvider, Transfer, UploadConfig};
+use crate::dropbox::{api_arg, check, is_not_found, API, CONTENT};
+use crate::dropbox_oauth;
+
+// Dropbox takes at most 150 MB in one request; bigger files go through an upload session
+const MAX_REQUEST_BYTES: u64 = 150 * 1024 * 1024;
+
+// Progress of an upload session, saved next to the file as `<name>.upload`. Chunks are
+// appended in order, so the acknowledged offset is all a restart needs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct SessionState {
+    key: String,
+    size: u64,                   // of the scrubbed bytes being sent
+    sha256: String,              // of the same; a changed file can't continue the session
+    chunk_size: u64,
+    session_id: String,          // empty until the first chunk starts the session
+    offset: u64,                 // bytes Dropbox has acknowledged
+}
+
+impl SessionState {
+    fn matches(&self, fresh: &SessionState) -> bool {
+        self.size == fresh.size && self.sha256 == fresh.sha256 && self.chunk_size == fresh.chunk_size
+    }
+}
+
+// Dropbox, as the connected account (see dropbox_oauth). Keys become paths under the app's
+// folder, or the Dropbox root for full-access apps.
+pub(super) struct DropboxProvider;
+
+fn dropbox_path(key: &str) -> String {
+    format!("/{}", key.trim_start_matches('/'))
+}
+
+// `commit` info for a file written at `key`
+fn commit(key: &str, mode: &str) -> Value {
+    json!({ "path": dropbox_path(key), "mode": mode, "autorename": false, "mute": true })
+}
+
+// Where an append that raced a lost reply should continue: Dropbox names the offset it holds
+fn correct_offset(body: &str) -> Option<u64> {
+    let v: Value = serde_json::from_str(body).ok()?;
+    v["error"]["correct_offset"].as_u64().or_else(|| v["error"]["lookup_failed"]["correct_offset"].as_u64())
+}
+
+impl DropboxProvider {
+    // An RPC endpoint taking and returning JSON; the status is left for the caller to check
+    fn rpc(&self, client: &Client, endpoint: &str, arg: &Value) -> Result<Response> {
+        client
+            .post(format!("{}/{}", API, endpoint))
+            .bearer_auth(dropbox_oauth::access_token()?)
+            .json(arg)
+            .timeout(Duration::from_secs(60))
+            .send()
+            .context("calling Dropbox")
+    }
+
+    // A content upload endpoint: arguments in the header, bytes in the body
+    fn content(&self, client: &Client, endpoint: &str, arg: &Value, bytes: Vec<u8>, transfer: Option<&Transfer>) -> Result<Response> {
+        let builder = client
+            .post(format!("{}/{}", CONTENT, endpoint))
+            .bearer_auth(dropbox_oauth::access_token()?)
+            .header("Dropbox-API-Arg", api_arg(arg))
+            .header("Content-Type", "application/octet-stream");
+        throttle::body(builder, bytes, Duration::from_secs(300), transfer)
+            .send()
+            .context("calling Dropbox")
+    }
+
+    // Send the chunk at `state.offset`, starting the session with the first one
+    fn send_chunk(&self, client: &Client, state: &mut SessionState, bytes: &[u8], transfer: &Transfer) -> Result<()> {
+        let end = (state.offset + state.chunk_size).min(state.size);
+        let chunk = bytes[state.offset as usize..end as usize].to_vec();
+        if state.session_id.is_empty() {
+            let r = check(self.content(client, "files/upload_session/start", &json!({ "close": false }), chunk, Some(transfer))?)?;
+            let started: Value = r.json()?;
+            state.session_id = started["session_id"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Dropbox didn't return a session id"))?
+                .to_string();
+        } else {
+            let arg = json!({ "cursor": { "session_id": state.session_id, "offset": state.offset }, "close": false });
+            let r = self.content(client, "files/upload_session/append_v2", &arg, chunk, Some(transfer))?;
+            if !r.status().is_success() {
+                let status = r.status();
+                let body = r.text().unwrap_or_default();
+                // An earlier attempt got through but its reply didn't; carry on from where
+                // Dropbox is
+                if let Some(offset) = correct_offset(&body) {
+                    state.offset = offset;
+                    return Ok(());
+                }
+                return Err(anyhow!("Dropbox returned {}: {}", status, body.trim()));
+            }
+        }
+        state.offset = end;
+        Ok(())
+    }
+
+    // Send a large file through an upload session, continuing a recorded one when there is
+    // one, then commit it at its key
+    fn upload_session(
+        &self,
+        client: &Client,
+        cfg: &UploadConfig,
+        path: &Path,
+        filename: &str,
+        bytes: &[u8],
+        transfer: &Transfer,
+    ) -> Result<String> {
+        let size = bytes.len() as u64;
+        let fresh = SessionState {
+            key: String::new(),
+            size,
+            sha256: format!("{:x}", Sha256::digest(bytes)),
+            chunk_size: cfg.part_size(size).min(MAX_REQUEST_BYTES),
+            session_id: String::new(),
+            offset: 0,
+        };
+
+        let state_file = state_path(path);
+        let recorded = load_state::<SessionState>(&state_file).filter(|s| s.matches(&fresh) && !s.session_id.is_empty());
+        let resumable = recorded.is_some();
+        let mut state = match recorded {
+            Some(state) => {
+                println!("⬆️  resuming upload session of {}: {} of {} bytes already sent", filename, state.offset, size);
+                state
+            }
+            None => {
+                let state = SessionState { key: object_key(&cfg.device_prefix(), filename), ..fresh };
+                save_state(&state_file, &state)?;
+                println!("⬆️  upload session for {}: {} chunks", filename, size.div_ceil(state.chunk_size));
+                state
+            }
+        };
+        transfer.resumed(state.offset);
+
+        let mut sent_now = 0;
+        while state.offset < size {
+            let sent = retry(
+                || {
+                    transfer.confirmed(state.offset);
+                    self.send_chunk(client, &mut state, bytes, transfer)
+                },
+                5,
+                700,
+            );
+            if let Err(e) = sent {
+                // An expired session takes no chunk at all; forget it so the next attempt
+                // starts a new one
+                if resumable && sent_now == 0 {
+                    let _ = fs::remove_file(&state_file);
+                }
+                eprintln!("⚠️  chunk at {} of {} failed: {e:?}", state.offset, filename);
+                return Err(anyhow!("upload session of {} incomplete; will resume on the next attempt", filename));
+            }
+            sent_now += 1;
+            if let Err(e) = save_state(&state_file, &state) {
+                eprintln!("⚠️  failed to save upload progress of {}: {e:?}", filename);
+            }
+        }
+
+        let arg = json!({ "cursor": { "session_id": state.session_id, "offset": size }, "commit": commit(&state.key, "add") });
+        let finished = retry(
+            || {
+                check(self.content(client, "files/upload_session/finish", &arg, Vec::new(), None)?)?;
+                Ok(())
+            },
+            3,
+            700,
+        );
+        // Whether it went through or the session is gone, there is nothing left to resume
+        let _ = fs::remove_file(&state_file);
+        finished?;
+        Ok(state.key)
+    }
+}
+
+impl StorageProvider for DropboxProvider {
+    fn name(&self) -> &'static str {
+        "dropbox"
+    }
+
+    fn upload(&self, client: &Client, cfg: &UploadConfig, path: &Path, filename: &str, bytes: &[u8], transfer: &Transfer) -> Result<String> {
+        if bytes.len() as u64 > cfg.multipart_threshold().min(MAX_REQUEST_BYTES) {
+            return self.upload_session(client, cfg, path, filename, bytes, transfer);
+        }
+        let key = object_key(&cfg.device_prefix(), filename);
+        retry(
+            || {
+                transfer.confirmed(0);
+                check(self.content(client, "files/upload", &commit(&key, "add"), bytes.to_vec(), Some(transfer))?)?;
+                Ok(())
+            },
+            5,
+            700,
+        )?;
+        Ok(key)
+    }
+
+    fn delete(&self, client: &Client, _cfg: &UploadConfig, key: &str) -> Result<()> {
+        let r = self.rpc(client, "files/delete_v2", &json!({ "path": dropbox_path(key) }))?;
+        if r.status().is_success() {
+            return Ok(());
+        }
+        let status = r.status();
+        let body = r.text().unwrap_or_default();
+        if is_not_found(&body) {
+            return Ok(());
+        }
+        Err(anyhow!("Dropbox returned {}: {}", status, body.trim()))
+    }
+
+    fn location(&self, _cfg: &UploadConfig, key: &str) -> String {
+        format!("dropbox:{}", dropbox_path(key))
+    }
+
+    fn list(&self, client: &Client, _cfg: &UploadConfig, prefix: &str) -> Result<Vec<String>> {
+        // Listings go by folder; the rest of the prefix filters what's under it
+        let folder = prefix.rfind('/').map_or("", |i| &prefix[..i]);
+        let folder = if folder.is_empty() { String::new() } else { dropbox_path(folder) };
+        let r = self.rpc(client, "files/list_folder", &json!({ "path": folder, "recursive": true, "limit": 2000 }))?;
+        if !r.status().is_success() {
+            let status = r.status();
+            let body = r.text().unwrap_or_default();
+            if is_not_found(&body) {
+                return Ok(Vec::new());
+            }
+            return Err(anyhow!("Dropbox returned {}: {}", status, body.trim()));
+        }
+        let mut page: Value = r.json()?;
+        let mut keys = Vec::new();
+        loop {
+            keys.extend(listed_keys(&page, prefix));
+            if page["has_more"].as_bool() != Some(true) {
+                return Ok(keys);
+            }
+            let cursor = page["cursor"].as_str().unwrap_or_default().to_string();
+            page = check(self.rpc(client, "files/list_folder/continue", &json!({ "cursor": cursor }))?)?.json()?;
+        }
+    }
+
+    fn download(&self, client: &Client, _cfg: &UploadConfig, key: &str) -> Result<Vec<u8>> {
+        let r = client
+            .post(format!("{}/files/download", CONTENT))
+            .bearer_auth(dropbox_oauth::access_token()?)
+            .header("Dropbox-API-Arg", api_arg(&json!({ "path": dropbox_path(key) })))
+            .timeout(Duration::from_secs(300))
+            .send()
+            .context("calling Dropbox")?;
+        Ok(check(r)?.bytes()?.to_vec())
+    }
+
+    fn put(&self, client: &Client, _cfg: &UploadConfig, key: &str, bytes: &[u8]) -> Result<()> {
+        check(self.content(client, "files/upload", &commit(key, "overwrite"), bytes.to_vec(), None)?)?;
+        Ok(())
+    }
+}
+
+// Keys of the files in one listing page that fall under `prefix`. Dropbox paths ignore
+// case, so the match does too.
+fn listed_keys(page: &Value, prefix: &str) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    page["entries"]
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter(|e| e[".tag"] == "file")
+                .filter_map(|e| {
+                    let lower = e["path_lower"].as_str()?.trim_start_matches('/');
+                    let display = e["path_display"].as_str()?.trim_start_matches('/');
+                    lower.starts_with(&prefix).then(|| display.to_string())
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing_and_session_offsets() {
+        let page = json!({ "entries": [
+            { ".tag": "folder", "path_lower": "/uploads/dev001", "path_display": "/uploads/dev001" },
+            { ".tag": "file", "path_lower": "/uploads/dev001/a.json", "path_display": "/uploads/dev001/a.json" },
+            { ".tag": "file", "path_lower": "/uploads/dev001/manifest.json", "path_display": "/uploads/DEV001/manifest.json" },
+            { ".tag": "file", "path_lower": "/uploads/dev002/b.json", "path_display": "/uploads/dev002/b.json" }
+        ], "has_more": false });
+        assert_eq!(listed_keys(&page, "uploads/dev001/"), vec!["uploads/dev001/a.json", "uploads/DEV001/manifest.json"]);
+        assert_eq!(dropbox_path("uploads/dev001/a.json"), "/uploads/dev001/a.json");
+        assert_eq!(commit("k", "overwrite")["mode"], "overwrite");
+
+        let race = r#"{"error_summary": "incorrect_offset/..", "error": {".tag": "incorrect_offset", "correct_offset": 8388608}}"#;
+        assert_eq!(correct_offset(race), Some(8_388_608));
+        assert_eq!(correct_offset(r#"{"error": {".tag": "not_found"}}"#), None);
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::file_storage::FileStorage;
+use crate::settings::{Settings, UploadFilters};
+
+/// The user's upload filters; none when settings.json can't be read
+pub(super) fn load() -> UploadFilters {
+    Settings::load().map(|s| s.cloud.filters).unwrap_or_default()
+}
+
+fn matches_conversation(patterns: &[String], json: &Value) -> bool {
+    let id = json["id"].as_str().unwrap_or_default();
+    let title = json["title"].as_str().unwrap_or_default();
+    patterns.iter().any(|p| {
+        let p = p.trim();
+        p == id || p.eq_ignore_ascii_case(title)
+    })
+}
+
+/// Whether the conversation export at `path` is uploaded at all. Exports that can't be read
+/// are let through, so the upload reports the problem.
+pub(super) fn admits(filters: &UploadFilters, path: &Path) -> bool {
+    if filters.include_conversations.is_empty() && filters.exclude_conversations.is_empty() {
+        return true;
+    }
+    let Some(json) = fs::read(path).ok().and_then(|b| serde_json::from_slice::<Value>(&b).ok()) else {
+        return true;
+    };
+    admits_conversation(filters, &json)
+}
+
+fn admits_conversation(filters: &UploadFilters, json: &Value) -> bool {
+    (filters.include_conversations.is_empty() || matches_conversation(&filters.include_conversations, json))
+        && !matches_conversation(&filters.exclude_conversations, json)
+}
+
+// An extension ("mp4", ".mp4"), a MIME type ("video/mp4") or a MIME family ("video/*", "video/")
+fn type_matches(pattern: &str, name: &str, mime: &str) -> bool {
+    let pattern = pattern.trim().trim_start_matches('.').to_ascii_lowercase();
+    let mime = mime.to_ascii_lowercase();
+    if let Some(family) = pattern.strip_suffix("/*").or_else(|| pattern.strip_suffix('/')) {
+        return mime.split('/').next() == Some(family);
+    }
+    if pattern.contains('/') {
+        return mime == pattern;
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(&pattern))
+}
+
+// Why an attachment is left out, if it is
+fn exclusion(filters: &UploadFilters, tagged: &HashSet<String>, file: &Value) -> Option<&'static str> {
+    let name = file["name"].as_str().unwrap_or_default();
+    let mime = file["type"].as_str().unwrap_or_default();
+    let size = file["size"]
+        .as_u64()
+        .unwrap_or_else(|| file["base64"].as_str().map_or(0, |b| b.len() as u64 / 4 * 3));
+    if !filters.include_types.is_empty() && !filters.include_types.iter().any(|p| type_matches(p, name, mime)) {
+        return Some("type");
+    }
+    if filters.exclude_types.iter().any(|p| type_matches(p, name, mime)) {
+        return Some("type");
+    }
+    if filters.max_attachment_mb.is_some_and(|mb| size > mb * 1024 * 1024) {
+        return Some("size");
+    }
+    if file["id"].as_str().is_some_and(|id| tagged.contains(id)) {
+        return Some("tag");
+    }
+    None
+}
+
+// Uploaded files carrying one of the excluded tags
+fn tagged_files(tags: &[String]) -> Result<HashSet<String>> {
+    if tags.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let storage = FileStorage::new()?;
+    let mut ids = HashSet::new();
+    for tag in tags {
+        ids.extend(storage.list_files_by_tag(tag)?.into_iter().map(|f| f.id));
+    }
+    Ok(ids)
+}
+
+/// Drop the content of the attachments the filters leave out, keeping their id, name, type
+/// and size with an `excluded` reason. Returns the bytes to upload and how many were dropped.
+pub(super) fn prune(filters: &UploadFilters, bytes: Vec<u8>) -> Result<(Vec<u8>, usize)> {
+    let attachment_filters = !filters.include_types.is_empty()
+        || !filters.exclude_types.is_empty()
+        || !filters.exclude_tags.is_empty()
+        || filters.max_attachment_mb.is_some();
+    if !attachment_filters {
+        return Ok((bytes, 0));
+    }
+    let Ok(mut json) = serde_json::from_slice::<Value>(&bytes) else {
+        return Ok((bytes, 0));
+    };
+    let tagged = tagged_files(&filters.exclude_tags).context("looking up tagged uploads")?;
+    Ok(prune_json(filters, &tagged, &mut json).map_or((bytes, 0), |dropped| {
+        (serde_json::to_vec_pretty(&json).unwrap_or_default(), dropped)
+    }))
+}
+
+/// Drop the content of the attachments `drops` picks by id, as `prune` does, with
+/// `excluded: "deleted"`. Returns the bytes and how many were dropped.
+pub(super) fn drop_attachments(bytes: Vec<u8>, drops: impl Fn(&str) -> bool) -> Result<(Vec<u8>, usize)> {
+    let mut json = serde_json::from_slice::<Value>(&bytes).context("not a conversation export")?;
+    let deleted = |file: &Value| file["id"].as_str().filter(|&id| drops(id)).map(|_| "deleted");
+    Ok(drop_content(&mut json, deleted).map_or((bytes, 0), |dropped| {
+        (serde_json::to_vec_pretty(&json).unwrap_or_default(), dropped)
+    }))
+}
+
+// Returns how many attachments were dropped, or None when nothing changed
+fn prune_json(filters: &UploadFilters, tagged: &HashSet<String>, json: &mut Value) -> Option<usize> {
+    drop_content(json, |file| exclusion(filters, tagged, file))
+}
+
+// Remove the content of the attachments `reason` gives a reason for, recording it in `excluded`
+fn drop_content(json: &mut Value, reason: impl Fn(&Value) -> Option<&'static str>) -> Option<usize> {
+    let mut dropped = 0;
+    let messages = json["messages"].as_array_mut()?;
+    for message in messages {
+        let Some(files) = message["attachedFiles"].as_array_mut() else {
+            continue;
+        };
+        for file in files.iter_mut().filter(|f| f.get("base64").is_some()) {
+            if let Some(reason) = reason(file) {
+                if let Some(file) = file.as_object_mut() {
+                    file.remove("base64");
+                    file.insert("excluded".into(), reason.into());
+                    dropped += 1;
+                }
+            }
+        }
+    }
+    (dropped > 0).then_some(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_and_attachment_filters() {
+        let mut json = serde_json::json!({
+            "id": "conv-1",
+            "title": "Trip planning",
+            "messages": [{
+                "content": "see attached",
+                "attachedFiles": [
+                    { "id": "v", "name": "clip.MOV", "type": "video/quicktime", "size": 80_000_000, "base64": "AAAA" },
+                    { "id": "p", "name": "plan.pdf", "type": "application/pdf", "size": 2_000_000, "base64": "AAAA" },
+                    { "id": "n", "name": "notes.md", "type": "text/markdown", "size": 10, "base64": "AAAA" },
+                    { "id": "i", "name": "map.png", "type": "image/png", "size": 30_000_000, "base64": "AAAA" }
+                ]
+            }]
+        });
+
+        let by_title = UploadFilters { exclude_conversations: vec!["trip PLANNING".into()], ..Default::default() };
+        assert!(!admits_conversation(&by_title, &json));
+        let only_other = UploadFilters { include_conversations: vec!["conv-2".into()], ..Default::default() };
+        assert!(!admits_conversation(&only_other, &json));
+        assert!(admits_conversation(&UploadFilters::default(), &json));
+
+        assert!(type_matches("video/*", "x", "video/mp4"));
+        assert!(type_matches(".mov", "clip.MOV", ""));
+        assert!(!type_matches("video/", "x", "videogame/x"));
+
+        let filters = UploadFilters {
+            exclude_types: vec!["video/*".into()],
+            max_attachment_mb: Some(25),
+            ..Default::default()
+        };
+        let tagged: HashSet<String> = ["n".to_string()].into();
+        assert_eq!(prune_json(&filters, &tagged, &mut json), Some(3));
+        let files = json["messages"][0]["attachedFiles"].as_array().unwrap();
+        let reasons: Vec<_> = files.iter().map(|f| f["excluded"].as_str()).collect();
+        assert_eq!(reasons, vec![Some("type"), None, Some("tag"), Some("size")]);
+        assert!(files[1]["base64"].is_string() && files[0].get("base64").is_none());
+        // Already pruned: nothing to do
+        assert_eq!(prune_json(&filters, &tagged, &mut json), None);
+
+        // A deleted upload leaves the re-uploaded conversation; the rest stays
+        let (bytes, dropped) = drop_attachments(serde_json::to_vec(&json).unwrap(), |id| id == "p").unwrap();
+        assert_eq!(dropped, 1);
+        let json: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["messages"][0]["attachedFiles"][1]["excluded"], "deleted");
+        assert_eq!(json["messages"][0]["content"], "see attached");
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::{load_state, save_state, UploadConfig};
+
+// Runs kept on disk; at one a minute while files keep arriving, several days' worth
+const HISTORY_LIMIT: usize = 500;
+// Per run, so one bad batch doesn't bloat the file
+const ERRORS_KEPT: usize = 5;
+
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// One pass of the uploader that tried to send something
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SyncRun {
+    pub trigger: String,               // "scan" (periodic, or trigger_aws_upload) or "watch" (a new file)
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_ms: u64,
+    pub uploaded: usize,
+    pub failed: usize,
+    pub bytes: u64,                    // Sent, after compression
+    pub errors: Vec<String>,           // "file: error" for the first few failures
+}
+
+/// What get_sync_history returns
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SyncHistory {
+    pub runs: Vec<SyncRun>,            // Newest first
+    pub last_success_at: Option<String>, // End of the latest run that uploaded without failures
+    pub last_failure_at: Option<String>,
+    pub total_uploaded: usize,         // Across the kept runs
+    pub total_failed: usize,
+    pub total_bytes: u64,
+}
+
+// Next to the ledger in the watch dir; no .json extension, so it's never uploaded
+fn history_path(watch_dir: &str) -> PathBuf {
+    Path::new(watch_dir).join(".sync_history")
+}
+
+/// A run being counted; saved by `finish` if it tried to send anything
+pub(super) struct Run {
+    run: SyncRun,
+    started: Instant,
+}
+
+impl Run {
+    pub(super) fn start(trigger: &str) -> Self {
+        let now = Utc::now().to_rfc3339();
+        let run = SyncRun {
+            trigger: trigger.to_string(),
+            started_at: now.clone(),
+            finished_at: now,
+            duration_ms: 0,
+            uploaded: 0,
+            failed: 0,
+            bytes: 0,
+            errors: Vec::new(),
+        };
+        Self { run, started: Instant::now() }
+    }
+
+    /// Count one file's outcome; Ok(None) is a file held for later
+    pub(super) fn add(&mut self, file: &str, result: &Result<Option<u64>>) {
+        match result {
+            Ok(Some(bytes)) => {
+                self.run.uploaded += 1;
+                self.run.bytes += bytes;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                self.run.failed += 1;
+                if self.run.errors.len() < ERRORS_KEPT {
+                    self.run.errors.push(format!("{}: {}", file, e));
+                }
+            }
+        }
+    }
+
+    pub(super) fn finish(mut self, cfg: &UploadConfig) {
+        if self.run.uploaded + self.run.failed == 0 {
+            return;
+        }
+        self.run.finished_at = Utc::now().to_rfc3339();
+        self.run.duration_ms = self.started.elapsed().as_millis() as u64;
+        if let Err(e) = append(&history_path(&cfg.watch_dir), self.run) {
+            eprintln!("⚠️  Cloud Uploader: failed to save sync history: {e:?}");
+        }
+    }
+}
+
+fn append(path: &Path, run: SyncRun) -> Result<()> {
+    let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut runs = load_state::<Vec<SyncRun>>(path).unwrap_or_default();
+    runs.push(run);
+    let excess = runs.len().saturating_sub(HISTORY_LIMIT);
+    runs.drain(..excess);
+    save_state(path, &runs)
+}
+
+fn summarize(mut runs: Vec<SyncRun>, limit: usize) -> SyncHistory {
+    runs.reverse();
+    let mut history = SyncHistory {
+        last_success_at: runs.iter().find(|r| r.failed == 0).map(|r| r.finished_at.clone()),
+        last_failure_at: runs.iter().find(|r| r.failed > 0).map(|r| r.finished_at.clone()),
+        total_uploaded: runs.iter().map(|r| r.uploaded).sum(),
+        total_failed: runs.iter().map(|r| r.failed).sum(),
+        total_bytes: runs.iter().map(|r| r.bytes).sum(),
+        ..Default::default()
+    };
+    runs.truncate(limit);
+    history.runs = runs;
+    history
+}
+
+/// The uploader's recent runs, newest first, and when it last backed up successfully
+#[tauri::command]
+pub fn get_sync_history(limit: Option<usize>) -> Result<SyncHistory, String> {
+    let cfg = UploadConfig::load().map_err(|e| format!("Failed to load cloud config: {}", e))?;
+    let runs = load_state::<Vec<SyncRun>>(&history_path(&cfg.watch_dir)).unwrap_or_default();
+    Ok(summarize(runs, limit.unwrap_or(50)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::fs;
+
+    #[test]
+    fn test_runs_are_recorded_and_summarized() {
+        let dir = std::env::temp_dir().join(format!("history-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let cfg: UploadConfig = toml::from_str(&format!("device_id = \"dev001\"\nwatch_dir = {:?}", dir.to_str().unwrap())).unwrap();
+
+        let mut ok = Run::start("scan");
+        ok.add("a.json", &Ok(Some(100)));
+        ok.add("held.json", &Ok(None));
+        ok.finish(&cfg);
+        // Nothing tried: not recorded
+        Run::start("scan").finish(&cfg);
+        let mut bad = Run::start("watch");
+        bad.add("b.json", &Ok(Some(50)));
+        bad.add("c.json", &Err(anyhow!("403 Forbidden")));
+        bad.finish(&cfg);
+
+        let runs = load_state::<Vec<SyncRun>>(&history_path(&cfg.watch_dir)).unwrap();
+        let history = summarize(runs, 1);
+        assert_eq!(history.runs.len(), 1);
+        assert_eq!(history.runs[0].trigger, "watch");
+        assert_eq!(history.runs[0].errors, vec!["c.json: 403 Forbidden"]);
+        assert_eq!((history.total_uploaded, history.total_failed, history.total_bytes), (2, 1, 150));
+        assert!(history.last_success_at.is_some() && history.last_failure_at.is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
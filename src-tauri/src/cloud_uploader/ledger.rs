@@ -0,0 +1,256 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, thread, time::Duration};
+
+use super::progress::Reporter;
+use super::{compression, filters, load_state, manifest, provider_for, retry, save_state, store, StorageProvider, UploadConfig};
+use crate::settings::Settings;
+
+// Serializes read-modify-write cycles of the ledger across the uploader threads
+static LEDGER_LOCK: Mutex<()> = Mutex::new(());
+
+// An object the uploader put in the bucket, and which uploaded files' content it carries
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct UploadedObject {
+    pub(super) provider: String, // StorageProvider::name at upload time
+    pub(super) key: String,
+    source: String,              // Local file name, e.g. conversation_<time>.json
+    pub(super) file_ids: Vec<String>, // Attachments whose content the object carries
+    #[serde(default)]
+    pub(super) sha256: Option<String>, // Of the bytes sent; None for objects recorded before hashes were kept
+    #[serde(default)]
+    pub(super) size: Option<u64>,
+    uploaded_at: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(super) pending_delete: bool, // Replaced, but deleting it failed; the next scan tries again
+}
+
+// Kept in the watch dir; no .json extension, so the uploader never picks it up itself
+fn ledger_path(watch_dir: &str) -> PathBuf {
+    Path::new(watch_dir).join(".cloud_objects")
+}
+
+fn load(path: &Path) -> Vec<UploadedObject> {
+    load_state::<Vec<UploadedObject>>(path).unwrap_or_default()
+}
+
+pub(super) fn record(cfg: &UploadConfig, object: UploadedObject) -> Result<()> {
+    let _guard = LEDGER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = ledger_path(&cfg.watch_dir);
+    let mut objects = load(&path);
+    objects.push(object);
+    save_state(&path, &objects)
+}
+
+pub(super) fn recorded_keys(cfg: &UploadConfig) -> HashSet<String> {
+    load(&ledger_path(&cfg.watch_dir)).into_iter().map(|o| o.key).collect()
+}
+
+pub(super) fn recorded(cfg: &UploadConfig) -> Vec<UploadedObject> {
+    load(&ledger_path(&cfg.watch_dir))
+}
+
+/// Objects whose deletion failed and is still to be retried
+pub(super) fn pending_deletes(cfg: &UploadConfig) -> Vec<UploadedObject> {
+    recorded(cfg).into_iter().filter(|o| o.pending_delete).collect()
+}
+
+// Remote deletion is off: the user keeps the bucket's copies. Unreadable settings count as
+// keeping them; deleting is the one that can't be undone
+pub(super) fn keeps_remote_copies() -> bool {
+    Settings::load().map(|s| s.cloud.keep_remote_copies).unwrap_or(true)
+}
+
+// Remove and return the recorded objects `matches` picks
+fn take(path: &Path, matches: impl Fn(&UploadedObject) -> bool) -> Result<Vec<UploadedObject>> {
+    let _guard = LEDGER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let (taken, kept): (Vec<_>, Vec<_>) = load(path).into_iter().partition(|o| matches(o));
+    if !taken.is_empty() {
+        save_state(path, &kept)?;
+    }
+    Ok(taken)
+}
+
+/// Ids of the attachments whose content a conversation export carries
+fn carried_file_ids(bytes: &[u8]) -> Vec<String> {
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(bytes) else {
+        return Vec::new();
+    };
+    let mut ids = attached_file_ids(&json);
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+    ids
+}
+
+fn attached_file_ids(json: &serde_json::Value) -> Vec<String> {
+    json["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|m| m["attachedFiles"].as_array().into_iter().flatten())
+        // Left out by the upload filters: the object doesn't carry their content
+        .filter(|f| f.get("excluded").is_none())
+        .filter_map(|f| f["id"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// The ledger entry for `bytes` stored at `key`, compressed or not
+pub(super) fn new_entry(provider: &str, key: String, source: &str, bytes: &[u8]) -> UploadedObject {
+    let content = compression::unpack(bytes).unwrap_or_default();
+    UploadedObject {
+        provider: provider.to_string(),
+        key,
+        source: source.to_string(),
+        file_ids: carried_file_ids(&content),
+        sha256: Some(format!("{:x}", Sha256::digest(bytes))),
+        size: Some(bytes.len() as u64),
+        uploaded_at: Utc::now().to_rfc3339(),
+        pending_delete: false,
+    }
+}
+
+/// Take `file_ids` (every attachment when None) out of the cloud copies carrying them, unless
+/// the user keeps remote copies. Each such conversation export is uploaded again without
+/// their content, and the old object is deleted, so the conversation stays backed up. A copy
+/// whose local export is gone is left alone. Runs in the background; objects that can't be
+/// deleted stay recorded for the next scan.
+pub fn delete_remote_copies(file_ids: Option<Vec<String>>) {
+    if keeps_remote_copies() {
+        return;
+    }
+    thread::spawn(move || {
+        if let Err(e) = delete_now(file_ids.as_deref()) {
+            eprintln!("⚠️  Cloud Uploader: failed to delete remote copies: {e:?}");
+        }
+    });
+}
+
+fn delete_now(file_ids: Option<&[String]>) -> Result<()> {
+    let cfg = UploadConfig::load()?;
+    let path = ledger_path(&cfg.watch_dir);
+    if !path.exists() {
+        return Ok(());
+    }
+    let provider = provider_for(&cfg)?;
+    let client = Client::builder().timeout(Duration::from_secs(20)).build()?;
+    let taken = take(&path, |o| match file_ids {
+        Some(ids) => o.file_ids.iter().any(|id| ids.contains(id)),
+        None => !o.file_ids.is_empty(),
+    })?;
+    let mut replaced = Vec::new();
+    for object in taken {
+        match reupload_without(&client, &cfg, provider.as_ref(), &object, file_ids) {
+            Ok(()) => replaced.push(object),
+            Err(e) => {
+                eprintln!("⚠️  Cloud Uploader: keeping {}, it couldn't be uploaded again without the deleted files: {e:?}", object.key);
+                record(&cfg, object)?;
+            }
+        }
+    }
+    delete_objects(&client, &cfg, provider.as_ref(), replaced)?;
+    manifest::publish(&client, &cfg, provider.as_ref());
+    Ok(())
+}
+
+// Upload the local export behind `object` again, leaving out the content of `file_ids` (all
+// attachments when None) and of any attachment `object` didn't carry, so a file deleted
+// earlier doesn't come back
+fn reupload_without(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider, object: &UploadedObject, file_ids: Option<&[String]>) -> Result<()> {
+    let path = Path::new(&cfg.watch_dir).join(format!("{}.synced", object.source));
+    let bytes = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+    let (bytes, _) = filters::prune(&filters::load(), bytes)?;
+    let (bytes, dropped) = filters::drop_attachments(bytes, |id| {
+        !object.file_ids.iter().any(|carried| carried == id) || file_ids.is_none_or(|ids| ids.iter().any(|d| d == id))
+    })?;
+    let (bytes, report) = crate::pii_scrubber::scrub_upload(bytes).map_err(anyhow::Error::msg)?;
+    let source = format!("{}_upload", provider.name());
+    crate::pii_audit::record(&source, Some(format!("{}:{}", provider.name(), object.source)), &report);
+    let (location, _) = store(client, cfg, provider, &Reporter::new(|_| {}), &path, &object.source, bytes)?;
+    println!("☁️  Cloud Uploader: {} uploaded again without {} deleted attachment(s)  →  {}", object.source, dropped, location);
+    Ok(())
+}
+
+/// Try the deletions that failed before, for the current provider; called after each scan.
+/// Returns how many were tried.
+pub(super) fn retry_pending_deletes(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider) -> Result<usize> {
+    if keeps_remote_copies() {
+        return Ok(0);
+    }
+    let taken = take(&ledger_path(&cfg.watch_dir), |o| o.pending_delete && o.provider == provider.name())?;
+    if taken.is_empty() {
+        return Ok(0);
+    }
+    let count = taken.len();
+    println!("🗑️  Cloud Uploader: retrying {} remote deletion(s)", count);
+    delete_objects(client, cfg, provider, taken)?;
+    Ok(count)
+}
+
+// Delete `objects` from the bucket; the ones that fail go back in the ledger, pending
+fn delete_objects(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider, objects: Vec<UploadedObject>) -> Result<()> {
+    let mut failed = Vec::new();
+    for mut object in objects {
+        if object.provider != provider.name() {
+            // Uploaded before the provider was switched; no credentials for it now
+            eprintln!("⚠️  Cloud Uploader: can't delete {} from {}, provider is now {}", object.key, object.provider, provider.name());
+            object.pending_delete = true;
+            failed.push(object);
+            continue;
+        }
+        match retry(|| provider.delete(client, cfg, &object.key), 3, 700) {
+            Ok(()) => println!("🗑️  deleted remote copy: {}", provider.location(cfg, &object.key)),
+            Err(e) => {
+                eprintln!("⚠️  failed to delete {}: {e:?}", object.key);
+                object.pending_delete = true;
+                failed.push(object);
+            }
+        }
+    }
+    for object in failed {
+        record(cfg, object)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ledger_take_by_file_id() {
+        let json = serde_json::json!({
+            "id": "conv-1",
+            "messages": [
+                { "content": "hi", "attachedFiles": [{ "id": "f1" }, { "id": "f2" }] },
+                { "content": "no files" }
+            ]
+        });
+        assert_eq!(attached_file_ids(&json), vec!["f1", "f2"]);
+
+        let dir = std::env::temp_dir().join(format!("ledger-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = ledger_path(dir.to_str().unwrap());
+        let entry = |key: &str, ids: &[&str]| UploadedObject {
+            file_ids: ids.iter().map(|id| id.to_string()).collect(),
+            ..new_entry("aws", key.into(), "a.json", b"{}")
+        };
+        let entries = vec![entry("k1", &["f1"]), entry("k2", &[]), entry("k3", &["f2", "f3"])];
+        save_state(&path, &entries).unwrap();
+
+        let ids = ["f3".to_string()];
+        let taken = take(&path, |o| o.file_ids.iter().any(|id| ids.contains(id))).unwrap();
+        assert_eq!(taken.iter().map(|o| o.key.as_str()).collect::<Vec<_>>(), vec!["k3"]);
+        // What a wipe takes: everything carrying a file, but not plain conversations
+        let taken = take(&path, |o| !o.file_ids.is_empty()).unwrap();
+        assert_eq!(taken.iter().map(|o| o.key.as_str()).collect::<Vec<_>>(), vec!["k1"]);
+        assert_eq!(load(&path).len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::ledger::{self, UploadedObject};
+use super::{provider_for, StorageProvider, UploadConfig};
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+// Keeps a slower writer from replacing a newer manifest with its older snapshot
+static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+
+// Next to the device's uploads, whose keys always have a <time>_<uuid>_ prefix
+fn manifest_key(cfg: &UploadConfig) -> String {
+    format!("{}{}", cfg.device_prefix(), MANIFEST_NAME)
+}
+
+pub(super) fn is_manifest(key: &str) -> bool {
+    key.rsplit('/').next() == Some(MANIFEST_NAME)
+}
+
+/// What the device has in the bucket, as published in <prefix>/<device_id>/manifest.json
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    device_id: String,
+    updated_at: String,
+    objects: Vec<UploadedObject>,    // Key, file ids, sha256 and size of each upload
+}
+
+// The recorded objects under this device's prefix, for the configured provider
+fn device_objects(cfg: &UploadConfig, provider: &dyn StorageProvider) -> Vec<UploadedObject> {
+    let prefix = cfg.device_prefix();
+    ledger::recorded(cfg)
+        .into_iter()
+        .filter(|o| o.provider == provider.name() && o.key.starts_with(&prefix))
+        .collect()
+}
+
+/// Replace the device's manifest with what the ledger holds now
+pub(super) fn write(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider) -> Result<()> {
+    let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let manifest = Manifest {
+        device_id: cfg.device_id.clone(),
+        updated_at: Utc::now().to_rfc3339(),
+        objects: device_objects(cfg, provider),
+    };
+    let bytes = serde_json::to_vec_pretty(&manifest)?;
+    provider.put(client, cfg, &manifest_key(cfg), &bytes)
+}
+
+/// Write the manifest once a scan, upload or deletion has changed what the device stores;
+/// a failure is logged, and the next change writes it again
+pub(super) fn publish(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider) {
+    if let Err(e) = write(client, cfg, provider) {
+        eprintln!("⚠️  Cloud Uploader: failed to update the manifest: {e:?}");
+    }
+}
+
+/// Outcome of checking the bucket against the manifest and the local ledger
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct VerifyReport {
+    pub checked: usize,                // Objects the manifest or the ledger expects
+    pub ok: usize,                     // Present, with the recorded hash
+    pub missing: Vec<String>,          // Expected, but not in the bucket
+    pub corrupted: Vec<String>,        // "key: reason" for content that doesn't match its hash or size
+    pub unverified: Vec<String>,       // Present, but uploaded before hashes were recorded
+    pub manifest_found: bool,
+    pub not_in_manifest: Vec<String>,  // Recorded here, but absent from the remote manifest
+}
+
+// Compare what the ledger (`local`) and the manifest expect against the `listed` keys,
+// fetching each present object to hash it
+fn check(
+    local: &[UploadedObject],
+    manifest: Option<&[UploadedObject]>,
+    listed: &HashSet<String>,
+    fetch: impl Fn(&str) -> Result<Vec<u8>>,
+) -> VerifyReport {
+    let mut report = VerifyReport { manifest_found: manifest.is_some(), ..Default::default() };
+    let remote: HashMap<&str, &UploadedObject> = manifest.unwrap_or_default().iter().map(|o| (o.key.as_str(), o)).collect();
+
+    // The local record wins where both have the object; a disagreement is itself corruption
+    let mut expected: Vec<&UploadedObject> = Vec::new();
+    for object in local {
+        match remote.get(object.key.as_str()) {
+            None if manifest.is_some() => report.not_in_manifest.push(object.key.clone()),
+            Some(published) if published.sha256.is_some() && object.sha256.is_some() && published.sha256 != object.sha256 => {
+                report.corrupted.push(format!("{}: manifest hash differs from the local record", object.key));
+            }
+            _ => {}
+        }
+        expected.push(object);
+    }
+    let local_keys: HashSet<&str> = local.iter().map(|o| o.key.as_str()).collect();
+    expected.extend(manifest.unwrap_or_default().iter().filter(|o| !local_keys.contains(o.key.as_str())));
+
+    for object in expected {
+        report.checked += 1;
+        if !listed.contains(&object.key) {
+            report.missing.push(object.key.clone());
+            continue;
+        }
+        let Some(sha256) = &object.sha256 else {
+            report.unverified.push(object.key.clone());
+            continue;
+        };
+        let problem = match fetch(&object.key) {
+            Err(e) => Some(format!("can't download: {}", e)),
+            Ok(bytes) if object.size.is_some_and(|size| size != bytes.len() as u64) => {
+                Some(format!("{} bytes, expected {}", bytes.len(), object.size.unwrap_or_default()))
+            }
+            Ok(bytes) if format!("{:x}", Sha256::digest(&bytes)) != *sha256 => Some("hash mismatch".to_string()),
+            Ok(_) => None,
+        };
+        match problem {
+            Some(problem) => report.corrupted.push(format!("{}: {}", object.key, problem)),
+            None => report.ok += 1,
+        }
+    }
+    report
+}
+
+/// Check every object this device uploaded is still in the bucket with the content it was
+/// sent with
+pub fn verify() -> Result<VerifyReport> {
+    let cfg = UploadConfig::load()?;
+    let provider = provider_for(&cfg)?;
+    let client = Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .context("building http client")?;
+
+    let local = device_objects(&cfg, provider.as_ref());
+    let key = manifest_key(&cfg);
+    let manifest = match provider.download(&client, &cfg, &key) {
+        Ok(bytes) => Some(serde_json::from_slice::<Manifest>(&bytes).context("reading the manifest")?),
+        Err(e) => {
+            eprintln!("⚠️  Cloud Uploader: no manifest at {}: {e:?}", provider.location(&cfg, &key));
+            None
+        }
+    };
+    let listed: HashSet<String> = provider.list(&client, &cfg, &cfg.device_prefix())?.into_iter().collect();
+
+    let report = check(&local, manifest.as_ref().map(|m| m.objects.as_slice()), &listed, |key| {
+        provider.download(&client, &cfg, key)
+    });
+    println!(
+        "🔍 Cloud Uploader: verified {} object(s): {} ok, {} missing, {} corrupted, {} unverified",
+        report.checked,
+        report.ok,
+        report.missing.len(),
+        report.corrupted.len(),
+        report.unverified.len()
+    );
+    Ok(report)
+}
+
+/// Compare the bucket against the manifest and this machine's record of its uploads,
+/// downloading each object to check its hash
+#[tauri::command]
+pub async fn verify_cloud_backup() -> Result<VerifyReport, String> {
+    tauri::async_runtime::spawn_blocking(|| verify().map_err(|e| format!("Failed to verify cloud backup: {}", e)))
+        .await
+        .map_err(|e| format!("Verify task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_check_reports_missing_and_corrupted() {
+        let entry = |key: &str, bytes: &[u8]| ledger::new_entry("aws", key.to_string(), "a.json", bytes);
+        let mut legacy = entry("uploads/dev001/old", b"old");
+        legacy.sha256 = None;
+        let local = vec![entry("uploads/dev001/a", b"a"), entry("uploads/dev001/b", b"b"), entry("uploads/dev001/gone", b"g"), legacy];
+        // The manifest is missing "old" and knows about "c", which this machine doesn't
+        let manifest = vec![local[0].clone(), local[1].clone(), local[2].clone(), entry("uploads/dev001/c", b"c")];
+        let listed: HashSet<String> = ["a", "b", "c", "old"].iter().map(|k| format!("uploads/dev001/{}", k)).collect();
+
+        let report = check(&local, Some(&manifest), &listed, |key| match key {
+            "uploads/dev001/b" => Ok(b"tampered".to_vec()),
+            "uploads/dev001/c" => Err(anyhow!("403")),
+            key => Ok(key.rsplit('/').next().unwrap().as_bytes().to_vec()),
+        });
+        assert_eq!(report.checked, 5);
+        assert_eq!(report.ok, 1);
+        assert_eq!(report.missing, vec!["uploads/dev001/gone"]);
+        assert_eq!(report.unverified, vec!["uploads/dev001/old"]);
+        assert_eq!(report.not_in_manifest, vec!["uploads/dev001/old"]);
+        assert_eq!(report.corrupted.len(), 2);
+        assert!(report.corrupted[0].starts_with("uploads/dev001/b: 8 bytes"), "{:?}", report.corrupted);
+
+        assert!(is_manifest("uploads/dev001/manifest.json"));
+        assert!(!is_manifest("uploads/dev001/2025-01-27T10-30-00Z_0b6f4f36-9d2c-4a8e-9a43-2f0d1c7e5b11_manifest.json"));
+    }
+}
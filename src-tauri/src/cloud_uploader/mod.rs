@@ -0,0 +1,835 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fs, io::Read, ops::Range, path::{Path, PathBuf}, thread, time::Duration, sync::mpsc::{channel, Receiver, RecvTimeoutError}, collections::HashSet, sync::{Arc, Mutex}};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use control::{Control, Gate};
+use progress::{Reporter, Transfer};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher, event::EventKind};
+
+mod azure;
+mod compression;
+pub mod control;
+pub mod credentials;
+mod dropbox;
+mod filters;
+mod gcs;
+pub mod history;
+pub mod ledger;
+pub mod manifest;
+pub mod preview;
+pub mod restore;
+pub mod progress;
+mod s3;
+pub mod target;
+mod throttle;
+
+// -------- config --------
+
+// Where uploads go; picked with `provider` in config.toml
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    S3,                          // the presigner Lambda, or any S3-compatible endpoint
+    Gcs,                         // Google Cloud Storage, with a service account
+    Azure,                       // Azure Blob Storage, with a SAS token or the account key
+    Dropbox,                     // the Dropbox account connected with connect_dropbox
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UploadConfig {
+    #[serde(default)]
+    pub provider: ProviderKind,
+    #[serde(default)]
+    pub api_url: String,         // e.g., https://<api-id>.execute-api.us-west-2.amazonaws.com/ingest/new
+    #[serde(default)]
+    pub device_id: String,       // e.g., "dev001"; defaults to the host name
+    #[serde(default)]
+    pub watch_dir: String,       // e.g., ".\\memory"; defaults to the app's memory folder
+    pub scan_interval_secs: Option<u64>,
+    pub concurrency: Option<usize>,
+    pub multipart_threshold_mb: Option<u64>, // files larger than this are uploaded in parts
+    pub part_size_mb: Option<u64>,           // S3 requires at least 5 MB per part (except the last)
+    pub part_concurrency: Option<usize>,     // parts sent in parallel
+    pub max_upload_kb_per_sec: Option<u64>,  // shared by all uploads; unset or 0 for no limit
+    #[serde(default)]
+    pub pause_on_metered: bool,              // hold uploads while the connection is metered (Windows, NetworkManager)
+    pub compress: Option<bool>,              // zstd-compress uploads that shrink (default true); restores read both
+    pub endpoint: Option<String>,            // S3-compatible endpoint (MinIO, R2); URLs are then presigned locally instead of via api_url
+                                             // (Azure: Blob service URL, e.g. Azurite's, instead of https://<account>.blob.core.windows.net)
+    pub bucket: Option<String>,
+    pub prefix: Option<String>,              // key root in place of "uploads" (not with api_url: the Lambda picks the keys)
+    pub region: Option<String>,              // signing region; "auto" for R2, us-east-1 for most MinIO setups
+    pub path_style: Option<bool>,            // https://host/bucket/key rather than https://bucket.host/key
+    pub access_key_id: Option<String>,       // falls back to the keychain, then AWS_ACCESS_KEY_ID
+    pub secret_access_key: Option<String>,   // falls back to the keychain, then AWS_SECRET_ACCESS_KEY
+    pub aws_profile: Option<String>,         // ~/.aws profile (static keys, SSO, AssumeRole, credential_process); uploads straight to S3
+    pub credentials_file: Option<String>,    // GCS service account key (JSON); falls back to the keychain, then GOOGLE_APPLICATION_CREDENTIALS
+    pub account: Option<String>,             // Azure storage account; falls back to AZURE_STORAGE_ACCOUNT
+    pub sas_token: Option<String>,           // Azure SAS token for the container; falls back to the keychain, then AZURE_STORAGE_SAS_TOKEN
+    pub account_key: Option<String>,         // Azure account key (base64); falls back to the keychain, then AZURE_STORAGE_KEY
+}
+
+// S3's minimum part size
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+// S3's maximum number of parts per upload
+const MAX_PARTS: u64 = 10_000;
+
+impl UploadConfig {
+    pub fn load() -> Result<Self> {
+        // Try to find config.toml in multiple locations
+        let config_paths = vec![
+            "config.toml",  // Current directory
+            "../config.toml",  // Parent directory (for when running from src-tauri)
+            "../../config.toml",  // Two levels up (fallback)
+        ];
+        
+        let mut config_content = None;
+        let mut found_path = None;
+        
+        for path in &config_paths {
+            if let Ok(content) = fs::read_to_string(path) {
+                config_content = Some(content);
+                found_path = Some(*path);
+                println!("🔍 Upload Config: Found config at {}", path);
+                break;
+            }
+        }
+        
+        // A target set from the settings window is enough without config.toml
+        let target = target::configured();
+        let text = match config_content {
+            Some(text) => text,
+            None if target.bucket.is_some() => String::new(),
+            None => return Err(anyhow!("config.toml not found in any expected location")),
+        };
+        let mut cfg: UploadConfig = toml::from_str(&text).context("parsing config.toml")?;
+        target::apply(&mut cfg, &target);
+        if cfg.device_id.is_empty() {
+            cfg.device_id = target::default_device_id();
+        }
+        if cfg.watch_dir.is_empty() {
+            cfg.watch_dir = crate::conversation_memory::memory_dir().to_string_lossy().to_string();
+        }
+        
+        // Resolve relative paths to absolute paths
+        if !cfg.watch_dir.starts_with("C:") && !cfg.watch_dir.starts_with("/") {
+            // Always resolve watch_dir relative to project root (one level up from where config.toml was found)
+            let project_root = match found_path {
+                Some("config.toml") => std::env::current_dir()?.join(".."),
+                Some("../config.toml") => std::env::current_dir()?.join("..").join(".."),
+                Some("../../config.toml") => std::env::current_dir()?.join("..").join("..").join(".."),
+                _ => std::env::current_dir()?.join(".."),
+            };
+            
+            // Resolve the watch_dir relative to the project root
+            let resolved_path = project_root.join(&cfg.watch_dir);
+            
+            // Canonicalize the path if possible, otherwise use the joined path
+            let final_path = if let Ok(canonical) = resolved_path.canonicalize() {
+                canonical
+            } else {
+                resolved_path
+            };
+            
+            cfg.watch_dir = final_path.to_string_lossy().to_string();
+            println!("🔍 Upload Config: Project root: {}", project_root.display());
+            println!("🔍 Upload Config: Resolved watch_dir to: {}", cfg.watch_dir);
+        }
+        
+        if cfg.scan_interval_secs.is_none() { cfg.scan_interval_secs = Some(60); }
+        if cfg.concurrency.is_none() { cfg.concurrency = Some(2); }
+        if cfg.multipart_threshold_mb.is_none() { cfg.multipart_threshold_mb = Some(64); }
+        if cfg.part_size_mb.is_none() { cfg.part_size_mb = Some(8); }
+        if cfg.part_concurrency.is_none() { cfg.part_concurrency = Some(4); }
+        // Secrets not in config.toml come from the keychain (see set_cloud_credentials), then
+        // the environment
+        let secret = |name: &str, var: &str| credentials::stored(name).or_else(|| std::env::var(var).ok());
+        if cfg.provider == ProviderKind::Gcs {
+            // The keychain holds the key itself rather than a path to it
+            if cfg.credentials_file.is_none() && credentials::stored("gcs_service_account").is_none() {
+                cfg.credentials_file = std::env::var("GOOGLE_APPLICATION_CREDENTIALS").ok();
+                if cfg.credentials_file.is_none() {
+                    return Err(anyhow!("provider = \"gcs\" needs credentials_file or a key saved with set_cloud_credentials"));
+                }
+            }
+            if cfg.bucket.is_none() {
+                return Err(anyhow!("provider = \"gcs\" needs bucket"));
+            }
+        } else if cfg.provider == ProviderKind::Azure {
+            if cfg.account.is_none() { cfg.account = std::env::var("AZURE_STORAGE_ACCOUNT").ok(); }
+            if cfg.sas_token.is_none() && cfg.account_key.is_none() {
+                cfg.sas_token = secret("azure_sas_token", "AZURE_STORAGE_SAS_TOKEN");
+                cfg.account_key = secret("azure_account_key", "AZURE_STORAGE_KEY");
+            }
+            if cfg.bucket.is_none() || cfg.account.is_none() || (cfg.sas_token.is_none() && cfg.account_key.is_none()) {
+                return Err(anyhow!("provider = \"azure\" needs bucket, account and either sas_token or account_key"));
+            }
+        } else if cfg.provider == ProviderKind::Dropbox {
+            if !crate::dropbox_oauth::is_dropbox_connected().unwrap_or(false) {
+                return Err(anyhow!("provider = \"dropbox\" needs Dropbox connected with connect_dropbox"));
+            }
+        } else if cfg.endpoint.is_some() || cfg.aws_profile.is_some() || (cfg.api_url.is_empty() && cfg.bucket.is_some()) {
+            // An explicit profile wins; otherwise keys, and failing those the default profile
+            if cfg.aws_profile.is_none() && cfg.access_key_id.is_none() && cfg.secret_access_key.is_none() {
+                cfg.access_key_id = secret("s3_access_key_id", "AWS_ACCESS_KEY_ID");
+                cfg.secret_access_key = secret("s3_secret_access_key", "AWS_SECRET_ACCESS_KEY");
+                if cfg.access_key_id.is_none() || cfg.secret_access_key.is_none() {
+                    cfg.aws_profile = Some(std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string()));
+                }
+            }
+            if cfg.region.is_none() {
+                cfg.region = cfg.aws_profile.as_deref().and_then(credentials::profile_region);
+            }
+            if cfg.endpoint.is_none() {
+                // Amazon S3 itself, in the bucket's region
+                let region = cfg.region.get_or_insert_with(|| "us-east-1".to_string());
+                cfg.endpoint = Some(format!("https://s3.{}.amazonaws.com", region));
+                if cfg.path_style.is_none() { cfg.path_style = Some(false); }
+            }
+            // Custom endpoints are nearly always addressed by path
+            if cfg.path_style.is_none() { cfg.path_style = Some(true); }
+            println!(
+                "🔍 Upload Config: Uploading straight to {} (bucket {}, region {}, credentials from {})",
+                cfg.endpoint.as_deref().unwrap_or_default(),
+                cfg.bucket.as_deref().unwrap_or_default(),
+                cfg.region.as_deref().unwrap_or("us-east-1"),
+                cfg.aws_profile.as_deref().map_or("access keys".to_string(), |p| format!("AWS profile {}", p))
+            );
+        } else if cfg.api_url.is_empty() {
+            return Err(anyhow!("config.toml needs either api_url or endpoint"));
+        }
+        Ok(cfg)
+    }
+
+    // Where this device's objects go: <prefix or "uploads">/<device_id>/
+    fn device_prefix(&self) -> String {
+        format!("{}/{}/", self.prefix.as_deref().unwrap_or("uploads"), self.device_id)
+    }
+
+    fn multipart_threshold(&self) -> u64 {
+        self.multipart_threshold_mb.unwrap_or(64) * 1024 * 1024
+    }
+
+    // Part size for a file, raised when needed to stay within S3's limits (GCS rounds it further)
+    fn part_size(&self, len: u64) -> u64 {
+        let configured = (self.part_size_mb.unwrap_or(8) * 1024 * 1024).max(MIN_PART_SIZE);
+        configured.max(len.div_ceil(MAX_PARTS))
+    }
+}
+
+// -------- storage providers --------
+
+// A place the uploader sends scrubbed files to
+trait StorageProvider: Send + Sync {
+    // Short name for logs and the PII audit log, e.g. "aws"
+    fn name(&self) -> &'static str;
+
+    // Upload a file's (scrubbed) bytes and return the object's key. Large files are sent in
+    // pieces, with progress saved next to `path` so a restart resumes. Request bodies count
+    // their bytes into `transfer`.
+    fn upload(&self, client: &Client, cfg: &UploadConfig, path: &Path, filename: &str, bytes: &[u8], transfer: &Transfer) -> Result<String>;
+
+    // Remove an uploaded object; one that is already gone is fine
+    fn delete(&self, client: &Client, cfg: &UploadConfig, key: &str) -> Result<()>;
+
+    // Where an object lives, for logs, e.g. s3://bucket/key
+    fn location(&self, cfg: &UploadConfig, key: &str) -> String;
+
+    // Keys of the objects under `prefix`, across every page of the listing
+    fn list(&self, client: &Client, cfg: &UploadConfig, prefix: &str) -> Result<Vec<String>>;
+
+    fn download(&self, client: &Client, cfg: &UploadConfig, key: &str) -> Result<Vec<u8>>;
+
+    // Write a small object at a fixed key, replacing what was there (e.g. the manifest)
+    fn put(&self, client: &Client, cfg: &UploadConfig, key: &str, bytes: &[u8]) -> Result<()>;
+}
+
+fn provider_for(cfg: &UploadConfig) -> Result<Arc<dyn StorageProvider>> {
+    Ok(match cfg.provider {
+        ProviderKind::S3 => Arc::new(s3::S3Provider),
+        ProviderKind::Gcs => Arc::new(gcs::GcsProvider::new(cfg)?),
+        ProviderKind::Azure => Arc::new(azure::AzureProvider::new(cfg)?),
+        ProviderKind::Dropbox => Arc::new(dropbox::DropboxProvider),
+    })
+}
+
+// -------- resumable upload state --------
+
+// Same layout the presigner Lambda uses: uploads/<device>/<time>_<uuid>_<filename>, with
+// `device_prefix` standing for uploads/<device>/
+fn object_key(device_prefix: &str, filename: &str) -> String {
+    format!(
+        "{}{}_{}_{}",
+        device_prefix,
+        Utc::now().format("%Y-%m-%dT%H-%M-%SZ"),
+        uuid::Uuid::new_v4(),
+        filename
+    )
+}
+
+// Progress of a large upload is saved next to the file as `<name>.upload`
+fn state_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".upload");
+    path.with_file_name(name)
+}
+
+fn load_state<T: DeserializeOwned>(state_file: &Path) -> Option<T> {
+    let text = fs::read_to_string(state_file).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+// Written to a temp file and renamed, so quitting mid-write never leaves a torn state
+fn save_state<T: Serialize>(state_file: &Path, state: &T) -> Result<()> {
+    let tmp = state_file.with_extension("upload.tmp");
+    fs::write(&tmp, serde_json::to_vec(state)?)?;
+    fs::rename(&tmp, state_file)?;
+    Ok(())
+}
+
+// -------- helpers --------
+
+fn is_complete_json(path: &Path) -> bool {
+    // Only pick *.json files (not *.tmp or already-synced files)
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        return false;
+    }
+    if path.file_name().and_then(|n| n.to_str()).map(|s| s.ends_with(".synced")).unwrap_or(false) {
+        return false;
+    }
+    true
+}
+
+fn mark_synced(path: &Path) -> Result<()> {
+    mark_with_suffix(path, "synced")
+}
+
+// Kept out of later scans, but left in place for the user to inspect
+fn mark_blocked(path: &Path) -> Result<()> {
+    mark_with_suffix(path, "blocked")
+}
+
+fn mark_with_suffix(path: &Path, suffix: &str) -> Result<()> {
+    let mut new_path = path.to_path_buf();
+    // change foo.json -> foo.json.<suffix>
+    let new_name = format!(
+        "{}.{}",
+        path.file_name().unwrap().to_string_lossy(),
+        suffix
+    );
+    new_path.set_file_name(new_name);
+    // prefer atomic rename; fallback to copy+delete if cross-device
+    if fs::rename(path, &new_path).is_err() {
+        fs::copy(path, &new_path)?;
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn read_all_bytes(path: &Path) -> Result<Vec<u8>> {
+    // If the producer writes atomically (tmp+rename), this just works.
+    // If not, you can add a small sleep or check size-stability.
+    let mut f = fs::File::open(path)?;
+    let mut buf = Vec::new();
+    f.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+// -------- request signing --------
+
+// RFC 3986 percent-encoding, as request signatures expect it; `/` is kept in object paths
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> ring::hmac::Tag {
+    ring::hmac::sign(&ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key), data)
+}
+
+// SigV4 query-string signing: a URL good for `expires` seconds that performs `method` on
+// scheme://host/path. `path` is already URI-encoded; only the host header is signed.
+#[allow(clippy::too_many_arguments)]
+fn sigv4_presign(
+    creds: &credentials::AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    scheme: &str,
+    host: &str,
+    path: &str,
+    query: &[(&str, String)],
+    now: DateTime<Utc>,
+    expires: u64,
+) -> String {
+    let date = now.format("%Y%m%d").to_string();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+
+    let mut params: Vec<(String, String)> = vec![
+        ("X-Amz-Algorithm".to_string(), "AWS4-HMAC-SHA256".to_string()),
+        ("X-Amz-Credential".to_string(), format!("{}/{}", creds.access_key_id, scope)),
+        ("X-Amz-Date".to_string(), timestamp.clone()),
+        ("X-Amz-Expires".to_string(), expires.to_string()),
+        ("X-Amz-SignedHeaders".to_string(), "host".to_string()),
+    ];
+    if let Some(token) = &creds.session_token {
+        params.push(("X-Amz-Security-Token".to_string(), token.clone()));
+    }
+    params.extend(query.iter().map(|(k, v)| (k.to_string(), v.clone())));
+    let mut params: Vec<String> = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", uri_encode(k, true), uri_encode(v, true)))
+        .collect();
+    params.sort();
+    let query = params.join("&");
+
+    let canonical = format!("{}\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD", method, path, query, host);
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+        timestamp,
+        scope,
+        Sha256::digest(canonical.as_bytes())
+    );
+    let mut key = hmac_sha256(format!("AWS4{}", creds.secret_access_key).as_bytes(), date.as_bytes());
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(key.as_ref(), part.as_bytes());
+    }
+    let signature: String = hmac_sha256(key.as_ref(), to_sign.as_bytes()).as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}://{}{}?{}&X-Amz-Signature={}", scheme, host, path, query, signature)
+}
+
+// Text of the first <tag> element in an XML response
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..end].to_string())
+}
+
+// Every <tag>value</tag> in a listing, with the XML escapes undone
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else { break };
+        let value = rest[..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&");
+        values.push(value);
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+// -------- core upload logic --------
+
+// Byte ranges of the parts of a file
+fn part_ranges(len: u64, part_size: u64) -> Vec<Range<usize>> {
+    (0..len.div_ceil(part_size))
+        .map(|i| (i * part_size) as usize..((i + 1) * part_size).min(len) as usize)
+        .collect()
+}
+
+// Send parts on up to `workers` threads, each part retried on its own so a dropped connection
+// costs one part instead of the whole file. `done` records a part as soon as it's sent; the first
+// part that keeps failing stops the rest. Returns how many parts were sent.
+fn send_parts<T, S, D>(pending: &[usize], workers: usize, filename: &str, send: S, done: D) -> usize
+where
+    S: Fn(usize) -> Result<T> + Sync,
+    D: Fn(usize, T) + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let sent = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, pending.len().max(1)) {
+            scope.spawn(|| loop {
+                let n = next.fetch_add(1, Ordering::SeqCst);
+                let Some(&i) = pending.get(n) else {
+                    break;
+                };
+                let mut value = None;
+                let result = retry(
+                    || {
+                        value = Some(send(i)?);
+                        Ok(())
+                    },
+                    5,
+                    700,
+                );
+                match (result, value) {
+                    (Ok(()), Some(value)) => {
+                        sent.fetch_add(1, Ordering::SeqCst);
+                        done(i, value);
+                    }
+                    (result, _) => {
+                        eprintln!("⚠️  part {} of {} failed: {:?}", i + 1, filename, result.err());
+                        next.store(pending.len(), Ordering::SeqCst); // stop the other workers
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    sent.into_inner()
+}
+
+// Exponential backoff helper
+fn retry<F>(mut f: F, attempts: usize, base_delay_ms: u64) -> Result<()>
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut delay = base_delay_ms;
+    for i in 0..attempts {
+        match f() {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                eprintln!("attempt {}/{} failed: {e:?}", i + 1, attempts);
+                if i + 1 == attempts { break; }
+                thread::sleep(Duration::from_millis(delay));
+                delay = (delay as f64 * 1.8).min(30_000.0) as u64; // cap ~30s
+            }
+        }
+    }
+    Err(anyhow!("all {} attempts failed", attempts))
+}
+
+// Upload one file, counting it into `run`; returns the bytes sent, or None when the file
+// is held for later
+fn process_file(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider, reporter: &Reporter, run: &mut history::Run, path: &Path) -> Result<Option<u64>> {
+    let filename = path.file_name().unwrap().to_string_lossy().to_string();
+
+    // The file stays unsynced, so a later scan picks it up once the connection changes
+    let result = if cfg.pause_on_metered && crate::network::is_metered() {
+        println!("⏸️  metered connection, holding {} for later", filename);
+        reporter.paused();
+        reporter.dropped();
+        Ok(None)
+    } else {
+        let sent = send_file(client, cfg, provider, reporter, path, &filename).map(Some);
+        reporter.finish(sent.is_ok());
+        sent
+    };
+    run.add(&filename, &result);
+    result
+}
+
+// A file's content as it would go out before scrubbing, and how many attachments the
+// filters left out of it
+fn filtered_bytes(path: &Path) -> Result<(Vec<u8>, usize)> {
+    let bytes = read_all_bytes(path).context("reading file before upload")?;
+    filters::prune(&filters::load(), bytes)
+}
+
+// Conversation exports in the watch dir waiting to be uploaded, and those the filters leave
+// out (which stay unsynced, so changing the filters later picks them up)
+fn pending_files(watch_dir: &str, filters: &crate::settings::UploadFilters) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut files = Vec::new();
+    let mut filtered = Vec::new();
+    for entry in WalkDir::new(watch_dir).max_depth(1) {
+        let entry = match entry { Ok(e) => e, Err(_) => continue };
+        let p = entry.path().to_path_buf();
+        if p.is_file() && is_complete_json(&p) {
+            if filters::admits(filters, &p) {
+                files.push(p);
+            } else {
+                filtered.push(p);
+            }
+        }
+    }
+    (files, filtered)
+}
+
+// Returns the bytes sent
+fn send_file(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider, reporter: &Reporter, path: &Path, filename: &str) -> Result<u64> {
+    // 1) read bytes, leave out the attachments the filters exclude, and scrub the rest per
+    //    the upload policy, before asking to upload
+    let (bytes, left_out) = filtered_bytes(path)?;
+    if left_out > 0 {
+        println!("🔍 Cloud Uploader: leaving {} attachment(s) of {} out of the upload", left_out, filename);
+    }
+    let (bytes, report) = match crate::pii_scrubber::scrub_upload(bytes) {
+        Ok(scrubbed) => scrubbed,
+        Err(e) => {
+            mark_blocked(path)?;
+            return Err(anyhow!("not uploading {}: {}", filename, e));
+        }
+    };
+    let source = format!("{}_upload", provider.name());
+    crate::pii_audit::record(&source, Some(format!("{}:{}", provider.name(), filename)), &report);
+
+    // 2) compress, upload and record them
+    let (location, sent) = store(client, cfg, provider, reporter, path, filename, bytes)?;
+
+    // 3) mark local file as synced
+    mark_synced(path)?;
+
+    println!("✅ uploaded: {}  →  {}", filename, location);
+    Ok(sent)
+}
+
+// Compress scrubbed bytes and hand them to the storage provider, then remember the object,
+// so deleting the files it carries can replace it. The caller publishes the manifest once it
+// is done. Returns where it went and the bytes sent.
+fn store(client: &Client, cfg: &UploadConfig, provider: &dyn StorageProvider, reporter: &Reporter, path: &Path, filename: &str, bytes: Vec<u8>) -> Result<(String, u64)> {
+    let (bytes, object_name) = compression::pack(cfg, bytes, filename);
+    let transfer = reporter.start(filename, bytes.len() as u64);
+    let key = provider.upload(client, cfg, path, &object_name, &bytes, &transfer)?;
+
+    let location = provider.location(cfg, &key);
+    let entry = ledger::new_entry(provider.name(), key, filename, &bytes);
+    if let Err(e) = ledger::record(cfg, entry) {
+        eprintln!("⚠️  failed to record upload of {}: {e:?}", filename);
+    }
+    Ok((location, bytes.len() as u64))
+}
+
+// -------- public interface --------
+
+pub struct CloudUploader {
+    config: UploadConfig,
+    client: Client,
+    provider: Arc<dyn StorageProvider>,
+    reporter: Reporter,
+    gate: Arc<Gate>,
+}
+
+impl CloudUploader {
+    pub fn new(reporter: Reporter) -> Result<Self> {
+        let config = UploadConfig::load()?;
+        let provider = provider_for(&config)?;
+        throttle::configure(config.max_upload_kb_per_sec);
+        fs::create_dir_all(&config.watch_dir).ok();
+
+        // HTTP client with sensible timeouts
+        let client = Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .context("building http client")?;
+
+        Ok(Self { config, client, provider, reporter, gate: Arc::new(Gate::default()) })
+    }
+
+    pub fn scan_and_upload(&self) -> Result<()> {
+        println!("🔍 Cloud Uploader: Starting scan of directory: {}", self.config.watch_dir);
+        
+        // gather candidate files
+        let (files, filtered) = pending_files(&self.config.watch_dir, &filters::load());
+        for p in &filtered {
+            println!("🔍 Cloud Uploader: Filtered out: {}", p.display());
+        }
+        for p in &files {
+            println!("🔍 Cloud Uploader: Found file: {}", p.display());
+        }
+
+        self.reporter.queued(files.len());
+        if !files.is_empty() {
+            println!("🔍 Cloud Uploader: Found {} file(s) to upload", files.len());
+        } else {
+            println!("🔍 Cloud Uploader: No files found to upload");
+        }
+
+        // process files sequentially for now (can be made parallel later)
+        let mut run = history::Run::start("scan");
+        let count = files.len();
+        let mut uploaded = 0;
+        for (i, p) in files.into_iter().enumerate() {
+            // Paused or stopped mid-scan; the rest wait for the next one
+            if !self.gate.is_open() {
+                println!("⏸️  Cloud Uploader: Sync paused, leaving {} file(s) for later", count - i);
+                for _ in i..count {
+                    self.reporter.dropped();
+                }
+                break;
+            }
+            // Check if file still exists and is still a valid JSON (not already processed)
+            if p.exists() && is_complete_json(&p) {
+                match process_file(&self.client, &self.config, self.provider.as_ref(), &self.reporter, &mut run, &p) {
+                    Ok(sent) => uploaded += sent.is_some() as usize,
+                    Err(e) => eprintln!("⚠️  failed processing {}: {e:?}", p.display()),
+                }
+            } else {
+                println!("🔍 Cloud Uploader: Skipping file (no longer valid): {}", p.display());
+                self.reporter.dropped();
+            }
+        }
+        run.finish(&self.config);
+
+        // remote copies whose deletion failed before; a failure here still leaves the
+        // uploads above to be published
+        let mut deleted = false;
+        if self.gate.is_open() {
+            match ledger::retry_pending_deletes(&self.client, &self.config, self.provider.as_ref()) {
+                Ok(count) => deleted = count > 0,
+                Err(e) => {
+                    // Some deletions may have gone through before it failed
+                    eprintln!("⚠️  Cloud Uploader: failed to retry remote deletions: {e:?}");
+                    deleted = true;
+                }
+            }
+        }
+
+        // One manifest for the whole scan, and none when it changed nothing
+        if uploaded > 0 || deleted {
+            manifest::publish(&self.client, &self.config, self.provider.as_ref());
+        }
+        Ok(())
+    }
+
+    /// Watch for new files and rescan periodically; progress goes to `reporter`. `gate` holds
+    /// uploads while paused, and `control` wakes the scan thread (see CloudSync).
+    fn start_background_uploader(reporter: Reporter, gate: Arc<Gate>, control: Receiver<Control>) -> Result<()> {
+        let uploader = CloudUploader { gate, ..CloudUploader::new(reporter)? };
+        let scan_secs = uploader.config.scan_interval_secs.unwrap_or(60);
+        let watch_dir = uploader.config.watch_dir.clone();
+        let config = uploader.config.clone();
+        let client = uploader.client.clone();
+        let provider = uploader.provider.clone();
+        let reporter = uploader.reporter.clone();
+        let gate = uploader.gate.clone();
+
+        // Start file watcher thread
+        std::thread::spawn(move || {
+            println!("🔍 Cloud Uploader: File watcher thread started");
+            
+            // Track currently processing files to prevent duplicates
+            let processing_files: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+            
+            // Create file watcher
+            let (tx, rx) = channel();
+            let mut watcher: RecommendedWatcher = match notify::recommended_watcher(move |res| {
+                let _ = tx.send(res);
+            }) {
+                Ok(w) => w,
+                Err(e) => {
+                    eprintln!("⚠️  Failed to create file watcher: {}", e);
+                    return;
+                }
+            };
+            
+            // Watch the memory directory
+            if let Err(e) = watcher.watch(Path::new(&watch_dir), RecursiveMode::NonRecursive) {
+                eprintln!("⚠️  Failed to watch directory {}: {}", watch_dir, e);
+                return;
+            }
+            
+            println!("🔍 Cloud Uploader: Watching directory: {}", watch_dir);
+            
+            // Event loop for file changes; wakes every second to notice a stop
+            loop {
+                if gate.is_stopped() {
+                    println!("🔍 Cloud Uploader: File watcher stopped");
+                    break;
+                }
+                match rx.recv_timeout(Duration::from_secs(1)) {
+                    // Paused: the files stay unsynced, and the scan after resuming takes them
+                    Ok(Ok(_)) if gate.is_paused() => {}
+                    Ok(Ok(event)) => {
+                        match event.kind {
+                            EventKind::Create(_) | EventKind::Modify(_) => {
+                                for path in event.paths {
+                                    if is_complete_json(&path) && filters::admits(&filters::load(), &path) {
+                                        let path_buf = PathBuf::from(&path);
+                                        
+                                        // Check if file is already being processed
+                                        {
+                                            let mut processing = processing_files.lock().unwrap();
+                                            if processing.contains(&path_buf) {
+                                                println!("🔍 Cloud Uploader: Skipping already processing file: {}", path_buf.display());
+                                                continue;
+                                            }
+                                            // Mark file as being processed
+                                            processing.insert(path_buf.clone());
+                                        }
+                                        
+                                        println!("🔍 Cloud Uploader: File event detected: {}", path_buf.display());
+                                        reporter.queued(1);
+                                        
+                                        // Small delay to ensure file is fully written
+                                        thread::sleep(Duration::from_millis(150));
+                                        
+                                        // Double-check file still exists and is valid before processing
+                                        if !path_buf.exists() || !is_complete_json(&path_buf) {
+                                            println!("🔍 Cloud Uploader: File no longer valid, skipping: {}", path_buf.display());
+                                            reporter.dropped();
+                                            // Remove from processing set
+                                            {
+                                                let mut processing = processing_files.lock().unwrap();
+                                                processing.remove(&path_buf);
+                                            }
+                                            continue;
+                                        }
+                                        
+                                        // Process the file
+                                        let mut run = history::Run::start("watch");
+                                        match process_file(&client, &config, provider.as_ref(), &reporter, &mut run, &path_buf) {
+                                            Ok(Some(_)) => manifest::publish(&client, &config, provider.as_ref()),
+                                            Ok(None) => {}
+                                            Err(e) => eprintln!("⚠️  Event-triggered upload failed: {}", e),
+                                        }
+                                        run.finish(&config);
+                                        
+                                        // Remove file from processing set
+                                        {
+                                            let mut processing = processing_files.lock().unwrap();
+                                            processing.remove(&path_buf);
+                                        }
+                                    }
+                                }
+                            }
+                            _ => {} // Ignore other events
+                        }
+                    }
+                    Ok(Err(e)) => eprintln!("⚠️  File watcher error: {}", e),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => {
+                        eprintln!("⚠️  File watcher channel closed");
+                        break;
+                    }
+                }
+            }
+        });
+
+        // Start periodic scan thread (fallback)
+        std::thread::spawn(move || {
+            println!("🔍 Cloud Uploader: Background scan thread started, scanning every {} seconds", scan_secs);
+            loop {
+                if uploader.gate.is_open() {
+                    println!("🔍 Cloud Uploader: Starting scan cycle...");
+                    if let Err(e) = uploader.scan_and_upload() {
+                        eprintln!("⚠️  Cloud Uploader error: {e:?}");
+                    }
+                    println!("🔍 Cloud Uploader: Scan cycle completed, sleeping for {} seconds", scan_secs);
+                }
+                // Sleep until the next scan, or until told otherwise
+                match control.recv_timeout(Duration::from_secs(scan_secs)) {
+                    Ok(Control::Resume) | Err(RecvTimeoutError::Timeout) => {}
+                    Ok(Control::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                        println!("🔍 Cloud Uploader: Background scan thread stopped");
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
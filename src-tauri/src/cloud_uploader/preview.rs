@@ -0,0 +1,100 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{compression, filtered_bytes, filters, ledger, pending_files, provider_for, UploadConfig};
+
+/// A file the next sync would upload
+#[derive(Debug, Serialize, Clone)]
+pub struct PreviewUpload {
+    pub file: String,
+    pub bytes: u64,                    // After filtering, scrubbing and compression: what goes out
+    pub attachments_left_out: usize,   // By the upload filters
+}
+
+/// A remote copy the next sync would delete
+#[derive(Debug, Serialize, Clone)]
+pub struct PreviewDelete {
+    pub key: String,
+    pub location: String,              // e.g. s3://bucket/key
+    pub file_ids: Vec<String>,         // Deleted uploads it carries
+}
+
+/// What the next sync would do, worked out without sending anything
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SyncPreview {
+    pub uploads: Vec<PreviewUpload>,
+    pub deletes: Vec<PreviewDelete>,   // Earlier deletions that failed and are retried
+    pub total_bytes: u64,              // Of the uploads
+    pub filtered_out: Vec<String>,     // Conversations the upload filters leave out
+    pub blocked: Vec<String>,          // "file: reason" for files that can't be read or the PII config refuses
+    pub held_metered: bool,            // Nothing goes out until the connection isn't metered
+}
+
+/// Go through the steps of a scan, up to but not including the transfers
+pub fn preview() -> Result<SyncPreview> {
+    let cfg = UploadConfig::load()?;
+    let (files, filtered) = pending_files(&cfg.watch_dir, &filters::load());
+    let name = |p: &std::path::Path| p.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+    let mut preview = SyncPreview {
+        filtered_out: filtered.iter().map(|p| name(p)).collect(),
+        held_metered: cfg.pause_on_metered && crate::network::is_metered(),
+        ..Default::default()
+    };
+    for path in files {
+        let file = name(&path);
+        let scrubbed = filtered_bytes(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|(bytes, left_out)| crate::pii_scrubber::scrub_upload(bytes).map(|(bytes, _)| (bytes, left_out)));
+        match scrubbed {
+            Ok((bytes, attachments_left_out)) => {
+                let (bytes, _) = compression::pack(&cfg, bytes, &file);
+                preview.total_bytes += bytes.len() as u64;
+                preview.uploads.push(PreviewUpload { file, bytes: bytes.len() as u64, attachments_left_out });
+            }
+            Err(e) => preview.blocked.push(format!("{}: {}", file, e)),
+        }
+    }
+
+    if !ledger::keeps_remote_copies() {
+        let provider = provider_for(&cfg)?;
+        preview.deletes = ledger::pending_deletes(&cfg)
+            .into_iter()
+            .filter(|o| o.provider == provider.name())
+            .map(|o| PreviewDelete { location: provider.location(&cfg, &o.key), key: o.key, file_ids: o.file_ids })
+            .collect();
+    }
+    Ok(preview)
+}
+
+/// List what the next sync would upload and delete, and how much it would send, without
+/// transferring anything
+#[tauri::command]
+pub async fn preview_cloud_sync() -> Result<SyncPreview, String> {
+    tauri::async_runtime::spawn_blocking(|| preview().map_err(|e| format!("Failed to preview cloud sync: {}", e)))
+        .await
+        .map_err(|e| format!("Preview task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::UploadFilters;
+    use std::fs;
+
+    #[test]
+    fn test_pending_files_skip_synced_and_filtered() {
+        let dir = std::env::temp_dir().join(format!("preview-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.json"), r#"{"id":"c1","title":"Work"}"#).unwrap();
+        fs::write(dir.join("b.json"), r#"{"id":"c2","title":"Scratch"}"#).unwrap();
+        fs::write(dir.join("c.json.synced"), "{}").unwrap();
+        fs::write(dir.join("d.json.tmp"), "{").unwrap();
+
+        let filters = UploadFilters { exclude_conversations: vec!["scratch".into()], ..Default::default() };
+        let (files, filtered) = super::pending_files(dir.to_str().unwrap(), &filters);
+        let names = |v: Vec<std::path::PathBuf>| v.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(files), vec!["a.json"]);
+        assert_eq!(names(filtered), vec!["b.json"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
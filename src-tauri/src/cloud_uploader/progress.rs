@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Progress events are throttled to this, so fast uploads don't flood the frontend
+const REPORT_EVERY: Duration = Duration::from_millis(250);
+
+/// "cloud-sync:progress": how far the file being uploaded has got
+#[derive(Debug, Serialize, Clone)]
+pub struct UploadProgress {
+    pub file: String,
+    pub bytes_sent: u64,               // Includes parts a resumed upload had already sent
+    pub total: u64,                    // Size after scrubbing
+    pub eta_secs: Option<u64>,         // None until there is a rate to go by
+}
+
+/// "cloud-sync:status": the upload queue as a whole
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SyncStatus {
+    pub state: &'static str,           // idle, uploading, paused, paused_metered, stopped
+    pub current_file: Option<String>,
+    pub queued: usize,                 // Files waiting, the current one included
+    pub uploaded: usize,               // Since the app started
+    pub failed: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum SyncEvent {
+    Progress(UploadProgress),
+    Status(SyncStatus),
+}
+
+impl SyncEvent {
+    /// Tauri event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncEvent::Progress(_) => "cloud-sync:progress",
+            SyncEvent::Status(_) => "cloud-sync:status",
+        }
+    }
+}
+
+type Sink = Arc<dyn Fn(SyncEvent) + Send + Sync>;
+
+/// Keeps the queue status shared by the watcher and the periodic scan, and hands every
+/// change to a sink (usually an emitter)
+#[derive(Clone)]
+pub struct Reporter {
+    sink: Sink,
+    status: Arc<Mutex<SyncStatus>>,
+}
+
+impl Reporter {
+    pub fn new(sink: impl Fn(SyncEvent) + Send + Sync + 'static) -> Self {
+        let status = SyncStatus { state: "idle", ..Default::default() };
+        Self { sink: Arc::new(sink), status: Arc::new(Mutex::new(status)) }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut SyncStatus)) {
+        let status = {
+            let mut status = self.status.lock().unwrap();
+            change(&mut status);
+            status.clone()
+        };
+        (self.sink)(SyncEvent::Status(status));
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub(super) fn set_state(&self, state: &'static str) {
+        self.update(|s| s.state = state);
+    }
+
+    /// `count` more files are waiting
+    pub(super) fn queued(&self, count: usize) {
+        if count > 0 {
+            self.update(|s| s.queued += count);
+        }
+    }
+
+    /// A file left the queue without being sent (gone, or held for later)
+    pub(super) fn dropped(&self) {
+        self.update(|s| s.queued = s.queued.saturating_sub(1));
+    }
+
+    pub(super) fn paused(&self) {
+        self.update(|s| {
+            s.state = "paused_metered";
+            s.current_file = None;
+        });
+    }
+
+    /// Start sending a file of `total` (scrubbed) bytes
+    pub(super) fn start(&self, file: &str, total: u64) -> Transfer {
+        self.update(|s| {
+            s.state = "uploading";
+            s.current_file = Some(file.to_string());
+        });
+        let transfer = Transfer(Arc::new(TransferInner {
+            file: file.to_string(),
+            total,
+            started: Instant::now(),
+            sent: AtomicU64::new(0),
+            resumed: AtomicU64::new(0),
+            last_report: Mutex::new(None),
+            sink: self.sink.clone(),
+        }));
+        transfer.report(true);
+        transfer
+    }
+
+    pub(super) fn finish(&self, ok: bool) {
+        self.update(|s| {
+            s.queued = s.queued.saturating_sub(1);
+            if ok { s.uploaded += 1 } else { s.failed += 1 }
+            s.current_file = None;
+            // A pause or stop that came in mid-file stays
+            if s.queued == 0 && s.state == "uploading" {
+                s.state = "idle";
+            }
+        });
+    }
+}
+
+struct TransferInner {
+    file: String,
+    total: u64,
+    started: Instant,
+    sent: AtomicU64,
+    resumed: AtomicU64,                // Already stored when this run started; not part of the rate
+    last_report: Mutex<Option<Instant>>,
+    sink: Sink,
+}
+
+/// One file's upload; request bodies count their bytes into it as they go out
+#[derive(Clone)]
+pub(super) struct Transfer(Arc<TransferInner>);
+
+impl Transfer {
+    /// Bytes a resumed upload had stored before this run
+    pub(super) fn resumed(&self, bytes: u64) {
+        self.0.resumed.store(bytes, Ordering::Relaxed);
+        self.0.sent.store(bytes, Ordering::Relaxed);
+        self.report(true);
+    }
+
+    /// Bytes the server says it holds, correcting for anything sent by a failed request
+    pub(super) fn confirmed(&self, bytes: u64) {
+        self.0.sent.store(bytes, Ordering::Relaxed);
+        self.report(false);
+    }
+
+    pub(super) fn add(&self, bytes: usize) {
+        self.0.sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.report(false);
+    }
+
+    pub(super) fn progress(&self) -> UploadProgress {
+        let inner = &self.0;
+        // A retried part is counted again until the next confirmation
+        let sent = inner.sent.load(Ordering::Relaxed).min(inner.total);
+        let this_run = sent.saturating_sub(inner.resumed.load(Ordering::Relaxed));
+        let elapsed = inner.started.elapsed().as_secs_f64();
+        let eta_secs = (this_run > 0 && elapsed >= 1.0)
+            .then(|| ((inner.total - sent) as f64 / (this_run as f64 / elapsed)).ceil() as u64);
+        UploadProgress { file: inner.file.clone(), bytes_sent: sent, total: inner.total, eta_secs }
+    }
+
+    fn report(&self, force: bool) {
+        {
+            let mut last = self.0.last_report.lock().unwrap();
+            let done = self.0.sent.load(Ordering::Relaxed) >= self.0.total;
+            if !force && !done && last.is_some_and(|at| at.elapsed() < REPORT_EVERY) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        (self.0.sink)(SyncEvent::Progress(self.progress()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_and_queue_events() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let reporter = Reporter::new(move |e| seen.lock().unwrap().push(e));
+        reporter.queued(2);
+        let transfer = reporter.start("a.json", 100);
+        transfer.resumed(40);
+        transfer.add(30);
+        // Counted twice by a retry, then corrected
+        transfer.add(60);
+        assert_eq!(transfer.progress().bytes_sent, 100);
+        transfer.confirmed(70);
+        assert_eq!(transfer.progress().bytes_sent, 70);
+        reporter.finish(true);
+
+        let events = events.lock().unwrap();
+        let names: Vec<&str> = events.iter().map(|e| e.name()).collect();
+        assert_eq!(names.first(), Some(&"cloud-sync:status"));
+        match events.last() {
+            Some(SyncEvent::Status(s)) => {
+                assert_eq!((s.state, s.queued, s.uploaded), ("uploading", 1, 1));
+                assert_eq!(s.current_file, None);
+            }
+            other => panic!("expected a status event, got {:?}", other),
+        }
+        let json = serde_json::to_value(&events[2]).unwrap();
+        assert_eq!(json["file"], "a.json");
+        assert_eq!(json["total"], 100);
+    }
+}
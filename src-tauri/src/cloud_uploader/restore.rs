@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use reqwest::blocking::Client;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::{fs, time::Duration};
+
+use super::{compression, ledger, manifest, provider_for, StorageProvider, UploadConfig};
+use crate::conversation_memory;
+use crate::file_storage::FileStorage;
+
+/// Outcome of a restore from the cloud
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RestoreReport {
+    pub listed: usize,                 // Objects under the prefix
+    pub restored: usize,               // Conversations written to ./memory
+    pub already_local: usize,          // Conversations this machine uploaded or restored before
+    pub files_restored: usize,         // Attachments added back to ./uploads
+    pub failed: Vec<String>,           // "key: error" per object that could not be restored
+}
+
+// The name a file had before upload: keys are uploads/<device>/<time>_<uuid>_<filename>,
+// with .zst added when compressed
+fn original_name(key: &str) -> &str {
+    let last = key.rsplit('/').next().unwrap_or(key);
+    let last = last.strip_suffix(compression::SUFFIX).unwrap_or(last);
+    let mut parts = last.splitn(3, '_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(_), Some(uuid), Some(name)) if uuid::Uuid::parse_str(uuid).is_ok() => name,
+        _ => last,
+    }
+}
+
+// The name becomes a path under ./memory: one plain component, nothing that climbs out of
+// it or names another drive, on any platform
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':', '\0'])
+}
+
+// The bytes of an attachment as the frontend stores them, with or without a data: URL prefix
+fn decode_attachment(data: &str) -> Result<Vec<u8>> {
+    let data = data.split_once(";base64,").map_or(data, |(_, b64)| b64);
+    base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| anyhow!("decoding attachment: {}", e))
+}
+
+/// Download the conversations under `prefix` (by default this device's uploads) that aren't
+/// here yet, putting their attachments back in the uploads index
+pub fn restore(prefix: Option<&str>) -> Result<RestoreReport> {
+    let cfg = UploadConfig::load()?;
+    let provider = provider_for(&cfg)?;
+    let client = Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .context("building http client")?;
+    let prefix = prefix.map(str::to_string).unwrap_or_else(|| cfg.device_prefix());
+
+    let keys = provider.list(&client, &cfg, &prefix)?;
+    println!("🔍 Cloud Uploader: {} object(s) under {}", keys.len(), provider.location(&cfg, &prefix));
+    let memory = conversation_memory::memory_dir();
+    fs::create_dir_all(&memory)?;
+    let storage = FileStorage::new()?;
+    let mut known_files: HashSet<String> = storage.list_files()?.into_iter().map(|f| f.id).collect();
+    let recorded = ledger::recorded_keys(&cfg);
+
+    let mut report = RestoreReport { listed: keys.len(), ..Default::default() };
+    for key in keys {
+        let filename = original_name(&key);
+        // Only conversation exports are uploaded; anything else in the bucket isn't ours
+        if !filename.ends_with(".json") || manifest::is_manifest(&key) {
+            continue;
+        }
+        if !is_plain_name(filename) {
+            eprintln!("⚠️  not restoring {}: unusable file name", key);
+            report.failed.push(format!("{}: unusable file name {:?}", key, filename));
+            continue;
+        }
+        let synced = memory.join(format!("{}.synced", filename));
+        if recorded.contains(&key) || memory.join(filename).exists() || synced.exists() {
+            report.already_local += 1;
+            continue;
+        }
+        match restore_one(&client, &cfg, provider.as_ref(), &storage, &mut known_files, &key, &synced) {
+            Ok(files) => {
+                println!("⬇️  restored: {}  →  {}", provider.location(&cfg, &key), synced.display());
+                report.restored += 1;
+                report.files_restored += files;
+            }
+            Err(e) => {
+                eprintln!("⚠️  failed to restore {}: {e:?}", key);
+                report.failed.push(format!("{}: {}", key, e));
+            }
+        }
+    }
+    Ok(report)
+}
+
+// Restore one conversation export; returns how many attachments were added back
+fn restore_one(
+    client: &Client,
+    cfg: &UploadConfig,
+    provider: &dyn StorageProvider,
+    storage: &FileStorage,
+    known_files: &mut HashSet<String>,
+    key: &str,
+    dest: &Path,
+) -> Result<usize> {
+    let object = provider.download(client, cfg, key)?;
+    let bytes = compression::unpack(&object)?;
+    let json: serde_json::Value = serde_json::from_slice(&bytes).context("not a conversation export")?;
+    let conversation_id = json["id"].as_str().map(str::to_string);
+
+    // Attachments first, so the conversation's file links resolve once it's back
+    let mut files = 0;
+    let attachments = json["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|m| m["attachedFiles"].as_array().into_iter().flatten());
+    for file in attachments {
+        let (Some(id), Some(data)) = (file["id"].as_str(), file["base64"].as_str()) else {
+            continue;
+        };
+        if known_files.contains(id) {
+            continue;
+        }
+        let name = file["name"].as_str().unwrap_or(id).to_string();
+        let restored = decode_attachment(data).and_then(|data| storage.restore_file(id, data, name, conversation_id.clone()));
+        match restored {
+            Ok(_) => {
+                known_files.insert(id.to_string());
+                files += 1;
+            }
+            Err(e) => eprintln!("⚠️  not restoring attachment {} of {}: {e:?}", id, key),
+        }
+    }
+
+    // Written as already synced, so the uploader doesn't send it back
+    fs::write(dest, &bytes)?;
+    let filename = original_name(key);
+    ledger::record(cfg, ledger::new_entry(provider.name(), key.to_string(), filename, &object))?;
+    conversation_memory::index_in_background(String::from_utf8_lossy(&bytes).into_owned());
+    Ok(files)
+}
+
+/// Download conversations from the bucket that aren't on this machine, e.g. after moving to
+/// a new one. `prefix` defaults to this device's uploads, "uploads/<device_id>/".
+#[tauri::command]
+pub async fn restore_from_cloud(prefix: Option<String>) -> Result<RestoreReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        restore(prefix.as_deref()).map_err(|e| format!("Failed to restore from cloud: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Restore task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_original_name_and_attachments() {
+        let key = "uploads/dev001/2025-01-27T10-30-00Z_0b6f4f36-9d2c-4a8e-9a43-2f0d1c7e5b11_conversation_2025-01-27T10-29-58-123Z.json";
+        assert_eq!(original_name(key), "conversation_2025-01-27T10-29-58-123Z.json");
+        // Not one of ours: kept whole
+        assert_eq!(original_name("backups/notes_v2_final.json"), "notes_v2_final.json");
+        assert_eq!(original_name(&format!("{}.zst", key)), "conversation_2025-01-27T10-29-58-123Z.json");
+
+        // Names from a shared bucket stay inside ./memory
+        assert!(is_plain_name(original_name(key)));
+        assert!(!is_plain_name(original_name("uploads/dev001/..\\..\\startup.json")));
+        assert!(!is_plain_name(".."));
+        assert!(!is_plain_name("C:evil.json"));
+
+        assert_eq!(decode_attachment("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_attachment("data:text/plain;base64,aGVsbG8=").unwrap(), b"hello");
+        assert!(decode_attachment("not base64!").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+
+use super::control::CloudSync;
+use super::UploadConfig;
+use crate::settings::{CloudTarget, Settings};
+
+/// The target saved from the settings window; empty when there is none or settings.json
+/// can't be read
+pub(super) fn configured() -> CloudTarget {
+    Settings::load().map(|s| s.cloud.target).unwrap_or_default()
+}
+
+/// Lay the saved target over config.toml
+pub(super) fn apply(cfg: &mut UploadConfig, target: &CloudTarget) {
+    let fields = [
+        (&mut cfg.bucket, &target.bucket),
+        (&mut cfg.prefix, &target.prefix),
+        (&mut cfg.region, &target.region),
+        (&mut cfg.endpoint, &target.endpoint),
+    ];
+    for (field, value) in fields {
+        if value.is_some() {
+            field.clone_from(value);
+        }
+    }
+}
+
+/// This machine's name, for the device id when config.toml doesn't give one
+pub(super) fn default_device_id() -> String {
+    let name = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .unwrap_or_default();
+    let id: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    if id.is_empty() { "device".to_string() } else { id }
+}
+
+// Blank fields are unset; the rest are checked before they are saved
+fn normalize(bucket: Option<String>, prefix: Option<String>, region: Option<String>, endpoint: Option<String>) -> Result<CloudTarget> {
+    let clean = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let target = CloudTarget {
+        bucket: clean(bucket),
+        prefix: clean(prefix.map(|p| p.trim().trim_matches('/').to_string())),
+        region: clean(region),
+        endpoint: clean(endpoint).map(|e| e.trim_end_matches('/').to_string()),
+    };
+    if let Some(bucket) = &target.bucket {
+        if bucket.contains('/') || bucket.contains(char::is_whitespace) {
+            return Err(anyhow!("bucket name {:?} can't contain slashes or spaces", bucket));
+        }
+    }
+    if let Some(endpoint) = &target.endpoint {
+        let url = reqwest::Url::parse(endpoint).map_err(|e| anyhow!("endpoint {:?} isn't a URL: {}", endpoint, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("endpoint {:?} must be http or https", endpoint));
+        }
+    }
+    Ok(target)
+}
+
+/// Save where backups go and restart the uploader on it. Blank values clear a field, so
+/// config.toml's applies again.
+#[tauri::command]
+pub fn configure_cloud_target(
+    sync: tauri::State<'_, CloudSync>,
+    bucket: Option<String>,
+    prefix: Option<String>,
+    region: Option<String>,
+    endpoint: Option<String>,
+) -> Result<CloudTarget, String> {
+    let target = normalize(bucket, prefix, region, endpoint).map_err(|e| format!("Invalid cloud target: {}", e))?;
+    let saved = Settings::update(|s| s.cloud.target = target)
+        .map(|s| s.cloud.target)
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("🔍 Cloud Uploader: target set to {:?}, restarting", saved);
+    sync.stop().map_err(|e| format!("Failed to stop cloud sync: {}", e))?;
+    sync.resume()
+        .map_err(|e| format!("Saved, but the cloud uploader couldn't start: {}", e))?;
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_overrides_config() {
+        let target = normalize(Some(" backups ".into()), Some("/team/alice/".into()), Some("".into()), Some("http://localhost:9000/".into())).unwrap();
+        assert_eq!(target.bucket.as_deref(), Some("backups"));
+        assert_eq!(target.prefix.as_deref(), Some("team/alice"));
+        assert_eq!(target.region, None);
+        assert_eq!(target.endpoint.as_deref(), Some("http://localhost:9000"));
+        assert!(normalize(Some("a/b".into()), None, None, None).is_err());
+        assert!(normalize(None, None, None, Some("ftp://host".into())).is_err());
+
+        let mut cfg: UploadConfig = toml::from_str("device_id = \"dev001\"\nbucket = \"old\"\nregion = \"eu-west-1\"").unwrap();
+        apply(&mut cfg, &target);
+        assert_eq!(cfg.bucket.as_deref(), Some("backups"));
+        // Unset in the target: config.toml's stays
+        assert_eq!(cfg.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(cfg.device_prefix(), "team/alice/dev001/");
+        assert!(!default_device_id().is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use reqwest::blocking::{Body, RequestBuilder};
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::progress::Transfer;
+
+// Bytes handed to the HTTP client per read; small enough for the pacing to be smooth
+const CHUNK: usize = 16 * 1024;
+
+// Upload rate shared by every transfer in the process, so parallel parts and files split
+// the budget instead of each getting all of it
+static LIMITER: RwLock<Option<Arc<RateLimiter>>> = RwLock::new(None);
+
+struct RateLimiter {
+    bytes_per_sec: u64,
+    next: Mutex<Instant>,        // when the budget is free again
+}
+
+impl RateLimiter {
+    // Reserve the next `n` bytes of budget, sleeping until their slot starts
+    fn take(&self, n: usize) {
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let start = (*next).max(now);
+            *next = start + Duration::from_secs_f64(n as f64 / self.bytes_per_sec as f64);
+            start - now
+        };
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+// A request body that only releases bytes as fast as the limiter allows, counting them
+// toward the file's progress
+struct Throttled {
+    data: Cursor<Vec<u8>>,
+    limiter: Option<Arc<RateLimiter>>,
+    transfer: Option<Transfer>,
+}
+
+impl Read for Throttled {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(CHUNK);
+        let n = self.data.read(&mut buf[..len])?;
+        if n > 0 {
+            if let Some(limiter) = &self.limiter {
+                limiter.take(n);
+            }
+            if let Some(transfer) = &self.transfer {
+                transfer.add(n);
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Set the upload rate limit in KB/s; None or 0 removes it
+pub(super) fn configure(kb_per_sec: Option<u64>) {
+    let bytes_per_sec = kb_per_sec.unwrap_or(0) * 1024;
+    let mut limiter = LIMITER.write().unwrap();
+    // Keep the running limiter when nothing changed so a new uploader doesn't get a fresh burst
+    if limiter.as_ref().map(|l| l.bytes_per_sec) == Some(bytes_per_sec) {
+        return;
+    }
+    *limiter = (bytes_per_sec > 0).then(|| {
+        Arc::new(RateLimiter { bytes_per_sec, next: Mutex::new(Instant::now()) })
+    });
+}
+
+/// Attach `bytes` as the request body, paced by the rate limit and counted into `transfer`,
+/// with `timeout` stretched by however long sending them at that rate takes
+pub(super) fn body(req: RequestBuilder, bytes: Vec<u8>, timeout: Duration, transfer: Option<&Transfer>) -> RequestBuilder {
+    let limiter = LIMITER.read().unwrap().clone();
+    if limiter.is_none() && transfer.is_none() {
+        return req.body(bytes).timeout(timeout);
+    }
+    let len = bytes.len() as u64;
+    // Other transfers share the budget, so allow for twice the time on our own
+    let sending = limiter
+        .as_ref()
+        .map_or(Duration::ZERO, |l| Duration::from_secs_f64(len as f64 / l.bytes_per_sec as f64) * 2);
+    let reader = Throttled { data: Cursor::new(bytes), limiter, transfer: transfer.cloned() };
+    req.body(Body::sized(reader, len)).timeout(timeout + sending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_paces_reads() {
+        let limiter = Arc::new(RateLimiter { bytes_per_sec: 64 * 1024, next: Mutex::new(Instant::now()) });
+        let mut reader = Throttled { data: Cursor::new(vec![7u8; 48 * 1024]), limiter: Some(limiter), transfer: None };
+        let started = Instant::now();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out.len(), 48 * 1024);
+        // Three 16 KiB chunks at 64 KiB/s: the last one may go out after 0.5s
+        assert!(started.elapsed() >= Duration::from_millis(450), "{:?}", started.elapsed());
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::google_oauth::now_ms;
+use crate::{dropbox_oauth, google_oauth, microsoft_oauth, slack_oauth};
+
+const PROVIDERS: &[&str] = &["google", "microsoft", "dropbox", "slack"];
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// A token without a refresh token counts as degraded this long before it runs out
+const EXPIRY_WARNING_MS: u128 = 10 * 60 * 1000;
+
+/// A provider's stored connection, for the settings window
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ConnectionStatus {
+    pub provider: String,
+    pub connected: bool,                   // Tokens stored and still accepted
+    pub account_email: Option<String>,     // When the provider tells us
+    pub scopes: Vec<String>,
+    pub expires_at: Option<String>,        // RFC 3339; None for tokens that don't expire
+    pub last_refresh: Option<String>,      // When the current access token was issued
+    pub can_refresh: bool,                 // A refresh token is kept
+    pub reauth_required: bool,             // The provider refused the grant; connect again
+    #[serde(skip)]
+    pub expires_at_ms: Option<u128>,
+}
+
+impl ConnectionStatus {
+    pub(crate) fn disconnected(provider: &str) -> Self {
+        Self { provider: provider.to_string(), ..Self::default() }
+    }
+}
+
+/// Payload of "connection:degraded"
+#[derive(Serialize, Clone, Debug)]
+pub struct ConnectionDegraded {
+    pub provider: String,
+    pub reason: String,
+    pub status: ConnectionStatus,
+}
+
+/// Milliseconds since the epoch as RFC 3339
+pub(crate) fn rfc3339(ms: u128) -> Option<String> {
+    DateTime::from_timestamp_millis(ms as i64).map(|t| t.to_rfc3339())
+}
+
+pub fn connection_status(app: &AppHandle, provider: &str) -> Result<ConnectionStatus> {
+    match provider.trim().to_lowercase().as_str() {
+        "google" => google_oauth::connection_status(app),
+        "microsoft" => microsoft_oauth::connection_status(),
+        "dropbox" => dropbox_oauth::connection_status(),
+        "slack" => slack_oauth::connection_status(),
+        other => Err(anyhow!("Unknown provider {:?}; expected one of {}", other, PROVIDERS.join(", "))),
+    }
+}
+
+// Why a stored connection needs attention, if it does: a refused grant, a token that ran out
+// without being renewed, or one about to run out that can't be
+fn degradation(status: &ConnectionStatus, now_ms: u128) -> Option<String> {
+    if status.reauth_required {
+        return Some("The provider no longer accepts the sign-in; connect again".to_string());
+    }
+    let expires_at = status.expires_at_ms?;
+    if now_ms >= expires_at {
+        return Some("The access token expired and couldn't be refreshed".to_string());
+    }
+    if !status.can_refresh && now_ms + EXPIRY_WARNING_MS >= expires_at {
+        return Some("The access token expires soon and can't be refreshed; connect again".to_string());
+    }
+    None
+}
+
+/// Check every connection once a minute, emitting "connection:degraded" when one starts to
+/// need attention so the UI can ask for a new sign-in before a request fails
+pub fn start_monitor(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut degraded: HashMap<&str, bool> = HashMap::new();
+        loop {
+            for provider in PROVIDERS {
+                let status = match connection_status(&app, provider) {
+                    Ok(status) => status,
+                    Err(e) => {
+                        eprintln!("[Connections] Couldn't read the {} connection: {}", provider, e);
+                        continue;
+                    }
+                };
+                let reason = degradation(&status, now_ms());
+                let was_degraded = degraded.insert(provider, reason.is_some()).unwrap_or(false);
+                if let Some(reason) = reason.filter(|_| !was_degraded) {
+                    println!("[Connections] {} degraded: {}", provider, reason);
+                    let _ = app.emit("connection:degraded", ConnectionDegraded { provider: provider.to_string(), reason, status });
+                }
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+/// Account, scopes, expiry and last refresh of a connection: "google", "microsoft", "dropbox"
+/// or "slack"
+#[tauri::command]
+pub async fn get_connection_status(app: AppHandle, provider: String) -> Result<ConnectionStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        connection_status(&app, &provider).map_err(|e| format!("Failed to read the {} connection: {}", provider, e))
+    })
+    .await
+    .map_err(|e| format!("Status task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degradation() {
+        let healthy = ConnectionStatus { connected: true, can_refresh: true, expires_at_ms: Some(60_000_000), ..ConnectionStatus::disconnected("google") };
+        assert_eq!(degradation(&healthy, 59_900_000), None);
+        assert!(degradation(&healthy, 60_000_000).is_some());
+        let no_refresh = ConnectionStatus { can_refresh: false, ..healthy.clone() };
+        assert!(degradation(&no_refresh, 59_900_000).is_some());
+        assert!(degradation(&ConnectionStatus { reauth_required: true, ..healthy.clone() }, 0).is_some());
+        // Slack user tokens don't expire
+        assert_eq!(degradation(&ConnectionStatus { expires_at_ms: None, ..no_refresh }, u128::MAX), None);
+        assert_eq!(degradation(&ConnectionStatus::disconnected("slack"), 0), None);
+
+        assert_eq!(rfc3339(1_760_600_000_000).as_deref(), Some("2025-10-16T07:33:20+00:00"));
+    }
+}
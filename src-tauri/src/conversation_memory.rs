@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::chunking;
+use crate::embeddings;
+use crate::file_storage::FileStorage;
+use crate::retrieval_trace::Tracer;
+use crate::settings::Settings;
+use crate::vector_store::{self, Namespace};
+
+/// Where `write_conversation_to_file` keeps scrubbed conversation exports
+pub fn memory_dir() -> PathBuf {
+    Path::new("C:\\Users\\parad\\Downloads\\pluely-master2").join("memory")
+}
+
+/// The frontend's ChatConversation as exported to the memory folder
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Conversation {
+    id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    messages: Vec<Message>,
+    #[serde(default)]
+    updated_at: i64,                   // Milliseconds since the epoch
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    role: String,
+    content: String,
+    #[serde(default)]
+    timestamp: i64,
+}
+
+/// A past conversation related to a query
+#[derive(Debug, Serialize)]
+pub struct RecalledConversation {
+    pub conversation_id: String,
+    pub title: String,
+    pub excerpts: Vec<String>,         // Best-matching parts of the transcript, best first
+    pub score: f32,                    // Of the best excerpt
+}
+
+/// Excerpts returned per conversation
+const EXCERPTS_PER_CONVERSATION: usize = 2;
+
+/// Transcript to chunk: the title as a heading, then a paragraph per message with its
+/// speaker and time, so excerpts say who said what and when
+fn transcript(conversation: &Conversation) -> String {
+    let title = conversation.title.trim();
+    let mut out = format!("# {}\n", if title.is_empty() { "Untitled conversation" } else { title });
+    for message in &conversation.messages {
+        let content = message.content.trim();
+        if message.role == "system" || content.is_empty() {
+            continue;
+        }
+        let speaker = match message.role.as_str() {
+            "user" => "User",
+            "assistant" => "Assistant",
+            other => other,
+        };
+        let when = Local
+            .timestamp_millis_opt(message.timestamp)
+            .single()
+            .filter(|_| message.timestamp > 0)
+            .map(|t| format!(" ({})", t.format("%Y-%m-%d %H:%M")))
+            .unwrap_or_default();
+        out.push_str(&format!("\n{}{}: {}\n", speaker, when, content));
+    }
+    out
+}
+
+fn index(storage: &FileStorage, conversation: &Conversation) -> Result<()> {
+    let config = Settings::load().unwrap_or_default().chunking;
+    let chunks = chunking::chunk_document(
+        &transcript(conversation),
+        config.embedding_tokens,
+        config.overlap_tokens,
+        |t| chunking::count_tokens(None, t),
+    );
+    let _indexing = vector_store::indexing();
+    let mut store = storage.vectors()?;
+    let embedded = embeddings::embed_chunks(&conversation.id, chunks, Some((&store, Namespace::Conversations)))?;
+    let update = store.upsert_file(Namespace::Conversations, &embedded)?;
+    println!(
+        "[Memory] Indexed conversation {}: {} chunks added, {} kept, {} removed",
+        conversation.id, update.added, update.kept, update.removed
+    );
+    FileStorage::reembed_stale(&mut store);
+    Ok(())
+}
+
+/// Embed an exported conversation into the index's conversation namespace, replacing
+/// what was indexed for it before; unchanged messages keep their vectors
+pub fn index_conversation(storage: &FileStorage, json: &str) -> Result<()> {
+    let conversation: Conversation = serde_json::from_str(json).context("parsing conversation")?;
+    index(storage, &conversation)
+}
+
+/// Drop a deleted conversation from the index, so it can't be recalled
+pub fn unindex(storage: &FileStorage, conversation_id: &str) -> Result<usize> {
+    let _indexing = vector_store::indexing();
+    storage.vectors()?.remove_file(Namespace::Conversations, conversation_id)
+}
+
+/// Index a conversation export on a background thread. Best-effort like file indexing:
+/// without a usable embedding model the conversation just isn't recallable.
+pub fn index_in_background(json: String) {
+    std::thread::spawn(move || {
+        let result = FileStorage::new().and_then(|storage| index_conversation(&storage, &json));
+        if let Err(e) = result {
+            println!("[Memory] Not indexing conversation for recall: {}", e);
+        }
+    });
+}
+
+/// Index every conversation in the memory folder. Each conversation is exported many
+/// times as it grows, so only its latest snapshot is used. Returns how many were indexed.
+pub fn index_history(storage: &FileStorage) -> Result<usize> {
+    let dir = memory_dir();
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let mut latest: HashMap<String, Conversation> = HashMap::new();
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let parsed = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(serde_json::from_str::<Conversation>(&json)?));
+        match parsed {
+            Ok(conversation) => {
+                let newer = latest
+                    .get(&conversation.id)
+                    .is_none_or(|seen| conversation.updated_at >= seen.updated_at);
+                if newer {
+                    latest.insert(conversation.id.clone(), conversation);
+                }
+            }
+            Err(e) => println!("[Memory] Skipping {:?}: {}", path, e),
+        }
+    }
+    for conversation in latest.values() {
+        index(storage, conversation)?;
+    }
+    Ok(latest.len())
+}
+
+/// Past conversations most related to `query`, best first, with their matching excerpts
+pub fn recall(storage: &FileStorage, query: &str, limit: usize) -> Result<Vec<RecalledConversation>> {
+    let hits = storage.retrieve_in(Namespace::Conversations, query, limit * 4, &mut Tracer::silent())?;
+    let mut recalled: Vec<RecalledConversation> = Vec::new();
+    for hit in hits {
+        let chunk = hit.chunk;
+        match recalled.iter_mut().find(|r| r.conversation_id == chunk.file_id) {
+            Some(r) if r.excerpts.len() < EXCERPTS_PER_CONVERSATION => r.excerpts.push(chunk.text),
+            Some(_) => {}
+            None => recalled.push(RecalledConversation {
+                conversation_id: chunk.file_id,
+                title: chunk.source.heading_path.first().cloned().unwrap_or_default(),
+                excerpts: vec![chunk.text],
+                score: hit.score,
+            }),
+        }
+    }
+    recalled.truncate(limit);
+    Ok(recalled)
+}
+
+/// Search past conversations for what was discussed about `query`
+#[tauri::command]
+pub async fn recall_related_conversations(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<RecalledConversation>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        recall(&storage, &query, limit.unwrap_or(5))
+            .map_err(|e| format!("Failed to recall conversations: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Recall task failed: {}", e))?
+}
+
+/// Index conversations exported before recall existed (or while no model was available)
+#[tauri::command]
+pub async fn index_conversation_history() -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let storage = FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        index_history(&storage).map_err(|e| format!("Failed to index conversation history: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Conversation indexing task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_rendering() {
+        let conversation: Conversation = serde_json::from_str(
+            r#"{"id": "c1", "title": "Trip planning", "createdAt": 0, "updatedAt": 0, "messages": [
+                {"id": "1", "role": "system", "content": "You are helpful", "timestamp": 0},
+                {"id": "2", "role": "user", "content": "Book the Lisbon hotel", "timestamp": 0},
+                {"id": "3", "role": "assistant", "content": "  Done.  ", "timestamp": 0}
+            ]}"#,
+        )
+        .unwrap();
+        let text = transcript(&conversation);
+        assert_eq!(text, "# Trip planning\n\nUser: Book the Lisbon hotel\n\nAssistant: Done.\n");
+
+        let chunks = chunking::chunk_document(&text, 256, 0, |t| t.split_whitespace().count());
+        assert_eq!(chunks[0].source.heading_path, vec!["Trip planning".to_string()]);
+    }
+}
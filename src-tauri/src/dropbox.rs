@@ -0,0 +1,183 @@
+use anyhow::{anyhow, Result};
+use reqwest::blocking::{Client, Response};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::dropbox_oauth;
+use crate::file_storage::{FileInfo, FileStorage};
+
+pub(crate) const API: &str = "https://api.dropboxapi.com/2";
+pub(crate) const CONTENT: &str = "https://content.dropboxapi.com/2";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_LIST: usize = 100;
+// Larger files are better opened in Dropbox than pulled into context
+const MAX_IMPORT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// A file or folder in the connected Dropbox
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct DropboxEntry {
+    pub id: String,
+    pub name: String,
+    pub path: String,                  // e.g. "/Reports/q3.pdf", as Dropbox displays it
+    pub size: u64,
+    pub modified_time: Option<String>, // None for folders
+    pub is_folder: bool,
+}
+
+/// The Dropbox-API-Arg header for content endpoints. HTTP headers must be ASCII, so anything
+/// else in the JSON is written as \u escapes.
+pub(crate) fn api_arg(arg: &Value) -> String {
+    let mut out = String::new();
+    for c in arg.to_string().chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                out.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    out
+}
+
+/// Whether a Dropbox error reply means the path isn't there, e.g.
+/// {"error_summary": "path/not_found/..", ...}
+pub(crate) fn is_not_found(body: &str) -> bool {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v["error_summary"].as_str().map(|s| s.contains("not_found")))
+        .unwrap_or(false)
+}
+
+/// Fail on a non-success status, carrying Dropbox's reply
+pub(crate) fn check(resp: Response) -> Result<Response> {
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(anyhow!("Dropbox returned {}: {}", status, resp.text().unwrap_or_default().trim()));
+    }
+    Ok(resp)
+}
+
+// Dropbox names the root "" and everything else with a leading '/'
+fn normalize_path(path: Option<&str>) -> String {
+    match path.map(|p| p.trim().trim_end_matches('/')).filter(|p| !p.is_empty()) {
+        Some(p) if p.starts_with('/') || p.starts_with("id:") => p.to_string(),
+        Some(p) => format!("/{}", p),
+        None => String::new(),
+    }
+}
+
+fn rpc(endpoint: &str, arg: &Value) -> Result<Value> {
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let resp = client.post(format!("{}/{}", API, endpoint)).bearer_auth(dropbox_oauth::access_token()?).json(arg).send()?;
+    Ok(check(resp)?.json()?)
+}
+
+fn parse_entry(v: &Value) -> DropboxEntry {
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    DropboxEntry {
+        id: text(&v["id"]),
+        name: text(&v["name"]),
+        path: text(&v["path_display"]),
+        size: v["size"].as_u64().unwrap_or_default(),
+        modified_time: v["server_modified"].as_str().map(|t| t.to_string()),
+        is_folder: v[".tag"] == "folder",
+    }
+}
+
+/// Entries in a folder, or matching a search within it
+pub fn list_entries(folder: Option<&str>, query: Option<&str>) -> Result<Vec<DropboxEntry>> {
+    let folder = normalize_path(folder);
+    let entries: Vec<DropboxEntry> = match query.map(str::trim).filter(|q| !q.is_empty()) {
+        Some(query) => {
+            let mut options = json!({ "max_results": MAX_LIST, "file_status": "active" });
+            if !folder.is_empty() {
+                options["path"] = json!(folder);
+            }
+            let found = rpc("files/search_v2", &json!({ "query": query, "options": options }))?;
+            found["matches"]
+                .as_array()
+                .map(|ms| ms.iter().map(|m| parse_entry(&m["metadata"]["metadata"])).collect())
+                .unwrap_or_default()
+        }
+        None => {
+            let listed = rpc("files/list_folder", &json!({ "path": folder, "limit": MAX_LIST }))?;
+            listed["entries"].as_array().map(|es| es.iter().map(parse_entry).collect()).unwrap_or_default()
+        }
+    };
+    println!("[Dropbox] Listed {} entr{}", entries.len(), if entries.len() == 1 { "y" } else { "ies" });
+    Ok(entries)
+}
+
+/// Download a Dropbox file and store it with the uploads
+pub fn import_entry(path: &str) -> Result<FileInfo> {
+    let path = normalize_path(Some(path));
+    if path.is_empty() {
+        return Err(anyhow!("No file given"));
+    }
+    let entry = parse_entry(&rpc("files/get_metadata", &json!({ "path": path }))?);
+    if entry.is_folder {
+        return Err(anyhow!("{} is a folder", entry.path));
+    }
+    if entry.size > MAX_IMPORT_BYTES {
+        return Err(anyhow!("{} is larger than {} MB", entry.name, MAX_IMPORT_BYTES / 1024 / 1024));
+    }
+    let client = Client::builder().timeout(DOWNLOAD_TIMEOUT).build()?;
+    let resp = client
+        .post(format!("{}/files/download", CONTENT))
+        .bearer_auth(dropbox_oauth::access_token()?)
+        .header("Dropbox-API-Arg", api_arg(&json!({ "path": entry.id })))
+        .send()?;
+    let bytes = check(resp)?.bytes()?;
+    println!("[Dropbox] Downloaded {} ({} bytes)", entry.path, bytes.len());
+    let stored = FileStorage::new()?.import_bytes(&bytes, &entry.name, &format!("dropbox:{}", entry.id))?;
+    println!("[Dropbox] Imported {} as {}", entry.path, stored.id);
+    Ok(stored)
+}
+
+/// Browse the connected Dropbox: a folder's contents, a search, or both
+#[tauri::command]
+pub async fn list_dropbox_files(folder: Option<String>, query: Option<String>) -> Result<Vec<DropboxEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_entries(folder.as_deref(), query.as_deref()).map_err(|e| format!("Failed to list Dropbox files: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Dropbox task failed: {}", e))?
+}
+
+/// Import a Dropbox file (by path or "id:...") into the uploads store
+#[tauri::command]
+pub async fn import_dropbox_file(path: String) -> Result<FileInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        import_entry(&path).map_err(|e| format!("Failed to import Dropbox file: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Dropbox task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_args_and_entries() {
+        assert_eq!(normalize_path(None), "");
+        assert_eq!(normalize_path(Some("/")), "");
+        assert_eq!(normalize_path(Some("Reports/")), "/Reports");
+        assert_eq!(normalize_path(Some("id:a4ayc_80_OEAAAAAAAAAXw")), "id:a4ayc_80_OEAAAAAAAAAXw");
+
+        assert_eq!(api_arg(&json!({ "path": "/Café 📄.txt" })), "{\"path\":\"/Caf\\u00e9 \\ud83d\\udcc4.txt\"}");
+        assert!(is_not_found(r#"{"error_summary": "path/not_found/..", "error": {}}"#));
+        assert!(!is_not_found(r#"{"error_summary": "too_many_write_operations/.."}"#));
+
+        let file = parse_entry(&json!({
+            ".tag": "file", "id": "id:1", "name": "q3.pdf", "path_display": "/Reports/q3.pdf",
+            "size": 4096, "server_modified": "2026-10-01T09:00:00Z"
+        }));
+        assert_eq!((file.path.as_str(), file.size, file.is_folder), ("/Reports/q3.pdf", 4096, false));
+        assert!(parse_entry(&json!({ ".tag": "folder", "name": "Reports" })).is_folder);
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{anyhow, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use std::net::TcpListener;
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::connections::{rfc3339, ConnectionStatus};
+use crate::google_oauth::{
+    generate_pkce_pair, load_dotenv, load_env, now_ms, open_in_browser, redirect_path, redirect_port, wait_for_redirect,
+};
+use crate::oauth_tokens::{token_request, TokenStore, Tokens};
+
+const AUTHORIZE_ENDPOINT: &str = "https://www.dropbox.com/oauth2/authorize";
+const TOKEN_ENDPOINT: &str = "https://api.dropboxapi.com/oauth2/token";
+const REVOKE_ENDPOINT: &str = "https://api.dropboxapi.com/2/auth/token/revoke";
+const ACCOUNT_ENDPOINT: &str = "https://api.dropboxapi.com/2/users/get_current_account";
+// Dropbox matches redirect URIs exactly, port included, so this one must be registered
+// with the app unless DROPBOX_REDIRECT_URI names another
+const DEFAULT_REDIRECT_URI: &str = "http://localhost:53682/";
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const LOST_GRANT: &str = "Dropbox authorization expired; connect Dropbox again";
+
+// The tokens as JSON in the keychain, shared by the background task, the uploader and the
+// file commands
+static STORE: TokenStore = TokenStore::new("agi-dropbox-oauth", "Dropbox", "Dropbox");
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct DropboxTokens {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    account_id: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    email: Option<String>,             // Looked up once when connecting
+    obtained_at_ms: u128,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    reauth_required: bool,             // Dropbox refused the refresh token; only connecting again helps
+}
+
+#[derive(Deserialize)]
+struct TokenResp {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    account_id: Option<String>,
+    scope: Option<String>,
+}
+
+/// Payload of "dropbox-oauth:reauth-required"
+#[derive(Serialize, Clone, Debug)]
+pub struct ReauthRequired {
+    pub reason: String,
+}
+
+fn redirect_uri() -> String {
+    std::env::var("DROPBOX_REDIRECT_URI").ok().filter(|u| !u.trim().is_empty()).unwrap_or_else(|| DEFAULT_REDIRECT_URI.to_string())
+}
+
+fn authorize_url(client_id: &str, redirect_uri: &str, state: &str, code_challenge: &str) -> String {
+    // Which files the token reaches is set by the app's permissions in the Dropbox console
+    format!(
+        "{}?client_id={}&response_type=code&redirect_uri={}&token_access_type=offline&state={}&code_challenge={}&code_challenge_method=S256",
+        AUTHORIZE_ENDPOINT,
+        urlencoding::encode(client_id),
+        urlencoding::encode(redirect_uri),
+        state,
+        code_challenge
+    )
+}
+
+// Dropbox only sends the refresh token with the first exchange; refreshes keep it
+fn from_response(resp: TokenResp, previous: Option<&DropboxTokens>, now_ms: u128) -> DropboxTokens {
+    DropboxTokens {
+        access_token: resp.access_token,
+        refresh_token: resp.refresh_token.or_else(|| previous.and_then(|p| p.refresh_token.clone())),
+        expires_in: resp.expires_in,
+        account_id: resp.account_id.or_else(|| previous.and_then(|p| p.account_id.clone())),
+        scope: resp.scope.or_else(|| previous.and_then(|p| p.scope.clone())),
+        email: previous.and_then(|p| p.email.clone()),
+        obtained_at_ms: now_ms,
+        reauth_required: false,
+    }
+}
+
+impl Tokens for DropboxTokens {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    // Access tokens last four hours
+    fn expires_at_ms(&self) -> u128 {
+        self.obtained_at_ms + self.expires_in.unwrap_or(14_400) as u128 * 1000
+    }
+
+    fn reauth_required(&self) -> bool {
+        self.reauth_required
+    }
+
+    fn flag_reauth(&mut self) {
+        self.reauth_required = true;
+    }
+}
+
+// Trade the refresh token for new tokens, or Dropbox's error body
+fn refresh(tokens: &DropboxTokens, refresh_token: &str) -> Result<std::result::Result<DropboxTokens, String>> {
+    load_dotenv("DropboxRefresh");
+    let client_id = load_env("DROPBOX_CLIENT_ID")?;
+    let form = [
+        ("client_id", client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+    ];
+    Ok(token_request(TOKEN_ENDPOINT, &form)?.map(|resp| from_response(resp, Some(tokens), now_ms())))
+}
+
+/// A current Dropbox access token, refreshing it first if needed. Takes no app handle so
+/// the cloud uploader can call it too; the background task announces a lost grant.
+pub(crate) fn access_token() -> Result<String> {
+    let tokens = STORE.refresh_if_needed(refresh)?.ok_or_else(|| anyhow!("Dropbox is not connected"))?;
+    if tokens.reauth_required {
+        return Err(anyhow!(LOST_GRANT));
+    }
+    Ok(tokens.access_token)
+}
+
+/// Keep the Dropbox tokens fresh in the background, emitting
+/// "dropbox-oauth:reauth-required" once when the grant is lost
+pub fn start_refresh_task(app: tauri::AppHandle) {
+    STORE.start_refresh_task(refresh, move || {
+        let _ = app.emit("dropbox-oauth:reauth-required", ReauthRequired { reason: LOST_GRANT.to_string() });
+    });
+}
+
+// The account's email, for the connection status; best-effort
+fn account_email(access_token: &str) -> Option<String> {
+    let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(30)).build().ok()?;
+    let resp = client.post(ACCOUNT_ENDPOINT).bearer_auth(access_token).json(&serde_json::Value::Null).send().ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let account: serde_json::Value = resp.json().ok()?;
+    account["email"].as_str().map(|e| e.to_string())
+}
+
+fn connect() -> Result<()> {
+    load_dotenv("DropboxConnect");
+    let client_id = load_env("DROPBOX_CLIENT_ID")?;
+
+    let redirect_uri = redirect_uri();
+    let port = redirect_port(&redirect_uri).ok_or_else(|| anyhow!("DROPBOX_REDIRECT_URI needs a port: {}", redirect_uri))?;
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let (code_verifier, code_challenge) = generate_pkce_pair();
+    let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+
+    println!("[Dropbox][Connect] Opening browser for sign-in (redirect {})", redirect_uri);
+    open_in_browser(&authorize_url(&client_id, &redirect_uri, &state, &code_challenge))?;
+    let code = wait_for_redirect(&listener, redirect_path(&redirect_uri), &state, AUTH_TIMEOUT)?;
+    drop(listener);
+
+    let form = [
+        ("client_id", client_id.as_str()),
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("code_verifier", code_verifier.as_str()),
+    ];
+    let resp = token_request(TOKEN_ENDPOINT, &form)?.map_err(|body| anyhow!("Token exchange failed: {}", body))?;
+    let mut tokens = from_response(resp, None, now_ms());
+    tokens.email = account_email(&tokens.access_token);
+    println!(
+        "[Dropbox][Connect] Tokens received (access: {} chars, has_refresh: {})",
+        tokens.access_token.len(),
+        tokens.refresh_token.is_some()
+    );
+    STORE.store(&tokens)
+}
+
+/// Sign in to Dropbox in the browser (PKCE, loopback redirect) and keep the tokens in the
+/// keychain. Needs DROPBOX_CLIENT_ID; DROPBOX_REDIRECT_URI is optional.
+#[tauri::command]
+pub async fn connect_dropbox() -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(|| connect().map_err(|e| format!("Failed to connect Dropbox: {}", e)))
+        .await
+        .map_err(|e| format!("Connect task failed: {}", e))??;
+    Ok("Dropbox connected successfully".to_string())
+}
+
+/// Revoke the Dropbox token and forget it
+#[tauri::command]
+pub async fn disconnect_dropbox() -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        if let Ok(token) = access_token() {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+            match client.post(REVOKE_ENDPOINT).bearer_auth(token).send() {
+                Ok(r) if r.status().is_success() => println!("[Dropbox][Disconnect] Token revoked"),
+                Ok(r) => eprintln!("[Dropbox][Disconnect] Revoke returned {}", r.status()),
+                Err(e) => eprintln!("[Dropbox][Disconnect] Revoke failed: {}", e),
+            }
+        }
+        STORE.delete().map_err(|e| format!("Failed to remove Dropbox tokens: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Disconnect task failed: {}", e))??;
+    println!("[Dropbox][Disconnect] Removed stored tokens");
+    Ok("Disconnected from Dropbox".to_string())
+}
+
+/// The stored Dropbox connection, for get_connection_status
+pub(crate) fn connection_status() -> Result<ConnectionStatus> {
+    let Some(tokens) = STORE.load::<DropboxTokens>()? else { return Ok(ConnectionStatus::disconnected("dropbox")) };
+    Ok(ConnectionStatus {
+        provider: "dropbox".to_string(),
+        connected: !tokens.reauth_required,
+        account_email: tokens.email.clone(),
+        scopes: tokens.scope.as_deref().unwrap_or_default().split_whitespace().map(|s| s.to_string()).collect(),
+        expires_at: rfc3339(tokens.expires_at_ms()),
+        last_refresh: rfc3339(tokens.obtained_at_ms),
+        can_refresh: tokens.refresh_token.is_some(),
+        reauth_required: tokens.reauth_required,
+        expires_at_ms: Some(tokens.expires_at_ms()),
+    })
+}
+
+#[tauri::command]
+pub fn is_dropbox_connected() -> Result<bool, String> {
+    match STORE.load::<DropboxTokens>() {
+        Ok(tokens) => Ok(tokens.is_some_and(|t| !t.reauth_required)),
+        Err(e) => {
+            eprintln!("[Dropbox][Status] Failed to load tokens: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth_tokens::needs_refresh;
+
+    #[test]
+    fn test_authorize_url_and_refresh_keeps_token() {
+        let url = authorize_url("key1", DEFAULT_REDIRECT_URI, "s1", "challenge");
+        assert!(url.starts_with(AUTHORIZE_ENDPOINT));
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A53682%2F"));
+        assert!(url.contains("&token_access_type=offline&state=s1&code_challenge=challenge&code_challenge_method=S256"));
+        assert_eq!(redirect_port(DEFAULT_REDIRECT_URI), Some(53682));
+
+        let first = from_response(
+            TokenResp { access_token: "a1".into(), refresh_token: Some("r1".into()), expires_in: Some(14_400), account_id: Some("dbid:1".into()), scope: Some("files.content.read".into()) },
+            None,
+            0,
+        );
+        assert!(!needs_refresh(&first, 14_000 * 1000));
+        assert!(needs_refresh(&first, 14_200 * 1000));
+        let refreshed = from_response(
+            TokenResp { access_token: "a2".into(), refresh_token: None, expires_in: Some(14_400), account_id: None, scope: None },
+            Some(&first),
+            1,
+        );
+        assert_eq!((refreshed.refresh_token.as_deref(), refreshed.account_id.as_deref()), (Some("r1"), Some("dbid:1")));
+    }
+}
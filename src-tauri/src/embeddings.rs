@@ -0,0 +1,455 @@
+use anyhow::{anyhow, Context, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use chrono::Utc;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use crate::chunking;
+use crate::file_storage::{self, FileStorage};
+use crate::keychain;
+use crate::pii_scrubber::ScrubPass;
+use crate::settings::{EmbeddingProvider, EmbeddingSettings, Settings};
+use crate::vector_store::{Namespace, VectorStore};
+
+/// A sentence-transformers BERT model loaded from ./models/<name>
+pub struct LocalModel {
+    dir: PathBuf,
+    model: BertModel,
+    tokenizer: Tokenizer,
+}
+
+// Where set_embedding_api_key keeps the key for the embedding API
+const KEYCHAIN_SERVICE: &str = "agi-embeddings";
+const KEYCHAIN_ACCOUNT: &str = "api_key";
+
+// Loading weights takes a while; keep the last model around between calls
+static LOCAL_MODEL: Mutex<Option<Arc<LocalModel>>> = Mutex::new(None);
+
+/// Files a BERT checkpoint directory under ./models needs
+pub const MODEL_FILES: &[&str] = &["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Load a BERT checkpoint with a padding, truncating tokenizer. The weights come back too
+/// so task heads (e.g. a cross-encoder's classifier) can be loaded from the same file.
+pub fn load_bert(dir: &Path) -> Result<(BertModel, Tokenizer, VarBuilder<'static>)> {
+    let config: Config = serde_json::from_str(&fs::read_to_string(dir.join("config.json"))?)
+        .context("parsing config.json")?;
+    let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+        .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+    tokenizer.with_padding(Some(PaddingParams::default()));
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length: config.max_position_embeddings,
+            ..Default::default()
+        }))
+        .map_err(|e| anyhow!("Failed to configure tokenizer: {}", e))?;
+
+    let weights = fs::read(dir.join("model.safetensors")).context("reading model.safetensors")?;
+    let vb = VarBuilder::from_buffered_safetensors(weights, DType::F32, &Device::Cpu)?;
+    let model = BertModel::load(vb.clone(), &config)?;
+    Ok((model, tokenizer, vb))
+}
+
+impl LocalModel {
+    fn load(dir: &Path) -> Result<Self> {
+        let (model, tokenizer, _) = load_bert(dir)?;
+        Ok(Self { dir: dir.to_path_buf(), model, tokenizer })
+    }
+
+    fn shared(dir: &Path) -> Result<Arc<Self>> {
+        let mut cached = LOCAL_MODEL.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(model) = cached.as_ref().filter(|m| m.dir == dir) {
+            return Ok(model.clone());
+        }
+        println!("[Embeddings] Loading local model from {:?}", dir);
+        let model = Arc::new(Self::load(dir)?);
+        *cached = Some(model.clone());
+        Ok(model)
+    }
+
+    /// Mean-pooled, L2-normalized sentence embeddings
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        let device = &self.model.device;
+        let ids = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let masks = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let ids = Tensor::stack(&ids, 0)?;
+        let mask = Tensor::stack(&masks, 0)?;
+
+        let hidden = self.model.forward(&ids, &ids.zeros_like()?, Some(&mask))?; // (batch, seq, dim)
+        let mask = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let pooled = hidden.broadcast_mul(&mask)?.sum(1)?.broadcast_div(&mask.sum(1)?)?;
+        let norm = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+        Ok(pooled.broadcast_div(&norm)?.to_vec2::<f32>()?)
+    }
+}
+
+#[derive(Serialize)]
+struct ApiRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    data: Vec<ApiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct ApiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Computes embeddings with the provider chosen in settings
+pub enum Embedder {
+    Local(Arc<LocalModel>, String),
+    Api { client: Client, url: String, model: String, api_key: Option<String> },
+}
+
+/// Directory of the configured local model
+pub fn local_model_dir(settings: &EmbeddingSettings) -> PathBuf {
+    file_storage::project_root().join("models").join(&settings.local_model)
+}
+
+/// Whether the configured local embedding model's files are present
+pub fn local_model_available() -> bool {
+    let dir = local_model_dir(&Settings::load().unwrap_or_default().embeddings);
+    MODEL_FILES
+        .iter()
+        .all(|f| dir.join(f).exists())
+}
+
+impl Embedder {
+    pub fn from_settings(settings: &EmbeddingSettings) -> Result<Self> {
+        match settings.provider {
+            EmbeddingProvider::Local => {
+                let dir = local_model_dir(settings);
+                if !dir.join("model.safetensors").exists() {
+                    return Err(anyhow!("Local embedding model not found in {:?}", dir));
+                }
+                Ok(Self::Local(LocalModel::shared(&dir)?, settings.local_model.clone()))
+            }
+            EmbeddingProvider::Api => Ok(Self::Api {
+                client: Client::builder()
+                    .timeout(Duration::from_secs(120))
+                    .build()
+                    .context("building http client")?,
+                url: settings.api_url.clone(),
+                model: settings.api_model.clone(),
+                // Self-hosted endpoints (e.g. Ollama) may not need a key
+                api_key: api_key(),
+            }),
+        }
+    }
+
+    /// Identifies the vector space, e.g. "local:all-MiniLM-L6-v2"; vectors from different
+    /// models must never be compared
+    pub fn model_id(&self) -> String {
+        match self {
+            Self::Local(_, name) => format!("local:{}", name),
+            Self::Api { model, .. } => format!("api:{}", model),
+        }
+    }
+
+    pub fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self {
+            Self::Local(model, _) => model.embed(texts),
+            Self::Api { client, url, model, api_key } => {
+                let mut pass = ScrubPass::new();
+                let input = scrubbed(&mut pass, texts);
+                pass.record("embed_api", url);
+                let mut request = client.post(url).json(&ApiRequest { model, input: &input });
+                if let Some(key) = api_key {
+                    request = request.bearer_auth(key);
+                }
+                let resp = request.send()?;
+                if !resp.status().is_success() {
+                    let status = resp.status();
+                    return Err(anyhow!("Embedding API error {}: {}", status, resp.text().unwrap_or_default()));
+                }
+                let mut data = resp.json::<ApiResponse>()?.data;
+                data.sort_by_key(|d| d.index);
+                Ok(data.into_iter().map(|d| d.embedding).collect())
+            }
+        }
+    }
+}
+
+/// The embedding API key saved with set_embedding_api_key, or else EMBEDDINGS_API_KEY or
+/// OPENAI_API_KEY from the environment. Keychain failures count as nothing saved.
+fn api_key() -> Option<String> {
+    let stored = keychain::get(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).unwrap_or_else(|e| {
+        eprintln!("[embeddings] Couldn't read the API key from the keychain: {}", e);
+        None
+    });
+    stored
+        .or_else(|| std::env::var("EMBEDDINGS_API_KEY").ok())
+        .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+}
+
+// Chunks leave the machine when a remote API embeds them, so they go out scrubbed
+fn scrubbed(pass: &mut ScrubPass, texts: &[String]) -> Vec<String> {
+    texts.iter().map(|t| pass.text(t)).collect()
+}
+
+/// One chunk of a file with its vector
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbeddedChunk {
+    pub index: usize,                  // Position within the file
+    pub heading: Option<String>,
+    pub text: String,
+    pub tokens: usize,
+    pub hash: String,                  // chunk_hash of heading and text
+    #[serde(default)]
+    pub source: chunking::Provenance,
+    pub vector: Vec<f32>,
+}
+
+/// All chunk vectors of one file, as written to the vector store
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileEmbeddings {
+    pub file_id: String,
+    pub model: String,                 // Embedder::model_id
+    pub dimensions: usize,
+    pub created: String,               // RFC 3339
+    pub chunks: Vec<EmbeddedChunk>,
+    #[serde(default)]
+    pub reused: usize,                 // Chunks that needed no embedding call
+}
+
+/// Identity of a chunk's content; an unchanged hash means its vector can be reused
+pub fn chunk_hash(heading: Option<&str>, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(heading.unwrap_or_default());
+    hasher.update([0]);
+    hasher.update(text);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Chunk a file's content and embed every chunk, batch by batch. Chunks whose content
+/// hash already has a vector in `previous` (from the same model) are not re-embedded,
+/// so an edited or re-extracted file only costs its changed chunks.
+pub fn embed_file(
+    storage: &FileStorage,
+    file_id: &str,
+    previous: Option<&VectorStore>,
+) -> Result<FileEmbeddings> {
+    let file_type = storage.get_file(file_id)?.file_type;
+    let content = storage.extract_file_content(file_id)?;
+
+    // Small models only attend to the first 256-512 word pieces, hence the separate size
+    let config = Settings::load().unwrap_or_default().chunking;
+    let chunks = chunking::chunk_file(&content, &file_type, config.embedding_tokens, config.overlap_tokens, &config, |t| {
+        chunking::count_tokens(None, t)
+    });
+    embed_chunks(file_id, chunks, previous.map(|store| (store, Namespace::Files)))
+}
+
+/// Embed the chunks of one document (an upload or a conversation), reusing the vectors
+/// `previous` already holds for `id` in its namespace and any the store's embedding cache
+/// has for the same text and model
+pub fn embed_chunks(
+    id: &str,
+    chunks: Vec<chunking::Chunk>,
+    previous: Option<(&VectorStore, Namespace)>,
+) -> Result<FileEmbeddings> {
+    let settings = Settings::load().unwrap_or_default().embeddings;
+    let embedder = Embedder::from_settings(&settings)?;
+    let model = embedder.model_id();
+    let mut known: HashMap<String, Vec<f32>> = match previous {
+        Some((store, namespace)) => store.vectors_for(namespace, id, &model)?,
+        None => HashMap::new(),
+    };
+
+    let mut embedded: Vec<EmbeddedChunk> = chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| EmbeddedChunk {
+            index,
+            hash: chunk_hash(chunk.heading.as_deref(), &chunk.text),
+            heading: chunk.heading,
+            text: chunk.text,
+            tokens: chunk.tokens,
+            source: chunk.source,
+            vector: Vec::new(),
+        })
+        .collect();
+
+    if let Some((store, _)) = previous {
+        let wanted: Vec<&str> = embedded
+            .iter()
+            .map(|c| c.hash.as_str())
+            .filter(|h| !known.contains_key(*h))
+            .collect();
+        let cached = store.cached_vectors(&model, &wanted)?;
+        known.extend(cached);
+    }
+
+    // Embed each new hash once; repeated chunks within the file share the vector
+    let mut missing: Vec<usize> = Vec::new();
+    for chunk in &embedded {
+        if !known.contains_key(&chunk.hash) && !missing.iter().any(|&i| embedded[i].hash == chunk.hash) {
+            missing.push(chunk.index);
+        }
+    }
+    for batch in missing.chunks(settings.batch_size.max(1)) {
+        let texts: Vec<String> = batch.iter().map(|&i| embedded[i].text.clone()).collect();
+        let vectors = embedder.embed(&texts)?;
+        if vectors.len() != batch.len() {
+            return Err(anyhow!("Expected {} embeddings, got {}", batch.len(), vectors.len()));
+        }
+        if let Some((store, _)) = previous {
+            let fresh: Vec<(&str, &[f32])> = batch
+                .iter()
+                .zip(&vectors)
+                .map(|(&i, v)| (embedded[i].hash.as_str(), v.as_slice()))
+                .collect();
+            store.cache_vectors(&model, &fresh)?;
+        }
+        for (&i, vector) in batch.iter().zip(vectors) {
+            known.insert(embedded[i].hash.clone(), vector);
+        }
+    }
+    for chunk in embedded.iter_mut() {
+        chunk.vector = known[&chunk.hash].clone();
+    }
+    let reused = embedded.len() - missing.len();
+
+    let result = FileEmbeddings {
+        file_id: id.to_string(),
+        model,
+        dimensions: embedded.first().map_or(0, |c| c.vector.len()),
+        created: Utc::now().to_rfc3339(),
+        chunks: embedded,
+        reused,
+    };
+    println!(
+        "[Embeddings] Embedded {} of {} chunks of {} with {}",
+        result.chunks.len() - reused,
+        result.chunks.len(),
+        id,
+        result.model
+    );
+    Ok(result)
+}
+
+/// Embed the chunks a model switch left stale again, from their stored text, in both
+/// namespaces. Called after each indexing, so an interrupted re-embedding resumes with the
+/// next one. Returns how many chunks got a vector.
+pub fn reembed_stale(store: &mut VectorStore) -> Result<usize> {
+    let stale = store.stale_chunks()?;
+    if stale.is_empty() {
+        return Ok(0);
+    }
+    let settings = Settings::load().unwrap_or_default().embeddings;
+    let embedder = Embedder::from_settings(&settings)?;
+    let model = embedder.model_id();
+    for batch in stale.chunks(settings.batch_size.max(1)) {
+        let texts: Vec<String> = batch.iter().map(|(_, _, text)| text.clone()).collect();
+        let vectors = embedder.embed(&texts)?;
+        if vectors.len() != batch.len() {
+            return Err(anyhow!("Expected {} embeddings, got {}", batch.len(), vectors.len()));
+        }
+        let fresh: Vec<(i64, &str, &[f32])> = batch
+            .iter()
+            .zip(&vectors)
+            .map(|((id, namespace, _), v)| (*id, namespace.as_str(), v.as_slice()))
+            .collect();
+        store.restore_vectors(&model, &fresh)?;
+    }
+    println!("[Embeddings] Re-embedded {} chunks with {}", stale.len(), model);
+    Ok(stale.len())
+}
+
+/// Summary returned to the frontend; vectors stay in the index
+#[derive(Debug, Serialize)]
+pub struct EmbeddingSummary {
+    pub file_id: String,
+    pub model: String,
+    pub dimensions: usize,
+    pub chunks: usize,
+    pub reused: usize,                 // Chunks whose vector was already indexed or cached
+}
+
+/// Compute embeddings for an uploaded file's chunks and (re)index them
+#[tauri::command]
+pub async fn embed_uploaded_file(file_id: String) -> Result<EmbeddingSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        let result =
+            storage.index_file(&file_id).map_err(|e| format!("Failed to embed file: {}", e))?;
+        Ok(EmbeddingSummary {
+            file_id: result.file_id,
+            model: result.model,
+            dimensions: result.dimensions,
+            chunks: result.chunks.len(),
+            reused: result.reused,
+        })
+    })
+    .await
+    .map_err(|e| format!("Embedding task failed: {}", e))?
+}
+
+/// Drop every cached vector, e.g. to reclaim disk space; indexed files keep theirs
+#[tauri::command]
+pub async fn clear_embedding_cache() -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let storage = FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        storage
+            .vectors()
+            .and_then(|store| store.clear_cache())
+            .map_err(|e| format!("Failed to clear embedding cache: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Cache task failed: {}", e))?
+}
+
+/// Save the key for the embedding API to the OS keychain; an empty or missing key removes it
+#[tauri::command]
+pub fn set_embedding_api_key(api_key: Option<String>) -> Result<(), String> {
+    match api_key.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        Some(key) => keychain::set(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, key),
+        None => keychain::delete(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT),
+    }
+    .map_err(|e| format!("Failed to save embedding API key: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pii_scrubber::Scrubber;
+    use crate::settings::PiiSettings;
+
+    #[test]
+    fn test_remote_chunks_are_scrubbed() {
+        let mut pass = ScrubPass::with(Scrubber::from_settings(PiiSettings::default()));
+        let texts = vec!["Invoice for john@example.com".to_string(), "Call 555-123-4567".to_string()];
+        assert_eq!(scrubbed(&mut pass, &texts), vec!["Invoice for BLOCKED", "Call BLOCKED"]);
+        assert!(scrubbed(&mut pass, &[]).is_empty());
+        // One report for the whole request, for the audit log
+        assert_eq!(pass.redacted(), 2);
+    }
+}
@@ -0,0 +1,246 @@
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use super::{ChildFile, Extracted};
+
+/// Archive types unpacked here; "x.tar.gz" arrives as "gz", as does a single gzipped file
+const ARCHIVE_TYPES: &[&str] = &["tar", "tgz", "gz"];
+
+/// Limits against decompression bombs, shared across nested archives
+const MAX_ENTRIES: usize = 2000;
+const MAX_UNPACKED_BYTES: u64 = 512 * 1024 * 1024; // Sum of declared member sizes
+const MAX_MEMBER_BYTES: u64 = 32 * 1024 * 1024;    // Larger members are listed, not ingested
+const MAX_DEPTH: usize = 3;                         // Nesting of archives inside archives
+
+pub fn is_archive(file_type: &str) -> bool {
+    ARCHIVE_TYPES.contains(&file_type)
+}
+
+fn member_type(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+#[derive(Default)]
+struct Walk {
+    listing: Vec<String>,
+    children: Vec<ChildFile>,
+    entries: usize,
+    bytes: u64,
+    limit_hit: Option<&'static str>,
+}
+
+impl Walk {
+    /// Unpack one (possibly gzipped) tar stream, recursing into nested archives
+    fn tar<'a>(&mut self, reader: impl Read + 'a, gzip: bool, prefix: &str, depth: usize) -> Result<()> {
+        let reader: Box<dyn Read + 'a> = if gzip {
+            Box::new(GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        let mut archive = tar::Archive::new(reader);
+        let entries = archive
+            .entries()
+            .map_err(|e| anyhow!("Not a tar archive: {}", e))?;
+
+        for entry in entries {
+            let mut entry = entry.map_err(|e| anyhow!("Corrupt tar archive: {}", e))?;
+            if self.entries >= MAX_ENTRIES {
+                self.limit_hit = Some("entry limit");
+                break;
+            }
+            self.entries += 1;
+
+            let path = entry.path()?.to_string_lossy().trim_start_matches("./").to_string();
+            let name = format!("{}{}", prefix, path.trim_start_matches('/'));
+            let kind = entry.header().entry_type();
+            if kind.is_dir() {
+                continue;
+            }
+            if !kind.is_file() {
+                // Links and devices are listed but never followed
+                self.listing.push(format!("{} (link)", name));
+                continue;
+            }
+
+            let size = entry.header().size()?;
+            let file_type = member_type(&name);
+            let unpack = is_archive(&file_type) && depth < MAX_DEPTH;
+            // An archive that gets unpacked counts by its members, not twice
+            if !unpack && !self.count(size) {
+                break;
+            }
+            self.listing.push(format!("{} ({} bytes)", name, size));
+
+            if unpack {
+                let gzip = file_type != "tar";
+                if let Err(e) = self.tar(&mut entry, gzip, &format!("{}/", name), depth + 1) {
+                    self.listing.push(format!("{}/ [not unpacked: {}]", name, e));
+                    self.count(size);
+                }
+                if self.limit_hit.is_some() {
+                    break;
+                }
+            } else if !is_archive(&file_type)
+                && size <= MAX_MEMBER_BYTES
+                && super::find(&file_type).is_some()
+            {
+                let mut data = Vec::with_capacity(size as usize);
+                entry.by_ref().take(size).read_to_end(&mut data)?;
+                self.children.push(ChildFile { name, data });
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a member's size to the total; false once that's over the limit
+    fn count(&mut self, size: u64) -> bool {
+        self.bytes += size;
+        if self.bytes > MAX_UNPACKED_BYTES {
+            self.limit_hit = Some("size limit");
+        }
+        self.limit_hit.is_none()
+    }
+}
+
+// Whether a gzip stream holds a tar: its first header block carries the "ustar" magic
+fn is_gzipped_tar(path: &Path) -> Result<bool> {
+    let mut block = Vec::with_capacity(512);
+    GzDecoder::new(BufReader::new(File::open(path)?))
+        .take(512)
+        .read_to_end(&mut block)
+        .map_err(|e| anyhow!("Corrupt gzip file: {}", e))?;
+    Ok(block.len() == 512 && &block[257..262] == b"ustar")
+}
+
+// A single gzipped file such as "notes.txt.gz", handed back as its one child under the
+// name kept in the gzip header
+fn extract_gzip(path: &Path) -> Result<Extracted> {
+    let mut decoder = GzDecoder::new(BufReader::new(File::open(path)?));
+    let mut data = Vec::new();
+    (&mut decoder)
+        .take(MAX_MEMBER_BYTES + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| anyhow!("Corrupt gzip file: {}", e))?;
+    let name = decoder
+        .header()
+        .and_then(|h| h.filename())
+        .map(|n| String::from_utf8_lossy(n).to_string())
+        .and_then(|n| Path::new(&n).file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "decompressed".to_string());
+
+    let mut children = Vec::new();
+    let text = if data.len() as u64 > MAX_MEMBER_BYTES {
+        format!("Compressed file: {} (over {} bytes, not unpacked)", name, MAX_MEMBER_BYTES)
+    } else {
+        let text = format!("Compressed file: {} ({} bytes)", name, data.len());
+        if super::find(&member_type(&name)).is_some() {
+            children.push(ChildFile { name, data });
+        }
+        text
+    };
+    Ok(Extracted {
+        text,
+        note: Some(format!("1 file, {} ingested", children.len())),
+        children,
+        ..Default::default()
+    })
+}
+
+/// List a tar, tar.gz or tgz archive and hand back members that have an extractor as
+/// child files. Nested archives are unpacked too, within the entry/size/depth limits.
+/// A ".gz" that isn't a tar is a single compressed file.
+pub fn extract_tar(path: &Path, file_type: &str) -> Result<Extracted> {
+    if file_type == "gz" && !is_gzipped_tar(path)? {
+        return extract_gzip(path);
+    }
+    let file = BufReader::new(File::open(path)?);
+    let mut walk = Walk::default();
+    walk.tar(file, file_type != "tar", "", 1)?;
+
+    let mut note = format!("{} files, {} ingested", walk.listing.len(), walk.children.len());
+    let mut text = format!(
+        "Archive contents ({} files):\n{}",
+        walk.listing.len(),
+        walk.listing.join("\n")
+    );
+    if let Some(limit) = walk.limit_hit {
+        note.push_str(&format!("; stopped at {}", limit));
+        text.push_str(&format!("\n[Listing stopped at the archive {}]", limit));
+    }
+    Ok(Extracted {
+        text,
+        children: walk.children,
+        note: Some(note),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+
+    fn tar_bytes(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_nested_tar_gz_members_become_children() {
+        let inner = tar_bytes(&[("notes.md", b"# Inner")]);
+        let outer = tar_bytes(&[
+            ("docs/readme.txt", b"hello"),
+            ("bin/tool.exe", b"MZ"),
+            ("inner.tar", &inner),
+        ]);
+        let mut gz = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gz, &outer).unwrap();
+
+        let path = std::env::temp_dir().join(format!("agi-archive-test-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, gz.finish().unwrap()).unwrap();
+        let extracted = extract_tar(&path, "gz").unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let names: Vec<&str> = extracted.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["docs/readme.txt", "inner.tar/notes.md"]);
+        assert!(extracted.text.contains("bin/tool.exe (2 bytes)"));
+        assert_eq!(extracted.note.as_deref(), Some("4 files, 2 ingested"));
+
+        // The nested tar's own size isn't counted on top of its members'
+        let mut walk = Walk::default();
+        walk.tar(&outer[..], false, "", 1).unwrap();
+        assert_eq!(walk.bytes, 5 + 2 + 7);
+    }
+
+    #[test]
+    fn test_single_gzipped_file() {
+        let mut gz = flate2::GzBuilder::new()
+            .filename("notes.txt")
+            .write(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gz, b"plain notes").unwrap();
+
+        let path = std::env::temp_dir().join(format!("agi-archive-test-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, gz.finish().unwrap()).unwrap();
+        let extracted = extract_tar(&path, "gz").unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(extracted.children.len(), 1);
+        assert_eq!(extracted.children[0].name, "notes.txt");
+        assert_eq!(extracted.children[0].data, b"plain notes");
+        assert_eq!(extracted.text, "Compressed file: notes.txt (11 bytes)");
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{DocumentMetadata, Extracted, Extractor};
+use crate::settings::Settings;
+
+/// What is persisted per entry; embedded children are only needed at upload time
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedExtraction {
+    text: String,
+    note: Option<String>,
+    #[serde(default)]
+    metadata: Option<DocumentMetadata>,
+}
+
+/// Persistent cache of extractor output, keyed by the file's content hash, the extraction
+/// settings and the extractor's name and version so re-uploads, changed limits or extractor
+/// changes never serve stale text
+pub struct ExtractionCache {
+    dir: PathBuf,
+}
+
+impl ExtractionCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn content_digest(path: &Path) -> Result<String> {
+        // Hash in a streaming fashion; blobs can be far larger than what gets extracted
+        let mut hasher = Sha256::new();
+        io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn entry_path(&self, extractor: &Extractor, path: &Path) -> Result<PathBuf> {
+        // CSV sampling, the byte cap and subtitle markers all change the output
+        let settings = serde_json::to_vec(&Settings::load().unwrap_or_default().extraction)?;
+        let settings = format!("{:x}", Sha256::digest(&settings));
+        Ok(self.dir.join(format!(
+            "{}-{}-{}-v{}.json",
+            Self::content_digest(path)?,
+            &settings[..16],
+            extractor.name,
+            extractor.version
+        )))
+    }
+
+    /// Return cached output when present, otherwise run the extractor and cache the result
+    pub fn extract(&self, extractor: &Extractor, path: &Path, file_type: &str) -> Result<Extracted> {
+        let entry = self.entry_path(extractor, path)?;
+        if let Ok(content) = fs::read_to_string(&entry) {
+            if let Ok(cached) = serde_json::from_str::<CachedExtraction>(&content) {
+                return Ok(Extracted {
+                    text: cached.text,
+                    note: cached.note,
+                    metadata: cached.metadata,
+                    ..Default::default()
+                });
+            }
+        }
+
+        let extracted = (extractor.extract)(path, file_type)?;
+        self.write(&entry, &extracted);
+        Ok(extracted)
+    }
+
+    /// Record output produced outside the cache (e.g. during upload, where children are needed)
+    pub fn store(&self, extractor: &Extractor, path: &Path, extracted: &Extracted) {
+        if let Ok(entry) = self.entry_path(extractor, path) {
+            self.write(&entry, extracted);
+        }
+    }
+
+    // Caching is best-effort; a failed write just means extracting again next time
+    fn write(&self, entry: &Path, extracted: &Extracted) {
+        let cached = CachedExtraction {
+            text: extracted.text.clone(),
+            note: extracted.note.clone(),
+            metadata: extracted.metadata.clone(),
+        };
+        let result = fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(fs::write(entry, serde_json::to_string(&cached)?)?));
+        if let Err(e) = result {
+            eprintln!("[ExtractionCache] Failed to write {:?}: {}", entry, e);
+        }
+    }
+
+    /// Drop every cached extraction of the file at `path`; call before deleting it
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        let prefix = format!("{}-", Self::content_digest(path)?);
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn counting(path: &Path, _file_type: &str) -> Result<Extracted> {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(fs::read_to_string(path)?.into())
+    }
+
+    #[test]
+    fn test_cache_keyed_by_content_and_version() {
+        let dir = std::env::temp_dir().join(format!("agi-cache-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("doc");
+        fs::write(&file, "first").unwrap();
+
+        let mut extractor = Extractor {
+            name: "counting",
+            label: "Test",
+            extensions: &[],
+            available: || true,
+            extract: counting,
+            version: 1,
+        };
+        let cache = ExtractionCache::new(dir.join("cache"));
+
+        assert_eq!(cache.extract(&extractor, &file, "txt").unwrap().text, "first");
+        assert_eq!(cache.extract(&extractor, &file, "txt").unwrap().text, "first");
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        fs::write(&file, "second").unwrap();
+        assert_eq!(cache.extract(&extractor, &file, "txt").unwrap().text, "second");
+        extractor.version = 2;
+        cache.extract(&extractor, &file, "txt").unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+
+        // Deleting the file leaves nothing of its text behind
+        cache.remove(&file).unwrap();
+        assert_eq!(fs::read_dir(dir.join("cache")).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use std::path::Path;
+use tree_sitter::{Language, Node, Parser};
+
+use super::{stream, Extracted};
+
+/// Max outline entries kept in content, and definition names listed in the summary note
+const MAX_OUTLINE_ENTRIES: usize = 200;
+const MAX_SUMMARY_NAMES: usize = 12;
+
+/// How to read one grammar: which node kinds are definitions and which are imports
+struct Grammar {
+    language: fn() -> Language,
+    definitions: &'static [(&'static str, &'static str)], // (node kind, outline label)
+    imports: &'static [&'static str],
+}
+
+static PYTHON: Grammar = Grammar {
+    language: || tree_sitter_python::LANGUAGE.into(),
+    definitions: &[("class_definition", "class"), ("function_definition", "def")],
+    imports: &["import_statement", "import_from_statement"],
+};
+
+static JAVASCRIPT: Grammar = Grammar {
+    language: || tree_sitter_javascript::LANGUAGE.into(),
+    definitions: &[
+        ("class_declaration", "class"),
+        ("function_declaration", "function"),
+        ("generator_function_declaration", "function"),
+        ("method_definition", "method"),
+    ],
+    imports: &["import_statement"],
+};
+
+static TYPESCRIPT: Grammar = Grammar {
+    language: || tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+    definitions: TS_DEFINITIONS,
+    imports: &["import_statement"],
+};
+
+static TSX: Grammar = Grammar {
+    language: || tree_sitter_typescript::LANGUAGE_TSX.into(),
+    definitions: TS_DEFINITIONS,
+    imports: &["import_statement"],
+};
+
+const TS_DEFINITIONS: &[(&str, &str)] = &[
+    ("class_declaration", "class"),
+    ("abstract_class_declaration", "class"),
+    ("interface_declaration", "interface"),
+    ("type_alias_declaration", "type"),
+    ("enum_declaration", "enum"),
+    ("function_declaration", "function"),
+    ("method_definition", "method"),
+];
+
+static RUST: Grammar = Grammar {
+    language: || tree_sitter_rust::LANGUAGE.into(),
+    definitions: &[
+        ("mod_item", "mod"),
+        ("struct_item", "struct"),
+        ("enum_item", "enum"),
+        ("trait_item", "trait"),
+        ("impl_item", "impl"),
+        ("function_item", "fn"),
+    ],
+    imports: &["use_declaration"],
+};
+
+static GO: Grammar = Grammar {
+    language: || tree_sitter_go::LANGUAGE.into(),
+    definitions: &[
+        ("type_spec", "type"),
+        ("function_declaration", "func"),
+        ("method_declaration", "func"),
+    ],
+    imports: &["import_spec"],
+};
+
+static JAVA: Grammar = Grammar {
+    language: || tree_sitter_java::LANGUAGE.into(),
+    definitions: &[
+        ("class_declaration", "class"),
+        ("interface_declaration", "interface"),
+        ("enum_declaration", "enum"),
+        ("method_declaration", "method"),
+        ("constructor_declaration", "constructor"),
+    ],
+    imports: &["import_declaration"],
+};
+
+static C: Grammar = Grammar {
+    language: || tree_sitter_c::LANGUAGE.into(),
+    definitions: &[("struct_specifier", "struct"), ("function_definition", "function")],
+    imports: &["preproc_include"],
+};
+
+static CPP: Grammar = Grammar {
+    language: || tree_sitter_cpp::LANGUAGE.into(),
+    definitions: &[
+        ("namespace_definition", "namespace"),
+        ("class_specifier", "class"),
+        ("struct_specifier", "struct"),
+        ("function_definition", "function"),
+    ],
+    imports: &["preproc_include"],
+};
+
+fn grammar_for(file_type: &str) -> Option<&'static Grammar> {
+    match file_type {
+        "py" => Some(&PYTHON),
+        "js" | "jsx" | "mjs" | "cjs" => Some(&JAVASCRIPT),
+        "ts" => Some(&TYPESCRIPT),
+        "tsx" => Some(&TSX),
+        "rs" => Some(&RUST),
+        "go" => Some(&GO),
+        "java" => Some(&JAVA),
+        "c" | "h" => Some(&C),
+        "cpp" | "cc" | "cxx" | "hpp" => Some(&CPP),
+        _ => None,
+    }
+}
+
+/// One definition found in the syntax tree
+struct Symbol {
+    label: &'static str,
+    name: String,
+    line: usize,                       // 1-based
+    depth: usize,                      // Nesting under other definitions
+}
+
+#[derive(Default)]
+struct Outline {
+    imports: Vec<String>,
+    symbols: Vec<Symbol>,
+}
+
+impl Outline {
+    fn render(&self) -> String {
+        let mut out = String::from("Outline:\n");
+        if !self.imports.is_empty() {
+            out.push_str(&format!("imports: {}\n", self.imports.join(", ")));
+        }
+        for s in &self.symbols {
+            out.push_str(&format!(
+                "{}{} {} (line {})\n",
+                "  ".repeat(s.depth),
+                s.label,
+                s.name,
+                s.line
+            ));
+        }
+        out
+    }
+
+    /// Top-level definition names for the file summary
+    fn summary_note(&self) -> Option<String> {
+        let mut names: Vec<&str> = Vec::new();
+        for s in self.symbols.iter().filter(|s| s.depth == 0) {
+            // A type and its impl block share a name
+            if !names.contains(&s.name.as_str()) {
+                names.push(&s.name);
+            }
+        }
+        if names.is_empty() {
+            return None;
+        }
+        let mut note = format!("defines {}", names[..names.len().min(MAX_SUMMARY_NAMES)].join(", "));
+        if names.len() > MAX_SUMMARY_NAMES {
+            note.push_str(&format!(" +{} more", names.len() - MAX_SUMMARY_NAMES));
+        }
+        Some(note)
+    }
+}
+
+/// Lines the outline takes up ahead of the source in extracted text, separator included
+pub fn outline_lines(text: &str) -> usize {
+    if !text.starts_with("Outline:\n") {
+        return 0;
+    }
+    text.lines().position(|l| l.is_empty()).map_or(0, |i| i + 1)
+}
+
+/// Top-level definitions of a source file as (1-based line, "label name"), or None when
+/// the language has no grammar
+pub fn top_level_definitions(file_type: &str, source: &str) -> Option<Vec<(usize, String)>> {
+    let outline = outline(grammar_for(file_type)?, source)?;
+    Some(
+        outline
+            .symbols
+            .into_iter()
+            .filter(|s| s.depth == 0)
+            .map(|s| (s.line, format!("{} {}", s.label, s.name)))
+            .collect(),
+    )
+}
+
+/// Read a source file and prepend a structural outline when a grammar is available
+pub fn extract_code(path: &Path, file_type: &str) -> Result<Extracted> {
+    let (source, truncated) = stream::read_bounded(path)?;
+
+    // Languages without a grammar, or sources that yield nothing, keep the raw code only
+    match grammar_for(file_type).and_then(|g| outline(g, &source)) {
+        Some(outline) if !outline.symbols.is_empty() || !outline.imports.is_empty() => Ok(Extracted {
+            text: format!("{}\n{}", outline.render(), source),
+            note: outline.summary_note().or(truncated),
+            ..Default::default()
+        }),
+        _ => Ok(Extracted { text: source, note: truncated, ..Default::default() }),
+    }
+}
+
+fn outline(grammar: &Grammar, source: &str) -> Option<Outline> {
+    let mut parser = Parser::new();
+    parser.set_language(&(grammar.language)()).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut outline = Outline::default();
+    collect(tree.root_node(), source, grammar, 0, &mut outline);
+    Some(outline)
+}
+
+fn collect(node: Node, source: &str, grammar: &Grammar, depth: usize, outline: &mut Outline) {
+    if outline.symbols.len() >= MAX_OUTLINE_ENTRIES {
+        return;
+    }
+    let kind = node.kind();
+
+    if grammar.imports.contains(&kind) {
+        if let Ok(text) = node.utf8_text(source.as_bytes()) {
+            outline.imports.push(import_target(text));
+        }
+        return;
+    }
+
+    let mut child_depth = depth;
+    if let Some((_, label)) = grammar.definitions.iter().find(|(k, _)| *k == kind) {
+        // C/C++ struct and class specifiers also appear in plain type references
+        let is_reference = kind.ends_with("_specifier") && node.child_by_field_name("body").is_none();
+        if let Some(name) = definition_name(node, source).filter(|_| !is_reference) {
+            outline.symbols.push(Symbol {
+                label,
+                name,
+                line: node.start_position().row + 1,
+                depth,
+            });
+            child_depth += 1;
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect(child, source, grammar, child_depth, outline);
+    }
+}
+
+/// Name of a definition node; impl blocks name their type and C functions bury the
+/// identifier inside declarators
+fn definition_name(node: Node, source: &str) -> Option<String> {
+    let named = node
+        .child_by_field_name("name")
+        .or_else(|| {
+            let mut decl = node.child_by_field_name("declarator")?;
+            while let Some(inner) = decl.child_by_field_name("declarator") {
+                decl = inner;
+            }
+            Some(decl)
+        })
+        .or_else(|| node.child_by_field_name("type"))?;
+    let text = named.utf8_text(source.as_bytes()).ok()?;
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Strip import keywords and punctuation down to the module being imported
+fn import_target(statement: &str) -> String {
+    let flat = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed = flat.trim_end_matches(';');
+    for prefix in ["#include ", "import ", "use "] {
+        if let Some(rest) = trimmed.strip_prefix(prefix) {
+            return rest.trim().to_string();
+        }
+    }
+    trimmed.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_nesting_and_declarators() {
+        let rust = "use std::fs;\nstruct Store;\nimpl Store {\n    fn load() {}\n}\n";
+        let parsed = outline(&RUST, rust).unwrap();
+        assert_eq!(
+            parsed.render(),
+            "Outline:\nimports: std::fs\nstruct Store (line 2)\nimpl Store (line 3)\n  fn load (line 4)\n"
+        );
+        assert_eq!(parsed.summary_note().as_deref(), Some("defines Store"));
+
+        let c = "#include <stdio.h>\nstruct point { int x; };\nstatic int *origin(struct point p) { return 0; }\n";
+        let parsed = outline(&C, c).unwrap();
+        assert_eq!(
+            parsed.render(),
+            "Outline:\nimports: <stdio.h>\nstruct point (line 2)\nfunction origin (line 3)\n"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{anyhow, Context, Result};
+use mail_parser::{MessageParser, MimeHeaders};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::{ChildFile, Extracted};
+
+/// Header fields and body shared by the .eml and .msg paths
+#[derive(Default)]
+struct Email {
+    subject: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    date: Option<String>,
+    body: String,
+    attachments: Vec<(String, Vec<u8>)>,
+}
+
+impl Email {
+    fn render(self) -> Extracted {
+        let mut out = String::new();
+        let fields = [
+            ("Subject", &self.subject),
+            ("From", &self.from),
+            ("To", &self.to),
+            ("Date", &self.date),
+        ];
+        for (label, value) in fields {
+            if let Some(v) = value.as_deref().filter(|v| !v.trim().is_empty()) {
+                out.push_str(&format!("{}: {}\n", label, v.trim()));
+            }
+        }
+        if !self.attachments.is_empty() {
+            let names: Vec<&str> = self.attachments.iter().map(|(n, _)| n.as_str()).collect();
+            out.push_str(&format!("Attachments: {}\n", names.join(", ")));
+        }
+        out.push('\n');
+        out.push_str(self.body.trim());
+
+        // Only attachments we can turn into text are worth ingesting as their own files
+        let children = self
+            .attachments
+            .into_iter()
+            .filter(|(name, _)| {
+                let ext = Path::new(name)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+                ext != "eml" && ext != "msg" && super::find(&ext).is_some()
+            })
+            .map(|(name, data)| ChildFile { name, data })
+            .collect();
+
+        Extracted {
+            text: out,
+            children,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parse an RFC 822 / MIME message (.eml)
+pub fn extract_eml(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let raw = fs::read(path)?;
+    let message = MessageParser::default()
+        .parse(&raw)
+        .ok_or_else(|| anyhow!("Not a valid MIME message"))?;
+
+    let format_addr = |addr: &mail_parser::Addr| match (addr.name(), addr.address()) {
+        (Some(name), Some(email)) => format!("{} <{}>", name, email),
+        (None, Some(email)) => email.to_string(),
+        (Some(name), None) => name.to_string(),
+        (None, None) => String::new(),
+    };
+    let join_addrs = |a: Option<&mail_parser::Address>| {
+        a.map(|a| a.iter().map(format_addr).collect::<Vec<_>>().join(", "))
+    };
+
+    // body_text falls back to converting the HTML part when there is no text/plain part
+    let body = message
+        .body_text(0)
+        .map(|b| b.to_string())
+        .unwrap_or_default();
+
+    let attachments = message
+        .attachments()
+        .enumerate()
+        .map(|(i, part)| {
+            let name = part
+                .attachment_name()
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| format!("attachment-{}", i + 1));
+            (name, part.contents().to_vec())
+        })
+        .collect();
+
+    Ok(Email {
+        subject: message.subject().map(|s| s.to_string()),
+        from: join_addrs(message.from()),
+        to: join_addrs(message.to()),
+        date: message.date().map(|d| d.to_rfc3339()),
+        body,
+        attachments,
+    }
+    .render())
+}
+
+// MAPI property ids used by Outlook .msg files
+const PR_SUBJECT: u16 = 0x0037;
+const PR_CLIENT_SUBMIT_TIME: u16 = 0x0039;
+const PR_SENDER_NAME: u16 = 0x0C1A;
+const PR_SENDER_EMAIL_ADDRESS: u16 = 0x0C1F;
+const PR_DISPLAY_TO: u16 = 0x0E04;
+const PR_BODY: u16 = 0x1000;
+const PR_BODY_HTML: u16 = 0x1013;
+const PR_ATTACH_DATA_BIN: u16 = 0x3701;
+const PR_ATTACH_FILENAME: u16 = 0x3704;
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+
+/// Parse an Outlook message (.msg), which is an OLE compound file of MAPI property streams
+pub fn extract_msg(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let mut cf = cfb::open(path).context("Not a valid Outlook .msg file")?;
+    let root = PathBuf::from("/");
+
+    let sender = match (
+        read_string_prop(&mut cf, &root, PR_SENDER_NAME),
+        read_string_prop(&mut cf, &root, PR_SENDER_EMAIL_ADDRESS),
+    ) {
+        (Some(name), Some(email)) if name != email => Some(format!("{} <{}>", name, email)),
+        (name, email) => name.or(email),
+    };
+
+    let body = read_string_prop(&mut cf, &root, PR_BODY)
+        .or_else(|| {
+            read_binary_prop(&mut cf, &root, PR_BODY_HTML)
+                .map(|html| strip_html(&String::from_utf8_lossy(&html)))
+        })
+        .unwrap_or_default();
+
+    let attach_dirs: Vec<PathBuf> = cf
+        .read_storage(&root)?
+        .filter(|e| e.is_storage() && e.name().starts_with("__attach_version1.0_"))
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    let mut attachments = Vec::new();
+    for (i, dir) in attach_dirs.iter().enumerate() {
+        let name = read_string_prop(&mut cf, dir, PR_ATTACH_LONG_FILENAME)
+            .or_else(|| read_string_prop(&mut cf, dir, PR_ATTACH_FILENAME))
+            .unwrap_or_else(|| format!("attachment-{}", i + 1));
+        let data = read_binary_prop(&mut cf, dir, PR_ATTACH_DATA_BIN).unwrap_or_default();
+        attachments.push((name, data));
+    }
+
+    Ok(Email {
+        subject: read_string_prop(&mut cf, &root, PR_SUBJECT),
+        from: sender,
+        to: read_string_prop(&mut cf, &root, PR_DISPLAY_TO),
+        date: read_submit_time(&mut cf),
+        body,
+        attachments,
+    }
+    .render())
+}
+
+fn read_stream(cf: &mut cfb::CompoundFile<fs::File>, path: &Path) -> Option<Vec<u8>> {
+    let mut stream = cf.open_stream(path).ok()?;
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+/// String properties are stored as UTF-16 (type 001F) or 8-bit (type 001E)
+fn read_string_prop(cf: &mut cfb::CompoundFile<fs::File>, dir: &Path, id: u16) -> Option<String> {
+    if let Some(bytes) = read_stream(cf, &dir.join(format!("__substg1.0_{:04X}001F", id))) {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        return Some(String::from_utf16_lossy(&units).trim_end_matches('\0').to_string());
+    }
+    read_stream(cf, &dir.join(format!("__substg1.0_{:04X}001E", id)))
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim_end_matches('\0').to_string())
+}
+
+fn read_binary_prop(cf: &mut cfb::CompoundFile<fs::File>, dir: &Path, id: u16) -> Option<Vec<u8>> {
+    read_stream(cf, &dir.join(format!("__substg1.0_{:04X}0102", id)))
+}
+
+/// Fixed-size properties live in the top-level property stream:
+/// a 32-byte header followed by 16-byte entries (tag, flags, 8-byte value).
+fn read_submit_time(cf: &mut cfb::CompoundFile<fs::File>) -> Option<String> {
+    const PT_SYSTIME: u16 = 0x0040;
+    let props = read_stream(cf, Path::new("/__properties_version1.0"))?;
+    for entry in props.get(32..)?.chunks_exact(16) {
+        let prop_type = u16::from_le_bytes([entry[0], entry[1]]);
+        let prop_id = u16::from_le_bytes([entry[2], entry[3]]);
+        if prop_id == PR_CLIENT_SUBMIT_TIME && prop_type == PT_SYSTIME {
+            // FILETIME: 100ns intervals since 1601-01-01
+            let filetime = u64::from_le_bytes(entry[8..16].try_into().ok()?);
+            let unix_secs = (filetime / 10_000_000) as i64 - 11_644_473_600;
+            return chrono::DateTime::from_timestamp(unix_secs, 0).map(|dt| dt.to_rfc3339());
+        }
+    }
+    None
+}
+
+/// Minimal HTML to text conversion for message bodies without a plain-text part
+pub(crate) fn strip_html(html: &str) -> String {
+    let block_break = regex::Regex::new(r"(?i)<\s*(br|/p|/div|/tr|/li|/h[1-6])[^>]*>").unwrap();
+    let drop_blocks = regex::Regex::new(r"(?is)<(script|style)[^>]*>.*?</(script|style)>").unwrap();
+    let tags = regex::Regex::new(r"(?s)<[^>]*>").unwrap();
+
+    let text = drop_blocks.replace_all(html, "");
+    let text = block_break.replace_all(&text, "\n");
+    let text = tags.replace_all(&text, "");
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    text.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eml_headers_body_and_attachments() {
+        let raw = "From: Anna Lee <anna@example.com>\r\n\
+To: bob@example.com\r\n\
+Subject: Contract draft\r\n\
+Date: Tue, 1 Oct 2024 10:00:00 +0000\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/mixed; boundary=\"b1\"\r\n\
+\r\n\
+--b1\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Please review the attached notes.\r\n\
+--b1\r\n\
+Content-Type: text/plain\r\n\
+Content-Disposition: attachment; filename=\"notes.txt\"\r\n\
+\r\n\
+Clause 4 needs work.\r\n\
+--b1\r\n\
+Content-Type: application/octet-stream\r\n\
+Content-Disposition: attachment; filename=\"data.bin\"\r\n\
+\r\n\
+xyz\r\n\
+--b1--\r\n";
+        let path = std::env::temp_dir().join("agi_test_message.eml");
+        fs::write(&path, raw).unwrap();
+        let extracted = extract_eml(&path, "eml").unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert!(extracted.text.contains("Subject: Contract draft"));
+        assert!(extracted.text.contains("From: Anna Lee <anna@example.com>"));
+        assert!(extracted.text.contains("Attachments: notes.txt, data.bin"));
+        assert!(extracted.text.contains("Please review the attached notes."));
+        // Only the text attachment is ingestible
+        assert_eq!(extracted.children.len(), 1);
+        assert_eq!(extracted.children[0].name, "notes.txt");
+    }
+}
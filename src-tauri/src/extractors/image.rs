@@ -0,0 +1,187 @@
+use anyhow::Result;
+use exif::{In, Reader, Tag, Value};
+use regex::Regex;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use super::{ocr, Extracted};
+use crate::pii_audit;
+use crate::pii_scrubber::Scrubber;
+
+/// Metadata pulled from EXIF and XMP; EXIF wins where both are present
+#[derive(Debug, Default)]
+struct ImageMetadata {
+    dimensions: Option<(u64, u64)>,
+    captured: Option<String>,          // "YYYY-MM-DD HH:MM:SS"
+    camera: Option<String>,            // Make + Model
+    gps: Option<(f64, f64)>,           // Decimal degrees, south/west negative
+}
+
+impl ImageMetadata {
+    /// Render as "Key: value" lines. GPS and capture date are plain text so the PII scrubber
+    /// redacts them before a conversation containing this block is saved for upload.
+    fn render(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some((w, h)) = self.dimensions {
+            lines.push(format!("Dimensions: {}x{}", w, h));
+        }
+        if let Some(captured) = &self.captured {
+            lines.push(format!("Captured: {}", captured));
+        }
+        if let Some(camera) = &self.camera {
+            lines.push(format!("Camera: {}", camera));
+        }
+        if let Some((lat, lon)) = self.gps {
+            lines.push(format!("GPS: {:.6}, {:.6}", lat, lon));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Extract metadata from an image and, when an OCR engine is installed, its text
+pub fn extract_image(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let mut meta = read_exif(path).unwrap_or_default();
+    if let Ok(bytes) = fs::read(path) {
+        merge_xmp(&mut meta, &bytes);
+    }
+    if meta.dimensions.is_none() {
+        meta.dimensions = imagesize::size(path)
+            .ok()
+            .map(|s| (s.width as u64, s.height as u64));
+    }
+
+    // Metadata is still worth keeping when OCR is missing or fails
+    let (ocr_text, note) = if !ocr::is_available() {
+        (String::new(), Some("OCR engine not installed".to_string()))
+    } else {
+        match ocr::extract_image_text(path) {
+            Ok(recognized) => (scrub_ocr_text(path, &recognized.text), recognized.note),
+            Err(e) => (String::new(), Some(format!("OCR failed: {}", e))),
+        }
+    };
+
+    let text = [meta.render(), ocr_text]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(Extracted { text, note, ..Default::default() })
+}
+
+// Text read off a screenshot goes into the chat context like typed text, so its PII is
+// redacted the same way
+fn scrub_ocr_text(path: &Path, text: &str) -> String {
+    let scrubbed = Scrubber::load().scrub_with_report(text);
+    let source = path.file_name().map(|n| n.to_string_lossy().to_string());
+    pii_audit::record("extract_image", source, &scrubbed.report);
+    scrubbed.text
+}
+
+fn read_exif(path: &Path) -> Option<ImageMetadata> {
+    let file = fs::File::open(path).ok()?;
+    let exif = Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let ascii = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY).and_then(|f| match &f.value {
+            Value::Ascii(parts) => parts
+                .first()
+                .map(|p| String::from_utf8_lossy(p).trim().to_string())
+                .filter(|s| !s.is_empty()),
+            _ => None,
+        })
+    };
+    let uint = |tag: Tag| exif.get_field(tag, In::PRIMARY).and_then(|f| f.value.get_uint(0));
+
+    let camera = match (ascii(Tag::Make), ascii(Tag::Model)) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    };
+    let dimensions = match (uint(Tag::PixelXDimension), uint(Tag::PixelYDimension)) {
+        (Some(w), Some(h)) => Some((w as u64, h as u64)),
+        _ => None,
+    };
+    let coordinate = |value: Tag, reference: Tag, negative: &str| {
+        let field = exif.get_field(value, In::PRIMARY)?;
+        let Value::Rational(parts) = &field.value else {
+            return None;
+        };
+        let part = |i: usize| parts.get(i).map(|r| r.to_f64()).unwrap_or(0.0);
+        let degrees = part(0) + part(1) / 60.0 + part(2) / 3600.0;
+        let sign = if ascii(reference).as_deref() == Some(negative) { -1.0 } else { 1.0 };
+        Some(sign * degrees)
+    };
+    let gps = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")
+        .zip(coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"));
+
+    Some(ImageMetadata {
+        dimensions,
+        captured: ascii(Tag::DateTimeOriginal)
+            .or_else(|| ascii(Tag::DateTime))
+            .map(|d| normalize_exif_date(&d)),
+        camera,
+        gps,
+    })
+}
+
+/// EXIF dates use colons throughout ("2024:06:01 12:30:00")
+fn normalize_exif_date(date: &str) -> String {
+    match date.split_once(' ') {
+        Some((day, time)) => format!("{} {}", day.replace(':', "-"), time),
+        None => date.replace(':', "-"),
+    }
+}
+
+/// Fill gaps from an embedded XMP packet, if the file has one
+fn merge_xmp(meta: &mut ImageMetadata, bytes: &[u8]) {
+    let haystack = String::from_utf8_lossy(bytes);
+    let Some(start) = haystack.find("<x:xmpmeta") else {
+        return;
+    };
+    let end = haystack[start..]
+        .find("</x:xmpmeta>")
+        .map(|i| start + i)
+        .unwrap_or(haystack.len());
+    let xmp = &haystack[start..end];
+
+    // Properties appear either as attributes (ns:Name="v") or elements (<ns:Name>v</ns:Name>)
+    let prop = |name: &str| {
+        let pattern = format!(r#"{0}="([^"]*)"|<{0}>([^<]*)</{0}>"#, regex::escape(name));
+        Regex::new(&pattern).ok()?.captures(xmp).and_then(|c| {
+            c.get(1)
+                .or_else(|| c.get(2))
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|s| !s.is_empty())
+        })
+    };
+
+    if meta.captured.is_none() {
+        meta.captured = prop("exif:DateTimeOriginal")
+            .or_else(|| prop("xmp:CreateDate"))
+            .or_else(|| prop("photoshop:DateCreated"))
+            .map(|d| d.replacen('T', " ", 1));
+    }
+    if meta.camera.is_none() {
+        meta.camera = prop("tiff:Model").or_else(|| prop("tiff:Make"));
+    }
+    if meta.gps.is_none() {
+        meta.gps = prop("exif:GPSLatitude")
+            .and_then(|v| parse_xmp_coordinate(&v))
+            .zip(prop("exif:GPSLongitude").and_then(|v| parse_xmp_coordinate(&v)));
+    }
+}
+
+/// XMP GPS values look like "37,46.494N" or "37,46,29.64N"
+fn parse_xmp_coordinate(value: &str) -> Option<f64> {
+    let direction = value.trim().chars().last()?;
+    let number = &value.trim()[..value.trim().len() - direction.len_utf8()];
+    let parts: Vec<f64> = number.split(',').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+    let degrees = parts.iter().zip([1.0, 60.0, 3600.0]).map(|(p, d)| p / d).sum::<f64>();
+    match direction {
+        'N' | 'E' => Some(degrees),
+        'S' | 'W' => Some(-degrees),
+        _ => None,
+    }
+}
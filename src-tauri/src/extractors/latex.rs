@@ -0,0 +1,459 @@
+use anyhow::Result;
+use std::path::Path;
+
+use super::{stream, Extracted};
+
+/// Environments copied through verbatim, like inline `$...$` math
+const MATH_ENVS: &[&str] = &[
+    "equation", "equation*", "align", "align*", "gather", "gather*", "multline", "multline*",
+    "eqnarray", "eqnarray*", "math", "displaymath", "flalign", "flalign*",
+];
+
+/// Commands dropped together with all of their arguments
+const DROPPED: &[&str] = &[
+    "label", "includegraphics", "bibliographystyle", "bibliography", "vspace", "hspace",
+    "usepackage", "documentclass", "newcommand", "renewcommand", "providecommand", "setlength",
+    "addbibresource", "thanks", "maketitle", "tableofcontents", "newpage", "clearpage", "centering",
+    "noindent", "date", "pagestyle", "thispagestyle", "graphicspath", "input", "include",
+];
+
+/// `{...}` starting at `start` (after optional whitespace): the inner text and the index after it
+fn group(s: &str, start: usize) -> Option<(&str, usize)> {
+    let open = start + s[start..].len() - s[start..].trim_start().len();
+    if !s[open..].starts_with('{') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in s[open..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&s[open + 1..open + i], open + i + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Skip any `[...]` optional arguments; returns the first of them and the index after them
+fn optionals(s: &str, mut pos: usize) -> (Option<&str>, usize) {
+    let mut first = None;
+    while s[pos..].starts_with('[') {
+        let Some(close) = s[pos..].find(']') else { break };
+        first.get_or_insert(&s[pos + 1..pos + close]);
+        pos += close + 1;
+    }
+    (first, pos)
+}
+
+/// Required argument of a command, converted to prose
+fn argument(s: &str, pos: usize) -> (String, usize) {
+    let (_, pos) = optionals(s, pos);
+    match group(s, pos) {
+        Some((inner, end)) => (prose(inner), end),
+        None => (String::new(), pos),
+    }
+}
+
+/// Copy `s[start..]` through the next `close` verbatim; returns the index after it
+fn verbatim_until(s: &str, start: usize, skip: usize, close: &str, out: &mut String) -> usize {
+    let end = s[start + skip..]
+        .find(close)
+        .map_or(s.len(), |i| start + skip + i + close.len());
+    out.push_str(&s[start..end]);
+    end
+}
+
+/// Turn LaTeX markup into readable prose: headings become "#" lines, formatting commands
+/// keep their text, citations and references become bracketed keys, and math is kept as-is
+fn prose(s: &str) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        let rest = &s[i..];
+        match c {
+            '%' => i += rest.find('\n').unwrap_or(rest.len()),
+            '$' => {
+                let delim = if rest.starts_with("$$") { "$$" } else { "$" };
+                i = verbatim_until(s, i, delim.len(), delim, &mut out);
+            }
+            '{' | '}' => i += 1,
+            '~' => {
+                out.push(' ');
+                i += 1;
+            }
+            '&' => {
+                out.push_str(" | "); // Table cell separator; math alignment is copied verbatim
+                i += 1;
+            }
+            '`' if rest.starts_with("``") => {
+                out.push('"');
+                i += 2;
+            }
+            '\'' if rest.starts_with("''") => {
+                out.push('"');
+                i += 2;
+            }
+            '\\' => i = command(s, i, &mut out),
+            _ => {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// Handle the command starting at the backslash at `start`; returns the index after it
+fn command(s: &str, start: usize, out: &mut String) -> usize {
+    let after = start + 1;
+    let name_len = s[after..]
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len() - after);
+    if name_len == 0 {
+        let Some(c) = s[after..].chars().next() else { return after };
+        return match c {
+            '[' => verbatim_until(s, start, 2, "\\]", out),
+            '(' => verbatim_until(s, start, 2, "\\)", out),
+            '\\' => {
+                out.push('\n');
+                optionals(s, after + 1).1
+            }
+            '%' | '&' | '_' | '#' | '$' | '{' | '}' => {
+                out.push(c);
+                after + 1
+            }
+            ',' | ';' | ' ' => {
+                out.push(' ');
+                after + 1
+            }
+            _ => after + c.len_utf8(),
+        };
+    }
+
+    let name = &s[after..after + name_len];
+    let mut pos = after + name_len;
+    let starred = s[pos..].starts_with('*');
+    if starred {
+        pos += 1;
+    }
+    match name {
+        "begin" => {
+            let Some((env, end)) = group(s, pos) else { return pos };
+            if MATH_ENVS.contains(&env) {
+                return verbatim_until(s, start, end - start, &format!("\\end{{{}}}", env), out);
+            }
+            match env {
+                "abstract" => out.push_str("\nAbstract\n"),
+                "thebibliography" => out.push_str("\nReferences\n"),
+                // Column spec / width argument
+                "tabular" | "tabular*" | "array" | "minipage" => {
+                    let (_, pos) = optionals(s, end);
+                    return group(s, pos).map_or(pos, |(_, end)| end);
+                }
+                _ => {}
+            }
+            optionals(s, end).1
+        }
+        "end" => {
+            out.push('\n');
+            group(s, pos).map_or(pos, |(_, end)| end)
+        }
+        "part" | "chapter" | "section" | "subsection" | "subsubsection" | "paragraph" => {
+            let level = match name {
+                "part" | "chapter" | "section" => 1,
+                "subsection" => 2,
+                "subsubsection" => 3,
+                _ => 4,
+            };
+            let (title, end) = argument(s, pos);
+            out.push_str(&format!("\n{} {}\n", "#".repeat(level), title.trim()));
+            end
+        }
+        "title" | "author" => {
+            let (text, end) = argument(s, pos);
+            let label = if name == "title" { "Title" } else { "Authors" };
+            out.push_str(&format!("{}: {}\n", label, collapse(&text)));
+            end
+        }
+        "and" => {
+            out.push_str(", ");
+            pos
+        }
+        "cite" | "citep" | "citet" | "parencite" | "textcite" | "autocite" | "nocite" => {
+            let (_, pos) = optionals(s, pos);
+            let Some((keys, end)) = group(s, pos) else { return pos };
+            let keys: Vec<&str> = keys.split(',').map(str::trim).collect();
+            out.push_str(&format!("[{}]", keys.join(", ")));
+            end
+        }
+        "ref" | "eqref" | "autoref" | "cref" | "Cref" | "pageref" => {
+            let Some((key, end)) = group(s, pos) else { return pos };
+            out.push_str(&format!("[ref: {}]", key.trim()));
+            end
+        }
+        "footnote" => {
+            let (text, end) = argument(s, pos);
+            out.push_str(&format!(" [Footnote: {}]", collapse(&text)));
+            end
+        }
+        "caption" => {
+            let (text, end) = argument(s, pos);
+            out.push_str(&format!("\nCaption: {}\n", collapse(&text)));
+            end
+        }
+        "href" => {
+            let Some((url, pos)) = group(s, pos) else { return pos };
+            let (text, end) = argument(s, pos);
+            out.push_str(&format!("{} ({})", text, url));
+            end
+        }
+        "url" => {
+            let Some((url, end)) = group(s, pos) else { return pos };
+            out.push_str(url);
+            end
+        }
+        "item" => {
+            let (label, pos) = optionals(s, pos);
+            out.push_str("\n- ");
+            if let Some(label) = label {
+                out.push_str(&format!("{}: ", prose(label)));
+            }
+            pos
+        }
+        "bibitem" => {
+            let (_, pos) = optionals(s, pos);
+            let Some((key, end)) = group(s, pos) else { return pos };
+            out.push_str(&format!("\n- [{}] ", key.trim()));
+            end
+        }
+        "LaTeX" | "TeX" => {
+            out.push_str(name);
+            pos
+        }
+        _ if DROPPED.contains(&name) => {
+            let (_, mut pos) = optionals(s, pos);
+            while let Some((_, end)) = group(s, pos) {
+                pos = end;
+            }
+            pos
+        }
+        // Formatting and unknown commands: drop the name, keep the text of their arguments
+        _ => optionals(s, pos).1,
+    }
+}
+
+/// Collapse whitespace runs into single spaces
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trim trailing whitespace and squeeze blank-line runs
+fn tidy(s: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    for line in s.lines().map(str::trim_end) {
+        if line.trim().is_empty() && out.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        out.push(if line.trim().is_empty() { "" } else { line });
+    }
+    out.join("\n").trim().to_string()
+}
+
+/// Extract a .tex source as prose. Only title/author are taken from the preamble.
+pub fn extract_latex(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let (source, truncated) = stream::read_bounded(path)?;
+
+    let (preamble, body) = match source.find("\\begin{document}") {
+        Some(begin) => {
+            let body = &source[begin + "\\begin{document}".len()..];
+            let body = body.find("\\end{document}").map_or(body, |end| &body[..end]);
+            (&source[..begin], body)
+        }
+        None => ("", source.as_str()),
+    };
+
+    let mut text = String::new();
+    for name in ["\\title", "\\author"] {
+        if let Some(at) = preamble.find(name) {
+            command(preamble, at, &mut text);
+        }
+    }
+    text.push_str(&prose(body));
+    let text = tidy(&text);
+
+    let sections = text.lines().filter(|l| l.starts_with('#')).count();
+    Ok(Extracted {
+        note: truncated.or_else(|| Some(format!("{} sections", sections))),
+        text,
+        ..Default::default()
+    })
+}
+
+/// One parsed BibTeX entry
+struct BibEntry {
+    kind: String,
+    key: String,
+    fields: Vec<(String, String)>,
+}
+
+impl BibEntry {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// "[key] Authors (year). Title. Venue. doi:..."
+    fn summary(&self) -> String {
+        let mut line = format!("- [{}]", self.key);
+        if let Some(authors) = self.field("author").or_else(|| self.field("editor")) {
+            let names: Vec<&str> = authors.split(" and ").map(str::trim).collect();
+            if names.len() > 3 {
+                line.push_str(&format!(" {} et al.", names[0]));
+            } else {
+                line.push_str(&format!(" {}", names.join("; ")));
+            }
+        }
+        if let Some(year) = self.field("year").or_else(|| self.field("date")) {
+            line.push_str(&format!(" ({})", year));
+        }
+        if let Some(title) = self.field("title") {
+            line.push_str(&format!(". {}", title.trim_end_matches('.')));
+        }
+        let venue = ["journal", "journaltitle", "booktitle", "publisher", "school", "howpublished"]
+            .iter()
+            .find_map(|f| self.field(f));
+        if let Some(venue) = venue {
+            line.push_str(&format!(". {}", venue));
+        }
+        line.push_str(&format!(" ({})", self.kind));
+        if let Some(doi) = self.field("doi") {
+            line.push_str(&format!(" doi:{}", doi));
+        }
+        line
+    }
+}
+
+/// Field value: `{...}`, `"..."` or a bare word/number, with `#` concatenation
+fn bib_value(body: &str, mut pos: usize) -> (String, usize) {
+    let mut value = String::new();
+    loop {
+        pos += body[pos..].len() - body[pos..].trim_start().len();
+        if let Some((inner, end)) = group(body, pos) {
+            value.push_str(inner);
+            pos = end;
+        } else if body[pos..].starts_with('"') {
+            let mut depth = 0;
+            let mut end = body.len();
+            for (i, c) in body[pos + 1..].char_indices() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    '"' if depth == 0 => {
+                        end = pos + 1 + i;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            value.push_str(&body[pos + 1..end]);
+            pos = (end + 1).min(body.len());
+        } else {
+            let end = body[pos..]
+                .find(|c: char| c == ',' || c == '#' || c.is_whitespace())
+                .map_or(body.len(), |i| pos + i);
+            value.push_str(&body[pos..end]);
+            pos = end;
+        }
+        let next = pos + body[pos..].len() - body[pos..].trim_start().len();
+        if !body[next..].starts_with('#') {
+            return (collapse(&prose(&value)), pos);
+        }
+        pos = next + 1;
+    }
+}
+
+fn parse_bib(source: &str) -> Vec<BibEntry> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while let Some(at) = source[pos..].find('@').map(|i| pos + i) {
+        let kind_len = source[at + 1..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(source.len() - at - 1);
+        let kind = source[at + 1..at + 1 + kind_len].to_ascii_lowercase();
+        let Some((body, end)) = group(source, at + 1 + kind_len) else {
+            pos = at + 1;
+            continue;
+        };
+        pos = end;
+        if matches!(kind.as_str(), "comment" | "string" | "preamble") || kind.is_empty() {
+            continue;
+        }
+
+        let (key, mut rest) = body.split_once(',').unwrap_or((body, ""));
+        let mut fields = Vec::new();
+        while let Some(eq) = rest.find('=') {
+            let name = rest[..eq].trim().trim_start_matches(',').trim().to_ascii_lowercase();
+            let (value, end) = bib_value(rest, eq + 1);
+            fields.push((name, value));
+            rest = &rest[end..];
+        }
+        entries.push(BibEntry { kind, key: key.trim().to_string(), fields });
+    }
+    entries
+}
+
+/// Extract a .bib file as one citation summary per entry
+pub fn extract_bib(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let (source, truncated) = stream::read_bounded(path)?;
+    let entries = parse_bib(&source);
+    let text = entries.iter().map(BibEntry::summary).collect::<Vec<_>>().join("\n");
+    Ok(Extracted {
+        text,
+        note: truncated.or_else(|| Some(format!("{} references", entries.len()))),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prose_keeps_math_and_headings() {
+        let tex = r"\section{Intro} We use \emph{deep} nets~\cite{a, b} % comment
+\begin{equation}E = mc^2\end{equation} and $x_1$ (see \ref{fig}).";
+        let out = tidy(&prose(tex));
+        assert!(out.starts_with("# Intro\n"));
+        assert!(out.contains("We use deep nets [a, b]"));
+        assert!(!out.contains("comment"));
+        assert!(out.contains(r"\begin{equation}E = mc^2\end{equation} and $x_1$ (see [ref: fig])."));
+    }
+
+    #[test]
+    fn test_parse_bib_entries() {
+        let bib = r#"@string{jml = "JMLR"}
+@article{smith2020,
+  author = {Smith, Jane and Doe, John},
+  title = {{Deep} Things},
+  journal = "J. " # jml,
+  year = 2020,
+}"#;
+        let entries = parse_bib(bib);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].summary(),
+            "- [smith2020] Smith, Jane; Doe, John (2020). Deep Things. J. jml (article)"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use std::path::Path;
+
+use super::{stream, DocumentMetadata, Extracted};
+
+/// Split a leading `---` YAML front-matter block from the Markdown body.
+/// The block closes with `---` or `...` on its own line.
+fn split_front_matter(source: &str) -> Option<(&str, &str)> {
+    let rest = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let rest = rest
+        .strip_prefix("---\n")
+        .or_else(|| rest.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Strip matching YAML quotes
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for q in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Tags from a flow list (`[a, b]`), a comma list or Obsidian's space-separated `#a #b`
+fn split_tags(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('[')
+        .and_then(|v| v.strip_suffix(']'))
+        .unwrap_or(value);
+    let sep: &[char] = if value.contains(',') { &[','] } else { &[' ', '\t'] };
+    value.split(sep).map(clean_tag).filter(|t| !t.is_empty()).collect()
+}
+
+fn clean_tag(tag: &str) -> String {
+    unquote(tag).trim().trim_start_matches('#').to_string()
+}
+
+/// Read title, tags and date from the simple YAML subset used by note apps:
+/// top-level `key: value` pairs and block lists of `- item` lines
+fn parse_front_matter(yaml: &str) -> DocumentMetadata {
+    let mut meta = DocumentMetadata::default();
+    let mut list_key: Option<String> = None;
+    for line in yaml.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if line.starts_with([' ', '\t', '-']) {
+            // Continuation of a block list
+            if let (Some("tags" | "tag" | "keywords"), Some(item)) =
+                (list_key.as_deref(), line.trim().strip_prefix('-'))
+            {
+                let tag = clean_tag(item);
+                if !tag.is_empty() {
+                    meta.tags.push(tag);
+                }
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        list_key = Some(key.clone());
+        if value.is_empty() {
+            continue;
+        }
+        match key.as_str() {
+            "title" => meta.title = Some(unquote(value).to_string()),
+            "tags" | "tag" | "keywords" => meta.tags.extend(split_tags(value)),
+            "date" | "created" if meta.date.is_none() => meta.date = Some(unquote(value).to_string()),
+            _ => {}
+        }
+    }
+    meta.tags.dedup();
+    meta
+}
+
+/// Markdown with optional YAML front-matter: the body becomes the content and the
+/// front-matter's title, tags and date are returned as structured metadata
+pub fn extract_markdown(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let (source, truncated) = stream::read_bounded(path)?;
+    let Some((yaml, body)) = split_front_matter(&source) else {
+        return Ok(Extracted { text: source, note: truncated, ..Default::default() });
+    };
+
+    let metadata = parse_front_matter(yaml);
+    let note = truncated.or_else(|| {
+        (!metadata.tags.is_empty()).then(|| format!("tags: {}", metadata.tags.join(", ")))
+    });
+    Ok(Extracted {
+        text: body.trim_start().to_string(),
+        note,
+        metadata: Some(metadata),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_title_tags_date() {
+        let source = "---\ntitle: \"Weekly review\"\ndate: 2024-05-01\ntags:\n  - work\n  - '#planning'\naliases: [wr]\n---\n# Notes\nBody";
+        let (yaml, body) = split_front_matter(source).unwrap();
+        assert_eq!(body, "# Notes\nBody");
+        let meta = parse_front_matter(yaml);
+        assert_eq!(meta.title.as_deref(), Some("Weekly review"));
+        assert_eq!(meta.date.as_deref(), Some("2024-05-01"));
+        assert_eq!(meta.tags, vec!["work", "planning"]);
+
+        assert_eq!(parse_front_matter("tags: [a, \"b c\"]").tags, vec!["a", "b c"]);
+        assert_eq!(parse_front_matter("tags: #x #y").tags, vec!["x", "y"]);
+    }
+}
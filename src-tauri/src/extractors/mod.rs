@@ -0,0 +1,234 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub mod archive;
+mod cache;
+mod code;
+pub mod email;
+mod image;
+mod latex;
+mod markdown;
+pub mod ocr;
+pub mod pdf;
+mod pdf_layout;
+mod stream;
+mod subtitles;
+mod tabular;
+
+/// A text extractor for one family of file types.
+pub struct Extractor {
+    pub name: &'static str,
+    pub label: &'static str,              // Used in summaries, e.g. "PDF document"
+    pub extensions: &'static [&'static str],
+    pub available: fn() -> bool,          // Whether the extractor's dependencies are present
+    pub extract: fn(&Path, &str) -> Result<Extracted>, // (stored blob, lowercase file type)
+    pub version: u32,                     // Bump when output changes so cached extractions are redone
+}
+
+impl Extractor {
+    /// "name-vN", recorded on files so outdated extractions can be found
+    pub fn stamp(&self) -> String {
+        format!("{}-v{}", self.name, self.version)
+    }
+}
+
+pub use cache::ExtractionCache;
+
+/// Output of an extractor: context text plus any embedded files worth storing on their own.
+#[derive(Debug, Default)]
+pub struct Extracted {
+    pub text: String,
+    pub children: Vec<ChildFile>,
+    pub note: Option<String>,          // Extra detail for the summary, e.g. OCR confidence
+    pub metadata: Option<DocumentMetadata>,
+}
+
+/// Structured metadata declared by the document itself (e.g. Markdown front-matter).
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub tags: Vec<String>,             // Without a leading '#'
+    pub date: Option<String>,          // As written, e.g. "2024-05-01"
+}
+
+/// An embedded file (e.g. an email attachment) that can be ingested as a linked upload.
+#[derive(Debug)]
+pub struct ChildFile {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl From<String> for Extracted {
+    fn from(text: String) -> Self {
+        Self { text, ..Default::default() }
+    }
+}
+
+/// Serializable view of a registered extractor for the frontend.
+#[derive(Debug, Serialize, Clone)]
+pub struct ExtractorInfo {
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub available: bool,
+}
+
+static EXTRACTORS: &[Extractor] = &[
+    Extractor {
+        name: "pdf",
+        label: "PDF document",
+        extensions: &["pdf"],
+        available: always,
+        extract: pdf::extract_pdf,
+        version: 1,
+    },
+    Extractor {
+        name: "text",
+        label: "Text document",
+        extensions: &["txt", "json", "xml", "yaml", "yml", "log", "rtf"],
+        available: always,
+        extract: read_text,
+        version: 2,
+    },
+    Extractor {
+        name: "markdown",
+        label: "Markdown document",
+        extensions: &["md", "markdown"],
+        available: always,
+        extract: markdown::extract_markdown,
+        version: 1,
+    },
+    Extractor {
+        name: "csv",
+        label: "Spreadsheet (CSV)",
+        extensions: &["csv", "tsv"],
+        available: always,
+        extract: tabular::extract_csv,
+        version: 2,
+    },
+    Extractor {
+        name: "subtitles",
+        label: "Subtitles",
+        extensions: &["srt", "vtt"],
+        available: always,
+        extract: subtitles::extract_subtitles,
+        version: 1,
+    },
+    Extractor {
+        name: "latex",
+        label: "LaTeX document",
+        extensions: &["tex", "ltx"],
+        available: always,
+        extract: latex::extract_latex,
+        version: 1,
+    },
+    Extractor {
+        name: "bibtex",
+        label: "Bibliography",
+        extensions: &["bib"],
+        available: always,
+        extract: latex::extract_bib,
+        version: 1,
+    },
+    Extractor {
+        name: "code",
+        label: "Code file",
+        extensions: &[
+            "py", "js", "ts", "jsx", "tsx", "java", "cpp", "c", "cc", "cxx", "h", "hpp", "go",
+            "rs", "php", "rb", "swift", "kt", "scala", "html", "htm", "css", "scss", "sass",
+            "less", "sql", "sh", "bash", "zsh", "fish", "ps1", "bat", "cmd",
+        ],
+        available: always,
+        extract: code::extract_code,
+        version: 2,
+    },
+    Extractor {
+        name: "email",
+        label: "Email message",
+        extensions: &["eml"],
+        available: always,
+        extract: email::extract_eml,
+        version: 1,
+    },
+    Extractor {
+        name: "outlook",
+        label: "Email message",
+        extensions: &["msg"],
+        available: always,
+        extract: email::extract_msg,
+        version: 1,
+    },
+    Extractor {
+        name: "archive",
+        label: "Archive",
+        extensions: &["tar", "tgz", "gz"],
+        available: always,
+        extract: archive::extract_tar,
+        version: 1,
+    },
+    Extractor {
+        name: "image",
+        label: "Image",
+        extensions: &["png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "heic"],
+        available: always,
+        extract: image::extract_image,
+        version: 1,
+    },
+];
+
+/// Find the extractor registered for a (lowercase) file extension.
+pub fn find(file_type: &str) -> Option<&'static Extractor> {
+    EXTRACTORS.iter().find(|e| e.extensions.contains(&file_type))
+}
+
+/// Lines of extracted text ahead of the file's own first line (the outline code
+/// extraction prepends), for mapping line numbers back to the source file.
+pub fn source_line_offset(file_type: &str, text: &str) -> usize {
+    match find(file_type) {
+        Some(e) if e.name == "code" => code::outline_lines(text),
+        _ => 0,
+    }
+}
+
+/// Top-level definitions (line, "label name") of a code file's source, for chunking on
+/// function and class boundaries. None for non-code files and languages without a grammar.
+pub fn top_level_definitions(file_type: &str, source: &str) -> Option<Vec<(usize, String)>> {
+    match find(file_type) {
+        Some(e) if e.name == "code" => code::top_level_definitions(file_type, source),
+        _ => None,
+    }
+}
+
+/// List all registered extractors and whether they can run on this machine.
+pub fn registered() -> Vec<ExtractorInfo> {
+    EXTRACTORS
+        .iter()
+        .map(|e| ExtractorInfo {
+            name: e.name.to_string(),
+            extensions: e.extensions.iter().map(|s| s.to_string()).collect(),
+            available: (e.available)(),
+        })
+        .collect()
+}
+
+/// Detect the dominant language of extracted text as an ISO 639-3 code.
+/// Returns None for short or mixed text where the guess isn't reliable.
+pub fn detect_language(text: &str) -> Option<String> {
+    // A prefix is plenty for detection and keeps large documents cheap
+    let mut end = text.len().min(4000);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    whatlang::detect(&text[..end])
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code().to_string())
+}
+
+fn always() -> bool {
+    true
+}
+
+fn read_text(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let (text, note) = stream::read_bounded(path)?;
+    Ok(Extracted { text, note, ..Default::default() })
+}
@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::Extracted;
+
+/// Tesseract binary; override with TESSERACT_CMD when it isn't on PATH
+fn tesseract_cmd() -> String {
+    std::env::var("TESSERACT_CMD").unwrap_or_else(|_| "tesseract".to_string())
+}
+
+/// Whether an OCR engine is installed
+pub fn is_available() -> bool {
+    crate::capabilities::binary_available(&tesseract_cmd())
+}
+
+/// A recognized word
+#[derive(Debug, Clone)]
+pub struct OcrWord {
+    pub text: String,
+    pub confidence: f32,               // 0-100 as reported by tesseract
+    pub line_key: (u32, u32, u32),     // (block, paragraph, line)
+    pub bbox: (u32, u32, u32, u32),    // (left, top, width, height) in pixels
+}
+
+/// Run tesseract in TSV mode and return every recognized word, in reading order
+pub fn recognize_words(path: &Path) -> Result<Vec<OcrWord>> {
+    let output = Command::new(tesseract_cmd())
+        .arg(path)
+        .arg("stdout")
+        .arg("tsv")
+        .stdin(Stdio::null())
+        .output()
+        .context("OCR engine (tesseract) is not installed")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Columns: level page block par line word left top width height conf text
+    let tsv = String::from_utf8_lossy(&output.stdout);
+    let words = tsv
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.splitn(12, '\t').collect();
+            if cols.len() < 12 || cols[0] != "5" {
+                return None;
+            }
+            let text = cols[11].trim();
+            let confidence: f32 = cols[10].parse().ok()?;
+            if text.is_empty() || confidence < 0.0 {
+                return None;
+            }
+            let num = |i: usize| cols[i].parse::<u32>().ok();
+            Some(OcrWord {
+                text: text.to_string(),
+                confidence,
+                line_key: (num(2)?, num(3)?, num(4)?),
+                bbox: (num(6)?, num(7)?, num(8)?, num(9)?),
+            })
+        })
+        .collect();
+    Ok(words)
+}
+
+/// Extract text from an image with OCR; the mean word confidence goes into the summary note
+pub fn extract_image_text(path: &Path) -> Result<Extracted> {
+    let words = recognize_words(path)?;
+    if words.is_empty() {
+        return Ok(Extracted {
+            note: Some("OCR found no text".to_string()),
+            ..Default::default()
+        });
+    }
+
+    let mut lines: BTreeMap<(u32, u32, u32), Vec<&str>> = BTreeMap::new();
+    for w in &words {
+        lines.entry(w.line_key).or_default().push(&w.text);
+    }
+    let text = lines
+        .values()
+        .map(|l| l.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let confidence = words.iter().map(|w| w.confidence).sum::<f32>() / words.len() as f32;
+    Ok(Extracted {
+        text,
+        note: Some(format!("OCR confidence {:.0}%", confidence)),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,70 @@
+use anyhow::{anyhow, Result};
+use pdf_extract::Document;
+use std::fs;
+use std::path::Path;
+
+use super::pdf_layout::LayoutOutput;
+use super::Extracted;
+
+/// Marks the start of each page in extracted text so chunks can cite page numbers
+pub fn page_marker(page: u32) -> String {
+    format!("[Page {}]", page)
+}
+
+fn load(path: &Path) -> Result<Document> {
+    let bytes = fs::read(path)?;
+    let mut doc = Document::load_mem(&bytes).map_err(|e| anyhow!("Failed to read PDF: {}", e))?;
+    // Owner-password-only PDFs open with an empty user password
+    if doc.is_encrypted() {
+        doc.decrypt("")
+            .map_err(|_| anyhow!("PDF is password protected"))?;
+    }
+    Ok(doc)
+}
+
+fn page_count(doc: &Document) -> u32 {
+    doc.get_pages().len() as u32
+}
+
+/// Extract one page; tabular regions come out as Markdown tables
+fn page_text(doc: &Document, page: u32) -> Result<String> {
+    let mut layout = LayoutOutput::default();
+    pdf_extract::output_doc_page(doc, &mut layout, page)
+        .map_err(|e| anyhow!("Failed to extract text from PDF page {}: {}", page, e))?;
+    Ok(layout.render())
+}
+
+fn render_pages(doc: &Document, from: u32, to: u32) -> Result<String> {
+    let mut pages = Vec::new();
+    for page in from..=to {
+        let text = page_text(doc, page)?;
+        pages.push(format!("{}\n{}", page_marker(page), text));
+    }
+    Ok(pages.join("\n\n"))
+}
+
+/// Extract text from every page of a PDF, each prefixed with its page marker
+pub fn extract_pdf(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let doc = load(path)?;
+    let total = page_count(&doc);
+    Ok(Extracted {
+        text: render_pages(&doc, 1, total)?,
+        note: Some(format!("{} pages", total)),
+        ..Default::default()
+    })
+}
+
+/// Extract an inclusive, 1-based page range. Returns the text and the document's page count.
+pub fn extract_page_range(path: &Path, from: u32, to: u32) -> Result<(String, u32)> {
+    let doc = load(path)?;
+    let total = page_count(&doc);
+    if from == 0 || from > to || to > total {
+        return Err(anyhow!(
+            "Invalid page range {}-{} (document has {} pages)",
+            from,
+            to,
+            total
+        ));
+    }
+    Ok((render_pages(&doc, from, to)?, total))
+}
@@ -0,0 +1,189 @@
+use pdf_extract::{MediaBox, OutputDev, OutputError, Transform};
+
+/// Gap between glyphs, in multiples of the font size, that separates words
+const WORD_GAP: f64 = 0.1;
+/// Gap that separates table cells; wider than any normal word spacing
+const CELL_GAP: f64 = 1.5;
+/// Consecutive aligned rows needed before a region is treated as a table
+const MIN_TABLE_ROWS: usize = 3;
+/// Rows with longer cells on average are prose columns, not tables
+const MAX_AVG_CELL_CHARS: usize = 30;
+
+/// A glyph positioned in page space, y growing downward
+#[derive(Debug, Clone)]
+struct Glyph {
+    x: f64,
+    end: f64,
+    y: f64,
+    size: f64,
+    text: String,
+}
+
+/// Records glyph positions instead of writing text straight out, so lines and
+/// columns can be reconstructed afterwards
+#[derive(Default)]
+pub struct LayoutOutput {
+    glyphs: Vec<Glyph>,
+    page_height: f64,
+}
+
+impl OutputDev for LayoutOutput {
+    fn begin_page(&mut self, _page_num: u32, media_box: &MediaBox, _: Option<(f64, f64, f64, f64)>) -> Result<(), OutputError> {
+        self.page_height = media_box.ury - media_box.lly;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn output_character(&mut self, trm: &Transform, width: f64, _spacing: f64, font_size: f64, char: &str) -> Result<(), OutputError> {
+        // Same font-size estimate as pdf-extract's plain text output
+        let sx = font_size * (trm.m11 + trm.m21);
+        let sy = font_size * (trm.m12 + trm.m22);
+        let size = (sx * sy).abs().sqrt().max(1.0);
+        self.glyphs.push(Glyph {
+            x: trm.m31,
+            end: trm.m31 + width * size,
+            y: self.page_height - trm.m32,
+            size,
+            text: char.to_string(),
+        });
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// A run of text within a line, separated from its neighbours by a cell-sized gap
+#[derive(Debug)]
+struct Cell {
+    x: f64,
+    end: f64,
+    text: String,
+}
+
+type Line = Vec<Cell>;
+
+impl LayoutOutput {
+    /// Reassemble the page into lines of text, rendering aligned multi-column regions
+    /// as Markdown tables
+    pub fn render(mut self) -> String {
+        let lines = self.lines();
+        let mut out: Vec<String> = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let table_len = table_run(&lines[i..]);
+            if table_len >= MIN_TABLE_ROWS {
+                out.push(markdown_table(&lines[i..i + table_len]));
+                i += table_len;
+            } else {
+                let text = lines[i].iter().map(|c| c.text.as_str()).collect::<Vec<_>>().join(" ");
+                out.push(text);
+                i += 1;
+            }
+        }
+        out.retain(|l| !l.trim().is_empty());
+        out.join("\n")
+    }
+
+    fn lines(&mut self) -> Vec<Line> {
+        // Group glyphs sharing a baseline, then order lines top to bottom
+        let mut rows: Vec<(f64, f64, Vec<Glyph>)> = Vec::new();
+        for g in self.glyphs.drain(..) {
+            match rows.iter_mut().find(|(y, size, _)| (g.y - *y).abs() < size.min(g.size) * 0.5) {
+                Some((_, _, glyphs)) => glyphs.push(g),
+                None => rows.push((g.y, g.size, vec![g])),
+            }
+        }
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        rows.into_iter()
+            .map(|(_, _, mut glyphs)| {
+                glyphs.sort_by(|a, b| a.x.total_cmp(&b.x));
+                split_cells(glyphs)
+            })
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
+
+fn split_cells(glyphs: Vec<Glyph>) -> Line {
+    let mut cells: Line = Vec::new();
+    let mut last_end: Option<f64> = None;
+    for g in glyphs {
+        // Explicit spaces only matter as gaps; positions decide the layout
+        if g.text.trim().is_empty() {
+            continue;
+        }
+        let gap = last_end.map(|end| g.x - end);
+        match (cells.last_mut(), gap) {
+            (Some(cell), Some(gap)) if gap < g.size * CELL_GAP => {
+                if gap > g.size * WORD_GAP {
+                    cell.text.push(' ');
+                }
+                cell.text.push_str(&g.text);
+                cell.end = g.end;
+            }
+            _ => cells.push(Cell {
+                x: g.x,
+                end: g.end,
+                text: g.text.clone(),
+            }),
+        }
+        last_end = Some(g.end);
+    }
+    cells
+}
+
+/// Length of the run of lines starting at `lines[0]` that share a column layout
+fn table_run(lines: &[Line]) -> usize {
+    let Some(first) = lines.first() else {
+        return 0;
+    };
+    let short_cells =
+        |line: &Line| line.iter().map(|c| c.text.chars().count()).sum::<usize>() <= line.len() * MAX_AVG_CELL_CHARS;
+    if first.len() < 2 || !short_cells(first) {
+        return 0;
+    }
+    let mut columns: Vec<(f64, f64)> = first.iter().map(|c| (c.x, c.end)).collect();
+    let mut run = 1;
+    for line in &lines[1..] {
+        let aligned = line.len() == columns.len()
+            && short_cells(line)
+            && line.iter().zip(&columns).all(|(cell, (start, end))| {
+                // Left-, right- and centre-aligned cells all overlap their column
+                cell.x <= *end && cell.end >= *start
+            });
+        if !aligned {
+            break;
+        }
+        for (col, cell) in columns.iter_mut().zip(line) {
+            col.0 = col.0.min(cell.x);
+            col.1 = col.1.max(cell.end);
+        }
+        run += 1;
+    }
+    run
+}
+
+fn markdown_table(rows: &[Line]) -> String {
+    let render_row = |row: &Line| {
+        let cells: Vec<String> = row.iter().map(|c| c.text.replace('|', "\\|")).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut out = vec![render_row(&rows[0])];
+    out.push(format!("|{}", " --- |".repeat(rows[0].len())));
+    out.extend(rows[1..].iter().map(render_row));
+    out.join("\n")
+}
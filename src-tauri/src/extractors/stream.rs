@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::settings::Settings;
+
+/// Read buffer for streaming extraction
+const BUFFER_BYTES: usize = 64 * 1024;
+
+/// Per-extraction memory cap from settings
+pub fn max_extraction_bytes() -> u64 {
+    Settings::load().unwrap_or_default().extraction.max_extraction_bytes
+}
+
+/// Stream a text file to `on_chunk` in pieces that end on line boundaries where possible,
+/// holding at most one buffer plus a partial line in memory. Invalid UTF-8 is replaced.
+/// Stops once `max_bytes` have been read; returns (bytes read, file size).
+pub fn for_each_chunk(path: &Path, max_bytes: u64, mut on_chunk: impl FnMut(&str)) -> Result<(u64, u64)> {
+    let mut file = File::open(path).map_err(|e| anyhow!("Failed to read text file: {}", e))?;
+    let total = file.metadata()?.len();
+    let mut buf = vec![0u8; BUFFER_BYTES];
+    let mut pending: Vec<u8> = Vec::new();
+    let mut read = 0u64;
+
+    while read < max_bytes {
+        let want = BUFFER_BYTES.min((max_bytes - read) as usize);
+        let n = file.read(&mut buf[..want])?;
+        if n == 0 {
+            break;
+        }
+        read += n as u64;
+        pending.extend_from_slice(&buf[..n]);
+
+        // Emit through the last newline; very long lines are cut at a char boundary instead
+        let cut = match pending.iter().rposition(|&b| b == b'\n') {
+            Some(nl) => nl + 1,
+            None if pending.len() >= BUFFER_BYTES => utf8_boundary(&pending),
+            None => continue,
+        };
+        on_chunk(&String::from_utf8_lossy(&pending[..cut]));
+        pending.drain(..cut);
+    }
+    // When the cap cut the file short, drop a trailing partial character
+    let end = if read < total { utf8_boundary(&pending) } else { pending.len() };
+    if end > 0 {
+        on_chunk(&String::from_utf8_lossy(&pending[..end]));
+    }
+    Ok((read, total))
+}
+
+/// Length of the longest prefix that doesn't end inside a multi-byte character
+fn utf8_boundary(bytes: &[u8]) -> usize {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => bytes.len(),
+    }
+}
+
+/// Read a text file up to the configured memory cap. The note says how much was read
+/// when the file was larger than the cap.
+pub fn read_bounded(path: &Path) -> Result<(String, Option<String>)> {
+    let cap = max_extraction_bytes();
+    let size = std::fs::metadata(path).map_err(|e| anyhow!("Failed to read text file: {}", e))?.len();
+    // Sized up front so the chunks land in one buffer that never grows past the cap
+    let mut text = String::with_capacity(size.min(cap) as usize);
+    let (read, total) = for_each_chunk(path, cap, |chunk| text.push_str(chunk))?;
+    let note = (read < total).then(|| {
+        format!(
+            "read first {} of {} MB",
+            read / (1024 * 1024),
+            total.div_ceil(1024 * 1024)
+        )
+    });
+    Ok((text, note))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_end_on_lines_and_respect_cap() {
+        let path = std::env::temp_dir().join(format!("agi-stream-test-{}", uuid::Uuid::new_v4()));
+        let line = "héllo wörld\n".repeat(20_000); // ~260 KB, multi-byte chars throughout
+        std::fs::write(&path, &line).unwrap();
+
+        let mut chunks = Vec::new();
+        let (read, total) = for_each_chunk(&path, u64::MAX, |c| chunks.push(c.to_string())).unwrap();
+        assert_eq!((read, total), (line.len() as u64, line.len() as u64));
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.ends_with('\n')));
+        assert_eq!(chunks.concat(), line);
+
+        let mut capped = String::new();
+        let (read, _) = for_each_chunk(&path, 1000, |c| capped.push_str(c)).unwrap();
+        assert_eq!(read, 1000);
+        assert!(capped.len() <= 1000 && !capped.contains('\u{FFFD}'));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use regex::Regex;
+use std::path::Path;
+
+use super::{stream, Extracted};
+use crate::settings::Settings;
+use crate::transcription::format_timecode;
+
+/// One caption: when it starts and its cleaned dialogue lines
+struct Cue {
+    start: f64,                        // Seconds
+    lines: Vec<String>,
+}
+
+/// Parse "HH:MM:SS,mmm" (SRT) or "[HH:]MM:SS.mmm" (WebVTT) into seconds
+fn parse_timestamp(ts: &str) -> Option<f64> {
+    let ts = ts.trim().replace(',', ".");
+    let parts: Vec<&str> = ts.split(':').collect();
+    let (h, m, s) = match parts.as_slice() {
+        [h, m, s] => (h.parse::<f64>().ok()?, m.parse::<f64>().ok()?, s.parse::<f64>().ok()?),
+        [m, s] => (0.0, m.parse::<f64>().ok()?, s.parse::<f64>().ok()?),
+        _ => return None,
+    };
+    Some(h * 3600.0 + m * 60.0 + s)
+}
+
+/// Split SRT/VTT text into cues, dropping cue numbers, timing lines, VTT header/NOTE/STYLE
+/// blocks and markup. A `<v Speaker>` voice tag becomes a "Speaker: " prefix.
+fn parse_cues(source: &str) -> Vec<Cue> {
+    let voice = Regex::new(r"<v(?:\.[^ >]*)?\s+([^>]+)>").unwrap();
+    let markup = Regex::new(r"<[^>]*>|\{\\[^}]*\}").unwrap();
+
+    let normalized = source.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in normalized.split("\n\n") {
+        let lines: Vec<&str> = block.lines().collect();
+        let Some(timing) = lines.iter().position(|l| l.contains("-->")) else {
+            continue; // WEBVTT header, NOTE, STYLE, REGION or stray text
+        };
+        let Some(start) = lines[timing].split("-->").next().and_then(parse_timestamp) else {
+            continue;
+        };
+        let text: Vec<String> = lines[timing + 1..]
+            .iter()
+            .map(|l| {
+                let l = voice.replace_all(l, "$1: ");
+                markup.replace_all(&l, "").trim().to_string()
+            })
+            .filter(|l| !l.is_empty())
+            .collect();
+        if !text.is_empty() {
+            cues.push(Cue { start, lines: text });
+        }
+    }
+    cues
+}
+
+/// Extract captions as clean dialogue, with a "[HH:MM:SS]" marker every N lines
+/// (`subtitle_timestamp_every` in the extraction settings; 0 disables markers)
+pub fn extract_subtitles(path: &Path, _file_type: &str) -> Result<Extracted> {
+    let every = Settings::load().unwrap_or_default().extraction.subtitle_timestamp_every;
+    let (source, _) = stream::read_bounded(path)?;
+    let cues = parse_cues(&source);
+
+    let mut out: Vec<String> = Vec::new();
+    let mut emitted = 0usize;
+    let mut last: Option<&str> = None;
+    for cue in &cues {
+        for line in &cue.lines {
+            // Auto-generated captions repeat the previous line as they scroll
+            if last == Some(line.as_str()) {
+                continue;
+            }
+            if every > 0 && emitted.is_multiple_of(every) {
+                out.push(format_timecode(cue.start));
+            }
+            out.push(line.clone());
+            last = Some(line);
+            emitted += 1;
+        }
+    }
+
+    Ok(Extracted {
+        text: out.join("\n"),
+        note: Some(format!("{} captions", cues.len())),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::Extracted;
+use crate::settings::Settings;
+
+/// Stop counting distinct values past this many; the profile reports "N+"
+const MAX_DISTINCT_TRACKED: usize = 1000;
+
+/// Running statistics for one column
+#[derive(Default)]
+struct ColumnProfile {
+    name: String,
+    empty: usize,
+    non_empty: usize,
+    all_integer: bool,
+    all_number: bool,
+    all_boolean: bool,
+    all_date: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    sum: f64,
+    first_date: Option<String>,
+    last_date: Option<String>,
+    distinct: HashSet<String>,
+    max_len: usize,
+}
+
+impl ColumnProfile {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            all_integer: true,
+            all_number: true,
+            all_boolean: true,
+            all_date: true,
+            ..Default::default()
+        }
+    }
+
+    fn observe(&mut self, raw: &str) {
+        let value = raw.trim();
+        if value.is_empty() {
+            self.empty += 1;
+            return;
+        }
+        self.non_empty += 1;
+        self.max_len = self.max_len.max(value.chars().count());
+        if self.distinct.len() < MAX_DISTINCT_TRACKED {
+            self.distinct.insert(value.to_string());
+        }
+
+        self.all_integer &= value.parse::<i64>().is_ok();
+        match value.replace(',', "").parse::<f64>() {
+            Ok(n) if n.is_finite() => {
+                self.min = Some(self.min.map_or(n, |m| m.min(n)));
+                self.max = Some(self.max.map_or(n, |m| m.max(n)));
+                self.sum += n;
+            }
+            _ => self.all_number = false,
+        }
+        self.all_boolean &= matches!(
+            value.to_ascii_lowercase().as_str(),
+            "true" | "false" | "yes" | "no"
+        );
+        if is_iso_date(value) {
+            // ISO dates sort lexicographically
+            if self.first_date.as_deref().is_none_or(|d| value < d) {
+                self.first_date = Some(value.to_string());
+            }
+            if self.last_date.as_deref().is_none_or(|d| value > d) {
+                self.last_date = Some(value.to_string());
+            }
+        } else {
+            self.all_date = false;
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            p if p.non_empty == 0 => "empty",
+            p if p.all_boolean => "boolean",
+            p if p.all_integer => "integer",
+            p if p.all_number => "number",
+            p if p.all_date => "date",
+            _ => "text",
+        }
+    }
+
+    fn describe(&self) -> String {
+        let kind = self.kind();
+        let mut parts = Vec::new();
+        match kind {
+            "integer" | "number" => {
+                if let (Some(min), Some(max)) = (self.min, self.max) {
+                    parts.push(format!(
+                        "min {}, max {}, mean {}",
+                        format_number(min),
+                        format_number(max),
+                        format_number(self.sum / self.non_empty as f64)
+                    ));
+                }
+            }
+            "date" => {
+                if let (Some(first), Some(last)) = (&self.first_date, &self.last_date) {
+                    parts.push(format!("{} .. {}", first, last));
+                }
+            }
+            "text" | "boolean" => {
+                let distinct = self.distinct.len();
+                if distinct >= MAX_DISTINCT_TRACKED {
+                    parts.push(format!("{}+ distinct", MAX_DISTINCT_TRACKED));
+                } else {
+                    parts.push(format!("{} distinct", distinct));
+                }
+                if kind == "text" {
+                    parts.push(format!("max length {}", self.max_len));
+                }
+            }
+            _ => {}
+        }
+        if self.empty > 0 {
+            parts.push(format!("{} empty", self.empty));
+        }
+        format!("- {} ({}): {}", self.name, kind, parts.join(", "))
+    }
+}
+
+fn is_iso_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() >= 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes[..10]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit())
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        format!("{:.2}", n)
+    }
+}
+
+fn reader(path: &Path, delimiter: u8) -> Result<csv::Reader<std::fs::File>> {
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| anyhow!("Failed to open CSV: {}", e))
+}
+
+/// Profile a CSV/TSV file instead of dumping it: header, column types and stats,
+/// row count, and rows sampled evenly across the file, capped by the extraction settings
+pub fn extract_csv(path: &Path, file_type: &str) -> Result<Extracted> {
+    let settings = Settings::load().unwrap_or_default().extraction;
+    let total_bytes = std::fs::metadata(path)?.len();
+    let delimiter = if file_type == "tsv" { b'\t' } else { b',' };
+
+    // Pass 1: column statistics and row count
+    let mut rdr = reader(path, delimiter)?;
+    let headers = rdr.headers()?.clone();
+    let mut columns: Vec<ColumnProfile> = headers.iter().map(ColumnProfile::new).collect();
+    let mut rows = 0usize;
+    let mut profiled_bytes = total_bytes;
+    for record in rdr.records() {
+        let record = record.map_err(|e| anyhow!("Malformed CSV at row {}: {}", rows + 2, e))?;
+        for (i, value) in record.iter().enumerate() {
+            if i >= columns.len() {
+                columns.push(ColumnProfile::new(&format!("column_{}", i + 1)));
+            }
+            columns[i].observe(value);
+        }
+        rows += 1;
+        // Records stream from a buffered reader; stop at the per-extraction cap
+        if let Some(pos) = record.position().filter(|p| p.byte() > settings.max_extraction_bytes) {
+            profiled_bytes = pos.byte();
+            break;
+        }
+    }
+
+    let mut out = format!("CSV: {} rows x {} columns\n", rows, columns.len());
+    if profiled_bytes < total_bytes {
+        out.push_str(&format!(
+            "Profiled the first {} of {} bytes; statistics cover those rows only\n",
+            profiled_bytes, total_bytes
+        ));
+    }
+    out.push_str("Columns:\n");
+    for column in &columns {
+        out.push_str(&column.describe());
+        out.push('\n');
+    }
+
+    // Pass 2: evenly spaced sample rows, whole rows only, until the size cap
+    let wanted = settings.csv_sample_rows.min(rows);
+    if wanted > 0 {
+        let picks: HashSet<usize> = (0..wanted).map(|i| i * rows / wanted).collect();
+        // The label and header row count against the cap too; the label can only get shorter
+        let header = render_row(&headers)?;
+        let framing = format!("Sample rows ({} of {}):\n", wanted, rows).len() + header.len();
+        let mut sample = String::new();
+        let mut taken = 0usize;
+        for (i, record) in reader(path, delimiter)?.records().enumerate().take(rows) {
+            if !picks.contains(&i) {
+                continue;
+            }
+            let line = render_row(&record?)?;
+            if out.len() + framing + sample.len() + line.len() > settings.csv_max_chars {
+                break;
+            }
+            sample.push_str(&line);
+            taken += 1;
+        }
+        if taken > 0 {
+            out.push_str(&format!("Sample rows ({} of {}):\n", taken, rows));
+            out.push_str(&header);
+            out.push_str(&sample);
+        }
+    }
+
+    Ok(Extracted {
+        text: out,
+        note: Some(format!("{} rows x {} columns", rows, columns.len())),
+        ..Default::default()
+    })
+}
+
+/// Re-quote a record as one comma-separated line
+fn render_row(record: &csv::StringRecord) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(record)?;
+    let bytes = writer.into_inner().map_err(|e| anyhow!("Failed to render CSV row: {}", e))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_profile_and_sample_cap() {
+        let path = std::env::temp_dir().join(format!("agi-tabular-test-{}.csv", uuid::Uuid::new_v4()));
+        let mut csv = String::from("id,name,joined,active\n");
+        for i in 0..500 {
+            csv.push_str(&format!("{},\"Doe, J{}\",2024-01-{:02},{}\n", i, i, i % 28 + 1, i % 2 == 0));
+        }
+        std::fs::write(&path, &csv).unwrap();
+
+        let extracted = extract_csv(&path, "csv").unwrap();
+        let settings = Settings::load().unwrap_or_default().extraction;
+        assert!(extracted.text.starts_with("CSV: 500 rows x 4 columns\n"));
+        assert_eq!(extracted.note.as_deref(), Some("500 rows x 4 columns"));
+        // Header and label included, the output stays within the cap
+        assert!(extracted.text.len() <= settings.csv_max_chars);
+        assert!(extracted.text.contains("- active (boolean)") && extracted.text.contains("- joined (date)"));
+        assert!(extracted.text.contains("id,name,joined,active\n"));
+        assert!(extracted.text.contains("\"Doe, J0\""));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -5,6 +5,18 @@ use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use chrono::Utc;
 
+use crate::chunking;
+use crate::embeddings;
+use crate::extractors;
+use crate::query_expansion;
+use crate::rerank;
+use crate::retrieval_trace::{SelectedChunk, Tracer};
+use crate::settings::{RerankProvider, Settings};
+use crate::vector_store::{self, Namespace, ScoredChunk, VectorStore};
+
+// The chunk vector index in uploads/, with its -wal and -shm files while it's open
+const VECTOR_DB: &str = "vectors.db";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileInfo {
     pub id: String,                    // UUID for unique identification
@@ -18,50 +30,115 @@ pub struct FileInfo {
     pub summary: String,               // Brief summary for prompts
     #[serde(default)]
     pub conversation_id: Option<String>, // Optional associated conversation id
+    #[serde(default)]
+    pub parent_id: Option<String>,     // Set for files extracted from another upload (e.g. email attachments)
+    #[serde(default)]
+    pub page_range: Option<(u32, u32)>, // PDF pages (inclusive) kept in context; None = whole document
+    #[serde(default)]
+    pub language: Option<String>,      // ISO 639-3 code (e.g. "eng") when detection is reliable
+    #[serde(default)]
+    pub metadata: Option<extractors::DocumentMetadata>, // Title/tags/date declared in the file (e.g. front-matter)
+    #[serde(default)]
+    pub extractor: Option<String>,     // Extractor run that produced `content`, e.g. "pdf-v1"
+    #[serde(default)]
+    pub origin: Option<String>,        // Where an imported file came from, e.g. "google-drive:<file id>"
+}
+
+/// One node of an archive's member tree; directories have no file_id
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveNode {
+    pub name: String,                  // Last path component
+    pub file_id: Option<String>,       // Set for members ingested as child files
+    pub children: Vec<ArchiveNode>,
+}
+
+/// Progress of a bulk re-extraction, emitted once per file
+#[derive(Debug, Serialize, Clone)]
+pub struct ReextractProgress {
+    pub file_id: String,
+    pub name: String,
+    pub index: usize,                  // 1-based position in this run
+    pub total: usize,
+}
+
+/// Outcome of a bulk re-extraction
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ReextractReport {
+    pub updated: usize,
+    pub failed: Vec<String>,           // "name: error" per file that could not be re-extracted
+}
+
+/// One piece of file context as sent to the model, with its token count for budgeting
+#[derive(Debug, Serialize, Clone)]
+pub struct ContextChunk {
+    pub file_id: String,
+    pub text: String,                  // Formatted "Document: ...\nContent:\n..." block
+    pub tokens: usize,                 // Tokens in `text` for the requested model
+    pub citation: Option<String>,      // e.g. "report.pdf, p. 12"; None when the whole file is included
+    pub name: String,                  // File name
+    pub score: Option<f32>,            // Retrieval (or rerank) score; None outside query-based context
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<String>,          // Other files with a near-identical passage that was collapsed into this one
+}
+
+/// What a query's context would contain, for auditing before asking
+#[derive(Debug, Serialize, Clone)]
+pub struct ContextPreview {
+    pub chunks: Vec<ContextChunk>,     // In the order they would be sent
+    pub total_tokens: usize,
+    pub max_tokens: usize,
+    pub retrieved: bool,               // false when nothing was indexed and whole files were used
+    pub duplicates: usize,             // Candidates dropped as repeats of an included chunk
+    pub over_budget: usize,            // Candidates dropped because they didn't fit
 }
 
 pub struct FileStorage {
     uploads_dir: PathBuf,              // ./uploads/ directory path
     index_path: PathBuf,               // ./uploads/index.json path
+    cache: extractors::ExtractionCache, // ./uploads/.cache/ extraction results
 }
 
-impl FileStorage {
-    pub fn new() -> Result<Self> {
-        // Determine a stable project root so we point at the same uploads dir as the Node sidecar
-        fn candidates() -> Vec<PathBuf> {
-            let mut v: Vec<PathBuf> = Vec::new();
-            // Highest precedence: explicit override
-            if let Ok(dir) = std::env::var("AGI_PROJECT_ROOT") {
-                v.push(PathBuf::from(dir));
-            }
-            // Try compile-time src-tauri path parent (dev builds)
-            let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-            if let Some(p) = manifest_dir.parent() { v.push(p.to_path_buf()); }
-            // Current dir and its parents
-            if let Ok(cd) = std::env::current_dir() {
-                v.push(cd.clone());
-                if let Some(p) = cd.parent() { v.push(p.to_path_buf()); }
-                if let Some(pp) = cd.parent().and_then(|p| p.parent()) { v.push(pp.to_path_buf()); }
-            }
-            // Around the executable path (packaged builds)
-            if let Ok(exe) = std::env::current_exe() {
-                let mut p = exe.parent();
-                for _ in 0..5 {
-                    if let Some(pp) = p { v.push(pp.to_path_buf()); p = pp.parent(); } else { break; }
-                }
+/// Determine a stable project root so we point at the same uploads dir as the Node sidecar
+pub fn project_root() -> PathBuf {
+    fn candidates() -> Vec<PathBuf> {
+        let mut v: Vec<PathBuf> = Vec::new();
+        // Highest precedence: explicit override
+        if let Ok(dir) = std::env::var("AGI_PROJECT_ROOT") {
+            v.push(PathBuf::from(dir));
+        }
+        // Try compile-time src-tauri path parent (dev builds)
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        if let Some(p) = manifest_dir.parent() { v.push(p.to_path_buf()); }
+        // Current dir and its parents
+        if let Ok(cd) = std::env::current_dir() {
+            v.push(cd.clone());
+            if let Some(p) = cd.parent() { v.push(p.to_path_buf()); }
+            if let Some(pp) = cd.parent().and_then(|p| p.parent()) { v.push(pp.to_path_buf()); }
+        }
+        // Around the executable path (packaged builds)
+        if let Ok(exe) = std::env::current_exe() {
+            let mut p = exe.parent();
+            for _ in 0..5 {
+                if let Some(pp) = p { v.push(pp.to_path_buf()); p = pp.parent(); } else { break; }
             }
-            v
         }
+        v
+    }
 
-        let mut chosen_root: Option<PathBuf> = None;
-        for base in candidates() {
-            // Choose a directory that already contains expected repo markers or uploads
-            if base.join("uploads").exists() || base.join("sidecar").exists() || base.join("src-tauri").exists() {
-                chosen_root = Some(base);
-                break;
-            }
+    let mut chosen_root: Option<PathBuf> = None;
+    for base in candidates() {
+        // Choose a directory that already contains expected repo markers or uploads
+        if base.join("uploads").exists() || base.join("sidecar").exists() || base.join("src-tauri").exists() {
+            chosen_root = Some(base);
+            break;
         }
-        let project_root = chosen_root.unwrap_or_else(|| PathBuf::from("."));
+    }
+    chosen_root.unwrap_or_else(|| PathBuf::from("."))
+}
+
+impl FileStorage {
+    pub fn new() -> Result<Self> {
+        let project_root = project_root();
 
         let uploads_dir = project_root.join("uploads");
         let index_path = uploads_dir.join("index.json");
@@ -70,6 +147,7 @@ impl FileStorage {
         fs::create_dir_all(&uploads_dir)?;
         
         Ok(Self {
+            cache: extractors::ExtractionCache::new(uploads_dir.join(".cache")),
             uploads_dir,
             index_path,
         })
@@ -78,7 +156,32 @@ impl FileStorage {
     pub fn upload_file(&self, file_data: Vec<u8>, filename: String) -> Result<FileInfo> {
         // 1. Generate unique UUID
         let file_id = Uuid::new_v4().to_string();
-        
+        self.store_file(file_id, file_data, filename, None)
+    }
+
+    /// Add a file recovered from a cloud backup under its original id, so the conversations
+    /// that reference it line up again
+    pub fn restore_file(
+        &self,
+        file_id: &str,
+        file_data: Vec<u8>,
+        filename: String,
+        conversation_id: Option<String>,
+    ) -> Result<FileInfo> {
+        // The id becomes a path under uploads/
+        if file_id.is_empty() || !file_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow!("Unusable file id: {}", file_id));
+        }
+        self.store_file(file_id.to_string(), file_data, filename, conversation_id)
+    }
+
+    fn store_file(
+        &self,
+        file_id: String,
+        file_data: Vec<u8>,
+        filename: String,
+        conversation_id: Option<String>,
+    ) -> Result<FileInfo> {
         // 2. Determine file type from extension
         let file_type = self.get_file_type(&filename);
         
@@ -90,26 +193,35 @@ impl FileStorage {
         fs::write(&file_path, &file_data)?;
         
         // 5. Extract text content based on file type
-        let content = self.extract_text_content(&file_path, &file_type)?;
+        let extracted = self.extract_text_content(&file_path, &file_type)?;
+        let content = extracted.text;
         
         // 6. Create metadata record (compute brief summary)
         let summary = Self::summarize(&filename, &file_type, file_size, &content);
         println!("[uploads] New file uploaded: name='{}' type='{}' size={} id={} summary='{}'", filename, file_type, file_size, file_id, summary);
         
+        let extractor = extractors::find(&file_type).map(extractors::Extractor::stamp);
         let file_info = FileInfo {
             id: file_id,
             name: filename,
             file_type,
             size: file_size,
             upload_date: Utc::now().to_rfc3339(),
+            language: extractors::detect_language(&content),
             content,
             is_context_enabled: true, // Default to enabled
             summary,
-            conversation_id: None,
+            conversation_id,
+            parent_id: None,
+            page_range: None,
+            metadata: extracted.metadata,
+            extractor,
+            origin: None,
         };
         
         // 7. Save to JSON index
         self.save_file_to_index(&file_info)?;
+        self.index_in_background(&file_info.id);
         
         Ok(file_info)
     }
@@ -122,51 +234,11 @@ impl FileStorage {
             .to_lowercase()
     }
     
-    fn extract_text_content(&self, file_path: &Path, file_type: &str) -> Result<String> {
-        match file_type {
-            // Text files - direct read
-            "txt" | "md" | "json" | "csv" | "xml" | "yaml" | "log" => {
-                let content = fs::read_to_string(file_path)?;
-                Ok(content)
-            }
-            // Code files - direct read with syntax preservation
-            "py" | "js" | "ts" | "java" | "cpp" | "c" | "go" | "rs" | "php" | "html" | "css" | "sql" => {
-                let content = fs::read_to_string(file_path)?;
-                Ok(content)
-            }
-            // PDF files - extract text content
-            "pdf" => {
-                self.extract_pdf_text(file_path)
-            }
-            // Unsupported types - return empty (future: DOCX, OCR)
-            _ => {
-                Ok("".to_string())
-            }
-        }
-    }
-    
-    /// Extract text content from PDF files using pdf-extract crate
-    fn extract_pdf_text(&self, file_path: &Path) -> Result<String> {
-        // Read the PDF file as bytes
-        let pdf_bytes = fs::read(file_path)?;
-        
-        // Extract text using pdf-extract
-        match pdf_extract::extract_text_from_mem(&pdf_bytes) {
-            Ok(text) => {
-                // Clean up the extracted text
-                let cleaned_text = text
-                    .lines()
-                    .map(|line| line.trim())
-                    .filter(|line| !line.is_empty())
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                
-                Ok(cleaned_text)
-            }
-            Err(e) => {
-                // If PDF extraction fails, return a helpful error message
-                Err(anyhow!("Failed to extract text from PDF: {}", e))
-            }
+    fn extract_text_content(&self, file_path: &Path, file_type: &str) -> Result<extractors::Extracted> {
+        match extractors::find(file_type) {
+            Some(extractor) => self.cache.extract(extractor, file_path, file_type),
+            // Unsupported types - return empty
+            None => Ok(extractors::Extracted::default()),
         }
     }
     
@@ -232,6 +304,7 @@ impl FileStorage {
             println!("[FileStorage] Attempting to delete file at path: {:?}", file_path);
             
             if file_path.exists() {
+                self.forget_extraction(&file_path);
                 fs::remove_file(&file_path)
                     .map_err(|e| anyhow!("Failed to remove file from filesystem: {}", e))?;
                 println!("[FileStorage] Successfully removed file from filesystem");
@@ -239,9 +312,22 @@ impl FileStorage {
                 println!("[FileStorage] Warning: File not found on filesystem: {:?}", file_path);
             }
             
-            // Remove from index
+            // Remove from index, along with any files extracted from it
             files.remove(index);
+            let mut removed = vec![file_id.to_string()];
+            files.retain(|f| {
+                if f.parent_id.as_deref() == Some(file_id) {
+                    let child_path = self.uploads_dir.join(&f.id);
+                    self.forget_extraction(&child_path);
+                    let _ = fs::remove_file(child_path);
+                    removed.push(f.id.clone());
+                    false
+                } else {
+                    true
+                }
+            });
             self.save_index(&files)?;
+            self.unindex(&removed.iter().map(String::as_str).collect::<Vec<_>>());
             println!("[FileStorage] Successfully removed file from index. New count: {}", files.len());
         } else {
             println!("[FileStorage] Error: File with ID {} not found in index", file_id);
@@ -251,6 +337,32 @@ impl FileStorage {
         Ok(())
     }
 
+    /// Delete the files imported from a source, by origin prefix (e.g. "google-drive:"), with
+    /// anything extracted from them. Returns number deleted.
+    pub fn delete_files_by_origin(&self, prefix: &str) -> Result<usize> {
+        let imported: Vec<String> = self
+            .list_files()?
+            .into_iter()
+            .filter(|f| f.origin.as_deref().is_some_and(|o| o.starts_with(prefix)))
+            .map(|f| f.id)
+            .collect();
+        for id in &imported {
+            self.delete_file(id)?;
+        }
+        println!("[FileStorage] Deleted {} file(s) imported from {}", imported.len(), prefix);
+        Ok(imported.len())
+    }
+
+    // Cached text of a file about to be deleted; uploads/.cache mustn't outlive it
+    fn forget_extraction(&self, file_path: &Path) {
+        if !file_path.exists() {
+            return;
+        }
+        if let Err(e) = self.cache.remove(file_path) {
+            println!("[FileStorage] Failed to remove cached extraction of {:?}: {}", file_path, e);
+        }
+    }
+
     /// Delete all files associated with a conversation id. Returns number deleted.
     pub fn delete_files_by_conversation(&self, conversation_id: &str) -> Result<usize> {
         let mut files = self.list_files()?;
@@ -266,6 +378,7 @@ impl FileStorage {
         for f in &to_delete {
             let file_path = self.uploads_dir.join(&f.id);
             if file_path.exists() {
+                self.forget_extraction(&file_path);
                 let _ = fs::remove_file(&file_path);
             }
         }
@@ -273,6 +386,7 @@ impl FileStorage {
         // Keep only remaining files in index
         files.retain(|f| f.conversation_id.as_deref() != Some(conversation_id));
         self.save_index(&files)?;
+        self.unindex(&to_delete.iter().map(|f| f.id.as_str()).collect::<Vec<_>>());
 
         Ok(to_delete.len())
     }
@@ -316,7 +430,12 @@ impl FileStorage {
                 let path = entry.path();
                 if path.is_file() {
                     // Keep index.json handling for last
-                    if path.file_name().and_then(|n| n.to_str()) == Some("index.json") {
+                    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                    if name == "index.json" {
+                        continue;
+                    }
+                    // The vector index also holds conversation history; its files' part goes below
+                    if name.starts_with(VECTOR_DB) {
                         continue;
                     }
                     match fs::remove_file(&path) {
@@ -332,6 +451,14 @@ impl FileStorage {
             }
             println!("[FileStorage] Deleted {} files from filesystem", deleted_count);
         }
+        let indexed = self.vectors().and_then(|store| store.indexed_files(Namespace::Files));
+        match indexed {
+            Ok(ids) => self.unindex(&ids.iter().map(String::as_str).collect::<Vec<_>>()),
+            Err(e) => println!("[FileStorage] Failed to read vector index: {}", e),
+        }
+        if let Err(e) = self.cache.clear() {
+            println!("[FileStorage] Failed to clear extraction cache: {}", e);
+        }
 
         // Clear index.json to an empty array
         self.save_index(&[])?;
@@ -346,6 +473,12 @@ impl FileStorage {
             files[index].is_context_enabled = !files[index].is_context_enabled;
             let file_info = files[index].clone();
             self.save_index(&files)?;
+            // Only context-enabled files are kept in the vector index
+            if file_info.is_context_enabled {
+                self.index_in_background(file_id);
+            } else {
+                self.unindex(&[file_id]);
+            }
             Ok(file_info)
         } else {
             Err(anyhow!("File not found: {}", file_id))
@@ -365,12 +498,15 @@ impl FileStorage {
         Ok(context_content)
     }
 
-    /// Store file from path with robust content extraction
+    /// Store file from path with robust content extraction.
+    /// When `ingest_children` is set, embedded files the extractor finds (e.g. email
+    /// attachments) are stored as separate uploads linked back via `parent_id`.
     pub fn store_file_from_path_robust(
         &self,
         source_path: &str,
         filename: &str,
         file_type: &str,
+        ingest_children: bool,
     ) -> Result<FileInfo> {
         println!(
             "[FileStorage] Storing file from path: source={}, filename={}, type={}",
@@ -388,129 +524,79 @@ impl FileStorage {
         fs::copy(source_path, &dest_path)
             .map_err(|e| anyhow!("Failed to copy file: {}", e))?;
 
-        // 4. Get file size
-        let file_size = fs::metadata(&dest_path)?.len();
-
-        // 5. Try to extract content based on file type with graceful fallback
-        let (content, summary) = match file_type {
-            "pdf" => match self.extract_pdf_text(&dest_path) {
-                Ok(text) => {
-                    let cleaned_text = if text.len() > 10000 {
-                        format!(
-                            "{}... [Truncated - {} characters total]",
-                            &text[..10000],
-                            text.len()
-                        )
-                    } else {
-                        text
-                    };
-                    let summary = format!(
-                        "PDF document: {} [{} bytes] - Text extracted: {} chars",
-                        filename, file_size, cleaned_text.len()
+        // 4. Extract content with graceful fallback
+        let (file_info, children) = self.build_file_info(file_id, filename, file_type)?;
+
+        let mut new_entries = vec![file_info.clone()];
+        if ingest_children {
+            for child in children {
+                let child_id = Uuid::new_v4().to_string();
+                fs::write(self.uploads_dir.join(&child_id), &child.data)?;
+                let child_type = Self::get_file_type_from_name(&child.name);
+                // Children are ingested one level deep only
+                let (mut child_info, _) = self.build_file_info(child_id, &child.name, &child_type)?;
+                child_info.parent_id = Some(file_info.id.clone());
+                println!(
+                    "[FileStorage] Ingested child file '{}' from '{}'",
+                    child_info.name, file_info.name
+                );
+                new_entries.push(child_info);
+            }
+        }
+
+        // 5. Save to JSON index
+        let mut files = self.list_files().unwrap_or_else(|_| vec![]);
+        for entry in &new_entries {
+            self.index_in_background(&entry.id);
+        }
+        files.extend(new_entries);
+        self.save_index(&files)?;
+
+        println!(
+            "[FileStorage] Successfully stored file: {} ({} bytes)",
+            file_info.name, file_info.size
+        );
+
+        Ok(file_info)
+    }
+
+    /// Run the extractor for an already-stored blob and build its index record
+    fn build_file_info(
+        &self,
+        file_id: String,
+        filename: &str,
+        file_type: &str,
+    ) -> Result<(FileInfo, Vec<extractors::ChildFile>)> {
+        let file_size = fs::metadata(self.uploads_dir.join(&file_id))?.len();
+
+        let (content, summary, children, metadata) = match extractors::find(file_type) {
+            Some(extractor) => match (extractor.extract)(&self.uploads_dir.join(&file_id), file_type) {
+                Ok(extracted) => {
+                    self.cache.store(extractor, &self.uploads_dir.join(&file_id), &extracted);
+                    let cleaned_text = Self::truncate_content(extracted.text);
+                    let mut summary = format!(
+                        "{}: {} [{} bytes] - Content extracted: {} chars",
+                        extractor.label, filename, file_size, cleaned_text.len()
                     );
-                    (cleaned_text, summary)
+                    if let Some(note) = extracted.note {
+                        summary.push_str(&format!(" ({})", note));
+                    }
+                    (cleaned_text, summary, extracted.children, extracted.metadata)
                 }
                 Err(e) => {
                     let summary = format!(
-                        "PDF document: {} [{} bytes] - Content extraction failed: {}",
-                        filename, file_size, e
+                        "{}: {} [{} bytes] - Content extraction failed: {}",
+                        extractor.label, filename, file_size, e
                     );
-                    (String::new(), summary)
+                    (String::new(), summary, Vec::new(), None)
                 }
             },
-            "txt" | "md" | "json" | "csv" | "xml" | "yaml" | "yml" | "log" | "rtf" => {
-                match fs::read_to_string(&dest_path) {
-                    Ok(text) => {
-                        let cleaned_text = if text.len() > 10000 {
-                            format!(
-                                "{}... [Truncated - {} characters total]",
-                                &text[..10000],
-                                text.len()
-                            )
-                        } else {
-                            text
-                        };
-                        let summary = format!(
-                            "Text document: {} [{} bytes] - Content extracted: {} chars",
-                            filename, file_size, cleaned_text.len()
-                        );
-                        (cleaned_text, summary)
-                    }
-                    Err(e) => {
-                        let summary = format!(
-                            "Text document: {} [{} bytes] - Content extraction failed: {}",
-                            filename, file_size, e
-                        );
-                        (String::new(), summary)
-                    }
-                }
-            }
-            "py" | "js" | "ts" | "jsx" | "tsx" | "java" | "cpp" | "c" | "cc" | "cxx" | "h"
-            | "hpp" | "go" | "rs" | "php" | "rb" | "swift" | "kt" | "scala" | "html" | "htm"
-            | "css" | "scss" | "sass" | "less" | "sql" | "sh" | "bash" | "zsh" | "fish" | "ps1"
-            | "bat" | "cmd" => {
-                // Code files - direct read with syntax preservation
-                match fs::read_to_string(&dest_path) {
-                    Ok(text) => {
-                        let cleaned_text = if text.len() > 10000 {
-                            format!(
-                                "{}... [Truncated - {} characters total]",
-                                &text[..10000],
-                                text.len()
-                            )
-                        } else {
-                            text
-                        };
-                        let summary = format!(
-                            "Code file: {} [{} bytes] - Content extracted: {} chars",
-                            filename, file_size, cleaned_text.len()
-                        );
-                        (cleaned_text, summary)
-                    }
-                    Err(e) => {
-                        let summary = format!(
-                            "Code file: {} [{} bytes] - Content extraction failed: {}",
-                            filename, file_size, e
-                        );
-                        (String::new(), summary)
-                    }
-                }
-            }
-            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" => {
-                let summary = format!(
-                    "Image file: {} [{} bytes] - Binary content not extractable",
-                    filename, file_size
-                );
-                (String::new(), summary)
-            }
-            "mp4" | "avi" | "mov" | "wmv" | "flv" | "webm" | "mkv" => {
-                let summary = format!(
-                    "Video file: {} [{} bytes] - Binary content not extractable",
-                    filename, file_size
-                );
-                (String::new(), summary)
-            }
-            "mp3" | "wav" | "flac" | "aac" | "ogg" => {
-                let summary = format!(
-                    "Audio file: {} [{} bytes] - Binary content not extractable",
-                    filename, file_size
-                );
-                (String::new(), summary)
-            }
-            "zip" | "rar" | "7z" | "tar" | "gz" => {
-                let summary = format!(
-                    "Archive file: {} [{} bytes] - Binary content not extractable",
-                    filename, file_size
-                );
-                (String::new(), summary)
-            }
-            _ => {
-                let summary = format!(
-                    "Unknown file type: {} [{} bytes] - Binary content not extractable",
-                    filename, file_size
-                );
-                (String::new(), summary)
-            }
+            None => (
+                String::new(),
+                Self::binary_summary(filename, file_type, file_size),
+                Vec::new(),
+                None,
+            ),
         };
 
         let file_info = FileInfo {
@@ -519,23 +605,54 @@ impl FileStorage {
             file_type: file_type.to_string(),
             size: file_size,
             upload_date: Utc::now().to_rfc3339(),
+            language: extractors::detect_language(&content),
             content,
             is_context_enabled: true, // Default to enabled
             summary,
             conversation_id: None,
+            parent_id: None,
+            page_range: None,
+            metadata,
+            extractor: extractors::find(file_type).map(extractors::Extractor::stamp),
+            origin: None,
         };
 
-        // 6. Save to JSON index
-        let mut files = self.list_files().unwrap_or_else(|_| vec![]);
-        files.push(file_info.clone());
-        self.save_index(&files)?;
+        Ok((file_info, children))
+    }
 
-        println!(
-            "[FileStorage] Successfully stored file: {} ({} bytes)",
-            file_info.name, file_info.size
-        );
+    /// Summary for file types without a text extractor
+    fn binary_summary(filename: &str, file_type: &str, file_size: u64) -> String {
+        format!(
+            "{}: {} [{} bytes] - Binary content not extractable",
+            Self::binary_kind(file_type), filename, file_size
+        )
+    }
 
-        Ok(file_info)
+    fn binary_kind(file_type: &str) -> &'static str {
+        match file_type {
+            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" => "Image file",
+            "mp4" | "avi" | "mov" | "wmv" | "flv" | "webm" | "mkv" | "m4v" => "Video file",
+            "mp3" | "wav" | "flac" | "aac" | "ogg" | "m4a" => "Audio file",
+            "zip" | "rar" | "7z" | "tar" | "gz" => "Archive file",
+            _ => "Unknown file type",
+        }
+    }
+
+    /// Cap stored content at 10k characters, cutting on a char boundary
+    fn truncate_content(text: String) -> String {
+        const MAX_CONTENT_CHARS: usize = 10000;
+        if text.len() <= MAX_CONTENT_CHARS {
+            return text;
+        }
+        let mut cut = MAX_CONTENT_CHARS;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        format!(
+            "{}... [Truncated - {} characters total]",
+            &text[..cut],
+            text.len()
+        )
     }
 
     /// Get file type from filename
@@ -561,32 +678,389 @@ impl FileStorage {
             return Err(anyhow!("File not found on filesystem: {:?}", file_path));
         }
 
+        if let Some((from, to)) = file_info.page_range {
+            return extractors::pdf::extract_page_range(&file_path, from, to).map(|(text, _)| text);
+        }
+
         // Extract content based on file type
-        match file_info.file_type.as_str() {
-            "pdf" => self.extract_pdf_text(&file_path),
-            "txt" | "md" | "json" | "csv" | "xml" | "yaml" | "yml" | "log" | "rtf" => {
-                fs::read_to_string(&file_path).map_err(|e| anyhow!("Failed to read text file: {}", e))
+        match extractors::find(&file_info.file_type) {
+            Some(extractor) => self
+                .cache
+                .extract(extractor, &file_path, &file_info.file_type)
+                .map(|e| e.text),
+            // Binary files only have content if it was produced later (e.g. a transcript)
+            None => Ok(file_info.content.clone()),
+        }
+    }
+
+    /// Whether a file's content came from an older (or no) run of its current extractor
+    pub fn is_stale(file: &FileInfo) -> bool {
+        extractors::find(&file.file_type)
+            .is_some_and(|e| file.extractor.as_deref() != Some(e.stamp().as_str()))
+    }
+
+    /// Re-run the current extractor against a stored blob, keeping the record's identity,
+    /// context toggle, links and PDF page range. Types without an extractor are left as-is.
+    pub fn reextract_file(&self, file_id: &str) -> Result<FileInfo> {
+        let existing = self.get_file(file_id)?;
+        if extractors::find(&existing.file_type).is_none() {
+            return Ok(existing);
+        }
+
+        // Embedded files were ingested at upload time; don't duplicate them
+        let (fresh, _) = self.build_file_info(existing.id.clone(), &existing.name, &existing.file_type)?;
+        let updated = FileInfo {
+            upload_date: existing.upload_date,
+            is_context_enabled: existing.is_context_enabled,
+            conversation_id: existing.conversation_id,
+            parent_id: existing.parent_id,
+            origin: existing.origin,
+            ..fresh
+        };
+        self.save_file_to_index(&updated)?;
+        println!("[FileStorage] Re-extracted {} ({} chars)", updated.name, updated.content.len());
+
+        match existing.page_range {
+            Some((from, to)) => self.extract_pdf_pages(file_id, from, to),
+            None => {
+                if updated.is_context_enabled {
+                    self.index_in_background(file_id);
+                }
+                Ok(updated)
             }
-            "py" | "js" | "ts" | "jsx" | "tsx" | "java" | "cpp" | "c" | "go" | "rs" | "php" 
-            | "html" | "css" | "sql" => {
-                fs::read_to_string(&file_path).map_err(|e| anyhow!("Failed to read code file: {}", e))
+        }
+    }
+
+    /// Re-extract every file (or only stale ones), reporting progress per file
+    pub fn reextract_all(
+        &self,
+        stale_only: bool,
+        mut on_file: impl FnMut(ReextractProgress),
+    ) -> Result<ReextractReport> {
+        let targets: Vec<FileInfo> = self
+            .list_files()?
+            .into_iter()
+            .filter(|f| extractors::find(&f.file_type).is_some())
+            .filter(|f| !stale_only || Self::is_stale(f))
+            .collect();
+
+        let mut report = ReextractReport::default();
+        for (i, file) in targets.iter().enumerate() {
+            on_file(ReextractProgress {
+                file_id: file.id.clone(),
+                name: file.name.clone(),
+                index: i + 1,
+                total: targets.len(),
+            });
+            match self.reextract_file(&file.id) {
+                Ok(_) => report.updated += 1,
+                Err(e) => report.failed.push(format!("{}: {}", file.name, e)),
             }
-            _ => {
-                // For binary files, return empty string
-                Ok(String::new())
+        }
+        Ok(report)
+    }
+
+    /// Look up a single file record by ID
+    pub fn get_file(&self, file_id: &str) -> Result<FileInfo> {
+        self.list_files()?
+            .into_iter()
+            .find(|f| f.id == file_id)
+            .ok_or_else(|| anyhow!("File not found: {}", file_id))
+    }
+
+    /// Files whose declared tags include `tag` (case-insensitive, leading '#' ignored)
+    pub fn list_files_by_tag(&self, tag: &str) -> Result<Vec<FileInfo>> {
+        let tag = tag.trim().trim_start_matches('#');
+        Ok(self
+            .list_files()?
+            .into_iter()
+            .filter(|f| {
+                f.metadata
+                    .as_ref()
+                    .is_some_and(|m| m.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            })
+            .collect())
+    }
+
+    /// Ingested members of an archive upload as a directory tree, built from their paths
+    pub fn archive_tree(&self, file_id: &str) -> Result<Vec<ArchiveNode>> {
+        let mut roots: Vec<ArchiveNode> = Vec::new();
+        for child in self.list_files()?.into_iter().filter(|f| f.parent_id.as_deref() == Some(file_id)) {
+            let mut level = &mut roots;
+            let parts: Vec<&str> = child.name.split('/').filter(|p| !p.is_empty()).collect();
+            for (i, part) in parts.iter().enumerate() {
+                let pos = match level.iter().position(|n| n.name == *part) {
+                    Some(pos) => pos,
+                    None => {
+                        level.push(ArchiveNode { name: part.to_string(), file_id: None, children: Vec::new() });
+                        level.len() - 1
+                    }
+                };
+                if i == parts.len() - 1 {
+                    level[pos].file_id = Some(child.id.clone());
+                }
+                level = &mut level[pos].children;
             }
         }
+        Ok(roots)
+    }
+
+    /// Chunk vector index for context-enabled files and conversation history
+    pub fn vectors(&self) -> Result<VectorStore> {
+        VectorStore::open(&self.uploads_dir.join(VECTOR_DB))
+    }
+
+    /// Embed a file's chunks and replace its entries in the vector index
+    pub fn index_file(&self, file_id: &str) -> Result<embeddings::FileEmbeddings> {
+        let _indexing = vector_store::indexing();
+        let mut store = self.vectors()?;
+        let embedded = embeddings::embed_file(self, file_id, Some(&store))?;
+        let update = store.upsert_file(Namespace::Files, &embedded)?;
+        println!(
+            "[FileStorage] Indexed {}: {} chunks added, {} kept, {} removed",
+            file_id, update.added, update.kept, update.removed
+        );
+        Self::reembed_stale(&mut store);
+        Ok(embedded)
+    }
+
+    /// After a model switch, the rest of the index gets vectors from the new model too.
+    /// Best-effort; what's left is picked up after the next indexing.
+    pub(crate) fn reembed_stale(store: &mut VectorStore) {
+        if let Err(e) = embeddings::reembed_stale(store) {
+            println!("[FileStorage] Failed to re-embed chunks for the new model: {}", e);
+        }
+    }
+
+    /// Index a context-enabled file on a background thread so uploads and toggles stay fast.
+    /// Best-effort: without a usable embedding model the file just isn't indexed.
+    fn index_in_background(&self, file_id: &str) {
+        let file_id = file_id.to_string();
+        std::thread::spawn(move || {
+            let result = FileStorage::new().and_then(|storage| storage.index_file(&file_id));
+            if let Err(e) = result {
+                println!("[FileStorage] Not indexing {} for retrieval: {}", file_id, e);
+            }
+        });
+    }
+
+    /// Bring the index in line with the files list: embed context-enabled files it is
+    /// missing and drop everything else. Returns (indexed, removed).
+    pub fn sync_index(&self) -> Result<(usize, usize)> {
+        let _indexing = vector_store::indexing();
+        let files = self.list_files()?;
+        let mut store = self.vectors()?;
+        let indexed = store.indexed_files(Namespace::Files)?;
+
+        let mut removed = 0;
+        for id in &indexed {
+            if !files.iter().any(|f| &f.id == id && f.is_context_enabled) {
+                store.remove_file(Namespace::Files, id)?;
+                removed += 1;
+            }
+        }
+        let mut added = 0;
+        for file in files.iter().filter(|f| f.is_context_enabled && !indexed.contains(&f.id)) {
+            let embedded = embeddings::embed_file(self, &file.id, Some(&store))?;
+            store.upsert_file(Namespace::Files, &embedded)?;
+            added += 1;
+        }
+        Self::reembed_stale(&mut store);
+        Ok((added, removed))
+    }
+
+    /// Chunks of context-enabled files most relevant to `query`, by keyword (optionally
+    /// expanded) and vector search fused together, then reranked when a reranker is configured. If the query
+    /// can't be embedded right now (model missing, API down) the keyword half still answers.
+    pub fn retrieve(&self, query: &str, limit: usize) -> Result<Vec<ScoredChunk>> {
+        self.retrieve_in(Namespace::Files, query, limit, &mut Tracer::silent())
+    }
+
+    /// `retrieve` over any namespace of the index, e.g. conversation history, reporting
+    /// each stage to `trace`
+    pub fn retrieve_in(
+        &self,
+        namespace: Namespace,
+        query: &str,
+        limit: usize,
+        trace: &mut Tracer,
+    ) -> Result<Vec<ScoredChunk>> {
+        let settings = Settings::load().unwrap_or_default();
+        let store = self.vectors()?;
+        let embedded = embeddings::Embedder::from_settings(&settings.embeddings)
+            .and_then(|embedder| {
+                let vector = embedder
+                    .embed(&[query.to_string()])?
+                    .pop()
+                    .ok_or_else(|| anyhow!("Embedding provider returned no vector"))?;
+                Ok((embedder.model_id(), vector))
+            });
+        let query_vector = match &embedded {
+            Ok((model, vector)) if store.model()?.as_deref() == Some(model.as_str()) => {
+                Some((model.as_str(), vector.as_slice()))
+            }
+            Ok(_) => None,
+            Err(e) => {
+                println!("[FileStorage] Keyword-only retrieval: {}", e);
+                None
+            }
+        };
+        let embed_detail = match (&embedded, query_vector) {
+            (Ok((model, _)), Some(_)) => model.clone(),
+            (Ok((model, _)), None) => format!("{} doesn't match the index; keyword only", model),
+            (Err(e), _) => format!("keyword only: {}", e),
+        };
+        trace.stage("embed_query", 0, Some(embed_detail));
+
+        // Expanded terms only widen the keyword half; the vector half already matches meaning
+        let expansions = query_expansion::expand(&settings.retrieval, query);
+        let keywords = if expansions.is_empty() {
+            query.to_string()
+        } else {
+            trace.stage("expand_query", expansions.len(), Some(expansions.join(", ")));
+            format!("{} {}", query, expansions.join(" "))
+        };
+        let reranking = settings.rerank.provider != RerankProvider::None;
+        let depth = if reranking { settings.rerank.candidates.max(limit) } else { limit };
+        let candidates = store.hybrid_search(namespace, &keywords, query_vector, depth)?;
+        trace.stage("search", candidates.len(), None);
+        if !reranking {
+            return Ok(candidates);
+        }
+        let hits = rerank::rerank(&settings.rerank, query, candidates, limit);
+        trace.stage("rerank", hits.len(), Some(format!("{:?}", settings.rerank.provider).to_lowercase()));
+        Ok(hits)
+    }
+
+    /// Drop files from the vector index
+    fn unindex(&self, file_ids: &[&str]) {
+        let result = self.vectors().and_then(|store| {
+            file_ids.iter().try_for_each(|id| store.remove_file(Namespace::Files, id).map(|_| ()))
+        });
+        if let Err(e) = result {
+            println!("[FileStorage] Failed to update vector index: {}", e);
+        }
+    }
+
+    /// Path of the stored blob for a file ID
+    pub fn blob_path(&self, file_id: &str) -> PathBuf {
+        self.uploads_dir.join(file_id)
+    }
+
+    /// Replace a media file's content with its timecoded transcript
+    pub fn set_transcript(&self, file_id: &str, transcript: String) -> Result<FileInfo> {
+        let mut file_info = self.get_file(file_id)?;
+        file_info.content = Self::truncate_content(transcript);
+        file_info.language = extractors::detect_language(&file_info.content);
+        file_info.summary = format!(
+            "{}: {} [{} bytes] - Transcript: {} chars",
+            Self::binary_kind(&file_info.file_type),
+            file_info.name,
+            file_info.size,
+            file_info.content.len()
+        );
+        self.save_file_to_index(&file_info)?;
+        if file_info.is_context_enabled {
+            self.index_in_background(file_id);
+        }
+        println!("[FileStorage] Stored transcript for {} ({} chars)", file_info.name, file_info.content.len());
+        Ok(file_info)
+    }
+
+    /// Store downloaded bytes (e.g. from Drive or OneDrive) like a file picked from disk, and
+    /// record where they came from
+    pub fn import_bytes(&self, bytes: &[u8], filename: &str, origin: &str) -> Result<FileInfo> {
+        // store_file_from_path_robust copies from a path
+        let dir = std::env::temp_dir().join(format!("agi-import-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("download");
+        fs::write(&path, bytes)?;
+        let file_type = Self::get_file_type_from_name(filename);
+        let stored = self.store_file_from_path_robust(&path.to_string_lossy(), filename, &file_type, false);
+        let _ = fs::remove_dir_all(&dir);
+        self.set_origin(&stored?.id, origin)
+    }
+
+    /// Record where an imported file came from
+    pub fn set_origin(&self, file_id: &str, origin: &str) -> Result<FileInfo> {
+        let mut file_info = self.get_file(file_id)?;
+        file_info.origin = Some(origin.to_string());
+        self.save_file_to_index(&file_info)?;
+        Ok(file_info)
+    }
+
+    /// Narrow a PDF's context to an inclusive page range, e.g. one chapter of a long manual
+    pub fn extract_pdf_pages(&self, file_id: &str, from_page: u32, to_page: u32) -> Result<FileInfo> {
+        let mut file_info = self.get_file(file_id)?;
+        if file_info.file_type != "pdf" {
+            return Err(anyhow!("Not a PDF: {}", file_info.name));
+        }
+
+        let (text, total) =
+            extractors::pdf::extract_page_range(&self.blob_path(file_id), from_page, to_page)?;
+        file_info.content = Self::truncate_content(text);
+        file_info.page_range = if from_page == 1 && to_page == total {
+            None
+        } else {
+            Some((from_page, to_page))
+        };
+        file_info.summary = format!(
+            "PDF document: {} [{} bytes] - Pages {}-{} of {}: {} chars",
+            file_info.name,
+            file_info.size,
+            from_page,
+            to_page,
+            total,
+            file_info.content.len()
+        );
+        self.save_file_to_index(&file_info)?;
+        if file_info.is_context_enabled {
+            self.index_in_background(file_id);
+        }
+        Ok(file_info)
     }
 
     /// Get optimized context content for AI conversations
     /// This implements smart chunking and summarization strategies
     /// Content is extracted on-demand to avoid parsing during upload
-    pub fn get_optimized_context(&self) -> Result<Vec<String>, String> {
-        let files = self
+    /// Files in `preferred_language` (the conversation's, if known) are placed first
+    pub fn get_optimized_context(
+        &self,
+        preferred_language: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<Vec<String>, String> {
+        Ok(self
+            .get_context_chunks(preferred_language, model)?
+            .into_iter()
+            .map(|c| c.text)
+            .collect())
+    }
+
+    /// Context blocks for enabled files with token counts for `model`; large documents
+    /// are split into chunks of at most CHUNK_TOKENS
+    pub fn get_context_chunks(
+        &self,
+        preferred_language: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<Vec<ContextChunk>, String> {
+        let mut files = self
             .list_files()
             .map_err(|e| format!("Failed to list files: {}", e))?;
+        if let Some(lang) = preferred_language {
+            files.sort_by_key(|f| f.language.as_deref() != Some(lang));
+        }
 
-        let mut context_content: Vec<String> = Vec::new();
+        let count = |text: &str| chunking::count_tokens(model, text);
+        let whole = |file: &FileInfo, text: String| ContextChunk {
+            file_id: file.id.clone(),
+            tokens: count(&text),
+            text,
+            citation: None,
+            name: file.name.clone(),
+            score: None,
+            also_in: Vec::new(),
+        };
+        let mut context_content: Vec<ContextChunk> = Vec::new();
 
         // Filter enabled files and create optimized context
         for file in files.iter().filter(|f| f.is_context_enabled) {
@@ -598,13 +1072,17 @@ impl FileStorage {
                         continue;
                     }
 
+                    // Declared title/tags go in the header so they can be matched on
+                    let label = Self::context_label(file);
+
                     // Use smart chunking for large documents
                     if content.len() > 2000 {
-                        let chunks = Self::create_smart_chunks(&file.name, &content);
-                        context_content.extend(chunks);
+                        context_content.extend(Self::create_smart_chunks(file, &label, &content, &count));
                     } else {
-                        context_content
-                            .push(format!("Document: {}\nContent:\n{}", file.name, content));
+                        context_content.push(whole(
+                            file,
+                            format!("Document: {}\nContent:\n{}", label, content),
+                        ));
                     }
                 }
                 Err(e) => {
@@ -613,9 +1091,9 @@ impl FileStorage {
                         file.name, e
                     );
                     // Add file info even if content extraction fails
-                    context_content.push(format!(
-                        "Document: {} [Content extraction failed: {}]",
-                        file.name, e
+                    context_content.push(whole(
+                        file,
+                        format!("Document: {} [Content extraction failed: {}]", file.name, e),
                     ));
                 }
             }
@@ -624,52 +1102,220 @@ impl FileStorage {
         Ok(context_content)
     }
 
-    /// Create smart chunks for large documents
-    /// Implements sliding window approach with overlap
-    fn create_smart_chunks(filename: &str, content: &str) -> Vec<String> {
-        const CHUNK_SIZE: usize = 1500; // Optimal for most LLMs
-        const OVERLAP_SIZE: usize = 200; // Overlap to maintain context
-
-        let words: Vec<&str> = content.split_whitespace().collect();
-        let mut chunks = Vec::new();
+    /// Context for one question: the chunks most relevant to `query` (hybrid retrieval),
+    /// with duplicates dropped, packed best-first into `max_tokens`. With a conversation,
+    /// files linked to other conversations are left out. Falls back to the whole enabled
+    /// corpus, still within budget, when nothing has been indexed yet.
+    pub fn get_context_for_query(
+        &self,
+        query: &str,
+        max_tokens: usize,
+        conversation_id: Option<&str>,
+        model: Option<&str>,
+        trace: Tracer,
+    ) -> Result<Vec<ContextChunk>> {
+        Ok(self.preview_context(query, max_tokens, conversation_id, model, trace)?.chunks)
+    }
 
-        if words.len() <= CHUNK_SIZE {
-            // Small document, return as single chunk
-            return vec![format!("Document: {}\nContent:\n{}", filename, content)];
-        }
+    /// The context `get_context_for_query` would send, with what was left out and why
+    pub fn preview_context(
+        &self,
+        query: &str,
+        max_tokens: usize,
+        conversation_id: Option<&str>,
+        model: Option<&str>,
+        mut trace: Tracer,
+    ) -> Result<ContextPreview> {
+        const CANDIDATES: usize = 50;
 
-        let mut start = 0;
-        let mut chunk_num = 1;
+        let files: Vec<FileInfo> = self
+            .list_files()?
+            .into_iter()
+            .filter(|f| f.is_context_enabled)
+            .filter(|f| match (conversation_id, f.conversation_id.as_deref()) {
+                (Some(wanted), Some(linked)) => wanted == linked,
+                _ => true,
+            })
+            .collect();
 
-        while start < words.len() {
-            let end = std::cmp::min(start + CHUNK_SIZE, words.len());
-            let chunk_words = &words[start..end];
-            let chunk_content = chunk_words.join(" ");
+        let count = |text: &str| chunking::count_tokens(model, text);
+        let hits: Vec<ScoredChunk> = self
+            .retrieve_in(Namespace::Files, query, CANDIDATES, &mut trace)?
+            .into_iter()
+            .filter(|hit| files.iter().any(|f| f.id == hit.chunk.file_id))
+            .collect();
+        trace.stage("filter", hits.len(), Some(format!("{} files in scope", files.len())));
 
-            let chunk_title = if words.len() > CHUNK_SIZE {
-                format!(
-                    "Document: {} (Part {}/{})",
-                    filename,
-                    chunk_num,
-                    (words.len() + CHUNK_SIZE - OVERLAP_SIZE - 1) / (CHUNK_SIZE - OVERLAP_SIZE)
-                )
-            } else {
-                format!("Document: {}", filename)
-            };
+        let retrieved = !hits.is_empty();
+        let candidates: Vec<ContextChunk> = if !retrieved {
+            let chunks: Vec<ContextChunk> = self
+                .get_context_chunks(None, model)
+                .map_err(|e| anyhow!(e))?
+                .into_iter()
+                .filter(|c| files.iter().any(|f| f.id == c.file_id))
+                .collect();
+            trace.stage("fallback", chunks.len(), Some("no retrieval hits; whole-file chunks".to_string()));
+            chunks
+        } else {
+            hits.into_iter()
+                .map(|hit| {
+                    let file = files.iter().find(|f| f.id == hit.chunk.file_id);
+                    let label = file.map_or(hit.chunk.file_id.clone(), Self::context_label);
+                    let name = file.map_or(hit.chunk.file_id.as_str(), |f| f.name.as_str());
+                    let citation = hit.chunk.source.cite(name);
+                    let section = hit
+                        .chunk
+                        .heading
+                        .map(|h| format!("Section: {}\n", h))
+                        .unwrap_or_default();
+                    let text = format!(
+                        "Document: {}\nSource: {}\n{}Content:\n{}",
+                        label, citation, section, hit.chunk.text
+                    );
+                    ContextChunk {
+                        name: name.to_string(),
+                        file_id: hit.chunk.file_id,
+                        tokens: count(&text),
+                        text,
+                        citation: Some(citation),
+                        score: Some(hit.score),
+                        also_in: Vec::new(),
+                    }
+                })
+                .collect()
+        };
 
-            chunks.push(format!("{}\nContent:\n{}", chunk_title, chunk_content));
+        // The same passage can come from re-uploads or boilerplate shared across files (email
+        // footers, license headers); collapse near-duplicates into their best-ranked copy
+        let mut kept_hashes: Vec<u64> = Vec::new();
+        let mut preview = ContextPreview {
+            chunks: Vec::new(),
+            total_tokens: 0,
+            max_tokens,
+            retrieved,
+            duplicates: 0,
+            over_budget: 0,
+        };
+        for chunk in candidates {
+            let body = chunk.text.split_once("Content:\n").map_or(chunk.text.as_str(), |(_, b)| b);
+            let hash = chunking::simhash(body);
+            if let Some(i) = kept_hashes.iter().position(|&h| chunking::near_duplicate(h, hash)) {
+                let kept = &mut preview.chunks[i];
+                if kept.name != chunk.name && !kept.also_in.contains(&chunk.name) {
+                    kept.also_in.push(chunk.name);
+                }
+                preview.duplicates += 1;
+                continue;
+            }
+            if preview.total_tokens + chunk.tokens > max_tokens {
+                preview.over_budget += 1;
+                continue;
+            }
+            kept_hashes.push(hash);
+            preview.total_tokens += chunk.tokens;
+            preview.chunks.push(chunk);
+        }
+        println!(
+            "[FileStorage] Context for query: {} chunks, {}/{} tokens",
+            preview.chunks.len(),
+            preview.total_tokens,
+            max_tokens
+        );
+        trace.stage(
+            "assemble",
+            preview.chunks.len(),
+            Some(format!(
+                "{}/{} tokens, {} duplicates, {} over budget",
+                preview.total_tokens, max_tokens, preview.duplicates, preview.over_budget
+            )),
+        );
+        trace.finish(
+            preview
+                .chunks
+                .iter()
+                .map(|c| SelectedChunk {
+                    file_id: c.file_id.clone(),
+                    name: c.name.clone(),
+                    citation: c.citation.clone(),
+                    score: c.score,
+                    tokens: c.tokens,
+                })
+                .collect(),
+        );
+        Ok(preview)
+    }
 
-            // Move start position with overlap
-            start = end.saturating_sub(OVERLAP_SIZE);
-            chunk_num += 1;
+    /// "name (title; tags: a, b)" when the file declares metadata, otherwise the name
+    fn context_label(file: &FileInfo) -> String {
+        let Some(meta) = &file.metadata else { return file.name.clone() };
+        let mut parts = Vec::new();
+        if let Some(title) = &meta.title {
+            parts.push(title.clone());
+        }
+        if !meta.tags.is_empty() {
+            parts.push(format!("tags: {}", meta.tags.join(", ")));
+        }
+        if parts.is_empty() {
+            file.name.clone()
+        } else {
+            format!("{} ({})", file.name, parts.join("; "))
+        }
+    }
 
-            // Prevent infinite loop
-            if start == end {
-                break;
-            }
+    /// Create smart chunks for large documents
+    /// Splits on headings, paragraphs and code blocks, falling back to sentences
+    fn create_smart_chunks(
+        file: &FileInfo,
+        label: &str,
+        content: &str,
+        count: &dyn Fn(&str) -> usize,
+    ) -> Vec<ContextChunk> {
+        let settings = Settings::load().unwrap_or_default().chunking;
+        let chunks = chunking::chunk_file(content, &file.file_type, settings.context_tokens, settings.context_overlap_tokens, &settings, count);
+        if chunks.len() <= 1 {
+            // Small document, return as single chunk
+            let text = format!("Document: {}\nContent:\n{}", label, content);
+            return vec![ContextChunk {
+                file_id: file.id.clone(),
+                tokens: count(&text),
+                text,
+                citation: None,
+                name: file.name.clone(),
+                score: None,
+                also_in: Vec::new(),
+            }];
         }
 
+        let total = chunks.len();
         chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let citation = chunk.source.cite(&file.name);
+                let section = chunk
+                    .heading
+                    .map(|h| format!("Section: {}\n", h))
+                    .unwrap_or_default();
+                let text = format!(
+                    "Document: {} (Part {}/{})\nSource: {}\n{}Content:\n{}",
+                    label,
+                    i + 1,
+                    total,
+                    citation,
+                    section,
+                    chunk.text
+                );
+                ContextChunk {
+                    file_id: file.id.clone(),
+                    tokens: count(&text),
+                    text,
+                    citation: Some(citation),
+                    name: file.name.clone(),
+                    score: None,
+                    also_in: Vec::new(),
+                }
+            })
+            .collect()
     }
 }
 
@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::Emitter;
+
+use crate::settings::Settings;
+
+/// A compiled-in feature flag and its default state
+struct FlagDef {
+    name: &'static str,
+    description: &'static str,
+    default: bool,
+}
+
+static FLAGS: &[FlagDef] = &[
+    FlagDef {
+        name: "google_mcp_token_bridge",
+        description: "Also write Google tokens in plaintext to ~/.google_workspace_mcp, ~/.calendar-mcp and ~/.gmail-mcp for the MCP servers",
+        default: false,
+    },
+    FlagDef {
+        name: "mcp",
+        description: "Enable MCP tool calling in the sidecar agent",
+        default: true,
+    },
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagSource {
+    Default,
+    Settings,
+    Policy,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub description: String,
+    pub enabled: bool,
+    pub default: bool,
+    pub source: FlagSource,
+    pub locked: bool,              // Set by the admin policy file; cannot be changed by the user
+}
+
+/// Admin policy file; overrides user settings and locks the flags it names.
+#[derive(Debug, Deserialize, Default)]
+struct Policy {
+    #[serde(default)]
+    feature_flags: HashMap<String, bool>,
+}
+
+fn policy_path() -> PathBuf {
+    if let Ok(path) = std::env::var("AGI_POLICY_FILE") {
+        return PathBuf::from(path);
+    }
+    if cfg!(target_os = "windows") {
+        let program_data = std::env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+        PathBuf::from(program_data).join("AGI").join("policy.json")
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Library/Application Support/AGI/policy.json")
+    } else {
+        PathBuf::from("/etc/agi/policy.json")
+    }
+}
+
+fn load_policy() -> Policy {
+    let path = policy_path();
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("[flags] Ignoring malformed policy file {:?}: {}", path, e);
+            Policy::default()
+        }),
+        Err(_) => Policy::default(),
+    }
+}
+
+fn resolve(def: &FlagDef, settings: &Settings, policy: &Policy) -> FeatureFlag {
+    let (enabled, source) = if let Some(v) = policy.feature_flags.get(def.name) {
+        (*v, FlagSource::Policy)
+    } else if let Some(v) = settings.feature_flags.get(def.name) {
+        (*v, FlagSource::Settings)
+    } else {
+        (def.default, FlagSource::Default)
+    };
+    FeatureFlag {
+        name: def.name.to_string(),
+        description: def.description.to_string(),
+        enabled,
+        default: def.default,
+        source,
+        locked: source == FlagSource::Policy,
+    }
+}
+
+/// Current state of every known flag
+pub fn list() -> Vec<FeatureFlag> {
+    let settings = Settings::load().unwrap_or_default();
+    let policy = load_policy();
+    FLAGS.iter().map(|def| resolve(def, &settings, &policy)).collect()
+}
+
+/// Whether a flag is on. Unknown flags are always off.
+pub fn is_enabled(name: &str) -> bool {
+    list().into_iter().any(|f| f.name == name && f.enabled)
+}
+
+/// Persist a user override for a flag
+pub fn set(name: &str, enabled: bool) -> Result<FeatureFlag> {
+    let def = FLAGS
+        .iter()
+        .find(|d| d.name == name)
+        .ok_or_else(|| anyhow!("Unknown feature flag: {}", name))?;
+    let policy = load_policy();
+    if policy.feature_flags.contains_key(name) {
+        return Err(anyhow!("Feature flag '{}' is locked by the admin policy", name));
+    }
+    let settings = Settings::update(|s| {
+        s.feature_flags.insert(name.to_string(), enabled);
+    })?;
+    Ok(resolve(def, &settings, &policy))
+}
+
+#[tauri::command]
+pub fn list_feature_flags() -> Vec<FeatureFlag> {
+    list()
+}
+
+#[tauri::command]
+pub fn set_feature_flag(app: tauri::AppHandle, name: String, enabled: bool) -> Result<FeatureFlag, String> {
+    let flag = set(&name, enabled).map_err(|e| format!("Failed to set feature flag: {}", e))?;
+    if let Err(e) = app.emit("feature-flags:changed", &flag) {
+        eprintln!("[flags] Failed to emit change event: {}", e);
+    }
+    Ok(flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_overrides_settings() {
+        let mcp = FLAGS.iter().find(|d| d.name == "mcp").unwrap();
+        let mut settings = Settings::default();
+        let mut policy = Policy::default();
+
+        let flag = resolve(mcp, &settings, &policy);
+        assert!(flag.enabled && flag.source == FlagSource::Default && !flag.locked);
+
+        settings.feature_flags.insert("mcp".to_string(), false);
+        let flag = resolve(mcp, &settings, &policy);
+        assert!(!flag.enabled && flag.source == FlagSource::Settings && !flag.locked);
+
+        // The admin's choice wins over the user's, either way, and locks the flag
+        policy.feature_flags.insert("mcp".to_string(), true);
+        let flag = resolve(mcp, &settings, &policy);
+        assert!(flag.enabled && flag.source == FlagSource::Policy && flag.locked);
+        settings.feature_flags.insert("mcp".to_string(), true);
+        policy.feature_flags.insert("mcp".to_string(), false);
+        assert!(!resolve(mcp, &settings, &policy).enabled);
+
+        // Policy entries for other flags leave this one to the user
+        policy.feature_flags.clear();
+        policy.feature_flags.insert("google_mcp_token_bridge".to_string(), true);
+        assert_eq!(resolve(mcp, &settings, &policy).source, FlagSource::Settings);
+    }
+}
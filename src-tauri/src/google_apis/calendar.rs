@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use super::{get_json, post_json};
+use crate::pii_scrubber::ScrubPass;
+
+const API: &str = "https://www.googleapis.com/calendar/v3";
+// Any of these lets the app read events
+const READ_SCOPES: &[&str] = &["calendar.readonly", "calendar", "calendar.events", "calendar.events.readonly"];
+// Any of these lets the app add events
+const WRITE_SCOPES: &[&str] = &["calendar.events", "calendar"];
+const DEFAULT_DAYS: i64 = 7;
+const MAX_EVENTS: usize = 250;
+
+/// Which events list_calendar_events returns
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CalendarRange {
+    pub start: Option<String>,         // RFC 3339, or a date for local midnight; default now
+    pub end: Option<String>,           // RFC 3339, or a date for the end of that day; default a week after start
+    pub calendar_id: Option<String>,   // Default "primary"
+    pub query: Option<String>,         // Free text matched against the events
+    pub max_results: Option<usize>,
+}
+
+/// An event to add with create_calendar_event
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct NewCalendarEvent {
+    pub summary: String,
+    pub start: String,                 // RFC 3339, or a date for an all-day event
+    pub end: Option<String>,           // Same form as start; or give duration_minutes
+    pub duration_minutes: Option<i64>, // Default 30 for timed events, one day for all-day ones
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub attendees: Vec<String>,        // Email addresses
+    pub notify_attendees: bool,        // Send invitations; off by default
+    pub calendar_id: Option<String>,
+}
+
+/// One calendar event, scrubbed with the PII config
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct CalendarEvent {
+    pub id: String,
+    pub summary: String,
+    pub start: String,                 // RFC 3339, or YYYY-MM-DD for all-day events
+    pub end: String,
+    pub all_day: bool,
+    pub location: String,
+    pub description: String,
+    pub organizer: String,
+    pub attendees: Vec<String>,
+    pub status: String,                // "confirmed", "tentative" or "cancelled"
+    pub html_link: String,
+}
+
+fn local_midnight(date: NaiveDate) -> Result<DateTime<FixedOffset>> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|t| t.fixed_offset())
+        .ok_or_else(|| anyhow!("{} has no local midnight", date))
+}
+
+// One end of a range: RFC 3339 as is, or a date as the local start (or end) of that day
+fn parse_bound(value: &str, end_of_day: bool) -> Result<DateTime<FixedOffset>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time);
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| anyhow!("{:?} isn't a date or an RFC 3339 time", value))?;
+    local_midnight(if end_of_day { date + Duration::days(1) } else { date })
+}
+
+fn parse_event(v: &Value) -> CalendarEvent {
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let time = |t: &Value| t["dateTime"].as_str().or_else(|| t["date"].as_str()).unwrap_or_default().to_string();
+    CalendarEvent {
+        id: text(&v["id"]),
+        summary: text(&v["summary"]),
+        start: time(&v["start"]),
+        end: time(&v["end"]),
+        all_day: v["start"]["date"].is_string(),
+        location: text(&v["location"]),
+        description: text(&v["description"]),
+        organizer: text(&v["organizer"]["email"]),
+        attendees: v["attendees"]
+            .as_array()
+            .map(|a| a.iter().filter_map(|p| p["email"].as_str().map(|e| e.to_string())).collect())
+            .unwrap_or_default(),
+        status: text(&v["status"]),
+        html_link: text(&v["htmlLink"]),
+    }
+}
+
+fn scrub_event(pass: &mut ScrubPass, event: CalendarEvent) -> CalendarEvent {
+    CalendarEvent {
+        summary: pass.text(&event.summary),
+        location: pass.text(&event.location),
+        description: pass.text(&event.description),
+        organizer: pass.text(&event.organizer),
+        attendees: event.attendees.iter().map(|a| pass.text(a)).collect(),
+        ..event
+    }
+}
+
+// The API resource for a new event
+fn event_body(details: &NewCalendarEvent) -> Result<Value> {
+    if details.summary.trim().is_empty() {
+        return Err(anyhow!("the event needs a title"));
+    }
+    let all_day = NaiveDate::parse_from_str(&details.start, "%Y-%m-%d").ok();
+    let (start, end) = match all_day {
+        Some(date) => {
+            let end = match &details.end {
+                Some(end) => NaiveDate::parse_from_str(end, "%Y-%m-%d").map_err(|_| anyhow!("an all-day event ends on a date"))?,
+                None => date + Duration::days(details.duration_minutes.map_or(1, |m| (m / (24 * 60)).max(1))),
+            };
+            if end <= date {
+                return Err(anyhow!("the event ends before it starts"));
+            }
+            (serde_json::json!({ "date": date.to_string() }), serde_json::json!({ "date": end.to_string() }))
+        }
+        None => {
+            let start = parse_bound(&details.start, false)?;
+            let end = match &details.end {
+                Some(end) => parse_bound(end, false)?,
+                None => start + Duration::minutes(details.duration_minutes.unwrap_or(30)),
+            };
+            if end <= start {
+                return Err(anyhow!("the event ends before it starts"));
+            }
+            (serde_json::json!({ "dateTime": start.to_rfc3339() }), serde_json::json!({ "dateTime": end.to_rfc3339() }))
+        }
+    };
+    let mut body = serde_json::json!({ "summary": details.summary.trim(), "start": start, "end": end });
+    if let Some(description) = &details.description {
+        body["description"] = description.as_str().into();
+    }
+    if let Some(location) = &details.location {
+        body["location"] = location.as_str().into();
+    }
+    if !details.attendees.is_empty() {
+        body["attendees"] = details.attendees.iter().map(|email| serde_json::json!({ "email": email.trim() })).collect();
+    }
+    Ok(body)
+}
+
+fn calendar_url(calendar_id: Option<&str>) -> String {
+    format!("{}/calendars/{}/events", API, urlencoding::encode(calendar_id.unwrap_or("primary")))
+}
+
+/// Events overlapping the range, in start order, with recurring events expanded
+pub fn list_events(app: &AppHandle, range: &CalendarRange) -> Result<Vec<CalendarEvent>> {
+    let start = match &range.start {
+        Some(start) => parse_bound(start, false)?,
+        None => Utc::now().fixed_offset(),
+    };
+    let end = match &range.end {
+        Some(end) => parse_bound(end, true)?,
+        None => start + Duration::days(DEFAULT_DAYS),
+    };
+    let mut query = vec![
+        ("timeMin", start.to_rfc3339()),
+        ("timeMax", end.to_rfc3339()),
+        ("singleEvents", "true".to_string()),
+        ("orderBy", "startTime".to_string()),
+        ("maxResults", range.max_results.unwrap_or(MAX_EVENTS).clamp(1, MAX_EVENTS).to_string()),
+    ];
+    if let Some(q) = &range.query {
+        query.push(("q", q.clone()));
+    }
+    let listed: Value = get_json(app, READ_SCOPES, &calendar_url(range.calendar_id.as_deref()), &query)?;
+    let mut pass = ScrubPass::new();
+    let events: Vec<CalendarEvent> = listed["items"]
+        .as_array()
+        .map(|items| items.iter().map(|e| scrub_event(&mut pass, parse_event(e))).collect())
+        .unwrap_or_default();
+    pass.finish(app, "list_calendar_events");
+    println!("[Calendar] {} event(s) between {} and {}", events.len(), start, end);
+    Ok(events)
+}
+
+/// Add an event; attendees are only emailed when `notify_attendees` is set
+pub fn create_event(app: &AppHandle, details: &NewCalendarEvent) -> Result<CalendarEvent> {
+    let body = event_body(details)?;
+    let send_updates = if details.notify_attendees { "all" } else { "none" };
+    let url = format!("{}?sendUpdates={}", calendar_url(details.calendar_id.as_deref()), send_updates);
+    let created: Value = post_json(app, WRITE_SCOPES, &url, &body)?;
+    let event = parse_event(&created);
+    println!("[Calendar] Created event {} ({} to {})", event.id, event.start, event.end);
+    Ok(event)
+}
+
+/// Events in a time range ("what's on Thursday?"); defaults to the next seven days of the
+/// primary calendar
+#[tauri::command]
+pub async fn list_calendar_events(app: AppHandle, range: Option<CalendarRange>) -> Result<Vec<CalendarEvent>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_events(&app, &range.unwrap_or_default()).map_err(|e| format!("Failed to list calendar events: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Calendar task failed: {}", e))?
+}
+
+/// Add an event to the connected calendar ("book 30 min with Sam")
+#[tauri::command]
+pub async fn create_calendar_event(app: AppHandle, details: NewCalendarEvent) -> Result<CalendarEvent, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        create_event(&app, &details).map_err(|e| format!("Failed to create calendar event: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Calendar task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_body_and_parsing() {
+        let meeting = NewCalendarEvent {
+            summary: "Sync with Sam".into(),
+            start: "2026-10-22T15:00:00+02:00".into(),
+            attendees: vec!["sam@example.com".into()],
+            ..Default::default()
+        };
+        let body = event_body(&meeting).unwrap();
+        assert_eq!(body["start"]["dateTime"], "2026-10-22T15:00:00+02:00");
+        assert_eq!(body["end"]["dateTime"], "2026-10-22T15:30:00+02:00");
+        assert_eq!(body["attendees"][0]["email"], "sam@example.com");
+
+        let offsite = NewCalendarEvent { summary: "Offsite".into(), start: "2026-10-22".into(), duration_minutes: Some(2 * 24 * 60), ..Default::default() };
+        let body = event_body(&offsite).unwrap();
+        assert_eq!((body["start"]["date"].as_str(), body["end"]["date"].as_str()), (Some("2026-10-22"), Some("2026-10-24")));
+        let backwards = NewCalendarEvent { end: Some("2026-10-22T14:00:00+02:00".into()), ..meeting };
+        assert!(event_body(&backwards).is_err());
+
+        // A date ends at the following local midnight
+        let day = parse_bound("2026-10-22", false).unwrap();
+        assert_eq!(parse_bound("2026-10-22", true).unwrap() - day, Duration::days(1));
+        assert!(parse_bound("Thursday", false).is_err());
+
+        let event = parse_event(&serde_json::json!({
+            "id": "e1", "summary": "Offsite", "status": "confirmed",
+            "start": { "date": "2026-10-22" }, "end": { "date": "2026-10-24" },
+            "attendees": [{ "email": "sam@example.com" }]
+        }));
+        assert!(event.all_day);
+        assert_eq!((event.start.as_str(), event.end.as_str()), ("2026-10-22", "2026-10-24"));
+        assert_eq!(event.attendees, vec!["sam@example.com"]);
+    }
+}
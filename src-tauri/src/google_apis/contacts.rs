@@ -0,0 +1,143 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::AppHandle;
+
+use super::{get_json};
+use crate::pii_scrubber::ScrubPass;
+
+const API: &str = "https://people.googleapis.com/v1";
+// Any of these lets the app read contacts
+const READ_SCOPES: &[&str] = &["contacts.readonly", "contacts"];
+const READ_MASK: &str = "names,emailAddresses,phoneNumbers,organizations";
+// The most searchContacts returns
+const MAX_RESULTS: usize = 30;
+
+// searchContacts answers from a cache that an empty query warms up
+static WARMED_UP: AtomicBool = AtomicBool::new(false);
+
+/// A contact matching a search. The fields are as stored in Google so a draft can be
+/// addressed; `scrubbed` is the form to keep in memory or history.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct Contact {
+    pub resource_name: String,         // e.g. "people/c123"
+    pub name: String,
+    pub emails: Vec<String>,           // Primary first
+    pub phones: Vec<String>,
+    pub organization: Option<String>,  // "Title, Company" when known
+    pub scrubbed: String,              // One-line summary with the PII config applied
+}
+
+fn values(list: &Value, field: &str) -> Vec<String> {
+    let Some(items) = list.as_array() else { return Vec::new() };
+    let mut primary_first: Vec<&Value> = items.iter().collect();
+    primary_first.sort_by_key(|item| !item["metadata"]["primary"].as_bool().unwrap_or(false));
+    primary_first.iter().filter_map(|item| item[field].as_str().map(|s| s.to_string())).collect()
+}
+
+fn parse_contact(person: &Value) -> Contact {
+    let organization = person["organizations"].as_array().and_then(|orgs| orgs.first()).and_then(|org| {
+        let parts: Vec<&str> = [org["title"].as_str(), org["name"].as_str()].into_iter().flatten().collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    });
+    Contact {
+        resource_name: person["resourceName"].as_str().unwrap_or_default().to_string(),
+        name: values(&person["names"], "displayName").into_iter().next().unwrap_or_default(),
+        emails: values(&person["emailAddresses"], "value"),
+        phones: values(&person["phoneNumbers"], "value"),
+        organization,
+        scrubbed: String::new(),
+    }
+}
+
+fn summary(contact: &Contact) -> String {
+    let mut summary = contact.name.clone();
+    if let Some(email) = contact.emails.first() {
+        summary.push_str(&format!(" <{}>", email));
+    }
+    if let Some(phone) = contact.phones.first() {
+        summary.push_str(&format!(", {}", phone));
+    }
+    if let Some(organization) = &contact.organization {
+        summary.push_str(&format!(" ({})", organization));
+    }
+    summary.trim().to_string()
+}
+
+/// Forget that the cache was warmed up, so the next search after connecting again warms
+/// the new account's; returns whether it was
+pub(crate) fn clear_cache() -> bool {
+    WARMED_UP.swap(false, Ordering::SeqCst)
+}
+
+/// Contacts whose name, email, phone or organization match `query`
+pub fn search(app: &AppHandle, query: &str) -> Result<Vec<Contact>> {
+    let url = format!("{}/people:searchContacts", API);
+    let search = |query: &str| -> Result<Value> {
+        get_json(
+            app,
+            READ_SCOPES,
+            &url,
+            &[("query", query.to_string()), ("readMask", READ_MASK.to_string()), ("pageSize", MAX_RESULTS.to_string())],
+        )
+    };
+    if !WARMED_UP.load(Ordering::SeqCst) {
+        search("")?;
+        WARMED_UP.store(true, Ordering::SeqCst);
+    }
+    let found = search(query.trim())?;
+
+    let mut pass = ScrubPass::new();
+    let contacts: Vec<Contact> = found["results"]
+        .as_array()
+        .map(|results| {
+            results
+                .iter()
+                .map(|r| {
+                    let contact = parse_contact(&r["person"]);
+                    Contact { scrubbed: pass.text(&summary(&contact)), ..contact }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    pass.finish(app, "search_contacts");
+    println!("[Contacts] {:?} matched {} contact(s)", query, contacts.len());
+    Ok(contacts)
+}
+
+/// Look up contacts by name, email, phone or company, e.g. to address a draft to "John from
+/// accounting"
+#[tauri::command]
+pub async fn search_contacts(app: AppHandle, query: String) -> Result<Vec<Contact>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        search(&app, &query).map_err(|e| format!("Failed to search contacts: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Contacts task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_contact_puts_primary_first() {
+        let person = serde_json::json!({
+            "resourceName": "people/c1",
+            "names": [{ "displayName": "John Doe" }],
+            "emailAddresses": [
+                { "value": "john@home.example" },
+                { "value": "john@work.example", "metadata": { "primary": true } }
+            ],
+            "phoneNumbers": [{ "value": "+1 555 0100" }],
+            "organizations": [{ "name": "Acme", "title": "Accountant" }]
+        });
+        let contact = parse_contact(&person);
+        assert_eq!(contact.name, "John Doe");
+        assert_eq!(contact.emails, vec!["john@work.example", "john@home.example"]);
+        assert_eq!(contact.organization.as_deref(), Some("Accountant, Acme"));
+        assert_eq!(summary(&contact), "John Doe <john@work.example>, +1 555 0100 (Accountant, Acme)");
+        assert_eq!(parse_contact(&serde_json::json!({})), Contact::default());
+    }
+}
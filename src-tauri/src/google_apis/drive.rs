@@ -0,0 +1,240 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use super::{get_bytes, get_json};
+use crate::file_storage::{FileInfo, FileStorage};
+use crate::google_oauth::DRIVE_ORIGIN_PREFIX;
+
+const API: &str = "https://www.googleapis.com/drive/v3";
+// Any of these lets the app list and download files
+const READ_SCOPES: &[&str] = &["drive.readonly", "drive", "drive.file"];
+const FOLDER_MIME: &str = "application/vnd.google-apps.folder";
+const FILE_FIELDS: &str = "id,name,mimeType,size,modifiedTime,parents,webViewLink,fileExtension";
+const MAX_LIST: usize = 100;
+// Larger files are better opened in Drive than pulled into context
+const MAX_IMPORT_BYTES: u64 = 100 * 1024 * 1024;
+
+// Google-native types and what they are exported as: (native type, export type, extension).
+// PDF keeps the layout and is what the PDF extractor reads.
+const EXPORTS: &[(&str, &str, &str)] = &[
+    ("application/vnd.google-apps.document", "application/pdf", "pdf"),
+    ("application/vnd.google-apps.spreadsheet", "application/pdf", "pdf"),
+    ("application/vnd.google-apps.presentation", "application/pdf", "pdf"),
+    ("application/vnd.google-apps.drawing", "application/pdf", "pdf"),
+    ("application/vnd.google-apps.script", "application/vnd.google-apps.script+json", "json"),
+];
+
+// Google-native types that have a text export: (native type, export type, extension).
+// Sheets export only their first sheet as CSV.
+const TEXT_EXPORTS: &[(&str, &str, &str)] = &[
+    ("application/vnd.google-apps.document", "text/plain", "txt"),
+    ("application/vnd.google-apps.spreadsheet", "text/csv", "csv"),
+    ("application/vnd.google-apps.presentation", "text/plain", "txt"),
+];
+
+/// A file or folder in the connected Drive
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct DriveFile {
+    pub id: String,
+    pub name: String,
+    pub mime_type: String,
+    pub size: Option<u64>,             // None for Google-native files and folders
+    pub modified_time: String,
+    pub parents: Vec<String>,
+    pub web_view_link: String,
+    pub is_folder: bool,
+    pub extension: Option<String>,     // From Drive, for uploaded (non-native) files
+}
+
+fn parse_file(v: &Value) -> DriveFile {
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let mime_type = text(&v["mimeType"]);
+    DriveFile {
+        id: text(&v["id"]),
+        name: text(&v["name"]),
+        is_folder: mime_type == FOLDER_MIME,
+        size: v["size"].as_str().and_then(|s| s.parse().ok()),
+        modified_time: text(&v["modifiedTime"]),
+        parents: v["parents"]
+            .as_array()
+            .map(|ps| ps.iter().filter_map(|p| p.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default(),
+        web_view_link: text(&v["webViewLink"]),
+        extension: v["fileExtension"].as_str().filter(|e| !e.is_empty()).map(|e| e.to_string()),
+        mime_type,
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// The Drive search expression: a folder's children, files matching `query`, or both.
+// Without either, the top of My Drive.
+fn search_expression(folder: Option<&str>, query: Option<&str>) -> String {
+    let mut terms = vec!["trashed = false".to_string()];
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+    match folder.map(str::trim).filter(|f| !f.is_empty()) {
+        Some(folder) => terms.push(format!("{} in parents", quote(folder))),
+        None if query.is_none() => terms.push("'root' in parents".to_string()),
+        None => {}
+    }
+    if let Some(query) = query {
+        terms.push(format!("(name contains {q} or fullText contains {q})", q = quote(query)));
+    }
+    terms.join(" and ")
+}
+
+// How to download `file`: Some((export type, name to store it under)) for Google-native files,
+// None for a plain download under its own name
+fn download_plan(file: &DriveFile, exports: &[(&str, &'static str, &str)]) -> Result<Option<(&'static str, String)>> {
+    if !file.mime_type.starts_with("application/vnd.google-apps.") {
+        return Ok(None);
+    }
+    let (_, export, extension) = exports
+        .iter()
+        .find(|(native, _, _)| *native == file.mime_type)
+        .ok_or_else(|| anyhow!("{} ({}) can't be exported from Drive", file.name, file.mime_type))?;
+    Ok(Some((export, format!("{}.{}", file.name, extension))))
+}
+
+// The name to store a plain download under, adding Drive's extension when the name has none
+fn stored_name(file: &DriveFile) -> String {
+    match &file.extension {
+        Some(ext) if !file.name.to_lowercase().ends_with(&format!(".{}", ext.to_lowercase())) => format!("{}.{}", file.name, ext),
+        _ => file.name.clone(),
+    }
+}
+
+/// Files in a folder and/or matching a search, folders first
+pub fn list_files(app: &AppHandle, folder: Option<&str>, query: Option<&str>) -> Result<Vec<DriveFile>> {
+    let listed: Value = get_json(
+        app,
+        READ_SCOPES,
+        &format!("{}/files", API),
+        &[
+            ("q", search_expression(folder, query)),
+            ("fields", format!("files({})", FILE_FIELDS)),
+            ("orderBy", "folder,modifiedTime desc".to_string()),
+            ("pageSize", MAX_LIST.to_string()),
+        ],
+    )?;
+    let files: Vec<DriveFile> = listed["files"].as_array().map(|fs| fs.iter().map(parse_file).collect()).unwrap_or_default();
+    println!("[Drive] Listed {} file(s)", files.len());
+    Ok(files)
+}
+
+fn file_url(file_id: &str) -> String {
+    format!("{}/files/{}", API, urlencoding::encode(file_id))
+}
+
+fn metadata(app: &AppHandle, file_id: &str) -> Result<DriveFile> {
+    let meta: Value = get_json(app, READ_SCOPES, &file_url(file_id), &[("fields", FILE_FIELDS.to_string())])?;
+    Ok(parse_file(&meta))
+}
+
+// Download `file` (exporting it as `exports` says when Google-native) and store it with the uploads
+fn import(app: &AppHandle, file: &DriveFile, exports: &[(&str, &'static str, &str)]) -> Result<FileInfo> {
+    let url = file_url(&file.id);
+    let (bytes, name) = match download_plan(file, exports)? {
+        Some((export, name)) => (get_bytes(app, READ_SCOPES, &format!("{}/export", url), &[("mimeType", export.to_string())])?, name),
+        None => (get_bytes(app, READ_SCOPES, &url, &[("alt", "media".to_string())])?, stored_name(file)),
+    };
+    println!("[Drive] Downloaded {} ({} bytes)", name, bytes.len());
+    // Text exports start with a byte order mark
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").map(|b| b.to_vec()).unwrap_or(bytes);
+
+    let stored = FileStorage::new()?.import_bytes(&bytes, &name, &format!("{}{}", DRIVE_ORIGIN_PREFIX, file.id))?;
+    println!("[Drive] Imported {} as {}", name, stored.id);
+    Ok(stored)
+}
+
+/// Download a Drive file (exporting Google-native documents) and store it with the uploads
+pub fn import_file(app: &AppHandle, file_id: &str) -> Result<FileInfo> {
+    let file = metadata(app, file_id)?;
+    if file.is_folder {
+        return Err(anyhow!("{} is a folder", file.name));
+    }
+    if file.size.is_some_and(|size| size > MAX_IMPORT_BYTES) {
+        return Err(anyhow!("{} is larger than {} MB", file.name, MAX_IMPORT_BYTES / 1024 / 1024));
+    }
+    import(app, &file, EXPORTS)
+}
+
+/// Pull a Google Doc, Sheet or Slides deck into the uploads as plain text (CSV for a Sheet),
+/// without going through PDF
+pub fn import_as_text(app: &AppHandle, doc_id: &str) -> Result<FileInfo> {
+    let file = metadata(app, doc_id)?;
+    if !TEXT_EXPORTS.iter().any(|(native, _, _)| *native == file.mime_type) {
+        return Err(anyhow!("{} isn't a Google Doc, Sheet or Slides deck ({})", file.name, file.mime_type));
+    }
+    import(app, &file, TEXT_EXPORTS)
+}
+
+/// Browse the connected Drive: a folder's contents, a search, or both; without either, the
+/// top of My Drive
+#[tauri::command]
+pub async fn list_drive_files(app: AppHandle, folder: Option<String>, query: Option<String>) -> Result<Vec<DriveFile>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_files(&app, folder.as_deref(), query.as_deref()).map_err(|e| format!("Failed to list Drive files: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Drive task failed: {}", e))?
+}
+
+/// Import a Drive file into the uploads store, exporting Docs, Sheets, Slides and Drawings
+/// as PDF
+#[tauri::command]
+pub async fn import_drive_file(app: AppHandle, file_id: String) -> Result<FileInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        import_file(&app, &file_id).map_err(|e| format!("Failed to import Drive file: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Drive task failed: {}", e))?
+}
+
+/// Import a Google Doc, Sheet or Slides deck as text; Sheets come in as CSV of their first
+/// sheet
+#[tauri::command]
+pub async fn import_google_doc_as_text(app: AppHandle, doc_id: String) -> Result<FileInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        import_as_text(&app, &doc_id).map_err(|e| format!("Failed to import Google document: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Drive task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_expression_and_download_plan() {
+        assert_eq!(search_expression(None, None), "trashed = false and 'root' in parents");
+        assert_eq!(
+            search_expression(Some("f1"), Some("Anna's contract")),
+            "trashed = false and 'f1' in parents and (name contains 'Anna\\'s contract' or fullText contains 'Anna\\'s contract')"
+        );
+        assert_eq!(search_expression(None, Some("budget")), "trashed = false and (name contains 'budget' or fullText contains 'budget')");
+
+        let doc = parse_file(&serde_json::json!({ "id": "d1", "name": "Contract", "mimeType": "application/vnd.google-apps.document" }));
+        assert_eq!(download_plan(&doc, EXPORTS).unwrap(), Some(("application/pdf", "Contract.pdf".to_string())));
+        assert_eq!(download_plan(&doc, TEXT_EXPORTS).unwrap(), Some(("text/plain", "Contract.txt".to_string())));
+        let sheet = DriveFile { mime_type: "application/vnd.google-apps.spreadsheet".into(), ..doc.clone() };
+        assert_eq!(download_plan(&sheet, TEXT_EXPORTS).unwrap(), Some(("text/csv", "Contract.csv".to_string())));
+        let drawing = DriveFile { mime_type: "application/vnd.google-apps.drawing".into(), ..doc.clone() };
+        assert!(download_plan(&drawing, TEXT_EXPORTS).is_err());
+        let form = DriveFile { mime_type: "application/vnd.google-apps.form".into(), ..doc };
+        assert!(download_plan(&form, EXPORTS).is_err());
+
+        let upload = parse_file(&serde_json::json!({
+            "id": "u1", "name": "notes", "mimeType": "text/plain", "size": "120", "fileExtension": "txt"
+        }));
+        assert_eq!(download_plan(&upload, EXPORTS).unwrap(), None);
+        assert_eq!((stored_name(&upload).as_str(), upload.size), ("notes.txt", Some(120)));
+        let named = DriveFile { name: "Notes.TXT".into(), ..upload };
+        assert_eq!(stored_name(&named), "Notes.TXT");
+    }
+}
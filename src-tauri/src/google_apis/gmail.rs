@@ -0,0 +1,407 @@
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use super::{get_json, post_json};
+use crate::pii_scrubber::ScrubPass;
+use crate::extractors::email::strip_html;
+
+const API: &str = "https://gmail.googleapis.com/gmail/v1/users/me";
+// Any of these lets the app read mail
+const READ_SCOPES: &[&str] = &["gmail.readonly", "gmail.modify", "https://mail.google.com/"];
+// Any of these lets the app create and send drafts
+const COMPOSE_SCOPES: &[&str] = &["gmail.compose", "gmail.modify", "https://mail.google.com/"];
+// How long the user has to approve a send before it has to be asked for again
+const CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_MAX_RESULTS: usize = 10;
+// Each hit is fetched in full, so keep searches small
+const MAX_RESULTS_LIMIT: usize = 25;
+// Characters of body returned per message; long threads quote everything before them
+const BODY_LIMIT: usize = 20_000;
+
+/// One Gmail message, scrubbed with the PII config
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct GmailMessage {
+    pub id: String,
+    pub thread_id: String,
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub date: String,                  // The Date header as sent
+    pub labels: Vec<String>,           // Label ids, e.g. "INBOX", "UNREAD"
+    pub snippet: String,
+    pub body: String,                  // Plain-text part, or the HTML part as text
+    pub truncated: bool,               // Body cut at BODY_LIMIT characters
+    pub redacted: usize,               // PII replacements across the fields
+}
+
+/// A draft saved in the connected account
+#[derive(Debug, Serialize, Clone)]
+pub struct GmailDraft {
+    pub id: String,
+    pub message_id: String,
+    pub thread_id: String,
+    pub to: String,
+    pub subject: String,
+}
+
+/// What send_gmail_message did
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SendOutcome {
+    ConfirmationRequired { draft_id: String }, // "gmail:send-confirmation" went to the UI
+    Sent { message_id: String, thread_id: String },
+}
+
+/// Payload of "gmail:send-confirmation": what the user is asked to approve. Only the UI sees
+/// the token, so nothing else can approve a send.
+#[derive(Debug, Serialize, Clone)]
+pub struct SendConfirmation {
+    pub draft_id: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub token: String,
+}
+
+// One-time tokens for drafts waiting on the user, by draft id
+struct Confirmations {
+    pending: HashMap<String, (String, Instant)>,
+}
+
+impl Confirmations {
+    fn issue(&mut self, draft_id: &str, now: Instant) -> String {
+        self.pending.retain(|_, (_, issued)| now.duration_since(*issued) < CONFIRMATION_TTL);
+        let token: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+        self.pending.insert(draft_id.to_string(), (token.clone(), now));
+        token
+    }
+
+    // Whether `token` approves sending the draft; a token works once
+    fn take(&mut self, draft_id: &str, token: &str, now: Instant) -> bool {
+        match self.pending.remove(draft_id) {
+            Some((expected, issued)) => expected == token && now.duration_since(issued) < CONFIRMATION_TTL,
+            None => false,
+        }
+    }
+}
+
+static CONFIRMATIONS: LazyLock<Mutex<Confirmations>> = LazyLock::new(|| Mutex::new(Confirmations { pending: HashMap::new() }));
+
+fn with_confirmations<T>(f: impl FnOnce(&mut Confirmations) -> T) -> T {
+    f(&mut CONFIRMATIONS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Drop every unconfirmed send, e.g. when Google is disconnected; returns how many there were
+pub(crate) fn clear_confirmations() -> usize {
+    with_confirmations(|c| c.pending.drain().count())
+}
+
+fn decode_data(data: &str) -> Option<String> {
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(data.trim_end_matches('=')).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// First inline part of `mime` type, depth first; attachments (parts with a file name) are skipped
+fn find_part(part: &Value, mime: &str) -> Option<String> {
+    let is_attachment = part["filename"].as_str().is_some_and(|f| !f.is_empty());
+    if !is_attachment && part["mimeType"].as_str() == Some(mime) {
+        if let Some(text) = part["body"]["data"].as_str().and_then(decode_data) {
+            return Some(text);
+        }
+    }
+    part["parts"].as_array()?.iter().find_map(|p| find_part(p, mime))
+}
+
+// A message resource (format=full) as it came from the API, not yet scrubbed
+fn parse_message(v: &Value) -> GmailMessage {
+    let payload = &v["payload"];
+    let header = |name: &str| {
+        payload["headers"]
+            .as_array()
+            .and_then(|hs| hs.iter().find(|h| h["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case(name))))
+            .and_then(|h| h["value"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let body = find_part(payload, "text/plain")
+        .or_else(|| find_part(payload, "text/html").map(|html| strip_html(&html)))
+        .unwrap_or_default();
+    let truncated = body.chars().count() > BODY_LIMIT;
+    GmailMessage {
+        id: v["id"].as_str().unwrap_or_default().to_string(),
+        thread_id: v["threadId"].as_str().unwrap_or_default().to_string(),
+        from: header("From"),
+        to: header("To"),
+        subject: header("Subject"),
+        date: header("Date"),
+        labels: v["labelIds"]
+            .as_array()
+            .map(|ls| ls.iter().filter_map(|l| l.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default(),
+        // Snippets come HTML-escaped
+        snippet: strip_html(v["snippet"].as_str().unwrap_or_default()),
+        body: if truncated { body.chars().take(BODY_LIMIT).collect() } else { body.trim().to_string() },
+        truncated,
+        redacted: 0,
+    }
+}
+
+fn scrub_message(pass: &mut ScrubPass, message: GmailMessage) -> GmailMessage {
+    let before = pass.redacted();
+    let mut message = GmailMessage {
+        from: pass.text(&message.from),
+        to: pass.text(&message.to),
+        subject: pass.text(&message.subject),
+        snippet: pass.text(&message.snippet),
+        body: pass.text(&message.body),
+        ..message
+    };
+    message.redacted = pass.redacted() - before;
+    message
+}
+
+// RFC 2047 encoding for header values that aren't plain ASCII
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(value))
+    }
+}
+
+// The RFC 2822 message for a plain-text draft; `reply` is the Message-ID being answered
+fn build_raw(to: &str, subject: &str, body: &str, reply: Option<&str>) -> Result<String> {
+    if [to, subject, reply.unwrap_or_default()].iter().any(|v| v.contains(['\r', '\n'])) {
+        return Err(anyhow!("recipients and subject can't contain line breaks"));
+    }
+    if !to.contains('@') {
+        return Err(anyhow!("{:?} isn't an email address", to));
+    }
+    let mut raw = format!(
+        "To: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=\"UTF-8\"\r\nContent-Transfer-Encoding: 8bit\r\n",
+        to,
+        encode_header(subject)
+    );
+    if let Some(reply) = reply {
+        raw.push_str(&format!("In-Reply-To: {}\r\nReferences: {}\r\n", reply, reply));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(&body.replace("\r\n", "\n").replace('\n', "\r\n"));
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw))
+}
+
+fn fetch(app: &AppHandle, id: &str) -> Result<GmailMessage> {
+    let v: Value = get_json(app, READ_SCOPES, &format!("{}/messages/{}", API, id), &[("format", "full".to_string())])?;
+    Ok(parse_message(&v))
+}
+
+/// Messages matching a Gmail search query (same syntax as the search box), newest first
+pub fn search(app: &AppHandle, query: &str, max_results: usize) -> Result<Vec<GmailMessage>> {
+    let list: Value = get_json(
+        app,
+        READ_SCOPES,
+        &format!("{}/messages", API),
+        &[("q", query.to_string()), ("maxResults", max_results.to_string())],
+    )?;
+    let ids: Vec<&str> = list["messages"]
+        .as_array()
+        .map(|ms| ms.iter().filter_map(|m| m["id"].as_str()).collect())
+        .unwrap_or_default();
+    println!("[Gmail] {:?} matched {} message(s)", query, ids.len());
+
+    let mut pass = ScrubPass::new();
+    let mut messages = Vec::with_capacity(ids.len());
+    for id in ids {
+        messages.push(scrub_message(&mut pass, fetch(app, id)?));
+    }
+    pass.finish(app, "search_gmail");
+    Ok(messages)
+}
+
+/// One message by id
+pub fn message(app: &AppHandle, id: &str) -> Result<GmailMessage> {
+    let mut pass = ScrubPass::new();
+    let message = scrub_message(&mut pass, fetch(app, id)?);
+    pass.finish(app, "get_gmail_message");
+    Ok(message)
+}
+
+/// Save a plain-text draft; with `reply_to` (a message id) it goes into that message's thread
+/// as a reply
+pub fn create_draft(app: &AppHandle, to: &str, subject: &str, body: &str, reply_to: Option<&str>) -> Result<GmailDraft> {
+    let original: Option<Value> = match reply_to {
+        Some(id) => Some(get_json(
+            app,
+            READ_SCOPES,
+            &format!("{}/messages/{}", API, id),
+            &[("format", "metadata".to_string()), ("metadataHeaders", "Message-ID".to_string())],
+        )?),
+        None => None,
+    };
+    let message_id_header = original.as_ref().and_then(|o| {
+        o["payload"]["headers"]
+            .as_array()?
+            .iter()
+            .find(|h| h["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case("Message-ID")))?["value"]
+            .as_str()
+            .map(|v| v.to_string())
+    });
+
+    let mut message = serde_json::json!({ "raw": build_raw(to, subject, body, message_id_header.as_deref())? });
+    if let Some(thread_id) = original.as_ref().and_then(|o| o["threadId"].as_str()) {
+        message["threadId"] = thread_id.into();
+    }
+    let draft: Value = post_json(app, COMPOSE_SCOPES, &format!("{}/drafts", API), &serde_json::json!({ "message": message }))?;
+    let draft = GmailDraft {
+        id: draft["id"].as_str().unwrap_or_default().to_string(),
+        message_id: draft["message"]["id"].as_str().unwrap_or_default().to_string(),
+        thread_id: draft["message"]["threadId"].as_str().unwrap_or_default().to_string(),
+        to: to.to_string(),
+        subject: subject.to_string(),
+    };
+    println!("[Gmail] Saved draft {} to {}", draft.id, to);
+    Ok(draft)
+}
+
+/// Send a draft once the user has approved it. Without a confirmation token, the draft is
+/// shown to the UI for approval instead.
+pub fn send_draft(app: &AppHandle, draft_id: &str, confirmation: Option<&str>) -> Result<SendOutcome> {
+    let Some(token) = confirmation else {
+        let draft: Value = get_json(app, COMPOSE_SCOPES, &format!("{}/drafts/{}", API, draft_id), &[("format", "full".to_string())])?;
+        let message = parse_message(&draft["message"]);
+        let token = with_confirmations(|c| c.issue(draft_id, Instant::now()));
+        let event = SendConfirmation { draft_id: draft_id.to_string(), to: message.to, subject: message.subject, body: message.body, token };
+        app.emit("gmail:send-confirmation", &event)?;
+        println!("[Gmail] Asked the user to confirm sending draft {}", draft_id);
+        return Ok(SendOutcome::ConfirmationRequired { draft_id: draft_id.to_string() });
+    };
+    if !with_confirmations(|c| c.take(draft_id, token, Instant::now())) {
+        return Err(anyhow!("sending wasn't confirmed, or the confirmation expired; ask again"));
+    }
+    let sent: Value = post_json(app, COMPOSE_SCOPES, &format!("{}/drafts/send", API), &serde_json::json!({ "id": draft_id }))?;
+    println!("[Gmail] Sent draft {}", draft_id);
+    Ok(SendOutcome::Sent {
+        message_id: sent["id"].as_str().unwrap_or_default().to_string(),
+        thread_id: sent["threadId"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+/// Search the connected Gmail account and return the matching messages with scrubbed bodies
+#[tauri::command]
+pub async fn search_gmail(app: AppHandle, query: String, max_results: Option<usize>) -> Result<Vec<GmailMessage>, String> {
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS).clamp(1, MAX_RESULTS_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        search(&app, &query, max_results).map_err(|e| format!("Failed to search Gmail: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Gmail task failed: {}", e))?
+}
+
+/// One Gmail message with its scrubbed body
+#[tauri::command]
+pub async fn get_gmail_message(app: AppHandle, id: String) -> Result<GmailMessage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        message(&app, &id).map_err(|e| format!("Failed to read Gmail message: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Gmail task failed: {}", e))?
+}
+
+/// Save a draft in the connected Gmail account, optionally as a reply to a message. Nothing
+/// is sent.
+#[tauri::command]
+pub async fn create_gmail_draft(
+    app: AppHandle,
+    to: String,
+    subject: String,
+    body: String,
+    reply_to: Option<String>,
+) -> Result<GmailDraft, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        create_draft(&app, &to, &subject, &body, reply_to.as_deref()).map_err(|e| format!("Failed to create Gmail draft: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Gmail task failed: {}", e))?
+}
+
+/// Send a draft. The first call only asks the user ("gmail:send-confirmation"); the UI sends
+/// it by calling again with the token from that event.
+#[tauri::command]
+pub async fn send_gmail_message(app: AppHandle, draft_id: String, confirmation: Option<String>) -> Result<SendOutcome, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        send_draft(&app, &draft_id, confirmation.as_deref()).map_err(|e| format!("Failed to send Gmail message: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Gmail task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message_prefers_plain_text() {
+        let b64 = |s: &str| base64::engine::general_purpose::URL_SAFE.encode(s);
+        let message = serde_json::json!({
+            "id": "m1",
+            "threadId": "t1",
+            "labelIds": ["INBOX"],
+            "snippet": "Contract&#39;s fine",
+            "payload": {
+                "mimeType": "multipart/mixed",
+                "headers": [{ "name": "From", "value": "Anna <anna@example.com>" }, { "name": "subject", "value": "Contract" }],
+                "parts": [
+                    { "mimeType": "multipart/alternative", "parts": [
+                        { "mimeType": "text/html", "body": { "data": b64("<p>HTML</p>") } },
+                        { "mimeType": "text/plain", "body": { "data": b64("The contract's fine.\n") } }
+                    ]},
+                    { "mimeType": "text/plain", "filename": "notes.txt", "body": { "attachmentId": "a1" } }
+                ]
+            }
+        });
+        let parsed = parse_message(&message);
+        assert_eq!((parsed.id.as_str(), parsed.thread_id.as_str()), ("m1", "t1"));
+        assert_eq!(parsed.from, "Anna <anna@example.com>");
+        assert_eq!(parsed.subject, "Contract");
+        assert_eq!(parsed.snippet, "Contract's fine");
+        assert_eq!(parsed.body, "The contract's fine.");
+        assert!(!parsed.truncated);
+
+        let html_only = serde_json::json!({
+            "payload": { "mimeType": "text/html", "body": { "data": b64(&format!("<div>{}</div>", "x".repeat(BODY_LIMIT + 5))) } }
+        });
+        let parsed = parse_message(&html_only);
+        assert!(parsed.truncated);
+        assert_eq!(parsed.body.len(), BODY_LIMIT);
+    }
+
+    #[test]
+    fn test_draft_raw_and_send_confirmation() {
+        let raw = build_raw("sam@example.com", "Réunion", "Hi Sam,\nThursday works.", Some("<abc@mail>")).unwrap();
+        let raw = String::from_utf8(base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(raw).unwrap()).unwrap();
+        assert!(raw.starts_with("To: sam@example.com\r\nSubject: =?UTF-8?B?"));
+        assert!(raw.contains("In-Reply-To: <abc@mail>\r\n"));
+        assert!(raw.ends_with("\r\n\r\nHi Sam,\r\nThursday works."));
+        assert!(build_raw("sam@example.com\r\nBcc: x@evil.test", "Hi", "", None).is_err());
+        assert!(build_raw("sam", "Hi", "", None).is_err());
+
+        let now = Instant::now();
+        let mut confirmations = Confirmations { pending: HashMap::new() };
+        let token = confirmations.issue("d1", now);
+        assert!(!confirmations.take("d1", "guess", now));
+        // A wrong guess uses the token up
+        assert!(!confirmations.take("d1", &token, now));
+        let token = confirmations.issue("d1", now);
+        assert!(confirmations.take("d1", &token, now + Duration::from_secs(10)));
+        assert!(!confirmations.take("d1", &token, now));
+        let token = confirmations.issue("d2", now);
+        assert!(!confirmations.take("d2", &token, now + CONFIRMATION_TTL));
+    }
+}
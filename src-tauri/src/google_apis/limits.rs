@@ -0,0 +1,201 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+// How long a GET response is served from memory
+const CACHE_TTL: Duration = Duration::from_secs(60);
+const MAX_CACHE_ENTRIES: usize = 200;
+// Backoff after a rate-limited reply without Retry-After: 1 s, 2 s, 4 s, ...
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(32);
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Requests made to one Google API since the app started
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ApiUsage {
+    pub service: String,               // e.g. "gmail.googleapis.com" or "www.googleapis.com/calendar"
+    pub requests: u64,
+    pub requests_last_minute: u64,
+    pub cache_hits: u64,
+    pub rate_limited: u64,             // 429s and rate-limit 403s
+    pub backing_off_secs: u64,         // Until requests to the API go out again
+}
+
+#[derive(Default)]
+struct ServiceState {
+    usage: ApiUsage,
+    window_start: Option<Instant>,
+    blocked_until: Option<Instant>,
+}
+
+// GET responses by URL, each with when it was fetched
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<String, (Instant, String)>,
+}
+
+impl Cache {
+    fn get(&self, key: &str, now: Instant) -> Option<String> {
+        self.entries.get(key).filter(|(fetched, _)| now.duration_since(*fetched) < CACHE_TTL).map(|(_, body)| body.clone())
+    }
+
+    fn insert(&mut self, key: String, body: String, now: Instant) {
+        self.entries.retain(|_, (fetched, _)| now.duration_since(*fetched) < CACHE_TTL);
+        if self.entries.len() >= MAX_CACHE_ENTRIES {
+            if let Some(oldest) = self.entries.iter().min_by_key(|(_, (fetched, _))| *fetched).map(|(k, _)| k.clone()) {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (now, body));
+    }
+
+    // Drop what was cached from one API, after a write to it
+    fn invalidate(&mut self, service: &str) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|key, _| service_of(key) != service);
+        before - self.entries.len()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    cache: Cache,
+    services: HashMap<String, ServiceState>,
+}
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(|| Mutex::new(State::default()));
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> T {
+    f(&mut STATE.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Which API a URL belongs to, for quotas: the host, plus the first path segment on the shared
+/// www.googleapis.com host
+pub(crate) fn service_of(url: &str) -> String {
+    let after_scheme = url.split("://").nth(1).unwrap_or(url);
+    let mut parts = after_scheme.split(['/', '?']);
+    let host = parts.next().unwrap_or_default();
+    match parts.next() {
+        Some(api) if host == "www.googleapis.com" && !api.is_empty() => format!("{}/{}", host, api),
+        _ => host.to_string(),
+    }
+}
+
+/// Whether a failed reply means "slow down": 429, or a 403 whose reason is a rate limit
+pub(crate) fn is_rate_limited(status: u16, body: &str) -> bool {
+    status == 429 || (status == 403 && (body.contains("rateLimitExceeded") || body.contains("userRateLimitExceeded")))
+}
+
+/// How long to wait before retry `attempt` (0-based): Retry-After when Google sends it,
+/// otherwise doubling from a second
+pub(crate) fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after.unwrap_or_else(|| BASE_BACKOFF.saturating_mul(1 << attempt.min(10))).min(MAX_BACKOFF)
+}
+
+/// A cached GET response for `key` still within its lifetime
+pub(crate) fn cached(key: &str) -> Option<String> {
+    with_state(|state| {
+        let body = state.cache.get(key, Instant::now())?;
+        state.services.entry(service_of(key)).or_default().usage.cache_hits += 1;
+        Some(body)
+    })
+}
+
+pub(crate) fn cache(key: String, body: String) {
+    with_state(|state| state.cache.insert(key, body, Instant::now()));
+}
+
+/// Forget cached responses of the API `url` belongs to
+pub(crate) fn invalidate(url: &str) {
+    with_state(|state| state.cache.invalidate(&service_of(url)));
+}
+
+/// Forget every cached response, e.g. when the account is disconnected; returns how many
+pub(crate) fn clear_cache() -> usize {
+    with_state(|state| std::mem::take(&mut state.cache.entries).len())
+}
+
+/// How long requests to `service` should hold off after a rate-limited reply
+pub(crate) fn backoff_remaining(service: &str) -> Option<Duration> {
+    let now = Instant::now();
+    with_state(|state| state.services.get(service)?.blocked_until.filter(|until| *until > now).map(|until| until - now))
+}
+
+/// Count a request going out to `service`
+pub(crate) fn record_request(service: &str) {
+    let now = Instant::now();
+    with_state(|state| {
+        let entry = state.services.entry(service.to_string()).or_default();
+        if entry.window_start.is_none_or(|start| now.duration_since(start) >= WINDOW) {
+            entry.window_start = Some(now);
+            entry.usage.requests_last_minute = 0;
+        }
+        entry.usage.requests += 1;
+        entry.usage.requests_last_minute += 1;
+    });
+}
+
+/// Hold requests to `service` back for `delay` after it rate-limited one
+pub(crate) fn record_rate_limit(service: &str, delay: Duration) {
+    with_state(|state| {
+        let entry = state.services.entry(service.to_string()).or_default();
+        entry.usage.rate_limited += 1;
+        entry.blocked_until = Some(Instant::now() + delay);
+    });
+}
+
+/// Usage per API, busiest first
+pub(crate) fn usage() -> Vec<ApiUsage> {
+    let now = Instant::now();
+    let mut usage: Vec<ApiUsage> = with_state(|state| {
+        state
+            .services
+            .iter()
+            .map(|(service, s)| ApiUsage {
+                service: service.clone(),
+                requests_last_minute: if s.window_start.is_some_and(|start| now.duration_since(start) < WINDOW) {
+                    s.usage.requests_last_minute
+                } else {
+                    0
+                },
+                backing_off_secs: s.blocked_until.filter(|until| *until > now).map_or(0, |until| (until - now).as_secs()),
+                ..s.usage.clone()
+            })
+            .collect()
+    });
+    usage.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.service.cmp(&b.service)));
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_services_backoff_and_cache() {
+        assert_eq!(service_of("https://gmail.googleapis.com/gmail/v1/users/me/messages"), "gmail.googleapis.com");
+        assert_eq!(service_of("https://www.googleapis.com/calendar/v3/calendars/primary/events?x=1"), "www.googleapis.com/calendar");
+        assert_eq!(service_of("https://www.googleapis.com/drive/v3/files"), "www.googleapis.com/drive");
+
+        assert!(is_rate_limited(429, ""));
+        assert!(is_rate_limited(403, r#"{"error": {"errors": [{"reason": "userRateLimitExceeded"}]}}"#));
+        assert!(!is_rate_limited(403, r#"{"error": {"errors": [{"reason": "insufficientPermissions"}]}}"#));
+        assert_eq!(backoff_delay(0, None), Duration::from_secs(1));
+        assert_eq!(backoff_delay(3, None), Duration::from_secs(8));
+        assert_eq!(backoff_delay(20, None), MAX_BACKOFF);
+        assert_eq!(backoff_delay(0, Some(Duration::from_secs(5))), Duration::from_secs(5));
+
+        let start = Instant::now();
+        let mut cache = Cache::default();
+        let events = "https://www.googleapis.com/calendar/v3/calendars/primary/events?maxResults=10";
+        let files = "https://www.googleapis.com/drive/v3/files";
+        cache.insert(events.to_string(), "{}".to_string(), start);
+        cache.insert(files.to_string(), "[]".to_string(), start);
+        assert_eq!(cache.get(events, start + Duration::from_secs(30)).as_deref(), Some("{}"));
+        assert_eq!(cache.get(events, start + CACHE_TTL), None);
+        // A write to Calendar leaves Drive's entries alone
+        assert_eq!(cache.invalidate("www.googleapis.com/calendar"), 1);
+        assert_eq!(cache.get(files, start).as_deref(), Some("[]"));
+    }
+}
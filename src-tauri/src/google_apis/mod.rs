@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::google_oauth;
+
+pub mod calendar;
+pub mod contacts;
+pub mod drive;
+pub mod gmail;
+pub mod limits;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+// Retries of a rate-limited request before the error is passed on
+const MAX_RETRIES: u32 = 4;
+
+// Send a request built on an authorized client; errors carry Google's reply. Rate-limited
+// replies are retried with backoff, and other requests to the same API wait it out too.
+fn send(app: &AppHandle, scopes: &[&str], url: &str, timeout: Duration, build: impl Fn(&Client) -> RequestBuilder) -> Result<Response> {
+    let service = limits::service_of(url);
+    let client = Client::builder().timeout(timeout).build()?;
+    let mut attempt = 0;
+    loop {
+        if let Some(wait) = limits::backoff_remaining(&service) {
+            std::thread::sleep(wait);
+        }
+        let token = google_oauth::access_token(app, scopes)?;
+        limits::record_request(&service);
+        let resp = build(&client).bearer_auth(token).send()?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let retry_after = resp
+            .headers()
+            .get("Retry-After")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let body = resp.text().unwrap_or_default();
+        if attempt < MAX_RETRIES && limits::is_rate_limited(status.as_u16(), &body) {
+            // Jitter keeps parallel callers from retrying in lockstep
+            let delay = limits::backoff_delay(attempt, retry_after) + Duration::from_millis(rand::thread_rng().gen_range(0..250));
+            eprintln!("[GoogleAPI] {} rate limited ({}); retrying in {:?}", service, status, delay);
+            limits::record_rate_limit(&service, delay);
+            attempt += 1;
+            continue;
+        }
+        return Err(anyhow!("{} returned {}: {}", url, status, body));
+    }
+}
+
+/// GET a Google API endpoint as the connected account, which must have one of `scopes`.
+/// Replies are reused for a minute, so repeated lookups in a conversation cost no quota.
+pub(crate) fn get_json<T: DeserializeOwned>(app: &AppHandle, scopes: &[&str], url: &str, query: &[(&str, String)]) -> Result<T> {
+    let key = reqwest::Url::parse_with_params(url, query)?.to_string();
+    if let Some(body) = limits::cached(&key) {
+        return Ok(serde_json::from_str(&body)?);
+    }
+    let body = send(app, scopes, url, REQUEST_TIMEOUT, |client| client.get(url).query(query))?.text()?;
+    let parsed = serde_json::from_str(&body)?;
+    limits::cache(key, body);
+    Ok(parsed)
+}
+
+/// Download the body of a GET, e.g. a file's content
+pub(crate) fn get_bytes(app: &AppHandle, scopes: &[&str], url: &str, query: &[(&str, String)]) -> Result<Vec<u8>> {
+    let resp = send(app, scopes, url, DOWNLOAD_TIMEOUT, |client| client.get(url).query(query))?;
+    Ok(resp.bytes()?.to_vec())
+}
+
+/// POST a JSON body to a Google API endpoint as the connected account. Cached replies from
+/// the same API are dropped, since the write may change them.
+pub(crate) fn post_json<T: DeserializeOwned>(app: &AppHandle, scopes: &[&str], url: &str, body: &serde_json::Value) -> Result<T> {
+    let resp = send(app, scopes, url, REQUEST_TIMEOUT, |client| client.post(url).json(body));
+    limits::invalidate(url);
+    Ok(resp?.json()?)
+}
+
+/// Requests, cache hits and rate limiting per Google API since the app started
+#[tauri::command]
+pub fn get_google_api_usage() -> Vec<limits::ApiUsage> {
+    limits::usage()
+}
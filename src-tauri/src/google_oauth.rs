@@ -1,4 +1,4 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -8,10 +8,35 @@ use std::time::Duration;
 use std::fs;
 use std::path::PathBuf;
 use base64::Engine;
-use tauri::Manager;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
 use chrono::DateTime;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::connections::{rfc3339, ConnectionStatus};
+use crate::{flags, keychain};
+
+// Keychain entry holding the tokens as JSON
+const KEYCHAIN_SERVICE: &str = "agi-google-oauth";
+const KEYCHAIN_ACCOUNT: &str = "tokens";
+// Feature flag (off by default) that also writes the tokens, in plaintext, where the Google
+// MCP servers read them
+const MCP_BRIDGE_FLAG: &str = "google_mcp_token_bridge";
+
+const TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+const REVOKE_ENDPOINT: &str = "https://oauth2.googleapis.com/revoke";
+// Origin prefix of files imported from Drive (see google_apis::drive)
+pub(crate) const DRIVE_ORIGIN_PREFIX: &str = "google-drive:";
+// Renew this long before the access token runs out
+const REFRESH_MARGIN_MS: u128 = 5 * 60 * 1000;
+// How often the background task looks at the tokens; also the retry delay after a failed refresh
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Google's default lifetime, for responses that leave out expires_in
+const DEFAULT_EXPIRES_IN_SECS: u64 = 3600;
+
+// Keeps the background task and an on-demand refresh from both spending the refresh token
+static REFRESH_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct GoogleTokens {
   access_token: String,
   expires_in: Option<u64>,
@@ -20,13 +45,195 @@ struct GoogleTokens {
   token_type: Option<String>,
   id_token: Option<String>,
   obtained_at_ms: u128,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  reauth_required: bool, // Google refused the refresh token; only connecting again helps
+}
+
+#[derive(Deserialize)]
+struct TokenResp {
+  access_token: String,
+  expires_in: Option<u64>,
+  refresh_token: Option<String>,
+  scope: Option<String>,
+  token_type: Option<String>,
+  id_token: Option<String>,
+}
+
+/// Payload of "google-oauth:reauth-required"
+#[derive(Serialize, Clone, Debug)]
+pub struct ReauthRequired {
+  pub reason: String,
+}
+
+/// Why a refresh didn't produce new tokens
+#[derive(Debug)]
+enum RefreshError {
+  Reauth(String),        // Refresh token missing, revoked or expired
+  Transient(anyhow::Error), // Network trouble or a Google outage; worth retrying
+}
+
+pub(crate) fn now_ms() -> u128 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis())
+    .unwrap_or_default()
+}
+
+fn expires_at_ms(tokens: &GoogleTokens) -> u128 {
+  tokens.obtained_at_ms + tokens.expires_in.unwrap_or(DEFAULT_EXPIRES_IN_SECS) as u128 * 1000
+}
+
+fn needs_refresh(tokens: &GoogleTokens, now_ms: u128) -> bool {
+  now_ms + REFRESH_MARGIN_MS >= expires_at_ms(tokens)
+}
+
+// Errors from the token endpoint that mean the grant itself is gone, not a passing failure
+pub(crate) fn is_reauth_error(body: &str) -> bool {
+  let error = serde_json::from_str::<serde_json::Value>(body)
+    .ok()
+    .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(|e| e.to_string()))
+    .unwrap_or_default();
+  matches!(error.as_str(), "invalid_grant" | "unauthorized_client" | "invalid_client")
+}
+
+// Google usually leaves the refresh token (and sometimes the scope) out of a refresh response
+fn apply_refresh(old: &GoogleTokens, resp: TokenResp, now_ms: u128) -> GoogleTokens {
+  GoogleTokens {
+    access_token: resp.access_token,
+    expires_in: resp.expires_in,
+    refresh_token: resp.refresh_token.or_else(|| old.refresh_token.clone()),
+    scope: resp.scope.or_else(|| old.scope.clone()),
+    token_type: resp.token_type.or_else(|| old.token_type.clone()),
+    id_token: resp.id_token.or_else(|| old.id_token.clone()),
+    obtained_at_ms: now_ms,
+    reauth_required: false,
+  }
+}
+
+// Scopes for Google services when none are chosen (broad access for MCP tools)
+const DEFAULT_SCOPES: &[&str] = &[
+  // Gmail
+  "https://www.googleapis.com/auth/gmail.readonly",
+  "https://www.googleapis.com/auth/gmail.modify",
+  "https://www.googleapis.com/auth/gmail.send",
+  "https://www.googleapis.com/auth/gmail.compose",
+  "https://www.googleapis.com/auth/gmail.labels",
+  // Calendar
+  "https://www.googleapis.com/auth/calendar",
+  "https://www.googleapis.com/auth/calendar.readonly",
+  "https://www.googleapis.com/auth/calendar.events",
+  // Drive
+  "https://www.googleapis.com/auth/drive",
+  "https://www.googleapis.com/auth/drive.file",
+  "https://www.googleapis.com/auth/drive.readonly",
+  // Docs
+  "https://www.googleapis.com/auth/documents",
+  "https://www.googleapis.com/auth/documents.readonly",
+  // Sheets
+  "https://www.googleapis.com/auth/spreadsheets",
+  "https://www.googleapis.com/auth/spreadsheets.readonly",
+  // Slides
+  "https://www.googleapis.com/auth/presentations",
+  "https://www.googleapis.com/auth/presentations.readonly",
+  // Tasks
+  "https://www.googleapis.com/auth/tasks",
+  "https://www.googleapis.com/auth/tasks.readonly",
+  // Forms
+  "https://www.googleapis.com/auth/forms.body",
+  "https://www.googleapis.com/auth/forms.body.readonly",
+  "https://www.googleapis.com/auth/forms.responses.readonly",
+  // Chat (user-level scopes)
+  "https://www.googleapis.com/auth/chat.messages",
+  "https://www.googleapis.com/auth/chat.messages.readonly",
+  "https://www.googleapis.com/auth/chat.memberships",
+  "https://www.googleapis.com/auth/chat.memberships.readonly",
+  "https://www.googleapis.com/auth/chat.spaces",
+  "https://www.googleapis.com/auth/chat.spaces.readonly",
+  // Contacts
+  "https://www.googleapis.com/auth/contacts.readonly",
+  // OpenID / user info
+  "openid",
+  "https://www.googleapis.com/auth/userinfo.email",
+  "https://www.googleapis.com/auth/userinfo.profile",
+];
+
+// Always requested, so the account's email is known
+const IDENTITY_SCOPES: &[&str] = &[
+  "openid",
+  "https://www.googleapis.com/auth/userinfo.email",
+  "https://www.googleapis.com/auth/userinfo.profile",
+];
+
+// Short names the settings window can offer instead of full scope URLs
+const SCOPE_ALIASES: &[(&str, &[&str])] = &[
+  ("gmail", &["gmail.readonly", "gmail.modify", "gmail.send", "gmail.compose", "gmail.labels"]),
+  ("gmail.readonly", &["gmail.readonly"]),
+  ("calendar", &["calendar", "calendar.events"]),
+  ("calendar.readonly", &["calendar.readonly"]),
+  ("drive", &["drive", "drive.file"]),
+  ("drive.readonly", &["drive.readonly"]),
+  ("docs", &["documents"]),
+  ("docs.readonly", &["documents.readonly"]),
+  ("sheets", &["spreadsheets"]),
+  ("sheets.readonly", &["spreadsheets.readonly"]),
+  ("slides", &["presentations"]),
+  ("slides.readonly", &["presentations.readonly"]),
+  ("tasks", &["tasks"]),
+  ("tasks.readonly", &["tasks.readonly"]),
+  ("forms", &["forms.body", "forms.responses.readonly"]),
+  ("forms.readonly", &["forms.body.readonly", "forms.responses.readonly"]),
+  ("chat", &["chat.messages", "chat.memberships", "chat.spaces"]),
+  ("chat.readonly", &["chat.messages.readonly", "chat.memberships.readonly", "chat.spaces.readonly"]),
+  ("contacts", &["contacts"]),
+  ("contacts.readonly", &["contacts.readonly"]),
+];
+
+const SCOPE_URL_PREFIX: &str = "https://www.googleapis.com/auth/";
+
+/// Turn aliases ("calendar.readonly") and full scope URLs into the space-separated scope
+/// parameter, with the identity scopes added; None asks for everything
+fn resolve_scopes(requested: Option<&[String]>) -> Result<String> {
+  let mut scopes: Vec<String> = Vec::new();
+  let mut add = |scope: String| {
+    if !scopes.contains(&scope) {
+      scopes.push(scope);
+    }
+  };
+  match requested {
+    None => DEFAULT_SCOPES.iter().for_each(|s| add(s.to_string())),
+    Some(requested) => {
+      for name in requested {
+        let name = name.trim();
+        if name.starts_with("https://") || name == "openid" {
+          add(name.to_string());
+        } else if let Some((_, expanded)) = SCOPE_ALIASES.iter().find(|(alias, _)| *alias == name) {
+          expanded.iter().for_each(|s| add(format!("{}{}", SCOPE_URL_PREFIX, s)));
+        } else {
+          return Err(anyhow!("Unknown Google scope: {:?}", name));
+        }
+      }
+    }
+  }
+  IDENTITY_SCOPES.iter().for_each(|s| add(s.to_string()));
+  Ok(scopes.join(" "))
+}
+
+// Union of two space-separated scope lists, keeping the first one's order
+fn merge_scopes(granted: Option<&str>, added: Option<&str>) -> Option<String> {
+  let mut scopes: Vec<&str> = Vec::new();
+  for scope in granted.into_iter().chain(added).flat_map(|s| s.split_whitespace()) {
+    if !scopes.contains(&scope) {
+      scopes.push(scope);
+    }
+  }
+  (!scopes.is_empty()).then(|| scopes.join(" "))
 }
 
 fn b64_url_no_pad(input: &[u8]) -> String {
   base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(input)
 }
 
-fn generate_pkce_pair() -> (String, String) {
+pub(crate) fn generate_pkce_pair() -> (String, String) {
   // code_verifier must be 43-128 chars
   let verifier: String = rand::thread_rng()
     .sample_iter(&Alphanumeric)
@@ -42,33 +249,76 @@ fn generate_pkce_pair() -> (String, String) {
   (verifier, challenge)
 }
 
-fn load_env(var: &str) -> Result<String> {
+pub(crate) fn load_env(var: &str) -> Result<String> {
   std::env::var(var).map_err(|_| anyhow!("Missing environment variable: {}", var))
 }
 
+// Where tokens were kept before they moved to the keychain; only read to migrate them
 fn tokens_path(app: &tauri::AppHandle) -> Result<PathBuf> {
   let mut path = app
     .path()
     .app_data_dir()
     .map_err(|e| anyhow!("Failed to resolve app data dir: {}", e))?;
   path.push("google_oauth");
-  fs::create_dir_all(&path).ok();
   path.push("tokens.json");
   Ok(path)
 }
 
-fn save_tokens(app: &tauri::AppHandle, tokens: &GoogleTokens) -> Result<()> {
+/// The stored tokens, moving a leftover tokens.json into the keychain on the way
+fn load_tokens(app: &tauri::AppHandle) -> Result<Option<GoogleTokens>> {
   let path = tokens_path(app)?;
-  let json = serde_json::to_string_pretty(tokens)?;
-  fs::write(&path, json)?;
-  
-  // Automatically bridge tokens to MCP directories and credential store
-  let _ = bridge_tokens_to_mcp(app, tokens);
-  
+  let stored = keychain::get(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).context("reading Google tokens from the keychain")?;
+  if let Some(json) = stored {
+    return Ok(Some(serde_json::from_str(&json)?));
+  }
+  if !path.exists() {
+    return Ok(None);
+  }
+
+  println!("[OAuth][Store] Migrating {:?} to the keychain", path);
+  let tokens: GoogleTokens = serde_json::from_str(&fs::read_to_string(&path)?)?;
+  match keychain::set(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, &serde_json::to_string(&tokens)?) {
+    Ok(()) => {
+      fs::remove_file(&path)?;
+      println!("[OAuth][Store] Migrated tokens and removed {:?}", path);
+    }
+    // The file stays put, so the next start tries again
+    Err(e) => eprintln!("[OAuth][Store] Couldn't migrate tokens to the keychain: {}", e),
+  }
+  Ok(Some(tokens))
+}
+
+// Save the tokens in the keychain only, without bridging to the MCP stores. There is no
+// plaintext fallback: without a keychain, connecting fails.
+fn store_tokens(tokens: &GoogleTokens) -> Result<()> {
+  keychain::set(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, &serde_json::to_string(tokens)?)
+    .context("saving Google tokens to the keychain")
+}
+
+// Forget the tokens wherever they are kept
+fn delete_tokens(app: &tauri::AppHandle) -> Result<()> {
+  let path = tokens_path(app)?;
+  if path.exists() {
+    fs::remove_file(&path)?;
+    println!("[OAuth][Store] Removed tokens file: {:?}", path);
+  }
+  keychain::delete(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+}
+
+fn save_tokens(app: &tauri::AppHandle, tokens: &GoogleTokens) -> Result<()> {
+  store_tokens(tokens)?;
+
+  // The MCP servers read tokens from plaintext files; only write them when the user opted in
+  if flags::is_enabled(MCP_BRIDGE_FLAG) {
+    if let Err(e) = bridge_tokens_to_mcp(app, tokens) {
+      eprintln!("[OAuth][Bridge] Failed to write MCP credentials: {}", e);
+    }
+  }
+
   Ok(())
 }
 
-fn extract_email_from_id_token(id_token: &str) -> Option<String> {
+pub(crate) fn extract_email_from_id_token(id_token: &str) -> Option<String> {
   let parts: Vec<&str> = id_token.split('.').collect();
   if parts.len() != 3 { return None; }
   let payload_b64 = parts[1];
@@ -91,6 +341,9 @@ fn get_user_email_from_api(access_token: &str) -> Option<String> {
   v.get("email").and_then(|e| e.as_str()).map(|s| s.to_string())
 }
 
+/// Write the tokens where the Google Workspace, Calendar and Gmail MCP servers read them.
+/// These files are plaintext, so this only runs with the "google_mcp_token_bridge" feature
+/// flag on (list_feature_flags/set_feature_flag); disconnecting removes them again.
 fn bridge_tokens_to_mcp(_app: &tauri::AppHandle, tokens: &GoogleTokens) -> Result<()> {
   // Determine user email
   let user_email = if let Some(ref idt) = tokens.id_token {
@@ -185,7 +438,115 @@ fn bridge_tokens_to_mcp(_app: &tauri::AppHandle, tokens: &GoogleTokens) -> Resul
   Ok(())
 }
 
-fn open_in_browser(url: &str) -> Result<()> {
+// How long the consent page may stay open before connecting gives up
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const REDIRECT_DONE_PAGE: &str = "<html><body><h2>Google authorization received.</h2><p>You can close this tab and return to AGI.</p><script>window.close()</script></body></html>";
+const REDIRECT_FAILED_PAGE: &str = "<html><body><h2>Google authorization was not completed.</h2><p>You can close this tab and try again from AGI.</p><script>window.close()</script></body></html>";
+
+/// What arrived at the loopback redirect
+#[derive(Debug, PartialEq)]
+enum Redirect {
+  Code(String),
+  Denied(String),  // Google's error, e.g. "access_denied" when the user cancels
+  Ignored,         // Another path (a favicon request) or a state that isn't ours
+}
+
+// Port of a redirect URI like http://localhost:3000/path
+pub(crate) fn redirect_port(uri: &str) -> Option<u16> {
+  let after_scheme = uri.split("://").nth(1)?; // localhost:3000/path
+  let host_port = after_scheme.split('/').next()?; // localhost:3000
+  host_port.split(':').nth(1)?.parse::<u16>().ok()
+}
+
+// Path of a redirect URI, "/" when it has none
+pub(crate) fn redirect_path(uri: &str) -> &str {
+  let after_scheme = uri.split("://").nth(1).unwrap_or(uri);
+  after_scheme.find('/').map_or("/", |i| &after_scheme[i..])
+}
+
+// Read the first line of an HTTP request: GET /path?code=...&state=... HTTP/1.1
+fn parse_redirect(request_line: &str, path: &str, state: &str) -> Redirect {
+  let Some(target) = request_line.split_whitespace().nth(1) else { return Redirect::Ignored };
+  let (target_path, query) = target.split_once('?').unwrap_or((target, ""));
+  if target_path != path {
+    return Redirect::Ignored;
+  }
+  let params: std::collections::HashMap<String, String> = query
+    .split('&')
+    .filter_map(|kv| {
+      let (k, v) = kv.split_once('=').unwrap_or((kv, ""));
+      Some((k.to_string(), urlencoding::decode(&v.replace('+', " ")).ok()?.into_owned()))
+    })
+    .collect();
+  if params.get("state").map(String::as_str) != Some(state) {
+    return Redirect::Ignored;
+  }
+  if let Some(error) = params.get("error") {
+    return Redirect::Denied(match params.get("error_description") {
+      Some(description) => format!("{}: {}", error, description),
+      None => error.clone(),
+    });
+  }
+  match params.get("code") {
+    Some(code) if !code.is_empty() => Redirect::Code(code.clone()),
+    _ => Redirect::Ignored,
+  }
+}
+
+fn respond(stream: &mut std::net::TcpStream, status: &str, html: &str) {
+  let _ = write!(
+    stream,
+    "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    html.len(),
+    html
+  );
+}
+
+/// Serve the loopback redirect until Google sends the authorization code for `state`, the
+/// user declines, or `timeout` passes
+pub(crate) fn wait_for_redirect(listener: &TcpListener, path: &str, state: &str, timeout: Duration) -> Result<String> {
+  listener.set_nonblocking(true)?;
+  let deadline = std::time::Instant::now() + timeout;
+  loop {
+    let mut stream = match listener.accept() {
+      Ok((stream, _)) => stream,
+      Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+        if std::time::Instant::now() >= deadline {
+          return Err(anyhow!("Timed out waiting for Google authorization"));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+        continue;
+      }
+      Err(e) => return Err(anyhow!("Failed to accept redirect: {}", e)),
+    };
+    stream.set_nonblocking(false).ok();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).ok();
+    let mut buffer = [0; 8192];
+    let n = match stream.read(&mut buffer) {
+      Ok(n) => n,
+      Err(e) => {
+        eprintln!("[OAuth][Connect] Failed reading redirect request: {}", e);
+        continue;
+      }
+    };
+    let request = String::from_utf8_lossy(&buffer[..n]);
+    match parse_redirect(request.lines().next().unwrap_or(""), path, state) {
+      Redirect::Code(code) => {
+        respond(&mut stream, "200 OK", REDIRECT_DONE_PAGE);
+        return Ok(code);
+      }
+      Redirect::Denied(error) => {
+        respond(&mut stream, "200 OK", REDIRECT_FAILED_PAGE);
+        return Err(anyhow!("Google authorization was declined: {}", error));
+      }
+      Redirect::Ignored => respond(&mut stream, "404 Not Found", ""),
+    }
+  }
+}
+
+pub(crate) fn open_in_browser(url: &str) -> Result<()> {
   if webbrowser::open(url).is_ok() {
     Ok(())
   } else {
@@ -193,51 +554,211 @@ fn open_in_browser(url: &str) -> Result<()> {
   }
 }
 
+/// Load .env from the current dir, then from the src-tauri paths
+pub(crate) fn load_dotenv(tag: &str) {
+  let _ = dotenvy::dotenv();
+  let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+  let env_candidates = [
+    manifest_dir.join(".env"),
+    manifest_dir.join("../.env"),
+    manifest_dir.join("../src-tauri/.env"),
+  ];
+  for path in env_candidates.iter() {
+    if path.exists() && dotenvy::from_path(path).is_ok() {
+      println!("[OAuth][{}] Loaded env from {:?}", tag, path);
+    }
+  }
+}
+
+// Trade the refresh token for a new access token
+fn request_refresh(tokens: &GoogleTokens) -> std::result::Result<GoogleTokens, RefreshError> {
+  let refresh_token = tokens
+    .refresh_token
+    .as_deref()
+    .ok_or_else(|| RefreshError::Reauth("No refresh token was issued".to_string()))?;
+  let client_id = load_env("GOOGLE_CLIENT_ID").map_err(RefreshError::Transient)?;
+  let client_secret = std::env::var("GOOGLE_CLIENT_SECRET").ok();
+
+  let mut form = vec![
+    ("grant_type", "refresh_token"),
+    ("refresh_token", refresh_token),
+    ("client_id", client_id.as_str()),
+  ];
+  if let Some(ref secret) = client_secret {
+    form.push(("client_secret", secret.as_str()));
+  }
+  let client = reqwest::blocking::Client::builder()
+    .timeout(Duration::from_secs(30))
+    .build()
+    .map_err(|e| RefreshError::Transient(e.into()))?;
+  let resp = client
+    .post(TOKEN_ENDPOINT)
+    .form(&form)
+    .send()
+    .map_err(|e| RefreshError::Transient(e.into()))?;
+  let status = resp.status();
+  if !status.is_success() {
+    let text = resp.text().unwrap_or_default();
+    return Err(if is_reauth_error(&text) {
+      RefreshError::Reauth(format!("Google rejected the refresh token: {}", text))
+    } else {
+      RefreshError::Transient(anyhow!("Token refresh failed ({}): {}", status, text))
+    });
+  }
+  let token_resp: TokenResp = resp.json().map_err(|e| RefreshError::Transient(e.into()))?;
+  Ok(apply_refresh(tokens, token_resp, now_ms()))
+}
+
+/// Refresh the stored tokens if they are close to expiry. Returns the tokens in use
+/// afterwards; on a refused grant they are flagged and "google-oauth:reauth-required" is
+/// emitted once.
+fn refresh_if_needed(app: &tauri::AppHandle, force: bool) -> Result<Option<GoogleTokens>> {
+  let _guard = REFRESH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+  let Some(mut tokens) = load_tokens(app)? else { return Ok(None) };
+  if tokens.reauth_required || (!force && !needs_refresh(&tokens, now_ms())) {
+    return Ok(Some(tokens));
+  }
+  println!("[OAuth][Refresh] Access token expires soon; refreshing...");
+  match request_refresh(&tokens) {
+    Ok(fresh) => {
+      save_tokens(app, &fresh)?;
+      println!("[OAuth][Refresh] Tokens refreshed");
+      Ok(Some(fresh))
+    }
+    Err(RefreshError::Reauth(reason)) => {
+      eprintln!("[OAuth][Refresh] Re-authentication required: {}", reason);
+      tokens.reauth_required = true;
+      store_tokens(&tokens)?;
+      let _ = app.emit("google-oauth:reauth-required", ReauthRequired { reason });
+      Ok(Some(tokens))
+    }
+    Err(RefreshError::Transient(e)) => Err(e),
+  }
+}
+
+/// A current access token for calling Google APIs, refreshing it first if needed. The
+/// tokens must carry one of `accepted` (aliases or full scope URLs); otherwise the error
+/// names what to pass to request_additional_scopes.
+pub(crate) fn access_token(app: &tauri::AppHandle, accepted: &[&str]) -> Result<String> {
+  let tokens = refresh_if_needed(app, false)?.ok_or_else(|| anyhow!("Google Suite is not connected"))?;
+  if tokens.reauth_required {
+    return Err(anyhow!("Google authorization expired; connect Google Suite again"));
+  }
+  if !has_any_scope(tokens.scope.as_deref().unwrap_or_default(), accepted) {
+    return Err(anyhow!("Google access to this isn't granted; request_additional_scopes([{:?}]) asks for it", accepted[0]));
+  }
+  Ok(tokens.access_token)
+}
+
+// Whether the space-separated `granted` includes any of `accepted`
+fn has_any_scope(granted: &str, accepted: &[&str]) -> bool {
+  accepted.iter().any(|scope| {
+    let url = if scope.starts_with("https://") { scope.to_string() } else { format!("{}{}", SCOPE_URL_PREFIX, scope) };
+    granted.split_whitespace().any(|g| g == url)
+  })
+}
+
+/// Keep the Google tokens fresh in the background, renewing them a few minutes before they
+/// expire
+pub fn start_refresh_task(app: tauri::AppHandle) {
+  std::thread::spawn(move || {
+    load_dotenv("Refresh");
+    loop {
+      if let Err(e) = refresh_if_needed(&app, false) {
+        eprintln!("[OAuth][Refresh] Refresh failed, retrying in {:?}: {}", REFRESH_CHECK_INTERVAL, e);
+      }
+      std::thread::sleep(REFRESH_CHECK_INTERVAL);
+    }
+  });
+}
+
+/// Refresh the Google access token now
+#[tauri::command]
+pub async fn refresh_google_token(app: tauri::AppHandle) -> Result<bool, String> {
+  tauri::async_runtime::spawn_blocking(move || {
+    let tokens = refresh_if_needed(&app, true)
+      .map_err(|e| format!("Failed to refresh Google token: {}", e))?
+      .ok_or_else(|| "Google Suite is not connected".to_string())?;
+    Ok(!tokens.reauth_required)
+  })
+  .await
+  .map_err(|e| format!("Refresh task failed: {}", e))?
+}
+
 #[tauri::command]
 pub fn is_google_connected(app: tauri::AppHandle) -> Result<bool, String> {
-  let path = match tokens_path(&app) {
-    Ok(p) => p,
+  let tokens = match load_tokens(&app) {
+    Ok(t) => t,
     Err(e) => {
-      eprintln!("[OAuth][Status] Failed to resolve tokens path: {}", e);
+      eprintln!("[OAuth][Status] Failed to load tokens: {}", e);
       return Ok(false);
     },
   };
-  let exists = path.exists();
-  println!("[OAuth][Status] Tokens path: {:?}, exists: {}", path, exists);
-  Ok(exists)
+  println!("[OAuth][Status] Tokens stored: {}", tokens.is_some());
+  // Tokens Google no longer accepts don't count as a connection
+  Ok(tokens.is_some_and(|t| !t.reauth_required))
+}
+
+/// What disconnect_google_suite revoked and removed
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct DisconnectSummary {
+  pub token_revoked: bool,             // Google confirmed the grant is gone
+  pub tokens_removed: bool,
+  pub imported_files_removed: usize,   // Drive imports deleted from the uploads store
+  pub mcp_credentials_removed: usize,  // Bridged credential files
+  pub contacts_cache_cleared: bool,
+  pub cached_responses_removed: usize, // API replies kept by the shared client
+  pub pending_sends_cleared: usize,    // Unconfirmed Gmail sends
+}
+
+// Ask Google to revoke the grant; revoking the refresh token ends the access tokens too
+fn revoke(tokens: &GoogleTokens) -> bool {
+  let has_refresh = tokens.refresh_token.is_some();
+  println!("[OAuth][Disconnect] Using {} token for revoke", if has_refresh {"refresh"} else {"access"});
+  let revoke_token = tokens.refresh_token.as_deref().unwrap_or(&tokens.access_token);
+  let client = match reqwest::blocking::Client::builder().timeout(Duration::from_secs(30)).build() {
+    Ok(client) => client,
+    Err(e) => {
+      eprintln!("[OAuth][Disconnect] Failed to build HTTP client: {}", e);
+      return false;
+    }
+  };
+  match client.post(REVOKE_ENDPOINT).form(&[("token", revoke_token)]).send() {
+    Ok(r) if r.status().is_success() => {
+      println!("[OAuth][Disconnect] Token revoked");
+      true
+    }
+    // 400 invalid_token: already revoked or expired, which leaves nothing to revoke
+    Ok(r) => {
+      let status = r.status();
+      let body = r.text().unwrap_or_default();
+      eprintln!("[OAuth][Disconnect] Revoke returned {}: {}", status, body.trim());
+      body.contains("invalid_token")
+    }
+    Err(e) => {
+      eprintln!("[OAuth][Disconnect] Revoke request failed: {}", e);
+      false
+    }
+  }
 }
 
+/// Revoke the Google grant and remove what came from it: the stored tokens, the bridged MCP
+/// credentials, files imported from Drive, the contacts cache and unconfirmed sends
 #[tauri::command]
-pub fn disconnect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
+pub fn disconnect_google_suite(app: tauri::AppHandle) -> Result<DisconnectSummary, String> {
   println!("[OAuth][Disconnect] Starting disconnect flow...");
-  // Attempt token revoke (best-effort)
-  let path = tokens_path(&app).map_err(|e| e.to_string())?;
-  if path.exists() {
-    println!("[OAuth][Disconnect] Found tokens at {:?}. Attempting revoke...", path);
-    if let Ok(content) = fs::read_to_string(&path) {
-      if let Ok(tokens) = serde_json::from_str::<GoogleTokens>(&content) {
-        let has_refresh = tokens.refresh_token.is_some();
-        println!("[OAuth][Disconnect] Using {} token for revoke", if has_refresh {"refresh"} else {"access"});
-        let revoke_token = tokens.refresh_token.as_deref().unwrap_or(&tokens.access_token);
-        let client = reqwest::blocking::Client::new();
-        let resp = client
-          .post("https://oauth2.googleapis.com/revoke")
-          .form(&[("token", revoke_token)])
-          .send();
-        match resp {
-          Ok(r) => println!("[OAuth][Disconnect] Revoke status: {}", r.status()),
-          Err(e) => eprintln!("[OAuth][Disconnect] Revoke request failed: {}", e),
-        }
-      } else {
-        eprintln!("[OAuth][Disconnect] Failed to parse tokens.json");
-      }
-    } else {
-      eprintln!("[OAuth][Disconnect] Failed to read tokens.json");
+  let mut summary = DisconnectSummary::default();
+  match load_tokens(&app) {
+    Ok(Some(tokens)) => {
+      println!("[OAuth][Disconnect] Found stored tokens. Attempting revoke...");
+      summary.token_revoked = revoke(&tokens);
     }
-    let _ = fs::remove_file(&path);
-    println!("[OAuth][Disconnect] Removed tokens file: {:?}", path);
-  } else {
-    println!("[OAuth][Disconnect] No tokens file found at {:?}", path);
+    Ok(None) => println!("[OAuth][Disconnect] No stored tokens found"),
+    Err(e) => eprintln!("[OAuth][Disconnect] Failed to load tokens: {}", e),
+  }
+  match delete_tokens(&app) {
+    Ok(()) => summary.tokens_removed = true,
+    Err(e) => eprintln!("[OAuth][Disconnect] Failed to remove stored tokens: {}", e),
   }
 
   // Remove MCP credential store files
@@ -252,35 +773,78 @@ pub fn disconnect_google_suite(app: tauri::AppHandle) -> Result<String, String>
   println!("[OAuth][Disconnect] Cleaning MCP credentials in {:?}", base_dir);
   if let Ok(entries) = fs::read_dir(&base_dir) {
     for entry in entries.flatten() {
-      if entry.path().extension().and_then(|s| s.to_str()) == Some("json") {
-        let _ = fs::remove_file(entry.path());
+      if entry.path().extension().and_then(|s| s.to_str()) == Some("json") && fs::remove_file(entry.path()).is_ok() {
+        summary.mcp_credentials_removed += 1;
       }
     }
   }
-  Ok("Disconnected from Google Suite".to_string())
+
+  match crate::file_storage::FileStorage::new().and_then(|storage| storage.delete_files_by_origin(DRIVE_ORIGIN_PREFIX)) {
+    Ok(removed) => summary.imported_files_removed = removed,
+    Err(e) => eprintln!("[OAuth][Disconnect] Failed to remove imported Drive files: {}", e),
+  }
+  summary.contacts_cache_cleared = crate::google_apis::contacts::clear_cache();
+  summary.cached_responses_removed = crate::google_apis::limits::clear_cache();
+  summary.pending_sends_cleared = crate::google_apis::gmail::clear_confirmations();
+  println!("[OAuth][Disconnect] Done: {:?}", summary);
+  Ok(summary)
 }
 
+/// The stored Google connection, for get_connection_status
+pub(crate) fn connection_status(app: &tauri::AppHandle) -> Result<ConnectionStatus> {
+  let Some(tokens) = load_tokens(app)? else { return Ok(ConnectionStatus::disconnected("google")) };
+  Ok(ConnectionStatus {
+    provider: "google".to_string(),
+    connected: !tokens.reauth_required,
+    account_email: tokens.id_token.as_deref().and_then(extract_email_from_id_token),
+    scopes: tokens.scope.as_deref().unwrap_or_default().split_whitespace().map(|s| s.to_string()).collect(),
+    expires_at: rfc3339(expires_at_ms(&tokens)),
+    last_refresh: rfc3339(tokens.obtained_at_ms),
+    can_refresh: tokens.refresh_token.is_some(),
+    reauth_required: tokens.reauth_required,
+    expires_at_ms: Some(expires_at_ms(&tokens)),
+  })
+}
+
+/// The scopes the stored tokens were granted
+#[tauri::command]
+pub fn get_google_granted_scopes(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+  let tokens = load_tokens(&app).map_err(|e| format!("Failed to load Google tokens: {}", e))?;
+  Ok(tokens
+    .and_then(|t| t.scope)
+    .map(|s| s.split_whitespace().map(|x| x.to_string()).collect())
+    .unwrap_or_default())
+}
+
+/// Connect Google Suite with only the chosen `scopes` (aliases such as "gmail.readonly" or
+/// "calendar", or full scope URLs); without them, every supported service is requested
 #[tauri::command]
-pub fn connect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
+pub fn connect_google_suite(app: tauri::AppHandle, scopes: Option<Vec<String>>) -> Result<String, String> {
   println!("[OAuth][Connect] Starting connect flow...");
-  // Load .env from current dir, then try explicit src-tauri paths
-  let _ = dotenvy::dotenv();
-  {
-    use std::path::PathBuf;
-    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let env_candidates = [
-      manifest_dir.join(".env"),
-      manifest_dir.join("../.env"),
-      manifest_dir.join("../src-tauri/.env"),
-    ];
-    for path in env_candidates.iter() {
-      if path.exists() {
-        if dotenvy::from_path(path).is_ok() {
-          println!("[OAuth][Connect] Loaded env from {:?}", path);
-        }
-      }
-    }
+  let scopes = resolve_scopes(scopes.as_deref()).map_err(|e| e.to_string())?;
+  authorize(&app, &scopes, false)?;
+  Ok("Google Suite connected successfully".to_string())
+}
+
+/// Ask for more scopes on top of those already granted, e.g. when a feature needs Gmail
+/// after only Calendar was connected
+#[tauri::command]
+pub fn request_additional_scopes(app: tauri::AppHandle, scopes: Vec<String>) -> Result<Vec<String>, String> {
+  println!("[OAuth][Connect] Requesting additional scopes: {:?}", scopes);
+  let previous = load_tokens(&app).map_err(|e| format!("Failed to load Google tokens: {}", e))?;
+  if previous.is_none() {
+    return Err("Google Suite is not connected".to_string());
   }
+  let scopes = resolve_scopes(Some(&scopes)).map_err(|e| e.to_string())?;
+  let tokens = authorize(&app, &scopes, true)?;
+  Ok(tokens.scope.map(|s| s.split_whitespace().map(|x| x.to_string()).collect()).unwrap_or_default())
+}
+
+// Run the consent flow for `scopes` and save the resulting tokens. `incremental` keeps the
+// scopes granted before (include_granted_scopes) and the earlier refresh token if Google
+// doesn't issue a new one.
+fn authorize(app: &tauri::AppHandle, scopes: &str, incremental: bool) -> Result<GoogleTokens, String> {
+  load_dotenv("Connect");
 
   // Read secrets from env with explicit debug
   let client_id = match load_env("GOOGLE_CLIENT_ID") {
@@ -307,64 +871,13 @@ pub fn connect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
   println!("[OAuth][Connect] Flow decision: oauth_flow={}, has_secret={}, using={}", 
     oauth_flow, client_secret.is_some(), if is_web_flow { "web" } else { "desktop (PKCE)" });
 
-  // Scopes for Google services (broad access for MCP tools)
-  let scopes = vec![
-    // Gmail
-    "https://www.googleapis.com/auth/gmail.readonly",
-    "https://www.googleapis.com/auth/gmail.modify",
-    "https://www.googleapis.com/auth/gmail.send",
-    "https://www.googleapis.com/auth/gmail.compose",
-    "https://www.googleapis.com/auth/gmail.labels",
-    // Calendar
-    "https://www.googleapis.com/auth/calendar",
-    "https://www.googleapis.com/auth/calendar.readonly",
-    "https://www.googleapis.com/auth/calendar.events",
-    // Drive
-    "https://www.googleapis.com/auth/drive",
-    "https://www.googleapis.com/auth/drive.file",
-    "https://www.googleapis.com/auth/drive.readonly",
-    // Docs
-    "https://www.googleapis.com/auth/documents",
-    "https://www.googleapis.com/auth/documents.readonly",
-    // Sheets
-    "https://www.googleapis.com/auth/spreadsheets",
-    "https://www.googleapis.com/auth/spreadsheets.readonly",
-    // Slides
-    "https://www.googleapis.com/auth/presentations",
-    "https://www.googleapis.com/auth/presentations.readonly",
-    // Tasks
-    "https://www.googleapis.com/auth/tasks",
-    "https://www.googleapis.com/auth/tasks.readonly",
-    // Forms
-    "https://www.googleapis.com/auth/forms.body",
-    "https://www.googleapis.com/auth/forms.body.readonly",
-    "https://www.googleapis.com/auth/forms.responses.readonly",
-    // Chat (user-level scopes)
-    "https://www.googleapis.com/auth/chat.messages",
-    "https://www.googleapis.com/auth/chat.messages.readonly",
-    "https://www.googleapis.com/auth/chat.memberships",
-    "https://www.googleapis.com/auth/chat.memberships.readonly",
-    "https://www.googleapis.com/auth/chat.spaces",
-    "https://www.googleapis.com/auth/chat.spaces.readonly",
-    // OpenID / user info
-    "openid",
-    "https://www.googleapis.com/auth/userinfo.email",
-    "https://www.googleapis.com/auth/userinfo.profile",
-  ].join(" ");
-  println!("[OAuth][Connect] Total scopes length: {}", scopes.len());
-
-  // Helper to parse a port number from a URL string like http://localhost:3000/path
-  let parse_port = |uri: &str| -> Option<u16> {
-    let after_scheme = uri.split("://").nth(1)?; // localhost:3000/path
-    let host_port = after_scheme.split('/').next()?; // localhost:3000
-    host_port.split(':').nth(1)?.parse::<u16>().ok()
-  };
+  println!("[OAuth][Connect] Requesting scopes: {} (incremental: {})", scopes, incremental);
 
-  // Start local server for OAuth redirect
+  // Start the loopback server for the OAuth redirect (RFC 8252 section 7.3)
   let (listener, redirect_uri) = if is_web_flow {
     let ru = std::env::var("GOOGLE_REDIRECT_URI")
       .unwrap_or_else(|_| "http://localhost:3000/oauth2callback".to_string());
-    let port = parse_port(&ru).unwrap_or(3000);
+    let port = redirect_port(&ru).unwrap_or(3000);
     let l = TcpListener::bind(format!("127.0.0.1:{}", port)).map_err(|e| {
       eprintln!("[OAuth][Connect] Failed to bind configured redirect port {}: {}", port, e);
       e.to_string()
@@ -372,29 +885,34 @@ pub fn connect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
     println!("[OAuth][Connect] Redirect URI (web flow): {}", ru);
     (l, ru)
   } else {
+    // Any free port; Google accepts every loopback port for desktop clients
     let l = TcpListener::bind("127.0.0.1:0").map_err(|e| {
       eprintln!("[OAuth][Connect] Failed to bind local port: {}", e);
       e.to_string()
     })?;
-    l.set_nonblocking(false).map_err(|e| {
-      eprintln!("[OAuth][Connect] Failed to set blocking mode: {}", e);
-      e.to_string()
-    })?;
     let port = l.local_addr().map_err(|e| {
       eprintln!("[OAuth][Connect] Failed to read local addr: {}", e);
       e.to_string()
     })?.port();
-    let ru = format!("http://127.0.0.1:{}", port);
+    let ru = format!("http://127.0.0.1:{}/", port);
     println!("[OAuth][Connect] Redirect URI (desktop flow): {}", ru);
     (l, ru)
   };
 
   let (code_verifier, code_challenge) = generate_pkce_pair();
   println!("[OAuth][Connect] Generated PKCE pair (verifier: {} chars)", code_verifier.len());
+  // Ties the redirect to this request, so a stray or forged one can't complete it
+  let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
 
   // Build authorization URL (use v2 endpoint)
   let auth_url = format!(
-    "https://accounts.google.com/o/oauth2/v2/auth?response_type=code&client_id={client_id}&redirect_uri={redirect_uri}&scope={scopes}&access_type=offline&prompt=consent&code_challenge={code_challenge}&code_challenge_method=S256",
+    "https://accounts.google.com/o/oauth2/v2/auth?response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&access_type=offline&prompt=consent&code_challenge={}&code_challenge_method=S256{}",
+    urlencoding::encode(&client_id),
+    urlencoding::encode(&redirect_uri),
+    urlencoding::encode(scopes),
+    state,
+    code_challenge,
+    if incremental { "&include_granted_scopes=true" } else { "" },
   );
   println!("[OAuth][Connect] Opening browser for consent page...");
 
@@ -403,66 +921,16 @@ pub fn connect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
     e.to_string()
   })?;
 
-  // Accept single connection for redirect
   println!("[OAuth][Connect] Waiting for OAuth redirect on {}...", redirect_uri);
-  listener
-    .set_nonblocking(false)
-    .map_err(|e| {
-      eprintln!("[OAuth][Connect] Failed to set blocking mode (second time): {}", e);
-      e.to_string()
-    })?;
-
-  let (mut stream, _) = listener.accept().map_err(|e| {
-    eprintln!("[OAuth][Connect] Failed to accept redirect: {}", e);
-    e.to_string()
-  })?;
-  stream
-    .set_read_timeout(Some(Duration::from_secs(120)))
-    .ok();
-
-  let mut buffer = [0; 8192];
-  let n = stream.read(&mut buffer).map_err(|e| {
-    eprintln!("[OAuth][Connect] Failed reading redirect request: {}", e);
+  let code = wait_for_redirect(&listener, redirect_path(&redirect_uri), &state, AUTH_TIMEOUT).map_err(|e| {
+    eprintln!("[OAuth][Connect] {}", e);
     e.to_string()
   })?;
-  let req = String::from_utf8_lossy(&buffer[..n]);
-  if let Some(first_line) = req.lines().next() { println!("[OAuth][Connect] Redirect first line: {}", first_line); }
-
-  // Parse the first line: GET /?code=... HTTP/1.1
-  let first_line = req.lines().next().unwrap_or("");
-  let code_opt = first_line
-    .split_whitespace()
-    .nth(1)
-    .and_then(|path| {
-      let parts: Vec<&str> = path.split('?').collect();
-      if parts.len() < 2 { return None; }
-      let query = parts[1];
-      for kv in query.split('&') {
-        let mut it = kv.splitn(2, '=');
-        let k = it.next()?;
-        let v = it.next().unwrap_or("");
-        if k == "code" { return Some(urlencoding::decode(v).ok()?.to_string()); }
-      }
-      None
-    });
-
-  let code = match code_opt {
-    Some(c) => {
-      println!("[OAuth][Connect] Received authorization code (len: {})", c.len());
-      c
-    },
-    None => {
-      let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Type: text/plain\r\nContent-Length: 12\r\n\r\nBad Request");
-      eprintln!("[OAuth][Connect] Authorization code not found in redirect");
-      return Err("Authorization code not found in redirect".into());
-    }
-  };
-
-  // Respond to browser so user can close the tab
-  let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n<html><body><h2>Google authorization received.</h2><p>You can close this window and return to AGI.</p></body></html>");
+  drop(listener);
+  println!("[OAuth][Connect] Received authorization code (len: {})", code.len());
 
   // Exchange code for tokens
-  let token_endpoint = "https://oauth2.googleapis.com/token";
+  let token_endpoint = TOKEN_ENDPOINT;
   let client = reqwest::blocking::Client::new();
   println!("[OAuth][Connect] Exchanging code for tokens...");
 
@@ -504,32 +972,28 @@ pub fn connect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
     return Err(format!("Token exchange failed: {}", text));
   }
 
-  #[derive(Deserialize)]
-  struct TokenResp {
-    access_token: String,
-    expires_in: Option<u64>,
-    refresh_token: Option<String>,
-    scope: Option<String>,
-    token_type: Option<String>,
-    id_token: Option<String>,
-  }
-
   let token_resp: TokenResp = resp.json().map_err(|e| {
     eprintln!("[OAuth][Connect] Failed parsing token JSON: {}", e);
     e.to_string()
   })?;
 
+  let previous = if incremental { load_tokens(app).ok().flatten() } else { None };
   let tokens = GoogleTokens {
     access_token: token_resp.access_token,
     expires_in: token_resp.expires_in,
-    refresh_token: token_resp.refresh_token,
-    scope: token_resp.scope,
+    refresh_token: token_resp.refresh_token.or_else(|| previous.as_ref().and_then(|p| p.refresh_token.clone())),
+    scope: if incremental {
+      merge_scopes(previous.as_ref().and_then(|p| p.scope.as_deref()), token_resp.scope.as_deref())
+    } else {
+      token_resp.scope
+    },
     token_type: token_resp.token_type,
     id_token: token_resp.id_token,
     obtained_at_ms: std::time::SystemTime::now()
       .duration_since(std::time::UNIX_EPOCH)
       .map_err(|e| e.to_string())?
       .as_millis(),
+    reauth_required: false,
   };
   println!(
     "[OAuth][Connect] Tokens received (access: {} chars, has_refresh: {}, has_id: {})",
@@ -538,11 +1002,96 @@ pub fn connect_google_suite(app: tauri::AppHandle) -> Result<String, String> {
     tokens.id_token.is_some()
   );
 
-  save_tokens(&app, &tokens).map_err(|e| {
+  save_tokens(app, &tokens).map_err(|e| {
     eprintln!("[OAuth][Connect] Failed to save/bridge tokens: {}", e);
     e.to_string()
   })?;
-  println!("[OAuth][Connect] Tokens saved and bridged to MCP stores");
+  println!("[OAuth][Connect] Tokens saved (scopes: {:?})", tokens.scope);
 
-  Ok("Google Suite connected successfully".to_string())
-} 
\ No newline at end of file
+  Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_refresh_keeps_refresh_token_and_detects_revocation() {
+    let old = GoogleTokens {
+      access_token: "old".into(),
+      expires_in: Some(3600),
+      refresh_token: Some("refresh".into()),
+      scope: Some("openid".into()),
+      token_type: Some("Bearer".into()),
+      id_token: None,
+      obtained_at_ms: 1_000_000,
+      reauth_required: false,
+    };
+    assert!(!needs_refresh(&old, 1_000_000 + 3000 * 1000));
+    assert!(needs_refresh(&old, 1_000_000 + 3400 * 1000));
+
+    let resp = TokenResp { access_token: "new".into(), expires_in: Some(3599), refresh_token: None, scope: None, token_type: None, id_token: None };
+    let fresh = apply_refresh(&old, resp, 5_000_000);
+    assert_eq!(fresh.access_token, "new");
+    assert_eq!(fresh.refresh_token.as_deref(), Some("refresh"));
+    assert_eq!(fresh.scope.as_deref(), Some("openid"));
+    assert_eq!(expires_at_ms(&fresh), 5_000_000 + 3599 * 1000);
+
+    assert!(is_reauth_error(r#"{"error":"invalid_grant","error_description":"Token has been expired or revoked."}"#));
+    assert!(!is_reauth_error(r#"{"error":"internal_failure"}"#));
+    assert!(!is_reauth_error("<html>502 Bad Gateway</html>"));
+  }
+
+  #[test]
+  fn test_scope_selection() {
+    let calendar = resolve_scopes(Some(&["calendar.readonly".to_string()])).unwrap();
+    assert_eq!(
+      calendar,
+      "https://www.googleapis.com/auth/calendar.readonly openid https://www.googleapis.com/auth/userinfo.email https://www.googleapis.com/auth/userinfo.profile"
+    );
+    assert!(!calendar.contains("gmail"));
+    assert!(resolve_scopes(Some(&["fax".to_string()])).is_err());
+    assert_eq!(resolve_scopes(None).unwrap().split(' ').count(), DEFAULT_SCOPES.len());
+
+    let merged = merge_scopes(Some("openid a"), Some("a b")).unwrap();
+    assert_eq!(merged, "openid a b");
+    assert_eq!(merge_scopes(None, None), None);
+    assert!(has_any_scope(&calendar, &["calendar", "calendar.readonly"]));
+    assert!(!has_any_scope(&calendar, &["gmail.readonly", "gmail.modify"]));
+  }
+
+  #[test]
+  fn test_loopback_redirect() {
+    assert_eq!(redirect_path("http://127.0.0.1:5123/"), "/");
+    assert_eq!(redirect_path("http://localhost:3000/oauth2callback"), "/oauth2callback");
+    assert_eq!(redirect_port("http://localhost:3000/oauth2callback"), Some(3000));
+
+    let line = |target: &str| format!("GET {} HTTP/1.1", target);
+    assert_eq!(parse_redirect(&line("/?state=s1&code=4%2F0Ab&scope=openid"), "/", "s1"), Redirect::Code("4/0Ab".into()));
+    assert_eq!(parse_redirect(&line("/favicon.ico"), "/", "s1"), Redirect::Ignored);
+    // Someone else's redirect doesn't complete ours
+    assert_eq!(parse_redirect(&line("/?state=other&code=x"), "/", "s1"), Redirect::Ignored);
+    assert_eq!(parse_redirect(&line("/?code=x"), "/", "s1"), Redirect::Ignored);
+    assert_eq!(
+      parse_redirect(&line("/oauth2callback?error=access_denied&state=s1"), "/oauth2callback", "s1"),
+      Redirect::Denied("access_denied".into())
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let browser = std::thread::spawn(move || {
+      let get = |target: &str| {
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n", target).unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        reply
+      };
+      assert!(get("/favicon.ico").starts_with("HTTP/1.1 404"));
+      assert!(get("/?state=s1&code=abc").starts_with("HTTP/1.1 200"));
+    });
+    assert_eq!(wait_for_redirect(&listener, "/", "s1", Duration::from_secs(10)).unwrap(), "abc");
+    browser.join().unwrap();
+    assert!(wait_for_redirect(&listener, "/", "s1", Duration::from_millis(200)).is_err());
+  }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{App, AppHandle};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::settings::Settings;
+use crate::window;
+
+// Shortcuts the OS or nearly every app already uses, with what they do; taking one globally
+// would break it everywhere
+#[cfg(target_os = "macos")]
+const RESERVED: &[(&str, &str)] = &[
+    ("Super+Space", "Spotlight"),
+    ("Control+Space", "switching input sources"),
+    ("Super+Tab", "the app switcher"),
+    ("Alt+Super+Escape", "Force Quit"),
+    ("Super+Shift+3", "screenshots"),
+    ("Super+Shift+4", "screenshots"),
+    ("Super+Shift+5", "screenshots"),
+    ("Super+Q", "quitting apps"),
+    ("Super+W", "closing windows"),
+    ("Super+H", "hiding apps"),
+];
+#[cfg(target_os = "windows")]
+const RESERVED: &[(&str, &str)] = &[
+    ("Alt+Tab", "the app switcher"),
+    ("Alt+F4", "closing windows"),
+    ("Control+Alt+Delete", "the security screen"),
+    ("Control+Shift+Escape", "Task Manager"),
+    ("Super+L", "locking the screen"),
+    ("Super+D", "showing the desktop"),
+    ("Super+Tab", "Task View"),
+    ("Super+Shift+S", "screenshots"),
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const RESERVED: &[(&str, &str)] = &[
+    ("Alt+Tab", "the app switcher"),
+    ("Alt+F4", "closing windows"),
+    ("Control+Alt+Delete", "logging out"),
+    ("Control+Alt+T", "opening a terminal"),
+    ("Super+L", "locking the screen"),
+];
+// Editing shortcuts, on every platform
+const EDITING: &[(&str, &str)] = &[
+    ("CommandOrControl+C", "copy"),
+    ("CommandOrControl+V", "paste"),
+    ("CommandOrControl+X", "cut"),
+    ("CommandOrControl+Z", "undo"),
+    ("CommandOrControl+A", "select all"),
+    ("CommandOrControl+S", "save"),
+];
+
+// The combo registered now, as the user wrote it
+static CURRENT: Mutex<Option<String>> = Mutex::new(None);
+
+/// The show/hide shortcut and whether the OS accepted it
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HotkeyStatus {
+    pub combo: Option<String>,
+    pub registered: bool,
+}
+
+/// Parse a combo like "CommandOrControl+Shift+Space", refusing bare keys and shortcuts the
+/// OS or other apps depend on
+fn parse(combo: &str) -> Result<Shortcut> {
+    let shortcut: Shortcut = combo.trim().parse().map_err(|e| anyhow!("{:?} isn't a key combination: {}", combo, e))?;
+    if shortcut.mods.is_empty() {
+        return Err(anyhow!("{:?} needs a modifier such as CommandOrControl, Alt or Shift", combo));
+    }
+    for (reserved, what) in RESERVED.iter().chain(EDITING) {
+        if reserved.parse::<Shortcut>().is_ok_and(|r| r == shortcut) {
+            return Err(anyhow!("{} is already used for {}", combo.trim(), what));
+        }
+    }
+    Ok(shortcut)
+}
+
+fn current() -> Option<String> {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// Swap the registered shortcut for `combo`. When the OS refuses it (another app holds it),
+// the previous one is put back.
+fn register(app: &AppHandle, combo: Option<&str>) -> Result<()> {
+    let shortcuts = app.global_shortcut();
+    let previous = current();
+    let next = combo.map(parse).transpose()?;
+    if let Some(old) = previous.as_deref().and_then(|c| c.parse::<Shortcut>().ok()) {
+        shortcuts.unregister(old)?;
+    }
+    if let Some(shortcut) = next {
+        if let Err(e) = shortcuts.register(shortcut) {
+            if let Some(old) = previous.as_deref().and_then(|c| c.parse::<Shortcut>().ok()) {
+                let _ = shortcuts.register(old);
+            }
+            return Err(anyhow!("{} is taken by another app: {}", combo.unwrap_or_default().trim(), e));
+        }
+    }
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = combo.map(|c| c.trim().to_string());
+    Ok(())
+}
+
+/// Install the global shortcut plugin and register the saved show/hide shortcut
+pub fn init(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+    app.handle().plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, _shortcut, event| {
+                if event.state() == ShortcutState::Pressed {
+                    if let Err(e) = window::toggle_main_window(app) {
+                        eprintln!("[Hotkey] Failed to toggle the window: {}", e);
+                    }
+                }
+            })
+            .build(),
+    )?;
+    let combo = Settings::load().map(|s| s.window.hotkey).unwrap_or_default();
+    match register(app.handle(), combo.as_deref()) {
+        Ok(()) => println!("[Hotkey] Show/hide shortcut: {}", combo.as_deref().unwrap_or("off")),
+        // A bad or taken combo shouldn't keep the app from starting
+        Err(e) => eprintln!("[Hotkey] Couldn't register {:?}: {}", combo, e),
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_global_hotkey() -> HotkeyStatus {
+    let saved = Settings::load().map(|s| s.window.hotkey).unwrap_or_default();
+    let registered = saved.is_some() && current() == saved;
+    HotkeyStatus { combo: saved, registered }
+}
+
+/// Change the show/hide shortcut, e.g. "Alt+Space"; an empty combo turns it off. Fails
+/// without changing anything when the combo is reserved or taken.
+#[tauri::command]
+pub fn set_global_hotkey(app: AppHandle, combo: String) -> Result<HotkeyStatus, String> {
+    let combo = Some(combo.trim().to_string()).filter(|c| !c.is_empty());
+    register(&app, combo.as_deref()).map_err(|e| format!("Failed to set the shortcut: {}", e))?;
+    Settings::update(|s| s.window.hotkey = combo.clone()).map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("[Hotkey] Show/hide shortcut set to {}", combo.as_deref().unwrap_or("off"));
+    Ok(HotkeyStatus { registered: combo.is_some(), combo })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_refuses_reserved_and_bare_keys() {
+        assert!(parse("CommandOrControl+Shift+Space").is_ok());
+        assert!(parse(" Alt+Shift+K ").is_ok());
+        assert!(parse("K").unwrap_err().to_string().contains("needs a modifier"));
+        assert!(parse("CommandOrControl+V").unwrap_err().to_string().contains("paste"));
+        assert!(parse(RESERVED[0].0).is_err());
+        assert!(parse("Shift+Nonsense").is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::{Context, Result};
+use keyring::{Entry, Error};
+
+// The OS store: macOS Keychain, Windows Credential Manager, or the Secret Service on Linux
+// (GNOME Keyring, KWallet). Secrets go through the platform API, never argv or a file.
+fn entry(service: &str, account: &str) -> Result<Entry> {
+    Entry::new(service, account).with_context(|| format!("Keychain entry {}/{}", service, account))
+}
+
+/// The secret stored for (service, account), if any
+pub fn get(service: &str, account: &str) -> Result<Option<String>> {
+    match entry(service, account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Reading {}/{} from the keychain", service, account)),
+    }
+}
+
+/// Store `secret` for (service, account), replacing any earlier one
+pub fn set(service: &str, account: &str, secret: &str) -> Result<()> {
+    entry(service, account)?
+        .set_password(secret)
+        .with_context(|| format!("Saving {}/{} to the keychain", service, account))
+}
+
+/// Remove the secret for (service, account); nothing to remove is fine
+pub fn delete(service: &str, account: &str) -> Result<()> {
+    match entry(service, account)?.delete_credential() {
+        Ok(()) | Err(Error::NoEntry) => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Removing {}/{} from the keychain", service, account)),
+    }
+}
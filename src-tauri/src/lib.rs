@@ -1,15 +1,48 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod window;
+#[cfg(desktop)]
+mod hotkey;
 mod pii_scrubber;
-mod aws_uploader;
+mod pii_ner;
+mod pii_audit;
+mod pii_image;
+mod clipboard;
+mod keychain;
+mod network;
+mod cloud_uploader;
 mod google_oauth;
+mod google_apis;
+mod microsoft_oauth;
+mod microsoft_apis;
+mod dropbox_oauth;
+mod dropbox;
+mod oauth_tokens;
+mod slack_oauth;
+mod slack;
+mod connections;
+mod notifications;
 mod file_storage;
+mod conversation_memory;
+mod chunking;
+mod embeddings;
+mod model_manager;
+mod query_expansion;
+mod rerank;
+mod retrieval_eval;
+mod retrieval_trace;
+mod vector_store;
+mod extractors;
+mod capabilities;
+mod settings;
+mod flags;
+mod self_test;
+mod transcription;
 
 use std::process::{Command as StdCommand, Stdio, Child};
 use std::sync::Mutex;
 use std::thread;
 use std::io::{BufRead, BufReader};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -21,53 +54,105 @@ fn get_app_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
+/// Resize the main window to `height` logical pixels, animating over `duration_ms`
+/// (150 ms by default, 0 to jump)
 #[tauri::command]
-fn set_window_height(window: tauri::WebviewWindow, height: u32) -> Result<(), String> {
-  use tauri::{LogicalSize, Size};
-  
-  let new_size = LogicalSize::new(700.0, height as f64);
-  
-  match window.set_size(Size::Logical(new_size)) {
-    Ok(_) => {
-      if let Err(e) = window::position_window_top_center(&window, 54) {
-        eprintln!("Failed to reposition window: {}", e);
-      }
-      Ok(())
-    }
-    Err(e) => Err(format!("Failed to resize window: {}", e))
-  }
+fn set_window_height(window: tauri::WebviewWindow, height: u32, duration_ms: Option<u64>) -> Result<(), String> {
+  let duration = std::time::Duration::from_millis(duration_ms.unwrap_or(150));
+  window::animate_height(&window, height as f64, duration)
+    .map_err(|e| format!("Failed to resize window: {}", e))
 }
 
+/// Scrub and save a conversation export; returns what was redacted
 #[tauri::command]
-fn write_conversation_to_file(conversation_data: String, filename: String) -> Result<(), String> {
+fn write_conversation_to_file(
+  app: tauri::AppHandle,
+  conversation_data: String,
+  filename: String,
+) -> Result<pii_scrubber::ScrubReport, String> {
   use std::fs;
-  use std::path::Path;
   
-  let clean_conversation_data = pii_scrubber::scrub_conversation_json(conversation_data)
+  let (clean_conversation_data, report) = pii_scrubber::scrub_conversation_json(conversation_data)
     .map_err(|e| format!("Failed to scrub PII: {}", e))?;
   
-  let project_dir = Path::new("C:\\Users\\parad\\Downloads\\pluely-master2");
-  
-  let memory_path = project_dir.join("memory");
+  let memory_path = conversation_memory::memory_dir();
   
   if !memory_path.exists() {
     fs::create_dir(&memory_path)
       .map_err(|e| format!("Failed to create memory directory: {}", e))?;
   }
   
-  let file_path = memory_path.join(filename);
+  let file_path = memory_path.join(&filename);
   
-  fs::write(&file_path, clean_conversation_data)
+  fs::write(&file_path, &clean_conversation_data)
     .map_err(|e| format!("Failed to write file: {}", e))?;
   
   println!("Clean conversation written to: {:?}", file_path);
-  Ok(())
+  let destination = Some(format!("memory/{}", filename));
+  pii_audit::record("write_conversation_to_file", destination.clone(), &report);
+  pii_scrubber::notify_flagged(&app, "write_conversation_to_file", destination, &report);
+  // Make it recallable later; the scrubbed copy is what gets embedded
+  conversation_memory::index_in_background(clean_conversation_data);
+  Ok(report)
+}
+
+/// Reporter that emits "cloud-sync:progress" and "cloud-sync:status", and raises a
+/// notification when the queue empties after sending files
+fn emitting_reporter(app: tauri::AppHandle) -> cloud_uploader::progress::Reporter {
+    let notified_uploads = Mutex::new(0);
+    cloud_uploader::progress::Reporter::new(move |event| {
+        let _ = app.emit(event.name(), &event);
+        if let cloud_uploader::progress::SyncEvent::Status(status) = &event {
+            let mut notified = notified_uploads.lock().unwrap_or_else(|e| e.into_inner());
+            if status.state == "idle" && status.queued == 0 && status.uploaded > *notified {
+                let sent = status.uploaded - *notified;
+                *notified = status.uploaded;
+                notifications::notify(
+                    &app,
+                    notifications::Notification::new(
+                        "Upload finished",
+                        format!("{} file{} synced to the cloud", sent, if sent == 1 { "" } else { "s" }),
+                    )
+                    .action("Show files", "files"),
+                );
+            }
+        }
+    })
 }
 
+/// Raise a notification when the sidecar has exited on its own. On shutdown the child is
+/// taken out of state before it's killed, so a deliberate stop isn't reported.
+fn notify_if_sidecar_crashed(app: &tauri::AppHandle) {
+  // stdout can close a moment before the process is reaped
+  for _ in 0..10 {
+    let status = {
+      let Some(mutex) = app.try_state::<Mutex<Option<Child>>>() else { return };
+      let Ok(mut guard) = mutex.lock() else { return };
+      match guard.as_mut().map(|child| child.try_wait()) {
+        Some(Ok(Some(status))) => status,
+        Some(Ok(None)) => {
+          drop(guard);
+          thread::sleep(std::time::Duration::from_millis(100));
+          continue;
+        }
+        _ => return,
+      }
+    };
+    eprintln!("[sidecar] Exited unexpectedly: {}", status);
+    notifications::notify(
+      app,
+      notifications::Notification::new("Assistant service stopped", format!("The background service exited ({}). Restart the app to reconnect.", status))
+        .action("Run self-test", "settings/diagnostics"),
+    );
+    return;
+  }
+}
+
+/// Emits the cloud-sync:* events while it runs, counted in the background uploader's status
 #[tauri::command]
-fn trigger_aws_upload() -> Result<String, String> {
-  let uploader = aws_uploader::AwsUploader::new()
-    .map_err(|e| format!("Failed to create AWS uploader: {}", e))?;
+fn trigger_aws_upload(sync: tauri::State<'_, cloud_uploader::control::CloudSync>) -> Result<String, String> {
+  let uploader = sync.uploader()
+    .map_err(|e| format!("Failed to create cloud uploader: {}", e))?;
   
   match uploader.scan_and_upload() {
     Ok(_) => Ok("AWS upload scan completed successfully".to_string()),
@@ -89,6 +174,7 @@ async fn upload_file(file_data: Vec<u8>, filename: String) -> Result<file_storag
 async fn upload_file_from_path(
     file_path: String,
     filename: String,
+    ingest_attachments: Option<bool>,
 ) -> Result<file_storage::FileInfo, String> {
     println!(
         "[Backend] upload_file_from_path command called: path={}, filename={}",
@@ -119,7 +205,12 @@ async fn upload_file_from_path(
     
     // Store file with content extraction
     let result = storage
-        .store_file_from_path_robust(&file_path, &filename, &file_type)
+        .store_file_from_path_robust(
+            &file_path,
+            &filename,
+            &file_type,
+            ingest_attachments.unwrap_or(false),
+        )
         .map_err(|e| {
             println!("[Backend] Upload failed: {}", e);
             format!("Failed to upload file: {}", e)
@@ -132,6 +223,19 @@ async fn upload_file_from_path(
     Ok(result)
 }
 
+#[tauri::command]
+async fn extract_pdf_pages(
+    file_id: String,
+    from_page: u32,
+    to_page: u32,
+) -> Result<file_storage::FileInfo, String> {
+    let storage = file_storage::FileStorage::new()
+        .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+
+    storage.extract_pdf_pages(&file_id, from_page, to_page)
+        .map_err(|e| format!("Failed to extract PDF pages: {}", e))
+}
+
 #[tauri::command]
 async fn list_uploaded_files() -> Result<Vec<file_storage::FileInfo>, String> {
     let storage = file_storage::FileStorage::new()
@@ -141,13 +245,34 @@ async fn list_uploaded_files() -> Result<Vec<file_storage::FileInfo>, String> {
         .map_err(|e| format!("Failed to list files: {}", e))
 }
 
+#[tauri::command]
+async fn list_files_by_tag(tag: String) -> Result<Vec<file_storage::FileInfo>, String> {
+    let storage = file_storage::FileStorage::new()
+        .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+
+    storage.list_files_by_tag(&tag)
+        .map_err(|e| format!("Failed to list files by tag: {}", e))
+}
+
+#[tauri::command]
+async fn list_archive_children(file_id: String) -> Result<Vec<file_storage::ArchiveNode>, String> {
+    let storage = file_storage::FileStorage::new()
+        .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+
+    storage.archive_tree(&file_id)
+        .map_err(|e| format!("Failed to list archive members: {}", e))
+}
+
+/// Also takes the file out of the cloud copies carrying it, unless remote copies are kept
 #[tauri::command]
 async fn delete_uploaded_file(file_id: String) -> Result<(), String> {
     let storage = file_storage::FileStorage::new()
         .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
     
     storage.delete_file(&file_id)
-        .map_err(|e| format!("Failed to delete file: {}", e))
+        .map_err(|e| format!("Failed to delete file: {}", e))?;
+    cloud_uploader::ledger::delete_remote_copies(Some(vec![file_id]));
+    Ok(())
 }
 
 #[tauri::command]
@@ -169,14 +294,139 @@ async fn get_file_context() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-async fn get_optimized_file_context() -> Result<Vec<String>, String> {
+async fn get_optimized_file_context(
+    language: Option<String>,
+    model: Option<String>,
+) -> Result<Vec<String>, String> {
     let storage = file_storage::FileStorage::new()
         .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
     
-    storage.get_optimized_context()
+    storage.get_optimized_context(language.as_deref(), model.as_deref())
         .map_err(|e| format!("Failed to get optimized file context: {}", e))
 }
 
+#[tauri::command]
+async fn get_context_chunks(
+    language: Option<String>,
+    model: Option<String>,
+) -> Result<Vec<file_storage::ContextChunk>, String> {
+    let storage = file_storage::FileStorage::new()
+        .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+
+    storage.get_context_chunks(language.as_deref(), model.as_deref())
+        .map_err(|e| format!("Failed to get context chunks: {}", e))
+}
+
+/// Tracer that emits "retrieval:started", "retrieval:stage" and "retrieval:finished"
+fn emitting_tracer(app: tauri::AppHandle, query: &str) -> retrieval_trace::Tracer<'static> {
+    retrieval_trace::Tracer::new(query, move |event| {
+        let _ = app.emit(event.name(), &event);
+    })
+}
+
+/// Emits the retrieval:* trace events while it runs
+#[tauri::command]
+async fn get_context_for_query(
+    app: tauri::AppHandle,
+    query: String,
+    max_tokens: Option<usize>,
+    conversation_id: Option<String>,
+    model: Option<String>,
+) -> Result<Vec<file_storage::ContextChunk>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = file_storage::FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        storage
+            .get_context_for_query(
+                &query,
+                max_tokens.unwrap_or(8000),
+                conversation_id.as_deref(),
+                model.as_deref(),
+                emitting_tracer(app, &query),
+            )
+            .map_err(|e| format!("Failed to get context for query: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Context task failed: {}", e))?
+}
+
+/// Dry run of get_context_for_query: what would be sent, without sending anything
+#[tauri::command]
+async fn preview_context(
+    app: tauri::AppHandle,
+    query: String,
+    max_tokens: Option<usize>,
+    conversation_id: Option<String>,
+    model: Option<String>,
+) -> Result<file_storage::ContextPreview, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = file_storage::FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        storage
+            .preview_context(
+                &query,
+                max_tokens.unwrap_or(8000),
+                conversation_id.as_deref(),
+                model.as_deref(),
+                emitting_tracer(app, &query),
+            )
+            .map_err(|e| format!("Failed to preview context: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Context task failed: {}", e))?
+}
+
+#[tauri::command]
+async fn retrieve_context(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<vector_store::ScoredChunk>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = file_storage::FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        let mut trace = emitting_tracer(app, &query);
+        let hits = storage
+            .retrieve_in(vector_store::Namespace::Files, &query, limit.unwrap_or(8), &mut trace)
+            .map_err(|e| format!("Failed to retrieve context: {}", e))?;
+        let files = storage.list_files().unwrap_or_default();
+        trace.finish(
+            hits.iter()
+                .map(|h| retrieval_trace::SelectedChunk {
+                    file_id: h.chunk.file_id.clone(),
+                    name: files
+                        .iter()
+                        .find(|f| f.id == h.chunk.file_id)
+                        .map_or(h.chunk.file_id.clone(), |f| f.name.clone()),
+                    citation: None,
+                    score: Some(h.score),
+                    tokens: h.chunk.tokens,
+                })
+                .collect(),
+        );
+        Ok(hits)
+    })
+    .await
+    .map_err(|e| format!("Retrieval task failed: {}", e))?
+}
+
+#[tauri::command]
+async fn sync_vector_index() -> Result<(usize, usize), String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let storage = file_storage::FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        storage.sync_index()
+            .map_err(|e| format!("Failed to sync vector index: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Index task failed: {}", e))?
+}
+
+#[tauri::command]
+fn detect_language(text: String) -> Option<String> {
+    extractors::detect_language(&text)
+}
+
 #[tauri::command]
 async fn extract_file_content(file_id: String) -> Result<String, String> {
     let storage = file_storage::FileStorage::new()
@@ -186,12 +436,44 @@ async fn extract_file_content(file_id: String) -> Result<String, String> {
         .map_err(|e| format!("Failed to extract file content: {}", e))
 }
 
+#[tauri::command]
+async fn reextract_file(file_id: String) -> Result<file_storage::FileInfo, String> {
+    let storage = file_storage::FileStorage::new()
+        .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+
+    storage.reextract_file(&file_id)
+        .map_err(|e| format!("Failed to re-extract file: {}", e))
+}
+
+/// Re-run extraction for all files, or only those extracted by an older extractor.
+/// Emits "reextract:progress" per file.
+#[tauri::command]
+async fn reextract_all(
+    app: tauri::AppHandle,
+    stale_only: Option<bool>,
+) -> Result<file_storage::ReextractReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = file_storage::FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        storage
+            .reextract_all(stale_only.unwrap_or(true), |progress| {
+                let _ = app.emit("reextract:progress", progress);
+            })
+            .map_err(|e| format!("Failed to re-extract files: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Re-extraction task failed: {}", e))?
+}
+
+/// Also takes every attachment out of the cloud copies, unless remote copies are kept
 #[tauri::command]
 async fn wipe_uploaded_files() -> Result<(), String> {
   let storage = file_storage::FileStorage::new()
     .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
   storage.wipe_all()
-    .map_err(|e| format!("Failed to wipe uploaded files: {}", e))
+    .map_err(|e| format!("Failed to wipe uploaded files: {}", e))?;
+  cloud_uploader::ledger::delete_remote_copies(None);
+  Ok(())
 }
 
 // Conversation-linked uploads management
@@ -199,6 +481,10 @@ async fn wipe_uploaded_files() -> Result<(), String> {
 async fn delete_files_by_conversation(conversation_id: String) -> Result<usize, String> {
   let storage = file_storage::FileStorage::new()
     .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+  // Called when the conversation itself is deleted, so it leaves recall too
+  if let Err(e) = conversation_memory::unindex(&storage, &conversation_id) {
+    println!("[Memory] Failed to unindex conversation {}: {}", conversation_id, e);
+  }
   storage.delete_files_by_conversation(&conversation_id)
     .map_err(|e| format!("Failed to delete files for conversation: {}", e))
 }
@@ -303,20 +589,134 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             get_app_version,
+            capabilities::get_capabilities,
+            flags::list_feature_flags,
+            flags::set_feature_flag,
+            settings::get_extraction_settings,
+            settings::set_extraction_settings,
+            settings::get_embedding_settings,
+            settings::set_embedding_settings,
+            settings::get_chunking_config,
+            settings::set_chunking_config,
+            settings::get_rerank_settings,
+            settings::set_rerank_settings,
+            settings::get_retrieval_settings,
+            settings::set_retrieval_settings,
+            settings::get_cloud_settings,
+            settings::set_cloud_settings,
+            embeddings::embed_uploaded_file,
+            embeddings::clear_embedding_cache,
+            embeddings::set_embedding_api_key,
+            rerank::set_rerank_api_key,
+            conversation_memory::recall_related_conversations,
+            conversation_memory::index_conversation_history,
+            model_manager::list_embedding_models,
+            model_manager::download_embedding_model,
+            model_manager::cancel_model_download,
+            model_manager::verify_embedding_model,
+            model_manager::delete_embedding_model,
+            self_test::run_self_test,
             set_window_height,
+            window::list_monitors,
+            window::move_to_monitor,
+            window::set_monitor_placement,
+            window::set_window_opacity,
+            window::dock_window,
+            window::set_content_protection,
+            window::set_dock_visibility,
+            window::set_visible_on_all_workspaces,
+            window::set_auto_hide,
+            window::set_response_streaming,
+            window::open_hud_at_cursor,
+            window::close_hud,
+            notifications::open_notification_link,
+            #[cfg(desktop)]
+            hotkey::get_global_hotkey,
+            #[cfg(desktop)]
+            hotkey::set_global_hotkey,
             write_conversation_to_file,
             trigger_aws_upload,
+            cloud_uploader::credentials::set_cloud_credentials,
+            cloud_uploader::control::pause_cloud_sync,
+            cloud_uploader::control::resume_cloud_sync,
+            cloud_uploader::control::stop_cloud_sync,
+            cloud_uploader::control::get_cloud_sync_status,
+            cloud_uploader::restore::restore_from_cloud,
+            cloud_uploader::manifest::verify_cloud_backup,
+            cloud_uploader::preview::preview_cloud_sync,
+            cloud_uploader::history::get_sync_history,
+            cloud_uploader::target::configure_cloud_target,
+            pii_scrubber::scrub_text,
+            pii_scrubber::preview_scrub,
+            pii_scrubber::test_pii_rules,
+            pii_scrubber::scrub_clipboard,
+            pii_audit::get_pii_audit_log,
+            pii_audit::export_pii_audit_log,
+            pii_audit::get_pii_stats,
+            pii_scrubber::get_pii_config,
+            pii_scrubber::set_pii_config,
+            pii_scrubber::set_pii_profile,
+            pii_scrubber::add_pii_term,
+            pii_scrubber::remove_pii_term,
+            pii_scrubber::list_pii_patterns,
+            pii_scrubber::add_pii_pattern,
+            pii_scrubber::remove_pii_pattern,
             google_oauth::connect_google_suite,
             google_oauth::disconnect_google_suite,
             google_oauth::is_google_connected,
+            google_oauth::refresh_google_token,
+            google_oauth::request_additional_scopes,
+            google_oauth::get_google_granted_scopes,
+            google_apis::gmail::search_gmail,
+            google_apis::gmail::get_gmail_message,
+            google_apis::gmail::create_gmail_draft,
+            google_apis::gmail::send_gmail_message,
+            google_apis::calendar::list_calendar_events,
+            google_apis::calendar::create_calendar_event,
+            google_apis::drive::list_drive_files,
+            google_apis::drive::import_drive_file,
+            google_apis::drive::import_google_doc_as_text,
+            google_apis::contacts::search_contacts,
+            google_apis::get_google_api_usage,
+            microsoft_oauth::connect_microsoft_365,
+            microsoft_oauth::disconnect_microsoft_365,
+            microsoft_oauth::is_microsoft_connected,
+            microsoft_apis::outlook::search_outlook_mail,
+            microsoft_apis::outlook::list_outlook_events,
+            microsoft_apis::onedrive::list_onedrive_files,
+            microsoft_apis::onedrive::import_onedrive_file,
+            dropbox_oauth::connect_dropbox,
+            dropbox_oauth::disconnect_dropbox,
+            dropbox_oauth::is_dropbox_connected,
+            dropbox::list_dropbox_files,
+            dropbox::import_dropbox_file,
+            slack_oauth::connect_slack,
+            slack_oauth::disconnect_slack,
+            slack_oauth::is_slack_connected,
+            slack::get_slack_channel_messages,
+            slack::get_slack_thread,
+            connections::get_connection_status,
             upload_file,
             upload_file_from_path,
+            transcription::transcribe_uploaded_file,
             list_uploaded_files,
+            list_files_by_tag,
+            list_archive_children,
             delete_uploaded_file,
             toggle_file_context,
             get_file_context,
             get_optimized_file_context,
+            get_context_chunks,
+            retrieve_context,
+            get_context_for_query,
+            preview_context,
+            retrieval_eval::run_retrieval_eval,
+            sync_vector_index,
+            detect_language,
             extract_file_content,
+            extract_pdf_pages,
+            reextract_file,
+            reextract_all,
             wipe_uploaded_files,
             delete_files_by_conversation,
             count_files_by_conversation,
@@ -333,12 +733,20 @@ pub fn run() {
             // Setup main window positioning
             window::setup_main_window(app).expect("Failed to setup main window");
 
-            // Start AWS background uploader (non-blocking)
-            if let Err(e) = aws_uploader::AwsUploader::start_background_uploader() {
-                eprintln!("Failed to start AWS uploader: {}", e);
-            } else {
-                println!("AWS background uploader started successfully");
-            }
+            // Show/hide the main window from anywhere
+            #[cfg(desktop)]
+            hotkey::init(app)?;
+
+            // Start cloud background uploader (non-blocking); the cloud sync commands control it
+            let reporter = emitting_reporter(app.handle().clone());
+            app.manage(cloud_uploader::control::CloudSync::start(reporter));
+
+            // Renew Google tokens before they expire; emits "google-oauth:reauth-required" when they can't be
+            google_oauth::start_refresh_task(app.handle().clone());
+            microsoft_oauth::start_refresh_task(app.handle().clone());
+            dropbox_oauth::start_refresh_task(app.handle().clone());
+            // Emits "connection:degraded" when a connection needs a new sign-in
+            connections::start_monitor(app.handle().clone());
 
             // Absolute path to sidecar script based on src-tauri dir
             let script_path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -396,13 +804,15 @@ pub fn run() {
               .current_dir(&sidecar_cwd)
               .arg(&script_path)
               .env("AGENT_PORT", "8765")
+              .env("AGI_FEATURE_MCP", if flags::is_enabled("mcp") { "1" } else { "0" })
               .stdout(Stdio::piped())
               .stderr(Stdio::piped())
               .spawn()
               .map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
 
-            // Pipe stdout
+            // Pipe stdout; it closes when the sidecar exits
             if let Some(stdout) = child.stdout.take() {
+              let app_handle = app.handle().clone();
               thread::spawn(move || {
                 let reader = BufReader::new(stdout);
                 for line in reader.lines() {
@@ -410,6 +820,7 @@ pub fn run() {
                     println!("[sidecar][stdout] {}", l);
                   }
                 }
+                notify_if_sidecar_crashed(&app_handle);
               });
             }
             // Pipe stderr
@@ -437,12 +848,25 @@ pub fn run() {
             Ok(())
         })
         .on_window_event(|w, e| {
+          let is_main = window::main_window(w.app_handle()).is_some_and(|m| m.label() == w.label());
+          match e {
+            tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) if is_main => {
+              window::remember_geometry(w, matches!(e, tauri::WindowEvent::Moved(_)));
+            }
+            tauri::WindowEvent::Focused(focused) if is_main => window::on_focus_changed(w, *focused),
+            // The HUD is for one question; clicking away dismisses it
+            tauri::WindowEvent::Focused(false) if w.label() == window::HUD_LABEL => {
+              let _ = w.hide();
+            }
+            _ => {}
+          }
           if let tauri::WindowEvent::CloseRequested { api, .. } = e {
             // Only prevent close and exit for the main window
-            // Allow auth, settings, and other windows to close normally
+            // Allow auth, settings, the HUD and other windows to close normally
             let label = w.label();
-            if label != "auth" && label != "settings" {
+            if label != "auth" && label != "settings" && label != window::HUD_LABEL {
               api.prevent_close();
+              window::save_geometry();
               // Attempt to kill sidecar gently
               let app_handle = w.app_handle();
               if let Some(mutex) = app_handle.try_state::<Mutex<Option<Child>>>() {
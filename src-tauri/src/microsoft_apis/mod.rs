@@ -0,0 +1,41 @@
+use anyhow::{anyhow, Result};
+use reqwest::blocking::{Client, Response};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::microsoft_oauth;
+
+pub mod onedrive;
+pub mod outlook;
+
+const GRAPH: &str = "https://graph.microsoft.com/v1.0";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+// GET a Graph path as the connected account; errors carry Graph's reply
+fn get(app: &AppHandle, path: &str, query: &[(&str, String)], headers: &[(&str, &str)], timeout: Duration) -> Result<Response> {
+    let token = microsoft_oauth::access_token(app)?;
+    let client = Client::builder().timeout(timeout).build()?;
+    let url = format!("{}{}", GRAPH, path);
+    let mut request = client.get(&url).bearer_auth(token).query(query);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let resp = request.send()?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(anyhow!("{} returned {}: {}", url, status, resp.text().unwrap_or_default()));
+    }
+    Ok(resp)
+}
+
+/// GET a Graph path (e.g. "/me/messages") as JSON
+pub(crate) fn get_json<T: DeserializeOwned>(app: &AppHandle, path: &str, query: &[(&str, String)], headers: &[(&str, &str)]) -> Result<T> {
+    Ok(get(app, path, query, headers, REQUEST_TIMEOUT)?.json()?)
+}
+
+/// Download the body of a Graph GET, e.g. a file's content
+pub(crate) fn get_bytes(app: &AppHandle, path: &str) -> Result<Vec<u8>> {
+    Ok(get(app, path, &[], &[], DOWNLOAD_TIMEOUT)?.bytes()?.to_vec())
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use super::{get_bytes, get_json};
+use crate::file_storage::{FileInfo, FileStorage};
+
+const ITEM_FIELDS: &str = "id,name,size,folder,file,lastModifiedDateTime,webUrl,parentReference";
+const MAX_LIST: usize = 100;
+// Larger files are better opened in OneDrive than pulled into context
+const MAX_IMPORT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// A file or folder in the connected OneDrive
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct OneDriveItem {
+    pub id: String,
+    pub name: String,
+    pub mime_type: Option<String>,     // None for folders
+    pub size: u64,
+    pub modified_time: String,
+    pub parent_id: Option<String>,
+    pub web_url: String,
+    pub is_folder: bool,
+}
+
+fn parse_item(v: &Value) -> OneDriveItem {
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    OneDriveItem {
+        id: text(&v["id"]),
+        name: text(&v["name"]),
+        mime_type: v["file"]["mimeType"].as_str().map(|m| m.to_string()),
+        size: v["size"].as_u64().unwrap_or_default(),
+        modified_time: text(&v["lastModifiedDateTime"]),
+        parent_id: v["parentReference"]["id"].as_str().map(|p| p.to_string()),
+        web_url: text(&v["webUrl"]),
+        is_folder: v["folder"].is_object(),
+    }
+}
+
+// The Graph path listing a folder's children or search results; without either, the top
+// of the drive
+fn list_path(folder: Option<&str>, query: Option<&str>) -> String {
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+    let folder = folder.map(str::trim).filter(|f| !f.is_empty());
+    let item = match folder {
+        Some(folder) => format!("/me/drive/items/{}", urlencoding::encode(folder)),
+        None => "/me/drive/root".to_string(),
+    };
+    match query {
+        // OData strings double their single quotes
+        Some(query) => format!("{}/search(q='{}')", item, urlencoding::encode(&query.replace('\'', "''"))),
+        None => format!("{}/children", item),
+    }
+}
+
+/// Items in a folder and/or matching a search
+pub fn list_items(app: &AppHandle, folder: Option<&str>, query: Option<&str>) -> Result<Vec<OneDriveItem>> {
+    let listed: Value = get_json(
+        app,
+        &list_path(folder, query),
+        &[("$select", ITEM_FIELDS.to_string()), ("$top", MAX_LIST.to_string())],
+        &[],
+    )?;
+    let items: Vec<OneDriveItem> = listed["value"].as_array().map(|vs| vs.iter().map(parse_item).collect()).unwrap_or_default();
+    println!("[OneDrive] Listed {} item(s)", items.len());
+    Ok(items)
+}
+
+/// Download a OneDrive file and store it with the uploads
+pub fn import_item(app: &AppHandle, item_id: &str) -> Result<FileInfo> {
+    let path = format!("/me/drive/items/{}", urlencoding::encode(item_id));
+    let meta: Value = get_json(app, &path, &[("$select", ITEM_FIELDS.to_string())], &[])?;
+    let item = parse_item(&meta);
+    if item.is_folder {
+        return Err(anyhow!("{} is a folder", item.name));
+    }
+    if item.size > MAX_IMPORT_BYTES {
+        return Err(anyhow!("{} is larger than {} MB", item.name, MAX_IMPORT_BYTES / 1024 / 1024));
+    }
+    let bytes = get_bytes(app, &format!("{}/content", path))?;
+    println!("[OneDrive] Downloaded {} ({} bytes)", item.name, bytes.len());
+    let stored = FileStorage::new()?.import_bytes(&bytes, &item.name, &format!("onedrive:{}", item.id))?;
+    println!("[OneDrive] Imported {} as {}", item.name, stored.id);
+    Ok(stored)
+}
+
+/// Browse the connected OneDrive: a folder's contents, a search, or both
+#[tauri::command]
+pub async fn list_onedrive_files(app: AppHandle, folder: Option<String>, query: Option<String>) -> Result<Vec<OneDriveItem>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_items(&app, folder.as_deref(), query.as_deref()).map_err(|e| format!("Failed to list OneDrive files: {}", e))
+    })
+    .await
+    .map_err(|e| format!("OneDrive task failed: {}", e))?
+}
+
+/// Import a OneDrive file into the uploads store
+#[tauri::command]
+pub async fn import_onedrive_file(app: AppHandle, item_id: String) -> Result<FileInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        import_item(&app, &item_id).map_err(|e| format!("Failed to import OneDrive file: {}", e))
+    })
+    .await
+    .map_err(|e| format!("OneDrive task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_path_and_items() {
+        assert_eq!(list_path(None, None), "/me/drive/root/children");
+        assert_eq!(list_path(Some("F1!2"), None), "/me/drive/items/F1%212/children");
+        assert_eq!(list_path(None, Some("Anna's budget")), "/me/drive/root/search(q='Anna%27%27s%20budget')");
+
+        let folder = parse_item(&serde_json::json!({ "id": "f", "name": "Docs", "folder": { "childCount": 2 } }));
+        assert!(folder.is_folder && folder.mime_type.is_none());
+        let file = parse_item(&serde_json::json!({
+            "id": "i", "name": "plan.pdf", "size": 2048, "file": { "mimeType": "application/pdf" }, "parentReference": { "id": "f" }
+        }));
+        assert_eq!((file.mime_type.as_deref(), file.size, file.parent_id.as_deref()), (Some("application/pdf"), 2048, Some("f")));
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::AppHandle;
+
+use super::get_json;
+use crate::pii_scrubber::ScrubPass;
+
+const DEFAULT_MAX_RESULTS: usize = 10;
+const MAX_RESULTS_LIMIT: usize = 25;
+// Characters of body returned per message
+const BODY_LIMIT: usize = 20_000;
+const DEFAULT_DAYS: i64 = 7;
+const MAX_EVENTS: usize = 250;
+
+/// One Outlook message, scrubbed with the PII config
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct OutlookMessage {
+    pub id: String,
+    pub conversation_id: String,
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub received: String,              // RFC 3339
+    pub preview: String,
+    pub body: String,                  // As text; Graph converts HTML bodies
+    pub web_link: String,
+    pub truncated: bool,               // Body cut at BODY_LIMIT characters
+    pub redacted: usize,               // PII replacements across the fields
+}
+
+/// One Outlook calendar event, scrubbed with the PII config
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct OutlookEvent {
+    pub id: String,
+    pub subject: String,
+    pub start: String,                 // UTC, e.g. "2026-10-22T13:00:00.0000000"
+    pub end: String,
+    pub all_day: bool,
+    pub location: String,
+    pub organizer: String,
+    pub attendees: Vec<String>,
+    pub preview: String,
+    pub web_link: String,
+}
+
+fn text(v: &Value) -> String {
+    v.as_str().unwrap_or_default().to_string()
+}
+
+// "Name <address>" of a Graph recipient
+fn recipient(r: &Value) -> String {
+    let name = text(&r["emailAddress"]["name"]);
+    let address = text(&r["emailAddress"]["address"]);
+    match (name.is_empty(), address.is_empty()) {
+        (false, false) if name != address => format!("{} <{}>", name, address),
+        (true, _) => address,
+        _ => name,
+    }
+}
+
+fn parse_message(v: &Value) -> OutlookMessage {
+    let body = text(&v["body"]["content"]);
+    let truncated = body.chars().count() > BODY_LIMIT;
+    OutlookMessage {
+        id: text(&v["id"]),
+        conversation_id: text(&v["conversationId"]),
+        from: recipient(&v["from"]),
+        to: v["toRecipients"].as_array().map(|rs| rs.iter().map(recipient).collect::<Vec<_>>().join(", ")).unwrap_or_default(),
+        subject: text(&v["subject"]),
+        received: text(&v["receivedDateTime"]),
+        preview: text(&v["bodyPreview"]),
+        body: if truncated { body.chars().take(BODY_LIMIT).collect() } else { body.trim().to_string() },
+        web_link: text(&v["webLink"]),
+        truncated,
+        redacted: 0,
+    }
+}
+
+fn parse_event(v: &Value) -> OutlookEvent {
+    OutlookEvent {
+        id: text(&v["id"]),
+        subject: text(&v["subject"]),
+        start: text(&v["start"]["dateTime"]),
+        end: text(&v["end"]["dateTime"]),
+        all_day: v["isAllDay"].as_bool().unwrap_or(false),
+        location: text(&v["location"]["displayName"]),
+        organizer: recipient(&v["organizer"]),
+        attendees: v["attendees"].as_array().map(|a| a.iter().map(recipient).collect()).unwrap_or_default(),
+        preview: text(&v["bodyPreview"]),
+        web_link: text(&v["webLink"]),
+    }
+}
+
+/// Messages matching a search (Outlook's search syntax), most relevant first
+pub fn search_mail(app: &AppHandle, query: &str, max_results: usize) -> Result<Vec<OutlookMessage>> {
+    let listed: Value = get_json(
+        app,
+        "/me/messages",
+        &[
+            // $search takes a quoted string
+            ("$search", format!("\"{}\"", query.replace('"', ""))),
+            ("$top", max_results.to_string()),
+            ("$select", "id,conversationId,from,toRecipients,subject,receivedDateTime,bodyPreview,body,webLink".to_string()),
+        ],
+        &[("Prefer", "outlook.body-content-type=\"text\"")],
+    )?;
+    let mut pass = ScrubPass::new();
+    let messages: Vec<OutlookMessage> = listed["value"]
+        .as_array()
+        .map(|ms| {
+            ms.iter()
+                .map(|m| {
+                    let message = parse_message(m);
+                    let before = pass.redacted();
+                    let mut message = OutlookMessage {
+                        from: pass.text(&message.from),
+                        to: pass.text(&message.to),
+                        subject: pass.text(&message.subject),
+                        preview: pass.text(&message.preview),
+                        body: pass.text(&message.body),
+                        ..message
+                    };
+                    message.redacted = pass.redacted() - before;
+                    message
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    pass.finish(app, "search_outlook_mail");
+    println!("[Outlook] {:?} matched {} message(s)", query, messages.len());
+    Ok(messages)
+}
+
+/// Events between `start` and `end` (RFC 3339; default the next seven days), recurring ones
+/// expanded, times in UTC
+pub fn list_events(app: &AppHandle, start: Option<&str>, end: Option<&str>) -> Result<Vec<OutlookEvent>> {
+    let start = start.map(str::to_string).unwrap_or_else(|| Utc::now().to_rfc3339());
+    let end = end.map(str::to_string).unwrap_or_else(|| (Utc::now() + Duration::days(DEFAULT_DAYS)).to_rfc3339());
+    let listed: Value = get_json(
+        app,
+        "/me/calendarView",
+        &[
+            ("startDateTime", start),
+            ("endDateTime", end),
+            ("$orderby", "start/dateTime".to_string()),
+            ("$top", MAX_EVENTS.to_string()),
+            ("$select", "id,subject,start,end,isAllDay,location,organizer,attendees,bodyPreview,webLink".to_string()),
+        ],
+        &[("Prefer", "outlook.timezone=\"UTC\"")],
+    )?;
+    let mut pass = ScrubPass::new();
+    let events: Vec<OutlookEvent> = listed["value"]
+        .as_array()
+        .map(|es| {
+            es.iter()
+                .map(|e| {
+                    let event = parse_event(e);
+                    OutlookEvent {
+                        subject: pass.text(&event.subject),
+                        location: pass.text(&event.location),
+                        organizer: pass.text(&event.organizer),
+                        attendees: event.attendees.iter().map(|a| pass.text(a)).collect(),
+                        preview: pass.text(&event.preview),
+                        ..event
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    pass.finish(app, "list_outlook_events");
+    println!("[Outlook] {} event(s)", events.len());
+    Ok(events)
+}
+
+/// Search the connected Outlook mailbox and return the matching messages with scrubbed bodies
+#[tauri::command]
+pub async fn search_outlook_mail(app: AppHandle, query: String, max_results: Option<usize>) -> Result<Vec<OutlookMessage>, String> {
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS).clamp(1, MAX_RESULTS_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        search_mail(&app, &query, max_results).map_err(|e| format!("Failed to search Outlook mail: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Outlook task failed: {}", e))?
+}
+
+/// Outlook calendar events in a time range; defaults to the next seven days
+#[tauri::command]
+pub async fn list_outlook_events(app: AppHandle, start: Option<String>, end: Option<String>) -> Result<Vec<OutlookEvent>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        list_events(&app, start.as_deref(), end.as_deref()).map_err(|e| format!("Failed to list Outlook events: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Outlook task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message_and_event() {
+        let message = parse_message(&serde_json::json!({
+            "id": "m1",
+            "conversationId": "c1",
+            "subject": "Contract",
+            "from": { "emailAddress": { "name": "Anna", "address": "anna@example.com" } },
+            "toRecipients": [
+                { "emailAddress": { "name": "me@example.com", "address": "me@example.com" } },
+                { "emailAddress": { "address": "legal@example.com" } }
+            ],
+            "body": { "contentType": "text", "content": "  The contract is fine.\r\n" }
+        }));
+        assert_eq!(message.from, "Anna <anna@example.com>");
+        assert_eq!(message.to, "me@example.com, legal@example.com");
+        assert_eq!(message.body, "The contract is fine.");
+        assert!(!message.truncated);
+
+        let event = parse_event(&serde_json::json!({
+            "id": "e1",
+            "subject": "Offsite",
+            "isAllDay": true,
+            "start": { "dateTime": "2026-10-22T00:00:00.0000000", "timeZone": "UTC" },
+            "end": { "dateTime": "2026-10-24T00:00:00.0000000", "timeZone": "UTC" },
+            "location": { "displayName": "Lisbon" },
+            "attendees": [{ "emailAddress": { "name": "Sam", "address": "sam@example.com" } }]
+        }));
+        assert!(event.all_day);
+        assert_eq!(event.location, "Lisbon");
+        assert_eq!(event.attendees, vec!["Sam <sam@example.com>"]);
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use std::net::TcpListener;
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::connections::{rfc3339, ConnectionStatus};
+use crate::google_oauth::{
+    extract_email_from_id_token, generate_pkce_pair, load_dotenv, load_env, now_ms, open_in_browser, redirect_path,
+    wait_for_redirect,
+};
+use crate::oauth_tokens::{token_request, TokenStore, Tokens};
+
+// Delegated Graph permissions: profile, mail, calendar and OneDrive, all read-only.
+// offline_access brings a refresh token.
+const SCOPES: &str = "openid profile email offline_access User.Read Mail.Read Calendars.Read Files.Read";
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const LOST_GRANT: &str = "Microsoft authorization expired; connect Microsoft 365 again";
+
+// The tokens as JSON in the keychain. Its lock matters here: Azure AD replaces the refresh
+// token on every use.
+static STORE: TokenStore = TokenStore::new("agi-microsoft-oauth", "MSAuth", "Azure AD");
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct MicrosoftTokens {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
+    id_token: Option<String>,
+    obtained_at_ms: u128,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    reauth_required: bool,             // Azure AD refused the refresh token; only connecting again helps
+}
+
+#[derive(Deserialize)]
+struct TokenResp {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
+    id_token: Option<String>,
+}
+
+/// Payload of "microsoft-oauth:reauth-required"
+#[derive(Serialize, Clone, Debug)]
+pub struct ReauthRequired {
+    pub reason: String,
+}
+
+// "common" takes work, school and personal accounts; a tenant id or domain limits sign-in
+// to one organization
+fn tenant() -> String {
+    std::env::var("MICROSOFT_TENANT").ok().filter(|t| !t.trim().is_empty()).unwrap_or_else(|| "common".to_string())
+}
+
+fn endpoint(path: &str) -> String {
+    format!("https://login.microsoftonline.com/{}/oauth2/v2.0/{}", tenant(), path)
+}
+
+fn authorize_url(client_id: &str, redirect_uri: &str, state: &str, code_challenge: &str) -> String {
+    format!(
+        "{}?client_id={}&response_type=code&redirect_uri={}&response_mode=query&scope={}&state={}&code_challenge={}&code_challenge_method=S256&prompt=select_account",
+        endpoint("authorize"),
+        urlencoding::encode(client_id),
+        urlencoding::encode(redirect_uri),
+        urlencoding::encode(SCOPES),
+        state,
+        code_challenge
+    )
+}
+
+fn from_response(resp: TokenResp, previous: Option<&MicrosoftTokens>, now_ms: u128) -> MicrosoftTokens {
+    MicrosoftTokens {
+        access_token: resp.access_token,
+        refresh_token: resp.refresh_token.or_else(|| previous.and_then(|p| p.refresh_token.clone())),
+        expires_in: resp.expires_in,
+        scope: resp.scope.or_else(|| previous.and_then(|p| p.scope.clone())),
+        id_token: resp.id_token.or_else(|| previous.and_then(|p| p.id_token.clone())),
+        obtained_at_ms: now_ms,
+        reauth_required: false,
+    }
+}
+
+impl Tokens for MicrosoftTokens {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    fn expires_at_ms(&self) -> u128 {
+        self.obtained_at_ms + self.expires_in.unwrap_or(3600) as u128 * 1000
+    }
+
+    fn reauth_required(&self) -> bool {
+        self.reauth_required
+    }
+
+    fn flag_reauth(&mut self) {
+        self.reauth_required = true;
+    }
+}
+
+// Trade the refresh token for new tokens, or Azure AD's error body
+fn refresh(tokens: &MicrosoftTokens, refresh_token: &str) -> Result<std::result::Result<MicrosoftTokens, String>> {
+    load_dotenv("MSRefresh");
+    let client_id = load_env("MICROSOFT_CLIENT_ID")?;
+    let form = [
+        ("client_id", client_id.as_str()),
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("scope", SCOPES),
+    ];
+    Ok(token_request(&endpoint("token"), &form)?.map(|resp| from_response(resp, Some(tokens), now_ms())))
+}
+
+fn announce_lost(app: &tauri::AppHandle) {
+    let _ = app.emit("microsoft-oauth:reauth-required", ReauthRequired { reason: LOST_GRANT.to_string() });
+}
+
+/// A current Graph access token, refreshing it first if needed. A lost grant emits
+/// "microsoft-oauth:reauth-required", once between here and the background task.
+pub(crate) fn access_token(app: &tauri::AppHandle) -> Result<String> {
+    let tokens = STORE.refresh_if_needed(refresh)?.ok_or_else(|| anyhow!("Microsoft 365 is not connected"))?;
+    STORE.announce_lost(tokens.reauth_required, || announce_lost(app));
+    if tokens.reauth_required {
+        return Err(anyhow!(LOST_GRANT));
+    }
+    Ok(tokens.access_token)
+}
+
+/// Keep the Microsoft tokens fresh in the background
+pub fn start_refresh_task(app: tauri::AppHandle) {
+    STORE.start_refresh_task(refresh, move || announce_lost(&app));
+}
+
+fn connect() -> Result<()> {
+    load_dotenv("MSConnect");
+    let client_id = load_env("MICROSOFT_CLIENT_ID")?;
+
+    // Azure AD matches http://localhost redirects on any port (RFC 8252 section 7.3)
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let redirect_uri = format!("http://localhost:{}/", listener.local_addr()?.port());
+    let (code_verifier, code_challenge) = generate_pkce_pair();
+    let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+
+    println!("[MSAuth][Connect] Opening browser for sign-in (redirect {})", redirect_uri);
+    open_in_browser(&authorize_url(&client_id, &redirect_uri, &state, &code_challenge))?;
+    let code = wait_for_redirect(&listener, redirect_path(&redirect_uri), &state, AUTH_TIMEOUT)?;
+    drop(listener);
+
+    let form = [
+        ("client_id", client_id.as_str()),
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("code_verifier", code_verifier.as_str()),
+        ("scope", SCOPES),
+    ];
+    let resp = token_request(&endpoint("token"), &form)?.map_err(|body| anyhow!("Token exchange failed: {}", body))?;
+    let tokens = from_response(resp, None, now_ms());
+    println!(
+        "[MSAuth][Connect] Tokens received (access: {} chars, has_refresh: {})",
+        tokens.access_token.len(),
+        tokens.refresh_token.is_some()
+    );
+    STORE.store(&tokens)
+}
+
+/// Sign in to Microsoft 365 in the browser (PKCE, loopback redirect) and keep the tokens in
+/// the keychain. Needs MICROSOFT_CLIENT_ID, a public client app registration; MICROSOFT_TENANT
+/// is optional.
+#[tauri::command]
+pub async fn connect_microsoft_365() -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(|| connect().map_err(|e| format!("Failed to connect Microsoft 365: {}", e)))
+        .await
+        .map_err(|e| format!("Connect task failed: {}", e))??;
+    Ok("Microsoft 365 connected successfully".to_string())
+}
+
+/// Forget the Microsoft tokens. Azure AD has no revocation endpoint for public clients; the
+/// refresh token stops working once it is unused for its lifetime.
+#[tauri::command]
+pub fn disconnect_microsoft_365() -> Result<String, String> {
+    STORE.delete().map_err(|e| format!("Failed to remove Microsoft tokens: {}", e))?;
+    println!("[MSAuth][Disconnect] Removed stored tokens");
+    Ok("Disconnected from Microsoft 365".to_string())
+}
+
+/// The stored Microsoft connection, for get_connection_status
+pub(crate) fn connection_status() -> Result<ConnectionStatus> {
+    let Some(tokens) = STORE.load::<MicrosoftTokens>()? else { return Ok(ConnectionStatus::disconnected("microsoft")) };
+    Ok(ConnectionStatus {
+        provider: "microsoft".to_string(),
+        connected: !tokens.reauth_required,
+        account_email: tokens.id_token.as_deref().and_then(extract_email_from_id_token),
+        scopes: tokens.scope.as_deref().unwrap_or_default().split_whitespace().map(|s| s.to_string()).collect(),
+        expires_at: rfc3339(tokens.expires_at_ms()),
+        last_refresh: rfc3339(tokens.obtained_at_ms),
+        can_refresh: tokens.refresh_token.is_some(),
+        reauth_required: tokens.reauth_required,
+        expires_at_ms: Some(tokens.expires_at_ms()),
+    })
+}
+
+#[tauri::command]
+pub fn is_microsoft_connected() -> Result<bool, String> {
+    match STORE.load::<MicrosoftTokens>() {
+        Ok(tokens) => Ok(tokens.is_some_and(|t| !t.reauth_required)),
+        Err(e) => {
+            eprintln!("[MSAuth][Status] Failed to load tokens: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth_tokens::needs_refresh;
+
+    #[test]
+    fn test_authorize_url_and_token_rotation() {
+        let url = authorize_url("client-1", "http://localhost:5123/", "s1", "challenge");
+        assert!(url.starts_with("https://login.microsoftonline.com/"));
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A5123%2F"));
+        assert!(url.contains("scope=openid%20profile%20email%20offline_access%20User.Read"));
+        assert!(url.contains("&state=s1&code_challenge=challenge&code_challenge_method=S256"));
+
+        let first = from_response(
+            TokenResp { access_token: "a1".into(), refresh_token: Some("r1".into()), expires_in: Some(3600), scope: Some(SCOPES.into()), id_token: None },
+            None,
+            0,
+        );
+        assert!(!needs_refresh(&first, 3000 * 1000));
+        assert!(needs_refresh(&first, 3400 * 1000));
+        // Azure AD rotates the refresh token; the new one replaces the old
+        let rotated = from_response(
+            TokenResp { access_token: "a2".into(), refresh_token: Some("r2".into()), expires_in: Some(3600), scope: None, id_token: None },
+            Some(&first),
+            1,
+        );
+        assert_eq!((rotated.refresh_token.as_deref(), rotated.scope.as_deref()), (Some("r2"), Some(SCOPES)));
+    }
+}
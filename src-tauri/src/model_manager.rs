@@ -0,0 +1,457 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use reqwest::blocking::Client;
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::embeddings::MODEL_FILES;
+use crate::file_storage;
+use crate::settings::Settings;
+
+/// Models known by short name; anything else can be given as "org/repo" on the Hub
+const CATALOG: &[(&str, &str)] = &[
+    ("all-MiniLM-L6-v2", "sentence-transformers/all-MiniLM-L6-v2"),
+    ("all-MiniLM-L12-v2", "sentence-transformers/all-MiniLM-L12-v2"),
+    ("bge-small-en-v1.5", "BAAI/bge-small-en-v1.5"),
+    ("ms-marco-MiniLM-L-6-v2", "cross-encoder/ms-marco-MiniLM-L-6-v2"),
+    ("bert-base-NER", "dslim/bert-base-NER"),
+];
+
+const MANIFEST: &str = "manifest.json";
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+// Downloads in flight, with their cancel flags
+static DOWNLOADS: Mutex<Option<HashMap<String, Arc<AtomicBool>>>> = Mutex::new(None);
+
+/// Hugging Face endpoint; override with HF_ENDPOINT for a mirror
+fn hub_url() -> String {
+    std::env::var("HF_ENDPOINT").unwrap_or_else(|_| "https://huggingface.co".to_string())
+}
+
+pub fn models_dir() -> PathBuf {
+    file_storage::project_root().join("models")
+}
+
+/// Directory name under ./models and Hub repo for a model name
+fn resolve(name: &str) -> Result<(String, String)> {
+    if let Some((short, repo)) = CATALOG.iter().find(|(short, repo)| *short == name || *repo == name) {
+        return Ok((short.to_string(), repo.to_string()));
+    }
+    let safe = |part: &str| {
+        !part.is_empty()
+            && !part.starts_with('.')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    match name.split_once('/') {
+        Some((org, model)) if safe(org) && safe(model) => Ok((model.to_string(), name.to_string())),
+        None if safe(name) => Err(anyhow!("Unknown model '{}'; give it as org/repo", name)),
+        _ => Err(anyhow!("Invalid model name '{}'", name)),
+    }
+}
+
+/// A file as listed by the Hub
+#[derive(Debug, Deserialize)]
+struct HubEntry {
+    path: String,
+    #[serde(default)]
+    size: u64,
+    lfs: Option<HubLfs>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HubLfs {
+    oid: String,                       // SHA-256 of the content
+    size: u64,
+}
+
+/// What was downloaded, for later verification
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Manifest {
+    repo: String,
+    downloaded: String,                // RFC 3339
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ManifestFile {
+    path: String,
+    size: u64,
+    sha256: String,
+}
+
+/// A model in the catalog or in ./models
+#[derive(Debug, Serialize, Clone)]
+pub struct ModelInfo {
+    pub name: String,
+    pub repo: Option<String>,
+    pub installed: bool,               // All model files present
+    pub partial: bool,                 // An interrupted download can be resumed
+    pub downloading: bool,
+    pub size_bytes: u64,               // On disk, partial files included
+    pub in_use: bool,                  // Configured as the embedding, rerank or NER model
+}
+
+/// Emitted as "model-download:progress" about every megabyte
+#[derive(Debug, Serialize, Clone)]
+pub struct ModelDownloadProgress {
+    pub name: String,
+    pub file: String,
+    pub downloaded: u64,               // Bytes across all files, resumed ones included
+    pub total: u64,
+}
+
+fn client() -> Result<Client> {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .timeout(None::<Duration>)
+        .build()
+        .context("building http client")
+}
+
+fn authorized(request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+    match std::env::var("HF_TOKEN") {
+        Ok(token) => request.bearer_auth(token),
+        Err(_) => request,
+    }
+}
+
+/// Sizes and LFS checksums of the model files in the repo
+fn remote_files(client: &Client, repo: &str) -> Result<Vec<HubEntry>> {
+    let url = format!("{}/api/models/{}/tree/main", hub_url(), repo);
+    let resp = authorized(client.get(&url)).send()?;
+    if !resp.status().is_success() {
+        return Err(anyhow!("Model listing failed for {}: {}", repo, resp.status()));
+    }
+    let mut entries: Vec<HubEntry> = resp.json().context("parsing model listing")?;
+    MODEL_FILES
+        .iter()
+        .map(|wanted| {
+            let i = entries
+                .iter()
+                .position(|e| e.path == *wanted)
+                .ok_or_else(|| anyhow!("{} has no {}", repo, wanted))?;
+            let mut entry = entries.swap_remove(i);
+            if let Some(lfs) = &entry.lfs {
+                entry.size = lfs.size;
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let name = dest.file_name().and_then(|n| n.to_str()).unwrap_or("download");
+    dest.with_file_name(format!("{}.part", name))
+}
+
+/// Fetch one file into `<dest>.part`, continuing from whatever an earlier attempt left,
+/// then check its size and (for LFS files) SHA-256 before moving it into place.
+/// `on_bytes` gets the bytes written so far for this file.
+fn download_file(
+    client: &Client,
+    url: &str,
+    dest: &Path,
+    expected: &HubEntry,
+    cancel: &AtomicBool,
+    on_bytes: &mut impl FnMut(u64),
+) -> Result<String> {
+    let part = part_path(dest);
+    let mut have = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+    if have > expected.size {
+        fs::remove_file(&part)?;
+        have = 0;
+    }
+
+    if have < expected.size {
+        let mut request = authorized(client.get(url));
+        if have > 0 {
+            request = request.header(RANGE, format!("bytes={}-", have));
+        }
+        let mut resp = request.send()?;
+        let append = match resp.status() {
+            StatusCode::PARTIAL_CONTENT => true,
+            s if s.is_success() => false,              // Server ignored the range; start over
+            s => return Err(anyhow!("Download of {} failed: {}", expected.path, s)),
+        };
+        if !append {
+            have = 0;
+        }
+        let mut out = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&part)
+            .with_context(|| format!("opening {}", part.display()))?;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(anyhow!("Download cancelled"));
+            }
+            let n = resp.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            out.write_all(&buf[..n])?;
+            have += n as u64;
+            on_bytes(have);
+        }
+        out.flush()?;
+    }
+
+    if have != expected.size {
+        return Err(anyhow!("{} is {} bytes, expected {}", expected.path, have, expected.size));
+    }
+    let sha256 = file_sha256(&part)?;
+    if let Some(lfs) = &expected.lfs {
+        if sha256 != lfs.oid {
+            // A corrupt partial file would otherwise be resumed forever
+            fs::remove_file(&part)?;
+            return Err(anyhow!("Checksum mismatch for {}", expected.path));
+        }
+    }
+    fs::rename(&part, dest).with_context(|| format!("moving {} into place", dest.display()))?;
+    Ok(sha256)
+}
+
+/// Removes the download from the in-flight list however it ends
+struct DownloadGuard(String);
+
+impl Drop for DownloadGuard {
+    fn drop(&mut self) {
+        let mut downloads = DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(map) = downloads.as_mut() {
+            map.remove(&self.0);
+        }
+    }
+}
+
+/// Download a model's config, tokenizer and weights into ./models/<name>, resuming
+/// partial files and skipping ones already complete, and record their checksums
+pub fn download(name: &str, mut on_progress: impl FnMut(ModelDownloadProgress)) -> Result<ModelInfo> {
+    let (dir_name, repo) = resolve(name)?;
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let mut downloads = DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner());
+        let map = downloads.get_or_insert_with(HashMap::new);
+        if map.contains_key(&dir_name) {
+            return Err(anyhow!("{} is already downloading", dir_name));
+        }
+        map.insert(dir_name.clone(), cancel.clone());
+    }
+    let guard = DownloadGuard(dir_name.clone());
+
+    let dir = models_dir().join(&dir_name);
+    fs::create_dir_all(&dir)?;
+    let client = client()?;
+    let files = remote_files(&client, &repo)?;
+    let total: u64 = files.iter().map(|f| f.size).sum();
+    println!("[ModelManager] Downloading {} ({} bytes) into {:?}", repo, total, dir);
+
+    let mut done = 0u64;
+    let mut manifest = Manifest { repo: repo.clone(), downloaded: Utc::now().to_rfc3339(), files: Vec::new() };
+    for file in &files {
+        let dest = dir.join(&file.path);
+        let sha256 = match &file.lfs {
+            // Already complete from an earlier run
+            Some(lfs) if dest.exists() && file_sha256(&dest)? == lfs.oid => lfs.oid.clone(),
+            _ => {
+                let url = format!("{}/{}/resolve/main/{}", hub_url(), repo, file.path);
+                let mut last = 0;
+                download_file(&client, &url, &dest, file, &cancel, &mut |bytes| {
+                    if bytes - last >= PROGRESS_STEP || bytes == file.size {
+                        last = bytes;
+                        on_progress(ModelDownloadProgress {
+                            name: dir_name.clone(),
+                            file: file.path.clone(),
+                            downloaded: done + bytes,
+                            total,
+                        });
+                    }
+                })?
+            }
+        };
+        done += file.size;
+        manifest.files.push(ManifestFile { path: file.path.clone(), size: file.size, sha256 });
+    }
+    fs::write(dir.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)?;
+    on_progress(ModelDownloadProgress { name: dir_name.clone(), file: String::new(), downloaded: total, total });
+    println!("[ModelManager] {} ready", dir_name);
+    drop(guard);
+    info(&dir_name)
+}
+
+fn read_manifest(dir: &Path) -> Option<Manifest> {
+    serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST)).ok()?).ok()
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn info(dir_name: &str) -> Result<ModelInfo> {
+    let dir = models_dir().join(dir_name);
+    let settings = Settings::load().unwrap_or_default();
+    let downloading = DOWNLOADS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .is_some_and(|m| m.contains_key(dir_name));
+    let repo = read_manifest(&dir)
+        .map(|m| m.repo)
+        .or_else(|| CATALOG.iter().find(|(short, _)| *short == dir_name).map(|(_, repo)| repo.to_string()));
+    Ok(ModelInfo {
+        name: dir_name.to_string(),
+        repo,
+        installed: MODEL_FILES.iter().all(|f| dir.join(f).exists()),
+        partial: MODEL_FILES.iter().any(|f| part_path(&dir.join(f)).exists()),
+        downloading,
+        size_bytes: if dir.exists() { dir_size(&dir) } else { 0 },
+        in_use: settings.embeddings.local_model == dir_name
+            || settings.rerank.local_model == dir_name
+            || (settings.pii.ner.enabled && settings.pii.ner.local_model == dir_name),
+    })
+}
+
+/// Catalog models plus any other model directories under ./models
+pub fn list() -> Result<Vec<ModelInfo>> {
+    let mut names: Vec<String> = CATALOG.iter().map(|(short, _)| short.to_string()).collect();
+    if let Ok(entries) = fs::read_dir(models_dir()) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names.iter().map(|n| info(n)).collect()
+}
+
+/// Re-hash a downloaded model against its manifest; returns the files that are missing
+/// or no longer match
+pub fn verify(name: &str) -> Result<Vec<String>> {
+    let (dir_name, _) = resolve(name)?;
+    let dir = models_dir().join(&dir_name);
+    let manifest = read_manifest(&dir).ok_or_else(|| anyhow!("{} was not downloaded by the model manager", dir_name))?;
+    let mut bad = Vec::new();
+    for file in &manifest.files {
+        let path = dir.join(&file.path);
+        if !path.exists() || file_sha256(&path)? != file.sha256 {
+            bad.push(file.path.clone());
+        }
+    }
+    Ok(bad)
+}
+
+/// Delete a model directory, returning the bytes freed
+pub fn delete(name: &str) -> Result<u64> {
+    let dir_name = resolve(name).map(|(d, _)| d).or_else(|e| {
+        // Directories placed by hand don't need to be in the catalog
+        if models_dir().join(name).is_dir() && !name.contains(['/', '\\']) && !name.starts_with('.') {
+            Ok(name.to_string())
+        } else {
+            Err(e)
+        }
+    })?;
+    let model = info(&dir_name)?;
+    if model.downloading {
+        return Err(anyhow!("{} is downloading; cancel it first", dir_name));
+    }
+    if model.in_use {
+        return Err(anyhow!("{} is the configured model; switch models first", dir_name));
+    }
+    let dir = models_dir().join(&dir_name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(model.size_bytes)
+}
+
+/// Stop a running download; its partial files stay so it can resume later
+pub fn cancel(name: &str) -> Result<bool> {
+    let (dir_name, _) = resolve(name)?;
+    let downloads = DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(match downloads.as_ref().and_then(|m| m.get(&dir_name)) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    })
+}
+
+#[tauri::command]
+pub fn list_embedding_models() -> Result<Vec<ModelInfo>, String> {
+    list().map_err(|e| format!("Failed to list models: {}", e))
+}
+
+/// Download (or resume) a model from the Hugging Face Hub. Emits "model-download:progress".
+#[tauri::command]
+pub async fn download_embedding_model(app: tauri::AppHandle, name: String) -> Result<ModelInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        download(&name, |progress| {
+            let _ = app.emit("model-download:progress", progress);
+        })
+        .map_err(|e| format!("Failed to download model: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Model download task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn cancel_model_download(name: String) -> Result<bool, String> {
+    cancel(&name).map_err(|e| format!("Failed to cancel download: {}", e))
+}
+
+#[tauri::command]
+pub async fn verify_embedding_model(name: String) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || verify(&name))
+        .await
+        .map_err(|e| format!("Model verification task failed: {}", e))?
+        .map_err(|e| format!("Failed to verify model: {}", e))
+}
+
+#[tauri::command]
+pub fn delete_embedding_model(name: String) -> Result<u64, String> {
+    delete(&name).map_err(|e| format!("Failed to delete model: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_model_names() {
+        let (dir, repo) = resolve("all-MiniLM-L6-v2").unwrap();
+        assert_eq!((dir.as_str(), repo.as_str()), ("all-MiniLM-L6-v2", "sentence-transformers/all-MiniLM-L6-v2"));
+        assert_eq!(resolve("BAAI/bge-small-en-v1.5").unwrap().0, "bge-small-en-v1.5");
+        assert_eq!(resolve("intfloat/e5-small-v2").unwrap().0, "e5-small-v2");
+        assert!(resolve("some-model").is_err());
+        assert!(resolve("../etc").is_err());
+        assert!(resolve("org/..").is_err());
+        assert!(resolve("a/b/c").is_err());
+        assert_eq!(part_path(Path::new("m/model.safetensors")), PathBuf::from("m/model.safetensors.part"));
+    }
+}
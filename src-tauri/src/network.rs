@@ -0,0 +1,57 @@
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Asking the OS means spawning a process, so an answer is reused for a while
+const CHECK_EVERY: Duration = Duration::from_secs(30);
+static LAST: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+
+// Stdout of a command that exited successfully
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+fn query_metered() -> bool {
+    if cfg!(target_os = "windows") {
+        // Fixed and Variable are the data-capped cost types
+        let script = "[void][Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]; \
+                      $p = [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile(); \
+                      if ($p) { $p.GetConnectionCost().NetworkCostType }";
+        matches!(output("powershell", &["-NoProfile", "-Command", script]).as_deref(), Some("Fixed" | "Variable"))
+    } else if cfg!(target_os = "macos") {
+        // macOS has no command-line view of Low Data Mode; treat every connection as unmetered
+        false
+    } else {
+        // NetworkManager: 1 = yes, 3 = guessed yes (e.g. a phone hotspot)
+        let metered = output(
+            "busctl",
+            &[
+                "get-property",
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ],
+        );
+        matches!(metered.as_deref(), Some("u 1" | "u 3"))
+    }
+}
+
+/// Whether the current internet connection is metered; false when the OS can't tell
+pub fn is_metered() -> bool {
+    let mut last = LAST.lock().unwrap();
+    if let Some((at, metered)) = *last {
+        if at.elapsed() < CHECK_EVERY {
+            return metered;
+        }
+    }
+    let metered = query_metered();
+    *last = Some((Instant::now(), metered));
+    metered
+}
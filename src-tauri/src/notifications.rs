@@ -0,0 +1,150 @@
+use anyhow::Result;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::window;
+
+// Links into the app carried by notification buttons, e.g. "agi://files/<id>"
+const LINK_SCHEME: &str = "agi://";
+const APP_NAME: &str = "The AGI Assistant";
+
+/// A button on a notification; choosing it opens `link` in the app
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct NotificationAction {
+    pub id: String,
+    pub label: String,
+    pub link: String,
+}
+
+/// "notification": what was shown, so the UI can offer the same buttons where the OS
+/// notification has none (macOS and Windows)
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    pub actions: Vec<NotificationAction>,
+}
+
+/// "notification:open": a notification button was chosen
+#[derive(Serialize, Clone, Debug)]
+pub struct NotificationOpen {
+    pub link: String,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self { title: title.into(), body: body.into(), actions: Vec::new() }
+    }
+
+    /// Add a button opening `path` in the app, e.g. "files/123"
+    pub fn action(mut self, label: &str, path: &str) -> Self {
+        let id = format!("action-{}", self.actions.len());
+        self.actions.push(NotificationAction { id, label: label.to_string(), link: format!("{}{}", LINK_SCHEME, path) });
+        self
+    }
+
+    // The link behind the button the OS reported; a click on the notification itself
+    // ("default") opens the first one
+    fn link_for(&self, action_id: &str) -> Option<&str> {
+        let action = match action_id {
+            "default" => self.actions.first(),
+            id => self.actions.iter().find(|a| a.id == id),
+        };
+        action.map(|a| a.link.as_str())
+    }
+}
+
+/// Raise an OS notification for background work (an upload, a transcription, the sidecar)
+/// and tell the UI about it. Failing to show one is logged, never fatal.
+pub fn notify(app: &AppHandle, notification: Notification) {
+    println!("[Notify] {}: {}", notification.title, notification.body);
+    if let Err(e) = app.emit("notification", &notification) {
+        eprintln!("[Notify] Failed to emit notification event: {}", e);
+    }
+    if let Err(e) = show_native(app, notification) {
+        eprintln!("[Notify] Failed to show notification: {}", e);
+    }
+}
+
+// Linux notification servers take buttons and report which was chosen
+#[cfg(all(desktop, not(any(target_os = "macos", target_os = "windows"))))]
+fn show_native(app: &AppHandle, notification: Notification) -> Result<()> {
+    let mut native = notify_rust::Notification::new();
+    native.appname(APP_NAME).summary(&notification.title).body(&notification.body);
+    for action in &notification.actions {
+        native.action(&action.id, &action.label);
+    }
+    if !notification.actions.is_empty() {
+        native.action("default", "Open");
+    }
+    let handle = native.show()?;
+    if notification.actions.is_empty() {
+        return Ok(());
+    }
+    let app = app.clone();
+    // Blocks until the notification is answered or dismissed
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action_id| {
+            if let Some(link) = notification.link_for(action_id) {
+                open(&app, link);
+            }
+        });
+    });
+    Ok(())
+}
+
+// Banners only; the buttons are offered in the app from the "notification" event
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn show_native(_app: &AppHandle, notification: Notification) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname(APP_NAME)
+        .summary(&notification.title)
+        .body(&notification.body)
+        .show()
+        .map(|_| ())?;
+    Ok(())
+}
+
+#[cfg(mobile)]
+fn show_native(_app: &AppHandle, _notification: Notification) -> Result<()> {
+    Ok(())
+}
+
+// Bring the app forward and hand it the link
+fn open(app: &AppHandle, link: &str) {
+    match window::show_main_window(app) {
+        Ok(Some(main)) => {
+            let _ = main.emit_to(main.label(), "notification:open", NotificationOpen { link: link.to_string() });
+        }
+        Ok(None) => eprintln!("[Notify] No window to open {} in", link),
+        Err(e) => eprintln!("[Notify] Failed to show the window for {}: {}", link, e),
+    }
+}
+
+/// Follow a notification button the UI rendered itself: shows the main window and emits
+/// "notification:open" to it
+#[tauri::command]
+pub fn open_notification_link(app: AppHandle, link: String) -> Result<(), String> {
+    if !link.starts_with(LINK_SCHEME) {
+        return Err(format!("Not an app link: {}", link));
+    }
+    open(&app, &link);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_link_into_the_app() {
+        let notification = Notification::new("Transcription complete", "meeting.m4a is ready")
+            .action("Open transcript", "files/f1/transcript")
+            .action("Show files", "files");
+        assert_eq!(notification.actions[1].id, "action-1");
+        assert_eq!(notification.link_for("action-1"), Some("agi://files"));
+        assert_eq!(notification.link_for("default"), Some("agi://files/f1/transcript"));
+        assert_eq!(notification.link_for("__closed"), None);
+        assert_eq!(Notification::new("Sidecar stopped", "").link_for("default"), None);
+    }
+}
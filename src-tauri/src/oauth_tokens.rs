@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::google_oauth::{is_reauth_error, now_ms};
+use crate::keychain;
+
+// Renew this long before an access token runs out
+const REFRESH_MARGIN_MS: u128 = 5 * 60 * 1000;
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Both providers keep their tokens under this keychain account
+const KEYCHAIN_ACCOUNT: &str = "tokens";
+
+/// Tokens a provider keeps in the keychain and renews with a refresh token
+pub(crate) trait Tokens: Serialize + DeserializeOwned {
+    fn refresh_token(&self) -> Option<&str>;
+    /// When the access token runs out, in ms since the epoch
+    fn expires_at_ms(&self) -> u128;
+    fn reauth_required(&self) -> bool;
+    /// The provider refused the refresh token; only connecting again helps
+    fn flag_reauth(&mut self);
+}
+
+pub(crate) fn needs_refresh<T: Tokens>(tokens: &T, now_ms: u128) -> bool {
+    now_ms + REFRESH_MARGIN_MS >= tokens.expires_at_ms()
+}
+
+/// POST a form to a token endpoint; a refusal comes back as the provider's error body
+pub(crate) fn token_request<R: DeserializeOwned>(endpoint: &str, form: &[(&str, &str)]) -> Result<std::result::Result<R, String>> {
+    let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let resp = client.post(endpoint).form(form).send()?;
+    if !resp.status().is_success() {
+        return Ok(Err(resp.text().unwrap_or_default()));
+    }
+    Ok(Ok(resp.json()?))
+}
+
+/// One provider's tokens in the keychain, and the refresh lifecycle around them
+pub(crate) struct TokenStore {
+    service: &'static str,             // Keychain service, e.g. "agi-dropbox-oauth"
+    tag: &'static str,                 // Log prefix, e.g. "Dropbox"
+    issuer: &'static str,              // Named when it refuses a refresh token
+    lock: Mutex<()>,                   // Keeps the background task and on-demand callers from spending the refresh token twice
+    announced: AtomicBool,             // The lost grant has been reported since the last good refresh
+}
+
+impl TokenStore {
+    pub const fn new(service: &'static str, tag: &'static str, issuer: &'static str) -> Self {
+        TokenStore { service, tag, issuer, lock: Mutex::new(()), announced: AtomicBool::new(false) }
+    }
+
+    pub fn load<T: Tokens>(&self) -> Result<Option<T>> {
+        match keychain::get(self.service, KEYCHAIN_ACCOUNT)? {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn store<T: Tokens>(&self, tokens: &T) -> Result<()> {
+        keychain::set(self.service, KEYCHAIN_ACCOUNT, &serde_json::to_string(tokens)?)
+    }
+
+    pub fn delete(&self) -> Result<()> {
+        keychain::delete(self.service, KEYCHAIN_ACCOUNT)
+    }
+
+    /// Refresh the stored tokens if they are close to expiry. `refresh` trades the refresh
+    /// token for new tokens or returns the provider's error body; a refused grant flags the
+    /// tokens as needing a new sign-in.
+    pub fn refresh_if_needed<T, F>(&self, refresh: F) -> Result<Option<T>>
+    where
+        T: Tokens,
+        F: FnOnce(&T, &str) -> Result<std::result::Result<T, String>>,
+    {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(mut tokens) = self.load::<T>()? else { return Ok(None) };
+        if tokens.reauth_required() || !needs_refresh(&tokens, now_ms()) {
+            return Ok(Some(tokens));
+        }
+        println!("[{}][Refresh] Access token expires soon; refreshing...", self.tag);
+        let reason = match tokens.refresh_token() {
+            None => "No refresh token was issued".to_string(),
+            Some(refresh_token) => match refresh(&tokens, refresh_token)? {
+                Ok(fresh) => {
+                    self.store(&fresh)?;
+                    println!("[{}][Refresh] Tokens refreshed", self.tag);
+                    return Ok(Some(fresh));
+                }
+                Err(body) if is_reauth_error(&body) => format!("{} rejected the refresh token: {}", self.issuer, body),
+                Err(body) => return Err(anyhow!("Token refresh failed: {}", body)),
+            },
+        };
+        eprintln!("[{}][Refresh] Re-authentication required: {}", self.tag, reason);
+        tokens.flag_reauth();
+        self.store(&tokens)?;
+        Ok(Some(tokens))
+    }
+
+    /// Call `on_lost` the first time the grant is seen lost, and again only after it has
+    /// come back. Whichever caller notices first reports it.
+    pub fn announce_lost(&self, lost: bool, on_lost: impl FnOnce()) {
+        if !lost {
+            self.announced.store(false, Ordering::Relaxed);
+        } else if !self.announced.swap(true, Ordering::Relaxed) {
+            on_lost();
+        }
+    }
+
+    /// Keep the tokens fresh in the background, calling `on_lost` once when the grant is lost
+    pub fn start_refresh_task<T, F, L>(&'static self, refresh: F, on_lost: L)
+    where
+        T: Tokens,
+        F: Fn(&T, &str) -> Result<std::result::Result<T, String>> + Send + 'static,
+        L: Fn() + Send + 'static,
+    {
+        std::thread::spawn(move || loop {
+            match self.refresh_if_needed(&refresh) {
+                Ok(tokens) => self.announce_lost(tokens.is_some_and(|t| t.reauth_required()), &on_lost),
+                Err(e) => eprintln!("[{}][Refresh] Refresh failed, retrying in {:?}: {}", self.tag, REFRESH_CHECK_INTERVAL, e),
+            }
+            std::thread::sleep(REFRESH_CHECK_INTERVAL);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lost_grant_is_announced_once() {
+        let store = TokenStore::new("agi-test-oauth", "Test", "Test");
+        let mut count = 0;
+        for lost in [true, true, false, true] {
+            store.announce_lost(lost, || count += 1);
+        }
+        assert_eq!(count, 2, "once when lost, again after a reconnect is lost too");
+    }
+}
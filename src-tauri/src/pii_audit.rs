@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::file_storage;
+use crate::pii_scrubber::ScrubReport;
+
+/// One scrub operation. Only counts are kept: the log itself must not hold the PII it
+/// describes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub timestamp: String,             // RFC 3339, UTC
+    pub source: String,                // Command or subsystem that scrubbed, e.g. "write_conversation_to_file"
+    pub destination: Option<String>,   // Where the scrubbed data went, e.g. "memory/conversation_1.json"
+    pub total: usize,
+    pub counts: BTreeMap<String, usize>, // Redactions per category
+}
+
+// Serializes appends across threads
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// Append-only JSON lines file in the project root
+pub fn log_path() -> PathBuf {
+    file_storage::project_root().join("pii_audit.jsonl")
+}
+
+fn append(path: &Path, entry: &AuditEntry) -> Result<()> {
+    let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Record a scrub operation. Failing to log never blocks the scrub itself.
+pub fn record(source: &str, destination: Option<String>, report: &ScrubReport) {
+    let entry = AuditEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        source: source.to_string(),
+        destination,
+        total: report.total,
+        counts: report.counts.clone(),
+    };
+    if let Err(e) = append(&log_path(), &entry) {
+        println!("[PII] Failed to write audit log: {}", e);
+    }
+}
+
+/// Entries oldest first; lines that don't parse are skipped
+fn read(path: &Path) -> Result<Vec<AuditEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Every entry, oldest first
+pub fn entries() -> Result<Vec<AuditEntry>> {
+    read(&log_path())
+}
+
+/// Write entries as CSV, one column per category seen
+fn write_csv(path: &Path, entries: &[AuditEntry]) -> Result<()> {
+    let mut categories: Vec<&String> = entries.iter().flat_map(|e| e.counts.keys()).collect();
+    categories.sort();
+    categories.dedup();
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header = vec!["timestamp", "source", "destination", "total"];
+    header.extend(categories.iter().map(|c| c.as_str()));
+    writer.write_record(&header)?;
+    for entry in entries {
+        let mut row = vec![
+            entry.timestamp.clone(),
+            entry.source.clone(),
+            entry.destination.clone().unwrap_or_default(),
+            entry.total.to_string(),
+        ];
+        row.extend(categories.iter().map(|c| entry.counts.get(*c).copied().unwrap_or(0).to_string()));
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Time window for statistics, ending now
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsPeriod {
+    Day,
+    Week,
+    Month,                             // Last 30 days
+    Year,
+    All,
+}
+
+impl StatsPeriod {
+    fn start(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let days = match self {
+            StatsPeriod::Day => 1,
+            StatsPeriod::Week => 7,
+            StatsPeriod::Month => 30,
+            StatsPeriod::Year => 365,
+            StatsPeriod::All => return None,
+        };
+        Some(now - Duration::days(days))
+    }
+}
+
+/// Redactions on one day (UTC)
+#[derive(Debug, Serialize, PartialEq)]
+pub struct DailyCount {
+    pub date: String,                  // YYYY-MM-DD
+    pub total: usize,
+}
+
+/// How much was redacted in a period
+#[derive(Debug, Serialize)]
+pub struct PiiStats {
+    pub period: StatsPeriod,
+    pub since: Option<String>,         // RFC 3339; None for all time
+    pub scrubs: usize,                 // Scrub operations logged
+    pub total: usize,                  // Redactions across them
+    pub by_category: BTreeMap<String, usize>,
+    pub by_destination: BTreeMap<String, usize>, // "memory", "aws", "clipboard", "uploads", or "none"
+    pub daily: Vec<DailyCount>,        // Days with redactions, oldest first
+}
+
+/// Kind of destination, without the file name: "memory/conversation_1.json" is "memory"
+fn destination_kind(destination: Option<&str>) -> String {
+    match destination {
+        Some(d) => d.split(['/', ':']).next().unwrap_or(d).to_string(),
+        None => "none".to_string(),
+    }
+}
+
+fn summarize(entries: &[AuditEntry], period: StatsPeriod, now: DateTime<Utc>) -> PiiStats {
+    let since = period.start(now);
+    let mut stats = PiiStats {
+        period,
+        since: since.map(|t| t.to_rfc3339()),
+        scrubs: 0,
+        total: 0,
+        by_category: BTreeMap::new(),
+        by_destination: BTreeMap::new(),
+        daily: Vec::new(),
+    };
+    let mut daily: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in entries {
+        let Ok(time) = DateTime::parse_from_rfc3339(&entry.timestamp) else {
+            continue;
+        };
+        let time = time.with_timezone(&Utc);
+        if since.is_some_and(|since| time < since) {
+            continue;
+        }
+        stats.scrubs += 1;
+        stats.total += entry.total;
+        for (category, n) in &entry.counts {
+            *stats.by_category.entry(category.clone()).or_default() += n;
+        }
+        if entry.total > 0 {
+            *stats.by_destination.entry(destination_kind(entry.destination.as_deref())).or_default() += entry.total;
+            *daily.entry(time.format("%Y-%m-%d").to_string()).or_default() += entry.total;
+        }
+    }
+    stats.daily = daily.into_iter().map(|(date, total)| DailyCount { date, total }).collect();
+    stats
+}
+
+/// Redaction totals by category, destination and day over `period` (default: the last 30 days)
+#[tauri::command]
+pub fn get_pii_stats(period: Option<StatsPeriod>) -> Result<PiiStats, String> {
+    let entries = entries().map_err(|e| format!("Failed to read PII audit log: {}", e))?;
+    Ok(summarize(&entries, period.unwrap_or(StatsPeriod::Month), Utc::now()))
+}
+
+/// The most recent audit entries, newest first
+#[tauri::command]
+pub fn get_pii_audit_log(limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+    let mut entries = entries().map_err(|e| format!("Failed to read PII audit log: {}", e))?;
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(100));
+    Ok(entries)
+}
+
+/// Export the whole audit log to `path`: CSV for a .csv path, a JSON array otherwise.
+/// Returns how many entries were written.
+#[tauri::command]
+pub fn export_pii_audit_log(path: String) -> Result<usize, String> {
+    let entries = entries().map_err(|e| format!("Failed to read PII audit log: {}", e))?;
+    let path = Path::new(&path);
+    let is_csv = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    let written = if is_csv {
+        write_csv(path, &entries)
+    } else {
+        serde_json::to_string_pretty(&entries)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(fs::write(path, json)?))
+    };
+    written.map_err(|e| format!("Failed to export PII audit log: {}", e))?;
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read_back() {
+        let dir = std::env::temp_dir().join(format!("agi-pii-audit-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("audit.jsonl");
+        let entry = |source: &str, counts: &[(&str, usize)]| AuditEntry {
+            timestamp: Utc::now().to_rfc3339(),
+            source: source.to_string(),
+            destination: None,
+            total: counts.iter().map(|(_, n)| n).sum(),
+            counts: counts.iter().map(|(c, n)| (c.to_string(), *n)).collect(),
+        };
+        append(&log, &entry("scrub_text", &[("emails", 2)])).unwrap();
+        fs::OpenOptions::new().append(true).open(&log).unwrap().write_all(b"not json\n").unwrap();
+        append(&log, &entry("aws_upload", &[("names", 1), ("emails", 1)])).unwrap();
+
+        let entries = read(&log).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].counts["emails"], 2);
+
+        let csv_path = dir.join("audit.csv");
+        write_csv(&csv_path, &entries).unwrap();
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        assert!(csv.starts_with("timestamp,source,destination,total,emails,names\n"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_stats_summary() {
+        let now = DateTime::parse_from_rfc3339("2024-05-10T12:00:00Z").unwrap().with_timezone(&Utc);
+        let entry = |timestamp: &str, destination: Option<&str>, counts: &[(&str, usize)]| AuditEntry {
+            timestamp: timestamp.to_string(),
+            source: "test".to_string(),
+            destination: destination.map(str::to_string),
+            total: counts.iter().map(|(_, n)| n).sum(),
+            counts: counts.iter().map(|(c, n)| (c.to_string(), *n)).collect(),
+        };
+        let entries = vec![
+            entry("2024-04-01T09:00:00Z", Some("aws:old.json"), &[("emails", 5)]),
+            entry("2024-05-09T09:00:00Z", Some("memory/conversation_1.json"), &[("emails", 2), ("names", 1)]),
+            entry("2024-05-10T08:00:00Z", Some("aws:conversation_1.json"), &[("emails", 1)]),
+            entry("2024-05-10T09:00:00Z", None, &[]),
+        ];
+
+        let week = summarize(&entries, StatsPeriod::Week, now);
+        assert_eq!((week.scrubs, week.total), (3, 4));
+        assert_eq!(week.by_category["emails"], 3);
+        assert_eq!(week.by_destination["memory"], 3);
+        assert_eq!(week.by_destination["aws"], 1);
+        assert!(!week.by_destination.contains_key("none"));
+        assert_eq!(
+            week.daily,
+            vec![
+                DailyCount { date: "2024-05-09".to_string(), total: 3 },
+                DailyCount { date: "2024-05-10".to_string(), total: 1 },
+            ]
+        );
+
+        let all = summarize(&entries, StatsPeriod::All, now);
+        assert_eq!((all.total, all.by_destination["aws"]), (9, 6));
+        assert!(all.since.is_none());
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::extractors::ocr::{self, OcrWord};
+use crate::pii_scrubber::{ScrubReport, Scrubbed, Scrubber};
+use crate::transcription;
+
+/// Pixels added around each word box so ascenders and descenders are covered too
+const PADDING: u32 = 2;
+
+/// OCR text as tesseract laid it out (a line per text line, words joined by spaces),
+/// with each word's byte range in it
+fn ocr_text(words: &[OcrWord]) -> (String, Vec<Range<usize>>) {
+    let mut lines: BTreeMap<(u32, u32, u32), Vec<usize>> = BTreeMap::new();
+    for (i, word) in words.iter().enumerate() {
+        lines.entry(word.line_key).or_default().push(i);
+    }
+    let mut text = String::new();
+    let mut ranges = vec![0..0; words.len()];
+    for (n, line) in lines.values().enumerate() {
+        if n > 0 {
+            text.push('\n');
+        }
+        for (k, &i) in line.iter().enumerate() {
+            if k > 0 {
+                text.push(' ');
+            }
+            let start = text.len();
+            text.push_str(&words[i].text);
+            ranges[i] = start..text.len();
+        }
+    }
+    (text, ranges)
+}
+
+/// Boxes of the words a scrub redacted any part of
+fn pii_boxes(words: &[OcrWord], ranges: &[Range<usize>], scrubbed: &Scrubbed) -> Vec<(u32, u32, u32, u32)> {
+    words
+        .iter()
+        .zip(ranges)
+        .filter(|(_, range)| scrubbed.redactions.iter().any(|r| r.start < range.end && range.start < r.end))
+        .map(|(word, _)| word.bbox)
+        .collect()
+}
+
+/// Copy a PNG with the given boxes filled black
+fn black_out(png_in: &Path, png_out: &Path, boxes: &[(u32, u32, u32, u32)]) -> Result<()> {
+    let mut decoder = png::Decoder::new(File::open(png_in).with_context(|| format!("opening {}", png_in.display()))?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    let pixels = &mut pixels[..info.buffer_size()];
+    let channels = info.color_type.samples();
+    let has_alpha = matches!(info.color_type, png::ColorType::GrayscaleAlpha | png::ColorType::Rgba);
+
+    for &(left, top, width, height) in boxes {
+        let x0 = left.saturating_sub(PADDING) as usize;
+        let y0 = top.saturating_sub(PADDING) as usize;
+        let x1 = (left + width + PADDING).min(info.width) as usize;
+        let y1 = (top + height + PADDING).min(info.height) as usize;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let at = y * info.line_size + x * channels;
+                let pixel = &mut pixels[at..at + channels];
+                pixel.fill(0);
+                if has_alpha {
+                    pixel[channels - 1] = 255;
+                }
+            }
+        }
+    }
+
+    let file = File::create(png_out).with_context(|| format!("creating {}", png_out.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), info.width, info.height);
+    encoder.set_color(info.color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(())
+}
+
+fn is_png(path: &Path) -> bool {
+    fs::read(path).is_ok_and(|bytes| bytes.starts_with(b"\x89PNG\r\n\x1a\n"))
+}
+
+/// The image as a PNG: itself, or a conversion made with ffmpeg at `converted`
+fn as_png(path: &Path, converted: &Path) -> Result<PathBuf> {
+    if is_png(path) {
+        return Ok(path.to_path_buf());
+    }
+    let output = Command::new(transcription::ffmpeg_cmd())
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-f", "image2", "-c:v", "png"])
+        .arg(converted)
+        .stdin(Stdio::null())
+        .output()
+        .context("Only PNG images can be redacted without ffmpeg")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to convert image to PNG: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(converted.to_path_buf())
+}
+
+/// OCR an image and write a copy to `out` with every word holding detected PII blacked out.
+/// Returns the redactions, by position in the OCR text.
+fn redact_image(path: &Path, out: &Path, scrubber: &Scrubber) -> Result<ScrubReport> {
+    // OCR the PNG that gets painted, so word boxes and pixels line up
+    let converted = out.with_extension("source.png");
+    let source = as_png(path, &converted)?;
+    let result = (|| {
+        let words = ocr::recognize_words(&source)?;
+        let (text, ranges) = ocr_text(&words);
+        let scrubbed = scrubber.scrub(&text);
+        let boxes = pii_boxes(&words, &ranges, &scrubbed);
+        black_out(&source, out, &boxes)?;
+        let mut report = ScrubReport::default();
+        report.add(scrubbed);
+        Ok(report)
+    })();
+    if source == converted {
+        let _ = fs::remove_file(&converted);
+    }
+    result
+}
+
+/// Redact image bytes about to leave the machine; the result is PNG bytes
+pub fn redact_bytes(bytes: &[u8], scrubber: &Scrubber) -> Result<(Vec<u8>, ScrubReport)> {
+    let dir = std::env::temp_dir().join(format!("agi-pii-image-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir)?;
+    let result = (|| {
+        let original = dir.join("image");
+        fs::write(&original, bytes)?;
+        let out = dir.join("redacted.png");
+        let report = redact_image(&original, &out, scrubber)?;
+        Ok((fs::read(&out)?, report))
+    })();
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::PiiSettings;
+
+    #[test]
+    fn test_black_out_pii_words() {
+        let word = |text: &str, line: u32, left: u32| OcrWord {
+            text: text.to_string(),
+            confidence: 95.0,
+            line_key: (1, 1, line),
+            bbox: (left, line * 10, 8, 6),
+        };
+        let words = vec![word("Mail", 0, 0), word("john@example.com", 0, 10), word("today", 1, 0)];
+        let (text, ranges) = ocr_text(&words);
+        assert_eq!(text, "Mail john@example.com\ntoday");
+        let scrubbed = Scrubber::from_settings(PiiSettings::default()).scrub(&text);
+        let boxes = pii_boxes(&words, &ranges, &scrubbed);
+        assert_eq!(boxes, vec![(10, 0, 8, 6)]);
+
+        let dir = std::env::temp_dir().join(format!("agi-pii-image-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("shot.png");
+        let mut encoder = png::Encoder::new(File::create(&original).unwrap(), 32, 16);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[255; 32 * 16 * 3]).unwrap();
+
+        let out = dir.join("shot.redacted.png");
+        black_out(&original, &out, &boxes).unwrap();
+        let mut reader = png::Decoder::new(File::open(&out).unwrap()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        let pixel = |x: usize, y: usize| &pixels[y * info.line_size + x * 3..][..3];
+        assert_eq!(pixel(12, 3), [0, 0, 0]);
+        assert_eq!(pixel(2, 3), [255, 255, 255]);
+        assert_eq!(pixel(12, 12), [255, 255, 255]);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,284 @@
+use anyhow::{anyhow, Context, Result};
+use candle_core::{DType, Tensor};
+use candle_nn::{Linear, Module};
+use candle_transformers::models::bert::BertModel;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokenizers::Tokenizer;
+
+use crate::embeddings;
+use crate::file_storage;
+use crate::settings::NerSettings;
+
+/// A BERT token classifier (e.g. bert-base-NER) loaded from ./models/<name>: labels every
+/// token with a BIO tag such as B-PER or I-ORG
+pub struct NerModel {
+    dir: PathBuf,
+    model: BertModel,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    splitter: Tokenizer,               // The same tokenizer without truncation, to cut text into windows
+    window: usize,                     // Tokens per tagging window
+    labels: Vec<String>,
+}
+
+/// A token's (BIO tag, probability, byte range)
+type TaggedToken = (String, f32, (usize, usize));
+
+static NER_MODEL: Mutex<Option<Arc<NerModel>>> = Mutex::new(None);
+
+/// Tokens kept free in each tagging window for the special tokens, and for a piece of text
+/// tokenizing a little differently on its own
+const WINDOW_MARGIN: usize = 16;
+/// Tokens neighbouring windows share, so an entity cut by one window's edge is whole in the next
+const WINDOW_OVERLAP: usize = 64;
+
+/// A stretch of text tagged in one pass: its byte range, and the byte range of entity
+/// starts it answers for
+#[derive(Debug, PartialEq)]
+struct Window {
+    start: usize,
+    end: usize,
+    owns: Range<usize>,
+}
+
+/// A named entity found in text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entity {
+    pub label: String,                 // PER, ORG, LOC, MISC
+    pub start: usize,                  // Byte range in the text
+    pub end: usize,
+    pub score: f32,                    // Mean tag probability over the entity's tokens
+}
+
+#[derive(Deserialize)]
+struct LabelConfig {
+    hidden_size: usize,
+    id2label: HashMap<String, String>,
+}
+
+impl NerModel {
+    fn load(dir: &Path) -> Result<Self> {
+        let (model, tokenizer, vb) = embeddings::load_bert(dir)?;
+        let config: LabelConfig = serde_json::from_str(&fs::read_to_string(dir.join("config.json"))?)
+            .context("reading labels from config.json")?;
+        let mut labels = vec![String::new(); config.id2label.len()];
+        for (id, label) in config.id2label {
+            let id: usize = id.parse().context("parsing id2label")?;
+            *labels.get_mut(id).ok_or_else(|| anyhow!("Label id {} out of range", id))? = label;
+        }
+        let classifier = candle_nn::linear(config.hidden_size, labels.len(), vb.pp("classifier"))
+            .context("loading classifier")?;
+        let max_length = tokenizer.get_truncation().map_or(512, |t| t.max_length);
+        let window = max_length.saturating_sub(WINDOW_MARGIN).max(WINDOW_OVERLAP * 2);
+        let mut splitter = tokenizer.clone();
+        splitter
+            .with_truncation(None)
+            .map_err(|e| anyhow!("Failed to configure tokenizer: {}", e))?;
+        Ok(Self { dir: dir.to_path_buf(), model, classifier, tokenizer, splitter, window, labels })
+    }
+
+    fn shared(dir: &Path) -> Result<Arc<Self>> {
+        let mut cached = NER_MODEL.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(model) = cached.as_ref().filter(|m| m.dir == dir) {
+            return Ok(model.clone());
+        }
+        println!("[PII] Loading NER model from {:?}", dir);
+        let model = Arc::new(Self::load(dir)?);
+        *cached = Some(model.clone());
+        Ok(model)
+    }
+
+    /// Windows covering `text` that each fit the model, so no token goes untagged
+    fn windows(&self, text: &str) -> Result<Vec<Window>> {
+        let encoding = self
+            .splitter
+            .encode(text, false)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        Ok(windows(encoding.get_offsets(), text.len(), self.window))
+    }
+
+    /// Every real token of `text`, tagged
+    fn tag(&self, text: &str) -> Result<Vec<TaggedToken>> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        let device = &self.model.device;
+        let ids = Tensor::new(encoding.get_ids(), device)?.unsqueeze(0)?;
+        let mask = Tensor::new(encoding.get_attention_mask(), device)?.unsqueeze(0)?;
+
+        let hidden = self.model.forward(&ids, &ids.zeros_like()?, Some(&mask))?; // (1, seq, dim)
+        let logits = self.classifier.forward(&hidden)?.squeeze(0)?;
+        let probs = candle_nn::ops::softmax_last_dim(&logits.to_dtype(DType::F32)?)?.to_vec2::<f32>()?;
+
+        let tagged = probs
+            .iter()
+            .zip(encoding.get_offsets())
+            .zip(encoding.get_special_tokens_mask())
+            .filter(|((_, offsets), special)| **special == 0 && offsets.1 > offsets.0)
+            .map(|((row, offsets), _)| {
+                let (best, p) = row
+                    .iter()
+                    .enumerate()
+                    .fold((0, f32::MIN), |best, (i, &p)| if p > best.1 { (i, p) } else { best });
+                (self.labels.get(best).cloned().unwrap_or_default(), p, *offsets)
+            })
+            .collect();
+        Ok(tagged)
+    }
+}
+
+/// Merge BIO-tagged tokens into entities. An I- tag continues the entity before it when the
+/// type matches (subword pieces of a word come through this way); anything else starts anew.
+fn merge_tokens(tokens: &[TaggedToken]) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+    let mut open = false;
+    for (tag, p, (start, end)) in tokens {
+        let (prefix, label) = tag.split_once('-').unwrap_or(("", tag.as_str()));
+        if label.is_empty() || tag == "O" {
+            open = false;
+            continue;
+        }
+        match entities.last_mut() {
+            Some(last) if open && prefix == "I" && last.label == label => {
+                last.end = *end;
+                last.score += p;
+                *counts.last_mut().unwrap() += 1;
+            }
+            _ => {
+                entities.push(Entity { label: label.to_string(), start: *start, end: *end, score: *p });
+                counts.push(1);
+            }
+        }
+        open = true;
+    }
+    for (entity, n) in entities.iter_mut().zip(counts) {
+        entity.score /= n as f32;
+    }
+    entities
+}
+
+/// Cut text whose tokens have these byte `offsets` into windows of `size` tokens, each
+/// sharing WINDOW_OVERLAP tokens with the next. An entity starting in a shared stretch
+/// belongs to the first window before its middle and to the second after it.
+fn windows(offsets: &[(usize, usize)], text_len: usize, size: usize) -> Vec<Window> {
+    if offsets.len() <= size {
+        return vec![Window { start: 0, end: text_len, owns: 0..text_len }];
+    }
+    let step = size - WINDOW_OVERLAP;
+    let mut out: Vec<Window> = Vec::new();
+    let mut first = 0;
+    loop {
+        let last = (first + size).min(offsets.len()) - 1;
+        let start = out.last().map_or(0, |_| offsets[first].0);
+        let owns_from = out.last().map_or(0, |previous| previous.owns.end);
+        if last == offsets.len() - 1 {
+            out.push(Window { start, end: text_len, owns: owns_from..text_len });
+            return out;
+        }
+        let middle = offsets[first + step + WINDOW_OVERLAP / 2].0;
+        out.push(Window { start, end: offsets[last].1, owns: owns_from..middle });
+        first += step;
+    }
+}
+
+/// Directory of the configured NER model
+pub fn local_model_dir(settings: &NerSettings) -> PathBuf {
+    file_storage::project_root().join("models").join(&settings.local_model)
+}
+
+/// Entities of the configured types scoring at least the configured sensitivity
+pub fn detect(settings: &NerSettings, text: &str) -> Result<Vec<Entity>> {
+    // The supported checkpoints are cased: without a capital letter there is nothing to find
+    if !text.chars().any(char::is_uppercase) {
+        return Ok(Vec::new());
+    }
+    let dir = local_model_dir(settings);
+    if !embeddings::MODEL_FILES.iter().all(|f| dir.join(f).exists()) {
+        return Err(anyhow!("NER model not found in {:?}", dir));
+    }
+    let model = NerModel::shared(&dir)?;
+    let mut entities = Vec::new();
+    for window in model.windows(text)? {
+        let found = merge_tokens(&model.tag(&text[window.start..window.end])?);
+        entities.extend(
+            found
+                .into_iter()
+                .map(|e| Entity { start: e.start + window.start, end: e.end + window.start, ..e })
+                .filter(|e| window.owns.contains(&e.start)),
+        );
+    }
+    entities.retain(|e| e.score >= settings.min_score && settings.entity_types.contains(&e.label));
+    Ok(entities)
+}
+
+/// Report category for a model label
+pub fn category(label: &str) -> &'static str {
+    match label {
+        "PER" => "names",
+        "ORG" => "organizations",
+        "LOC" => "places",
+        _ => "other_entities",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_tokens() {
+        let text = "Ask Johnson at Acme Corp today";
+        let token = |tag: &str, p: f32, word: &str| {
+            let start = text.find(word).unwrap();
+            (tag.to_string(), p, (start, start + word.len()))
+        };
+        let tokens = vec![
+            token("O", 0.99, "Ask"),
+            token("B-PER", 0.9, "John"),
+            token("I-PER", 0.7, "son"),
+            token("O", 0.99, "at"),
+            token("B-ORG", 0.8, "Acme"),
+            token("I-ORG", 0.6, "Corp"),
+            token("O", 0.99, "today"),
+        ];
+        let entities = merge_tokens(&tokens);
+        assert_eq!(entities.len(), 2);
+        assert_eq!(&text[entities[0].start..entities[0].end], "Johnson");
+        assert!((entities[0].score - 0.8).abs() < 1e-6);
+        assert_eq!(entities[1].label, "ORG");
+        assert_eq!(&text[entities[1].start..entities[1].end], "Acme Corp");
+
+    }
+
+    #[test]
+    fn test_windows_cover_every_token() {
+        // 300 five-byte tokens, e.g. "1234 "
+        let offsets: Vec<(usize, usize)> = (0..300).map(|i| (i * 5, i * 5 + 4)).collect();
+        let text_len = 1500;
+        assert_eq!(windows(&offsets[..100], 500, 200), vec![Window { start: 0, end: 500, owns: 0..500 }]);
+
+        let cut = windows(&offsets, text_len, 200);
+        // Tokens 0..200, then 136..300 (step 136, 64 shared), split at token 168
+        assert_eq!(cut.len(), 2);
+        assert_eq!(cut[0], Window { start: 0, end: 199 * 5 + 4, owns: 0..168 * 5 });
+        assert_eq!(cut[1], Window { start: 136 * 5, end: text_len, owns: 168 * 5..text_len });
+
+        // Every token falls in a window no longer than the limit, and is owned exactly once
+        let small = windows(&offsets, text_len, 100);
+        for &(start, end) in &offsets {
+            let owners: Vec<&Window> = small.iter().filter(|w| w.owns.contains(&start)).collect();
+            assert_eq!(owners.len(), 1);
+            assert!(owners[0].start <= start && end <= owners[0].end);
+        }
+        assert!(small
+            .iter()
+            .all(|w| offsets.iter().filter(|(s, e)| *s >= w.start && *e <= w.end).count() <= 100));
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::pii_scrubber::ScrubPass;
+use crate::settings::{QueryExpansion, RetrievalSettings};
+
+/// Groups of interchangeable words common in personal and work documents
+const SYNONYMS: &[&[&str]] = &[
+    &["invoice", "bill", "receipt"],
+    &["cost", "price", "fee", "charge"],
+    &["pay", "payment", "salary", "compensation"],
+    &["buy", "purchase", "order"],
+    &["contract", "agreement"],
+    &["meeting", "call", "appointment"],
+    &["error", "exception", "failure", "bug"],
+    &["delete", "remove"],
+    &["create", "add", "new"],
+    &["config", "configuration", "settings", "setup"],
+    &["start", "begin", "launch"],
+    &["stop", "end", "terminate"],
+    &["doc", "document", "documentation"],
+    &["repo", "repository"],
+    &["db", "database"],
+    &["auth", "authentication", "login"],
+    &["car", "vehicle"],
+    &["doctor", "physician"],
+    &["address", "location"],
+    &["phone", "telephone", "mobile"],
+];
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "did", "do", "does", "for", "from", "how",
+    "i", "in", "is", "it", "me", "my", "of", "on", "or", "the", "this", "to", "was", "what",
+    "when", "where", "which", "who", "why", "with", "you",
+];
+
+/// "parseConfig" / "parse_config" / "parse-config" -> ["parse", "config"]
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in word.chars() {
+        if c == '_' || c == '-' {
+            parts.push(std::mem::take(&mut current));
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower {
+            parts.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    parts.push(current);
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// Singular/plural counterpart of a word
+fn inflections(word: &str) -> Vec<String> {
+    if let Some(stem) = word.strip_suffix("ies") {
+        vec![format!("{}y", stem)]
+    } else if let Some(stem) = word.strip_suffix("es").filter(|s| s.ends_with(['s', 'x', 'h'])) {
+        vec![stem.to_string()]
+    } else if let Some(stem) = word.strip_suffix('s').filter(|s| !s.ends_with('s') && s.len() > 2) {
+        vec![stem.to_string()]
+    } else if let Some(stem) = word.strip_suffix('y') {
+        vec![format!("{}ies", stem)]
+    } else {
+        vec![format!("{}s", word)]
+    }
+}
+
+/// Identifier parts, singular/plural forms and synonyms of the query's words
+fn expand_rules(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for raw in query.split_whitespace() {
+        let raw = raw.trim_matches(|c: char| !c.is_alphanumeric() && c != '_' && c != '-');
+        let parts = split_identifier(raw);
+        if parts.len() > 1 {
+            terms.extend(parts.iter().cloned());
+        }
+        for word in parts {
+            if word.len() < 3 || STOPWORDS.contains(&word.as_str()) || word.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            terms.extend(inflections(&word));
+            for group in SYNONYMS {
+                let matched = group
+                    .iter()
+                    .any(|s| *s == word || inflections(s).contains(&word));
+                if matched {
+                    terms.extend(group.iter().map(|s| s.to_string()));
+                }
+            }
+        }
+    }
+    let lower = query.to_lowercase();
+    let mut seen = Vec::new();
+    terms.retain(|t| {
+        let new = !lower.split_whitespace().any(|w| w == t) && !seen.contains(t);
+        seen.push(t.clone());
+        new
+    });
+    terms
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// Ask an OpenAI-compatible chat endpoint for alternative search keywords. The query is
+/// scrubbed first; it leaves the machine.
+fn expand_llm(settings: &RetrievalSettings, query: &str) -> Result<Vec<String>> {
+    let mut pass = ScrubPass::new();
+    let query = pass.text(query);
+    pass.record("query_expansion", &settings.expansion_api_url);
+    let client = Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .context("building http client")?;
+    let mut request = client.post(&settings.expansion_api_url).json(&json!({
+        "model": settings.expansion_model,
+        "temperature": 0,
+        "messages": [
+            {
+                "role": "system",
+                "content": format!(
+                    "You expand search queries for a document search engine. Reply with up to {} \
+                     alternative keywords or short phrases (synonyms, related terms, likely document \
+                     wording), one per line, nothing else.",
+                    settings.max_expansions
+                ),
+            },
+            { "role": "user", "content": query },
+        ],
+    }));
+    if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+        request = request.bearer_auth(key);
+    }
+    let resp = request.send()?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        return Err(anyhow!("Expansion API error {}: {}", status, resp.text().unwrap_or_default()));
+    }
+    let content = resp
+        .json::<ChatResponse>()?
+        .choices
+        .into_iter()
+        .next()
+        .map(|c| c.message.content)
+        .unwrap_or_default();
+    Ok(content
+        .lines()
+        .map(|l| l.trim().trim_start_matches(['-', '*', '•']).trim().trim_matches('"').to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// Extra keyword terms for the query, per the configured expansion mode. An LLM that
+/// can't be reached falls back to the rules rather than failing retrieval.
+pub fn expand(settings: &RetrievalSettings, query: &str) -> Vec<String> {
+    let mut terms = match settings.query_expansion {
+        QueryExpansion::Off => return Vec::new(),
+        QueryExpansion::Rules => expand_rules(query),
+        QueryExpansion::Llm => expand_llm(settings, query).unwrap_or_else(|e| {
+            println!("[QueryExpansion] Falling back to rules: {}", e);
+            expand_rules(query)
+        }),
+    };
+    terms.truncate(settings.max_expansions);
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_expansion() {
+        let terms = expand_rules("Where is the invoice for parseConfig errors?");
+        for expected in ["invoices", "bill", "receipt", "parse", "config", "configuration", "error", "exception"] {
+            assert!(terms.contains(&expected.to_string()), "missing {} in {:?}", expected, terms);
+        }
+        assert!(!terms.contains(&"invoice".to_string()), "query words aren't repeated");
+        assert!(!terms.iter().any(|t| t == "where" || t == "the"));
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Context, Result};
+use candle_core::{DType, Tensor};
+use candle_nn::{Linear, Module};
+use candle_transformers::models::bert::BertModel;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokenizers::Tokenizer;
+
+use crate::embeddings;
+use crate::file_storage;
+use crate::keychain;
+use crate::pii_scrubber::ScrubPass;
+use crate::settings::{RerankProvider, RerankSettings};
+use crate::vector_store::ScoredChunk;
+
+// Where set_rerank_api_key keeps the key for the rerank API
+const KEYCHAIN_SERVICE: &str = "agi-rerank";
+const KEYCHAIN_ACCOUNT: &str = "api_key";
+
+/// A BERT cross-encoder (e.g. ms-marco-MiniLM-L-6-v2) loaded from ./models/<name>: reads
+/// question and passage together and outputs one relevance logit
+pub struct CrossEncoder {
+    dir: PathBuf,
+    model: BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+}
+
+static CROSS_ENCODER: Mutex<Option<Arc<CrossEncoder>>> = Mutex::new(None);
+
+impl CrossEncoder {
+    fn load(dir: &Path) -> Result<Self> {
+        let (model, tokenizer, vb) = embeddings::load_bert(dir)?;
+        let hidden = model_hidden_size(dir)?;
+        let pooler = candle_nn::linear(hidden, hidden, vb.pp("bert.pooler.dense"))
+            .context("loading pooler")?;
+        let classifier = candle_nn::linear(hidden, 1, vb.pp("classifier")).context("loading classifier")?;
+        Ok(Self { dir: dir.to_path_buf(), model, pooler, classifier, tokenizer })
+    }
+
+    fn shared(dir: &Path) -> Result<Arc<Self>> {
+        let mut cached = CROSS_ENCODER.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(model) = cached.as_ref().filter(|m| m.dir == dir) {
+            return Ok(model.clone());
+        }
+        println!("[Rerank] Loading cross-encoder from {:?}", dir);
+        let model = Arc::new(Self::load(dir)?);
+        *cached = Some(model.clone());
+        Ok(model)
+    }
+
+    /// Relevance logit of each passage for the query
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+        let pairs: Vec<(String, String)> = passages.iter().map(|p| (query.to_string(), p.clone())).collect();
+        let encodings = self
+            .tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        let device = &self.model.device;
+        let stack = |f: fn(&tokenizers::Encoding) -> &[u32]| -> Result<Tensor> {
+            let rows = encodings
+                .iter()
+                .map(|e| Tensor::new(f(e), device))
+                .collect::<candle_core::Result<Vec<_>>>()?;
+            Ok(Tensor::stack(&rows, 0)?)
+        };
+        let ids = stack(|e| e.get_ids())?;
+        let types = stack(|e| e.get_type_ids())?;
+        let mask = stack(|e| e.get_attention_mask())?;
+
+        let hidden = self.model.forward(&ids, &types, Some(&mask))?; // (batch, seq, dim)
+        let cls = hidden.narrow(1, 0, 1)?.squeeze(1)?;
+        let pooled = self.pooler.forward(&cls)?.tanh()?;
+        let logits = self.classifier.forward(&pooled)?.squeeze(1)?;
+        Ok(logits.to_dtype(DType::F32)?.to_vec1::<f32>()?)
+    }
+}
+
+fn model_hidden_size(dir: &Path) -> Result<usize> {
+    #[derive(Deserialize)]
+    struct Hidden {
+        hidden_size: usize,
+    }
+    let config: Hidden = serde_json::from_str(&std::fs::read_to_string(dir.join("config.json"))?)?;
+    Ok(config.hidden_size)
+}
+
+#[derive(Serialize)]
+struct ApiRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [String],
+    top_n: usize,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    results: Vec<ApiResult>,
+}
+
+#[derive(Deserialize)]
+struct ApiResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+/// Directory of the configured local cross-encoder
+pub fn local_model_dir(settings: &RerankSettings) -> PathBuf {
+    file_storage::project_root().join("models").join(&settings.local_model)
+}
+
+/// The rerank API key saved with set_rerank_api_key, or else RERANK_API_KEY or
+/// COHERE_API_KEY from the environment. Keychain failures count as nothing saved.
+fn api_key() -> Option<String> {
+    let stored = keychain::get(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).unwrap_or_else(|e| {
+        eprintln!("[Rerank] Couldn't read the API key from the keychain: {}", e);
+        None
+    });
+    stored
+        .or_else(|| std::env::var("RERANK_API_KEY").ok())
+        .or_else(|| std::env::var("COHERE_API_KEY").ok())
+}
+
+/// Relevance score per passage, in input order
+fn scores(settings: &RerankSettings, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+    match settings.provider {
+        RerankProvider::None => Err(anyhow!("Reranking is disabled")),
+        RerankProvider::Local => {
+            let dir = local_model_dir(settings);
+            if !embeddings::MODEL_FILES.iter().all(|f| dir.join(f).exists()) {
+                return Err(anyhow!("Cross-encoder not found in {:?}", dir));
+            }
+            let model = CrossEncoder::shared(&dir)?;
+            let mut scores = Vec::with_capacity(passages.len());
+            for batch in passages.chunks(settings.batch_size.max(1)) {
+                scores.extend(model.score(query, batch)?);
+            }
+            Ok(scores)
+        }
+        // Cohere/Jina/TEI-style /rerank endpoint; the query and passages leave the machine,
+        // so they go out scrubbed
+        RerankProvider::Api => {
+            let mut pass = ScrubPass::new();
+            let query = pass.text(query);
+            let documents: Vec<String> = passages.iter().map(|p| pass.text(p)).collect();
+            pass.record("rerank_api", &settings.api_url);
+            let client = Client::builder()
+                .timeout(Duration::from_secs(60))
+                .build()
+                .context("building http client")?;
+            let mut request = client.post(&settings.api_url).json(&ApiRequest {
+                model: &settings.api_model,
+                query: &query,
+                documents: &documents,
+                top_n: passages.len(),
+            });
+            if let Some(key) = api_key() {
+                request = request.bearer_auth(key);
+            }
+            let resp = request.send()?;
+            if !resp.status().is_success() {
+                let status = resp.status();
+                return Err(anyhow!("Rerank API error {}: {}", status, resp.text().unwrap_or_default()));
+            }
+            let mut scores = vec![f32::NEG_INFINITY; passages.len()];
+            for result in resp.json::<ApiResponse>()?.results {
+                if let Some(score) = scores.get_mut(result.index) {
+                    *score = result.relevance_score;
+                }
+            }
+            Ok(scores)
+        }
+    }
+}
+
+/// Re-score retrieval candidates against the query and keep the best `limit`. Scores are
+/// replaced with the reranker's. Candidates come back untouched (truncated) when reranking
+/// is off or fails, so retrieval never breaks because of it.
+pub fn rerank(settings: &RerankSettings, query: &str, mut hits: Vec<ScoredChunk>, limit: usize) -> Vec<ScoredChunk> {
+    if settings.provider == RerankProvider::None || hits.len() <= 1 {
+        hits.truncate(limit);
+        return hits;
+    }
+    let passages: Vec<String> = hits.iter().map(|h| h.chunk.text.clone()).collect();
+    match scores(settings, query, &passages) {
+        Ok(scores) => {
+            for (hit, score) in hits.iter_mut().zip(scores) {
+                hit.score = score;
+            }
+            hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
+        Err(e) => println!("[Rerank] Keeping retrieval order: {}", e),
+    }
+    hits.truncate(limit);
+    hits
+}
+
+/// Save the key for the rerank API to the OS keychain; an empty or missing key removes it
+#[tauri::command]
+pub fn set_rerank_api_key(api_key: Option<String>) -> Result<(), String> {
+    match api_key.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        Some(key) => keychain::set(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, key),
+        None => keychain::delete(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT),
+    }
+    .map_err(|e| format!("Failed to save rerank API key: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::StoredChunk;
+
+    fn hit(text: &str, score: f32) -> ScoredChunk {
+        ScoredChunk {
+            chunk: StoredChunk {
+                file_id: "f".to_string(),
+                chunk_index: 0,
+                heading: None,
+                text: text.to_string(),
+                tokens: 1,
+                source: Default::default(),
+            },
+            score,
+        }
+    }
+
+    #[test]
+    fn test_failed_rerank_keeps_retrieval_order() {
+        let settings = RerankSettings {
+            provider: RerankProvider::Api,
+            api_url: "http://127.0.0.1:9/rerank".to_string(),
+            ..Default::default()
+        };
+        let hits = vec![hit("a", 0.3), hit("b", 0.2), hit("c", 0.1)];
+        let kept = rerank(&settings, "q", hits, 2);
+        let texts: Vec<&str> = kept.iter().map(|h| h.chunk.text.as_str()).collect();
+        assert_eq!(texts, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::file_storage::{FileInfo, FileStorage};
+use crate::settings::Settings;
+
+/// One labelled question. Expected files are given by upload id or file name.
+#[derive(Debug, Deserialize)]
+struct EvalCase {
+    question: String,
+    #[serde(default)]
+    expected_file: Option<String>,
+    #[serde(default)]
+    expected_files: Vec<String>,
+}
+
+/// Outcome for one question
+#[derive(Debug, Serialize)]
+pub struct EvalResult {
+    pub question: String,
+    pub expected: Vec<String>,
+    pub rank: Option<usize>,           // 1-based position of the first expected file, None = missed
+    pub retrieved: Vec<String>,        // Names of the distinct files retrieved, in order
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvalReport {
+    pub questions: usize,
+    pub k: usize,
+    pub hit_rate: f32,                 // Share of questions with an expected file in the top k
+    pub mrr: f32,                      // Mean reciprocal rank, 0 for misses
+    pub config: String,                // Settings the numbers were measured with
+    pub results: Vec<EvalResult>,
+    pub unknown_files: Vec<String>,    // Expected files not among the uploads
+}
+
+/// (hit rate, MRR) over ranks within the top k
+fn metrics(ranks: &[Option<usize>]) -> (f32, f32) {
+    if ranks.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = ranks.len() as f32;
+    let hits = ranks.iter().filter(|r| r.is_some()).count() as f32;
+    let mrr: f32 = ranks.iter().flatten().map(|&r| 1.0 / r as f32).sum();
+    (hits / n, mrr / n)
+}
+
+/// Distinct file ids in first-appearance order, at most `k`
+fn ranked_files(file_ids: impl IntoIterator<Item = String>, k: usize) -> Vec<String> {
+    let mut ranked: Vec<String> = Vec::new();
+    for id in file_ids {
+        if !ranked.contains(&id) {
+            ranked.push(id);
+        }
+    }
+    ranked.truncate(k);
+    ranked
+}
+
+fn describe_config(settings: &Settings) -> String {
+    let c = &settings.chunking;
+    format!(
+        "embeddings={:?}:{} chunking={:?} embedding_tokens={} overlap={} rerank={:?} expansion={:?}",
+        settings.embeddings.provider,
+        settings.embeddings.local_model,
+        c.strategy,
+        c.embedding_tokens,
+        c.overlap_tokens,
+        settings.rerank.provider,
+        settings.retrieval.query_expansion,
+    )
+}
+
+/// Run every question of a dataset (a JSON array of {"question", "expected_file" or
+/// "expected_files"}) through retrieval and score where the expected files land among
+/// the top `k` distinct files retrieved
+pub fn run(storage: &FileStorage, dataset: &Path, k: usize) -> Result<EvalReport> {
+    let content = fs::read_to_string(dataset).with_context(|| format!("reading {}", dataset.display()))?;
+    let cases: Vec<EvalCase> = serde_json::from_str(&content).context("parsing dataset")?;
+    if cases.is_empty() {
+        return Err(anyhow!("Dataset has no questions"));
+    }
+    let k = k.max(1);
+    let files = storage.list_files()?;
+    let find = |wanted: &str| -> Option<&FileInfo> {
+        files
+            .iter()
+            .find(|f| f.id == wanted)
+            .or_else(|| files.iter().find(|f| f.name.eq_ignore_ascii_case(wanted)))
+    };
+
+    let mut results = Vec::new();
+    let mut unknown_files: Vec<String> = Vec::new();
+    for case in cases {
+        let expected: Vec<String> = case.expected_file.into_iter().chain(case.expected_files).collect();
+        let expected_ids: Vec<&str> = expected
+            .iter()
+            .filter_map(|e| match find(e) {
+                Some(f) => Some(f.id.as_str()),
+                None => {
+                    if !unknown_files.contains(e) {
+                        unknown_files.push(e.clone());
+                    }
+                    None
+                }
+            })
+            .collect();
+
+        // Over-fetch chunks so k distinct files are likely to be represented
+        let hits = storage.retrieve(&case.question, k * 4)?;
+        let ranked = ranked_files(hits.into_iter().map(|h| h.chunk.file_id), k);
+        let rank = ranked.iter().position(|id| expected_ids.contains(&id.as_str())).map(|i| i + 1);
+        results.push(EvalResult {
+            question: case.question,
+            expected,
+            rank,
+            retrieved: ranked.iter().map(|id| find(id).map_or(id.clone(), |f| f.name.clone())).collect(),
+        });
+    }
+
+    let ranks: Vec<Option<usize>> = results.iter().map(|r| r.rank).collect();
+    let (hit_rate, mrr) = metrics(&ranks);
+    println!("[RetrievalEval] {} questions: hit@{} {:.3}, MRR {:.3}", results.len(), k, hit_rate, mrr);
+    Ok(EvalReport {
+        questions: results.len(),
+        k,
+        hit_rate,
+        mrr,
+        config: describe_config(&Settings::load().unwrap_or_default()),
+        results,
+        unknown_files,
+    })
+}
+
+/// Measure retrieval on a labelled dataset; see `run` for the file format
+#[tauri::command]
+pub async fn run_retrieval_eval(dataset_path: String, k: Option<usize>) -> Result<EvalReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        run(&storage, Path::new(&dataset_path), k.unwrap_or(5))
+            .map_err(|e| format!("Failed to run retrieval eval: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Retrieval eval task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranks_and_metrics() {
+        let ids = ["a", "a", "b", "c", "b", "d"].map(String::from);
+        assert_eq!(ranked_files(ids, 3), vec!["a", "b", "c"]);
+
+        let (hit_rate, mrr) = metrics(&[Some(1), Some(2), None, Some(4)]);
+        assert_eq!(hit_rate, 0.75);
+        assert!((mrr - (1.0 + 0.5 + 0.25) / 4.0).abs() < 1e-6);
+        assert_eq!(metrics(&[]), (0.0, 0.0));
+
+        let cases: Vec<EvalCase> = serde_json::from_str(
+            r#"[{"question": "q1", "expected_file": "a.pdf"}, {"question": "q2", "expected_files": ["b", "c"]}]"#,
+        )
+        .unwrap();
+        assert_eq!(cases[0].expected_file.as_deref(), Some("a.pdf"));
+        assert_eq!(cases[1].expected_files.len(), 2);
+    }
+}
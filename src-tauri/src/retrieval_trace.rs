@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::time::Instant;
+use uuid::Uuid;
+
+/// "retrieval:started": a question is being looked up in the documents
+#[derive(Debug, Serialize, Clone)]
+pub struct RetrievalStarted {
+    pub trace_id: String,
+    pub query: String,
+}
+
+/// "retrieval:stage": one pipeline step finished
+#[derive(Debug, Serialize, Clone)]
+pub struct RetrievalStage {
+    pub trace_id: String,
+    pub stage: &'static str,           // embed_query, expand_query, search, rerank, filter, fallback, assemble
+    pub millis: u64,                   // Time spent in this stage
+    pub candidates: usize,             // Chunks (or terms, for expand_query) coming out of it
+    pub detail: Option<String>,
+}
+
+/// A chunk that made it into the final selection
+#[derive(Debug, Serialize, Clone)]
+pub struct SelectedChunk {
+    pub file_id: String,
+    pub name: String,
+    pub citation: Option<String>,
+    pub score: Option<f32>,
+    pub tokens: usize,
+}
+
+/// "retrieval:finished": what was selected, and how long the whole lookup took
+#[derive(Debug, Serialize, Clone)]
+pub struct RetrievalFinished {
+    pub trace_id: String,
+    pub millis: u64,
+    pub selected: Vec<SelectedChunk>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum RetrievalEvent {
+    Started(RetrievalStarted),
+    Stage(RetrievalStage),
+    Finished(RetrievalFinished),
+}
+
+impl RetrievalEvent {
+    /// Tauri event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            RetrievalEvent::Started(_) => "retrieval:started",
+            RetrievalEvent::Stage(_) => "retrieval:stage",
+            RetrievalEvent::Finished(_) => "retrieval:finished",
+        }
+    }
+}
+
+/// Times the stages of one retrieval and hands each event to a sink (usually an emitter)
+pub struct Tracer<'a> {
+    id: String,
+    started: Instant,
+    last: Instant,
+    sink: Box<dyn FnMut(RetrievalEvent) + Send + 'a>,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(query: &str, sink: impl FnMut(RetrievalEvent) + Send + 'a) -> Self {
+        let mut tracer = Self {
+            id: Uuid::new_v4().to_string(),
+            started: Instant::now(),
+            last: Instant::now(),
+            sink: Box::new(sink),
+        };
+        let event = RetrievalStarted { trace_id: tracer.id.clone(), query: query.to_string() };
+        (tracer.sink)(RetrievalEvent::Started(event));
+        tracer
+    }
+
+    /// A tracer that reports nowhere, for internal lookups
+    pub fn silent() -> Tracer<'static> {
+        Tracer::new("", |_| {})
+    }
+
+    /// Close the stage running since the previous one
+    pub fn stage(&mut self, stage: &'static str, candidates: usize, detail: Option<String>) {
+        let now = Instant::now();
+        let event = RetrievalStage {
+            trace_id: self.id.clone(),
+            stage,
+            millis: now.duration_since(self.last).as_millis() as u64,
+            candidates,
+            detail,
+        };
+        self.last = now;
+        (self.sink)(RetrievalEvent::Stage(event));
+    }
+
+    pub fn finish(mut self, selected: Vec<SelectedChunk>) {
+        let event = RetrievalFinished {
+            trace_id: self.id.clone(),
+            millis: self.started.elapsed().as_millis() as u64,
+            selected,
+        };
+        (self.sink)(RetrievalEvent::Finished(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracer_event_sequence() {
+        let mut events: Vec<RetrievalEvent> = Vec::new();
+        let mut tracer = Tracer::new("q", |e| events.push(e));
+        tracer.stage("search", 12, None);
+        tracer.finish(Vec::new());
+
+        let names: Vec<&str> = events.iter().map(|e| e.name()).collect();
+        assert_eq!(names, vec!["retrieval:started", "retrieval:stage", "retrieval:finished"]);
+        let json = serde_json::to_value(&events[1]).unwrap();
+        assert_eq!(json["stage"], "search");
+        assert_eq!(json["candidates"], 12);
+    }
+}
@@ -0,0 +1,289 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::extractors;
+use crate::pii_scrubber;
+
+/// A bundled sample document and text its extraction must contain
+struct Sample {
+    extractor: &'static str,
+    filename: &'static str,
+    data: &'static [u8],
+    expected: &'static [&'static str],
+}
+
+/// Carries EXIF (camera, capture date, GPS) and rendered text for OCR
+static SAMPLE_IMAGE: &[u8] = include_bytes!("../selftest/sample.png");
+const SAMPLE_IMAGE_TEXT: &str = "SAMPLE";
+
+static SAMPLES: &[Sample] = &[
+    Sample {
+        extractor: "pdf",
+        filename: "sample.pdf",
+        data: include_bytes!("../selftest/sample.pdf"),
+        expected: &[
+            "[Page 1]",
+            "AGI self-test PDF sample",
+            "| Quarter | Revenue | Growth |\n| --- | --- | --- |\n| Q1 | 1,200 | 4% |",
+        ],
+    },
+    Sample {
+        extractor: "text",
+        filename: "sample.txt",
+        data: include_bytes!("../selftest/sample.txt"),
+        expected: &["AGI self-test text sample", "lazy dog"],
+    },
+    Sample {
+        extractor: "markdown",
+        filename: "sample.md",
+        data: include_bytes!("../selftest/sample.md"),
+        expected: &["# Meeting notes\nFront-matter is stored as metadata, not content."],
+    },
+    Sample {
+        extractor: "csv",
+        filename: "sample.csv",
+        data: include_bytes!("../selftest/sample.csv"),
+        expected: &[
+            "CSV: 4 rows x 5 columns",
+            "- id (integer): min 1, max 4, mean 2.50",
+            "- joined (date): 2019-01-02 .. 2022-07-15",
+            "- score (number): min 79.25, max 91.50, mean 86.25, 1 empty",
+            "3,\"Lin, Mei\",2022-07-15,,true",
+        ],
+    },
+    Sample {
+        extractor: "subtitles",
+        filename: "sample.vtt",
+        data: include_bytes!("../selftest/sample.vtt"),
+        expected: &[
+            "[00:00:01]\nWelcome to the AGI self-test lecture.",
+            "Professor: Captions lose their cue numbers and timings.",
+        ],
+    },
+    Sample {
+        extractor: "latex",
+        filename: "sample.tex",
+        data: include_bytes!("../selftest/sample.tex"),
+        expected: &[
+            "Title: AGI Self-Test Paper",
+            "# Method",
+            "We minimise $L(\\theta)$ as in [smith2020].",
+        ],
+    },
+    Sample {
+        extractor: "bibtex",
+        filename: "sample.bib",
+        data: include_bytes!("../selftest/sample.bib"),
+        expected: &["- [smith2020] Smith, Jane; Doe, John (2020). Deep Things. Journal of Tests (article)"],
+    },
+    Sample {
+        extractor: "code",
+        filename: "sample.py",
+        data: include_bytes!("../selftest/sample.py"),
+        expected: &["def greet (line 1)", "def greet(name):"],
+    },
+    Sample {
+        extractor: "email",
+        filename: "sample.eml",
+        data: include_bytes!("../selftest/sample.eml"),
+        expected: &["Subject: AGI self-test email sample", "exercises the email extractor"],
+    },
+    Sample {
+        extractor: "outlook",
+        filename: "sample.msg",
+        data: include_bytes!("../selftest/sample.msg"),
+        expected: &[
+            "Subject: AGI self-test Outlook sample",
+            "From: Self Test <selftest@example.com>",
+            "Date: 2024-10-01T10:00:00+00:00",
+        ],
+    },
+    Sample {
+        extractor: "image",
+        filename: "sample.png",
+        data: SAMPLE_IMAGE,
+        expected: &[
+            "Dimensions: 558x102",
+            "Captured: 2024-06-01 12:30:00",
+            "Camera: AGI SelfTest Camera",
+            "GPS: 37.774900, -122.419400",
+        ],
+    },
+];
+
+/// Scrubber input and the exact output expected from it
+static SCRUB_CASES: &[(&str, &str, &str)] = &[
+    ("ssn", "My SSN is 123-45-6789", "My SSN is BLOCKED"),
+    ("email", "Email me at john@example.com", "Email me at BLOCKED"),
+    ("phone", "Phone: 555-123-4567", "Phone: BLOCKED"),
+    ("credit_card", "Card 4111 1111 1111 1111 on file", "Card BLOCKED on file"),
+    ("ip_address", "Server at 192.168.1.20 is down", "Server at BLOCKED is down"),
+    ("gps", "GPS: 37.774900, -122.419400", "GPS: BLOCKED"),
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Unavailable,                       // Extractor dependencies missing on this machine
+    Skipped,                           // No bundled sample for this extractor
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelfTestCheck {
+    pub component: String,             // "extractor" or "pii_scrubber"
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+fn check_extractor(info: &extractors::ExtractorInfo, work_dir: &Path) -> SelfTestCheck {
+    let result = |status, detail: String| SelfTestCheck {
+        component: "extractor".to_string(),
+        name: info.name.clone(),
+        status,
+        detail,
+    };
+
+    let Some(sample) = SAMPLES.iter().find(|s| s.extractor == info.name) else {
+        return result(CheckStatus::Skipped, "No bundled sample".to_string());
+    };
+    if !info.available {
+        return result(CheckStatus::Unavailable, "Required system dependency not found".to_string());
+    }
+
+    let path = work_dir.join(sample.filename);
+    if let Err(e) = fs::write(&path, sample.data) {
+        return result(CheckStatus::Fail, format!("Could not write sample: {}", e));
+    }
+    let file_type = Path::new(sample.filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let Some(extractor) = extractors::find(file_type).filter(|e| e.name == info.name) else {
+        return result(CheckStatus::Fail, format!("*.{} is not routed to this extractor", file_type));
+    };
+
+    match (extractor.extract)(&path, file_type) {
+        Ok(extracted) => {
+            let missing: Vec<&str> = sample
+                .expected
+                .iter()
+                .copied()
+                .filter(|needle| !extracted.text.contains(needle))
+                .collect();
+            if missing.is_empty() {
+                result(CheckStatus::Pass, format!("{} chars extracted", extracted.text.len()))
+            } else {
+                result(CheckStatus::Fail, format!("Missing expected text: {:?}", missing))
+            }
+        }
+        Err(e) => result(CheckStatus::Fail, format!("Extraction failed: {}", e)),
+    }
+}
+
+/// OCR is optional inside the image extractor, so it gets its own check
+fn check_ocr(work_dir: &Path) -> SelfTestCheck {
+    let result = |status, detail: String| SelfTestCheck {
+        component: "ocr".to_string(),
+        name: "tesseract".to_string(),
+        status,
+        detail,
+    };
+    if !extractors::ocr::is_available() {
+        return result(CheckStatus::Unavailable, "Required system dependency not found".to_string());
+    }
+
+    let path = work_dir.join("ocr-sample.png");
+    if let Err(e) = fs::write(&path, SAMPLE_IMAGE) {
+        return result(CheckStatus::Fail, format!("Could not write sample: {}", e));
+    }
+    match extractors::ocr::extract_image_text(&path) {
+        Ok(extracted) if extracted.text.contains(SAMPLE_IMAGE_TEXT) => result(
+            CheckStatus::Pass,
+            extracted.note.unwrap_or_else(|| "Text recognized".to_string()),
+        ),
+        Ok(extracted) => result(
+            CheckStatus::Fail,
+            format!("Expected {:?}, got {:?}", SAMPLE_IMAGE_TEXT, extracted.text),
+        ),
+        Err(e) => result(CheckStatus::Fail, format!("OCR failed: {}", e)),
+    }
+}
+
+fn check_scrubber() -> Vec<SelfTestCheck> {
+    SCRUB_CASES
+        .iter()
+        .map(|(name, input, expected)| {
+            let actual = pii_scrubber::scrub_text_string(input);
+            let passed = actual == *expected;
+            SelfTestCheck {
+                component: "pii_scrubber".to_string(),
+                name: name.to_string(),
+                status: if passed { CheckStatus::Pass } else { CheckStatus::Fail },
+                detail: if passed {
+                    "Redacted as expected".to_string()
+                } else {
+                    format!("Expected {:?}, got {:?}", expected, actual)
+                },
+            }
+        })
+        .collect()
+}
+
+/// Run every registered extractor over its bundled sample and the scrubber over known inputs
+pub fn run() -> SelfTestReport {
+    let work_dir = std::env::temp_dir().join(format!("agi-self-test-{}", uuid::Uuid::new_v4()));
+    let mut checks = Vec::new();
+
+    match fs::create_dir_all(&work_dir) {
+        Ok(_) => {
+            for info in extractors::registered() {
+                checks.push(check_extractor(&info, &work_dir));
+            }
+            checks.push(check_ocr(&work_dir));
+            let _ = fs::remove_dir_all(&work_dir);
+        }
+        Err(e) => checks.push(SelfTestCheck {
+            component: "extractor".to_string(),
+            name: "setup".to_string(),
+            status: CheckStatus::Fail,
+            detail: format!("Could not create temp dir {:?}: {}", work_dir, e),
+        }),
+    }
+    checks.extend(check_scrubber());
+
+    SelfTestReport {
+        passed: checks.iter().all(|c| c.status != CheckStatus::Fail),
+        checks,
+    }
+}
+
+#[tauri::command]
+pub async fn run_self_test() -> SelfTestReport {
+    run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_samples_pass() {
+        let report = run();
+        let failures: Vec<_> = report
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Fail)
+            .collect();
+        assert!(failures.is_empty(), "self-test failures: {:?}", failures);
+    }
+}
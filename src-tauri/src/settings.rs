@@ -0,0 +1,571 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::file_storage;
+
+/// Backend settings persisted to `settings.json` in the project root.
+/// Every section defaults so older files keep loading as new settings are added.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub feature_flags: HashMap<String, bool>,
+    #[serde(default)]
+    pub extraction: ExtractionSettings,
+    #[serde(default)]
+    pub embeddings: EmbeddingSettings,
+    #[serde(default)]
+    pub chunking: ChunkingSettings,
+    #[serde(default)]
+    pub rerank: RerankSettings,
+    #[serde(default)]
+    pub retrieval: RetrievalSettings,
+    #[serde(default)]
+    pub pii: PiiSettings,
+    #[serde(default)]
+    pub cloud: CloudSettings,
+    #[serde(default)]
+    pub window: WindowSettings,
+}
+
+/// Limits applied when turning uploads into context text
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExtractionSettings {
+    pub csv_max_chars: usize,          // Size cap for the CSV profile, sample rows included
+    pub csv_sample_rows: usize,        // Rows sampled evenly across the file
+    pub max_extraction_bytes: u64,     // Input read per text/CSV extraction; the rest is skipped
+    pub subtitle_timestamp_every: usize, // Caption lines between "[HH:MM:SS]" markers; 0 = none
+}
+
+impl Default for ExtractionSettings {
+    fn default() -> Self {
+        Self {
+            csv_max_chars: 8000,
+            csv_sample_rows: 20,
+            max_extraction_bytes: 16 * 1024 * 1024,
+            subtitle_timestamp_every: 10,
+        }
+    }
+}
+
+/// Where chunk embeddings are computed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProvider {
+    Local,                             // sentence-transformers model under ./models
+    Api,                               // OpenAI-compatible /embeddings endpoint
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmbeddingSettings {
+    pub provider: EmbeddingProvider,
+    pub local_model: String,           // Directory in ./models with config.json, tokenizer.json, model.safetensors
+    pub api_url: String,
+    pub api_model: String,
+    pub batch_size: usize,             // Chunks per model call / request
+}
+
+impl Default for EmbeddingSettings {
+    fn default() -> Self {
+        Self {
+            provider: EmbeddingProvider::Local,
+            local_model: "all-MiniLM-L6-v2".to_string(),
+            api_url: "https://api.openai.com/v1/embeddings".to_string(),
+            api_model: "text-embedding-3-small".to_string(),
+            batch_size: 32,
+        }
+    }
+}
+
+/// How documents are cut into chunks
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkingStrategy {
+    Structural,                        // Per file type: code definitions, CSV row groups, prose headings and paragraphs
+    SlidingWindow,                     // Fixed-size windows over the words, ignoring structure
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ChunkingSettings {
+    pub strategy: ChunkingStrategy,
+    pub context_tokens: usize,         // Max tokens per chunk when building model context
+    pub embedding_tokens: usize,       // Max tokens per chunk embedded for retrieval
+    pub overlap_tokens: usize,         // Tokens repeated from the end of a chunk at the start of the next
+    pub context_overlap_tokens: usize, // The same for context chunks; by default the 200 words they always shared
+}
+
+impl Default for ChunkingSettings {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkingStrategy::Structural,
+            context_tokens: 2000,
+            embedding_tokens: 256,
+            overlap_tokens: 0,
+            context_overlap_tokens: 260,
+        }
+    }
+}
+
+impl ChunkingSettings {
+    fn validate(&self) -> Result<(), String> {
+        for (name, size) in [("context_tokens", self.context_tokens), ("embedding_tokens", self.embedding_tokens)] {
+            if size == 0 {
+                return Err(format!("{} must be at least 1", name));
+            }
+            if self.overlap_tokens >= size {
+                return Err(format!("overlap_tokens must be smaller than {}", name));
+            }
+        }
+        if self.context_overlap_tokens >= self.context_tokens {
+            return Err("context_overlap_tokens must be smaller than context_tokens".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Optional second pass over retrieval candidates
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RerankProvider {
+    None,
+    Local,                             // BERT cross-encoder under ./models
+    Api,                               // Cohere-compatible /rerank endpoint
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RerankSettings {
+    pub provider: RerankProvider,
+    pub local_model: String,           // Directory in ./models with config.json, tokenizer.json, model.safetensors
+    pub api_url: String,
+    pub api_model: String,
+    pub candidates: usize,             // Retrieved chunks handed to the reranker
+    pub batch_size: usize,             // Question/passage pairs per local model call
+}
+
+impl Default for RerankSettings {
+    fn default() -> Self {
+        Self {
+            provider: RerankProvider::None,
+            local_model: "ms-marco-MiniLM-L-6-v2".to_string(),
+            api_url: "https://api.cohere.com/v2/rerank".to_string(),
+            api_model: "rerank-v3.5".to_string(),
+            candidates: 50,
+            batch_size: 16,
+        }
+    }
+}
+
+/// How a question is widened before keyword search
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryExpansion {
+    Off,
+    Rules,                             // Synonyms, plural/singular forms, identifier parts
+    Llm,                               // Keywords suggested by an OpenAI-compatible chat endpoint
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetrievalSettings {
+    pub query_expansion: QueryExpansion,
+    pub expansion_api_url: String,
+    pub expansion_model: String,
+    pub max_expansions: usize,         // Extra terms added to the keyword query
+}
+
+impl Default for RetrievalSettings {
+    fn default() -> Self {
+        Self {
+            query_expansion: QueryExpansion::Off,
+            expansion_api_url: "https://api.openai.com/v1/chat/completions".to_string(),
+            expansion_model: "gpt-4o-mini".to_string(),
+            max_expansions: 8,
+        }
+    }
+}
+
+/// A user-defined identifier scrubbed alongside the built-in detectors
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CustomPiiPattern {
+    pub name: String,
+    pub regex: String,
+    pub replacement: String,
+}
+
+/// Which built-in detector categories the scrubber runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PiiDetectors {
+    pub personal_ids: bool,            // SSN, driver's license, passport, employee IDs
+    pub locations: bool,               // GPS coordinates
+    pub phone_numbers: bool,
+    pub emails: bool,
+    pub addresses: bool,
+    pub social_handles: bool,
+    pub financial: bool,               // Cards, bank accounts, IBANs, tax IDs
+    pub medical: bool,                 // MRNs, insurance IDs, ICD codes
+    pub dates: bool,                   // Dates, ages, birth years
+    pub network_ids: bool,             // IP and MAC addresses
+    pub urls: bool,                    // URLs and file paths
+    pub device_ids: bool,
+    pub names: bool,
+    pub secrets: bool,                 // API keys, tokens, private keys, high-entropy strings
+}
+
+impl Default for PiiDetectors {
+    fn default() -> Self {
+        Self {
+            personal_ids: true,
+            locations: true,
+            phone_numbers: true,
+            emails: true,
+            addresses: true,
+            social_handles: true,
+            financial: true,
+            medical: true,
+            dates: true,
+            network_ids: true,
+            urls: true,
+            device_ids: true,
+            names: true,
+            secrets: true,
+        }
+    }
+}
+
+/// Optional on-device named entity pass for the names, organizations and places the
+/// regex detectors can't recognize
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NerSettings {
+    pub enabled: bool,
+    pub local_model: String,           // BERT token classifier under ./models
+    pub min_score: f32,                // Sensitivity: lower redacts more, with more false positives
+    pub entity_types: Vec<String>,     // Model labels to redact: PER, ORG, LOC, MISC
+}
+
+impl Default for NerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            local_model: "bert-base-NER".to_string(),
+            min_score: 0.8,
+            entity_types: vec!["PER".to_string(), "ORG".to_string(), "LOC".to_string()],
+        }
+    }
+}
+
+/// Regions whose ID, phone, postcode and tax formats the scrubber recognizes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiLocale {
+    Us,                                // SSN, US phone and ZIP formats, routing numbers, EIN
+    Uk,                                // NI and NHS numbers, UK phones, postcodes
+    Eu,                                // Spaced IBANs, VAT IDs, 00-prefixed phones
+    De,                                // Steuer-ID
+    Fr,                                // NIR
+    Es,                                // DNI, NIE
+    It,                                // Codice fiscale
+    In,                                // Aadhaar, PAN
+    Ca,                                // SIN, postal codes
+}
+
+/// How much scrubbing data gets before it leaves for a destination
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrubPolicy {
+    Full,                              // Custom patterns, NER pass and built-in detectors
+    CustomOnly,                        // The user's custom patterns and denylist, plus credentials
+    Off,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScrubDestinations {
+    pub memory: ScrubPolicy,           // Conversation exports written to ./memory
+    pub aws_upload: ScrubPolicy,       // Files sent by the cloud uploader
+}
+
+impl Default for ScrubDestinations {
+    fn default() -> Self {
+        Self { memory: ScrubPolicy::Full, aws_upload: ScrubPolicy::Full }
+    }
+}
+
+/// Named bundles of PII settings, applied with `set_pii_profile`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiProfile {
+    Off,                               // Nothing is scrubbed
+    #[default]
+    Standard,                          // Every regex detector, everywhere
+    Strict,                            // Plus the NER pass, and uploads that can't be scrubbed are blocked
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PiiSettings {
+    pub profile: PiiProfile,           // Last profile applied; settings may have been tuned since
+    pub detectors: PiiDetectors,
+    pub locales: Vec<PiiLocale>,       // Format sets to detect besides the universal ones
+    pub ner: NerSettings,
+    pub min_confidence: f32,           // Detections scoring below this are flagged, not redacted
+    pub destinations: ScrubDestinations,
+    pub block_unscrubbable_uploads: bool, // Refuse to upload content the scrubber can't read (binary files)
+    pub custom_patterns: Vec<CustomPiiPattern>, // Applied before the built-in detectors
+    pub allowlist: Vec<String>,        // Terms kept even when a detector would redact them
+    pub denylist: Vec<String>,         // Terms always redacted, like custom patterns
+}
+
+impl Default for PiiSettings {
+    fn default() -> Self {
+        Self {
+            profile: PiiProfile::default(),
+            detectors: PiiDetectors::default(),
+            locales: vec![PiiLocale::Us, PiiLocale::Uk, PiiLocale::Eu],
+            ner: NerSettings::default(),
+            min_confidence: 0.0,
+            destinations: ScrubDestinations::default(),
+            block_unscrubbable_uploads: false,
+            custom_patterns: Vec::new(),
+            allowlist: Vec::new(),
+            denylist: Vec::new(),
+        }
+    }
+}
+
+/// What the cloud uploader does beyond sending new files
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CloudSettings {
+    pub keep_remote_copies: bool,      // Deleting or wiping uploads leaves their content in the bucket's copies
+    pub filters: UploadFilters,
+    pub target: CloudTarget,
+}
+
+/// Where backups go, set with configure_cloud_target; each field that is set overrides
+/// config.toml
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct CloudTarget {
+    pub bucket: Option<String>,        // Bucket, or Azure container
+    pub prefix: Option<String>,        // Key root in place of "uploads", without slashes at either end
+    pub region: Option<String>,
+    pub endpoint: Option<String>,      // S3-compatible endpoint; unset for Amazon S3 in `region`
+}
+
+/// Which conversations the cloud uploader sends, and which of their attachments go with them.
+/// A left-out attachment stays in the export, without its content.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UploadFilters {
+    pub include_conversations: Vec<String>, // Ids or titles; empty = every conversation
+    pub exclude_conversations: Vec<String>, // Ids or titles
+    pub include_types: Vec<String>,    // Attachments kept: extensions ("pdf") or MIME types ("image/*"); empty = all
+    pub exclude_types: Vec<String>,    // Attachments left out, e.g. ["video/*"]
+    pub exclude_tags: Vec<String>,     // Attachments whose declared tags include one of these
+    pub max_attachment_mb: Option<u64>, // Larger attachments are left out
+}
+
+/// Which screen the main window is placed on
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorPlacement {
+    #[default]
+    Active,                            // The screen the window is on (the primary one at launch)
+    Cursor,                            // The screen under the mouse when the window is shown
+    Remembered,                        // The screen picked with move_to_monitor
+}
+
+/// The screen edge the main window is docked against
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DockSide {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Where the main window was last, in physical pixels
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,                   // Not restored; the chat sets it at runtime
+    pub monitor_name: Option<String>,  // The screen it was on
+}
+
+/// How the main window is summoned and shown
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub hotkey: Option<String>,        // Global shortcut that shows/hides the window, e.g. "CommandOrControl+Shift+Space"; None = off
+    pub monitor: MonitorPlacement,
+    pub monitor_name: Option<String>,  // The remembered screen, by the name the OS gives it
+    pub geometry: Option<WindowGeometry>, // Restored at launch while it's still on a screen
+    pub dock: Option<DockSide>,        // Edge it was snapped or docked to; None = floating
+    pub content_protected: bool,       // Left out of screen shares, screenshots and recordings
+    pub show_in_dock: bool,            // false = no Dock icon (macOS) or taskbar button; reached by hotkey
+    pub all_workspaces: bool,          // Follows the user across Spaces / virtual desktops
+    pub auto_hide: bool,               // Hide when another app takes focus, like Spotlight
+    pub auto_hide_delay_ms: u64,       // Grace period before hiding, so a stray click can be undone
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            hotkey: Some("CommandOrControl+Shift+Space".to_string()),
+            monitor: MonitorPlacement::default(),
+            monitor_name: None,
+            geometry: None,
+            dock: None,
+            content_protected: false,
+            show_in_dock: true,
+            all_workspaces: true,
+            auto_hide: false,
+            auto_hide_delay_ms: 300,
+        }
+    }
+}
+
+// Serializes read-modify-write cycles across concurrent commands
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        file_storage::project_root().join("settings.json")
+    }
+
+    /// Load settings, falling back to defaults when the file doesn't exist yet
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(Self::path(), content)?;
+        Ok(())
+    }
+
+    /// Apply a change and persist it atomically with respect to other updates
+    pub fn update<F>(f: F) -> Result<Self>
+    where
+        F: FnOnce(&mut Settings),
+    {
+        let _guard = SETTINGS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut settings = Self::load()?;
+        f(&mut settings);
+        settings.save()?;
+        Ok(settings)
+    }
+}
+
+#[tauri::command]
+pub fn get_extraction_settings() -> Result<ExtractionSettings, String> {
+    Settings::load()
+        .map(|s| s.extraction)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+#[tauri::command]
+pub fn set_extraction_settings(extraction: ExtractionSettings) -> Result<ExtractionSettings, String> {
+    Settings::update(|s| s.extraction = extraction)
+        .map(|s| s.extraction)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_embedding_settings() -> Result<EmbeddingSettings, String> {
+    Settings::load()
+        .map(|s| s.embeddings)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+#[tauri::command]
+pub fn set_embedding_settings(embeddings: EmbeddingSettings) -> Result<EmbeddingSettings, String> {
+    Settings::update(|s| s.embeddings = embeddings)
+        .map(|s| s.embeddings)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_chunking_config() -> Result<ChunkingSettings, String> {
+    Settings::load()
+        .map(|s| s.chunking)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+/// Takes effect the next time context is built or a file is re-chunked for indexing
+#[tauri::command]
+pub fn set_chunking_config(chunking: ChunkingSettings) -> Result<ChunkingSettings, String> {
+    chunking.validate()?;
+    Settings::update(|s| s.chunking = chunking)
+        .map(|s| s.chunking)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_rerank_settings() -> Result<RerankSettings, String> {
+    Settings::load()
+        .map(|s| s.rerank)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+#[tauri::command]
+pub fn set_rerank_settings(rerank: RerankSettings) -> Result<RerankSettings, String> {
+    Settings::update(|s| s.rerank = rerank)
+        .map(|s| s.rerank)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_retrieval_settings() -> Result<RetrievalSettings, String> {
+    Settings::load()
+        .map(|s| s.retrieval)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+#[tauri::command]
+pub fn set_retrieval_settings(retrieval: RetrievalSettings) -> Result<RetrievalSettings, String> {
+    Settings::update(|s| s.retrieval = retrieval)
+        .map(|s| s.retrieval)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[tauri::command]
+pub fn get_cloud_settings() -> Result<CloudSettings, String> {
+    Settings::load()
+        .map(|s| s.cloud)
+        .map_err(|e| format!("Failed to load settings: {}", e))
+}
+
+/// Change the fields that are sent; the rest, and the target (see configure_cloud_target),
+/// keep their saved values
+#[tauri::command]
+pub fn set_cloud_settings(keep_remote_copies: Option<bool>, filters: Option<UploadFilters>) -> Result<CloudSettings, String> {
+    Settings::update(|s| {
+        if let Some(keep) = keep_remote_copies {
+            s.cloud.keep_remote_copies = keep;
+        }
+        if let Some(filters) = filters {
+            s.cloud.filters = filters;
+        }
+    })
+        .map(|s| s.cloud)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
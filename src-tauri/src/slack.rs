@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::pii_scrubber::ScrubPass;
+use crate::slack_oauth;
+
+const API: &str = "https://slack.com/api";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_MESSAGES: usize = 50;
+const MAX_MESSAGES_LIMIT: usize = 200;
+// Channels looked through per page when resolving a name
+const CHANNEL_PAGE: usize = 1000;
+
+/// One Slack message, scrubbed with the PII config
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct SlackMessage {
+    pub ts: String,                    // Slack's id for the message, e.g. "1760600000.000100"
+    pub thread_ts: Option<String>,     // Set on thread parents and replies
+    pub time: String,                  // RFC 3339, from `ts`
+    pub user: String,                  // Display name, or the bot's name
+    pub text: String,                  // Mentions and links as plain text
+    pub reply_count: u64,
+    pub redacted: usize,               // PII replacements across the fields
+}
+
+/// Messages of a channel or thread, oldest first
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SlackConversation {
+    pub channel_id: String,
+    pub channel_name: String,
+    pub messages: Vec<SlackMessage>,
+    pub has_more: bool,                // More messages in the range than were returned
+    pub redacted: usize,
+}
+
+// Call a Web API method as the connected user. Slack answers 200 with "ok": false on errors.
+fn call(client: &Client, token: &str, method: &str, params: &[(&str, String)]) -> Result<Value> {
+    let resp = client.get(format!("{}/{}", API, method)).bearer_auth(token).query(params).send()?;
+    if resp.status().as_u16() == 429 {
+        let wait = resp.headers().get("Retry-After").and_then(|v| v.to_str().ok()).unwrap_or("a few");
+        return Err(anyhow!("Slack is rate limiting {}; try again in {} seconds", method, wait));
+    }
+    let body: Value = resp.json()?;
+    if body["ok"].as_bool() == Some(true) {
+        return Ok(body);
+    }
+    match body["error"].as_str() {
+        Some(error @ ("invalid_auth" | "token_revoked" | "account_inactive")) => {
+            Err(anyhow!("Slack authorization is no longer valid ({}); connect Slack again", error))
+        }
+        Some(error) => Err(anyhow!("{} returned {}", method, error)),
+        None => Err(anyhow!("{} failed", method)),
+    }
+}
+
+// Slack epoch timestamps ("1760600000.000100") as RFC 3339
+fn ts_to_rfc3339(ts: &str) -> String {
+    let secs = ts.split('.').next().and_then(|s| s.parse::<i64>().ok()).unwrap_or_default();
+    DateTime::<Utc>::from_timestamp(secs, 0).map(|t| t.to_rfc3339()).unwrap_or_default()
+}
+
+// RFC 3339 as a Slack timestamp, for `oldest` and `latest`
+fn rfc3339_to_ts(time: &str) -> Result<String> {
+    let parsed = DateTime::parse_from_rfc3339(time).map_err(|e| anyhow!("{:?} isn't an RFC 3339 time: {}", time, e))?;
+    Ok(parsed.timestamp().to_string())
+}
+
+// Slack's markup as plain text: <@U1> becomes @name, <#C1|general> #general, <url|label> the
+// label, and &amp; &lt; &gt; their characters
+fn plain_text(text: &str, names: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else { break };
+        out.push_str(&rest[..start]);
+        let inner = &rest[start + 1..start + len];
+        let (target, label) = inner.split_once('|').unwrap_or((inner, ""));
+        if let Some(user) = target.strip_prefix('@') {
+            out.push('@');
+            out.push_str(if label.is_empty() { names.get(user).map_or(user, String::as_str) } else { label });
+        } else if let Some(channel) = target.strip_prefix('#') {
+            out.push('#');
+            out.push_str(if label.is_empty() { channel } else { label });
+        } else if let Some(special) = target.strip_prefix('!') {
+            // <!here>, <!channel>, <!subteam^S1|@team>
+            if label.is_empty() {
+                out.push('@');
+                out.push_str(special);
+            } else {
+                out.push_str(label);
+            }
+        } else {
+            out.push_str(if label.is_empty() { target } else { label });
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+// User ids a message mentions or was sent by
+fn user_ids(v: &Value) -> Vec<String> {
+    let mut ids: Vec<String> = v["user"].as_str().map(|u| vec![u.to_string()]).unwrap_or_default();
+    let text = v["text"].as_str().unwrap_or_default();
+    for part in text.split("<@").skip(1) {
+        if let Some(id) = part.split(['>', '|']).next() {
+            ids.push(id.to_string());
+        }
+    }
+    ids
+}
+
+fn parse_message(v: &Value, names: &HashMap<String, String>) -> SlackMessage {
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let ts = text(&v["ts"]);
+    let user = match v["user"].as_str() {
+        Some(id) => names.get(id).cloned().unwrap_or_else(|| id.to_string()),
+        None => v["username"].as_str().or(v["bot_profile"]["name"].as_str()).unwrap_or_default().to_string(),
+    };
+    SlackMessage {
+        time: ts_to_rfc3339(&ts),
+        thread_ts: v["thread_ts"].as_str().map(|t| t.to_string()),
+        ts,
+        user,
+        text: plain_text(v["text"].as_str().unwrap_or_default(), names),
+        reply_count: v["reply_count"].as_u64().unwrap_or_default(),
+        redacted: 0,
+    }
+}
+
+// A channel id ("C0123ABCD") as given, or a name ("#incident-42") looked up among the
+// channels the user can see
+fn resolve_channel(client: &Client, token: &str, channel: &str) -> Result<(String, String)> {
+    let channel = channel.trim();
+    let is_id = channel.len() >= 9
+        && channel.starts_with(['C', 'G', 'D'])
+        && channel.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    if is_id {
+        let info = call(client, token, "conversations.info", &[("channel", channel.to_string())])?;
+        return Ok((channel.to_string(), info["channel"]["name"].as_str().unwrap_or(channel).to_string()));
+    }
+    let name = channel.trim_start_matches('#').to_lowercase();
+    let mut cursor = String::new();
+    loop {
+        let mut params = vec![
+            ("types", "public_channel,private_channel".to_string()),
+            ("limit", CHANNEL_PAGE.to_string()),
+        ];
+        if !cursor.is_empty() {
+            params.push(("cursor", cursor.clone()));
+        }
+        let page = call(client, token, "conversations.list", &params)?;
+        let found = page["channels"]
+            .as_array()
+            .and_then(|cs| cs.iter().find(|c| c["name"].as_str() == Some(name.as_str())));
+        if let Some(c) = found {
+            return Ok((c["id"].as_str().unwrap_or_default().to_string(), name));
+        }
+        cursor = page["response_metadata"]["next_cursor"].as_str().unwrap_or_default().to_string();
+        if cursor.is_empty() {
+            return Err(anyhow!("No channel named #{} that you can see", name));
+        }
+    }
+}
+
+// Display names of the users behind `messages`, one users.info call each
+fn user_names(client: &Client, token: &str, messages: &[Value]) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    for id in messages.iter().flat_map(user_ids) {
+        if names.contains_key(&id) {
+            continue;
+        }
+        let name = match call(client, token, "users.info", &[("user", id.clone())]) {
+            Ok(info) => {
+                let profile = &info["user"]["profile"];
+                [&profile["display_name"], &profile["real_name"], &info["user"]["name"]]
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .find(|n| !n.is_empty())
+                    .unwrap_or(&id)
+                    .to_string()
+            }
+            Err(e) => {
+                eprintln!("[Slack] Couldn't look up {}: {}", id, e);
+                id.clone()
+            }
+        };
+        names.insert(id, name);
+    }
+    names
+}
+
+// Parse and scrub a page of raw messages, oldest first
+fn conversation(app: &AppHandle, client: &Client, token: &str, source: &str, id: String, name: String, page: &Value) -> SlackConversation {
+    let raw: Vec<Value> = page["messages"].as_array().cloned().unwrap_or_default();
+    let names = user_names(client, token, &raw);
+    let mut pass = ScrubPass::new();
+    let mut messages: Vec<SlackMessage> = raw
+        .iter()
+        .map(|m| {
+            let message = parse_message(m, &names);
+            let before = pass.redacted();
+            let mut message = SlackMessage { user: pass.text(&message.user), text: pass.text(&message.text), ..message };
+            message.redacted = pass.redacted() - before;
+            message
+        })
+        .collect();
+    // Replies come oldest first, history newest first
+    messages.sort_by(|a, b| a.ts.cmp(&b.ts));
+    let redacted = pass.finish(app, source);
+    SlackConversation { channel_id: id, channel_name: name, messages, has_more: page["has_more"].as_bool().unwrap_or(false), redacted }
+}
+
+/// Recent messages in a channel, optionally between `oldest` and `latest` (RFC 3339)
+pub fn channel_messages(app: &AppHandle, channel: &str, oldest: Option<&str>, latest: Option<&str>, limit: usize) -> Result<SlackConversation> {
+    let token = slack_oauth::access_token()?;
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let (id, name) = resolve_channel(&client, &token, channel)?;
+    let mut params = vec![("channel", id.clone()), ("limit", limit.to_string()), ("inclusive", "true".to_string())];
+    if let Some(oldest) = oldest {
+        params.push(("oldest", rfc3339_to_ts(oldest)?));
+    }
+    if let Some(latest) = latest {
+        params.push(("latest", rfc3339_to_ts(latest)?));
+    }
+    let page = call(&client, &token, "conversations.history", &params)?;
+    let history = conversation(app, &client, &token, "get_slack_channel_messages", id, name, &page);
+    println!("[Slack] #{}: {} message(s)", history.channel_name, history.messages.len());
+    Ok(history)
+}
+
+/// A thread: its parent message and the replies
+pub fn thread_messages(app: &AppHandle, channel: &str, thread_ts: &str, limit: usize) -> Result<SlackConversation> {
+    let token = slack_oauth::access_token()?;
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let (id, name) = resolve_channel(&client, &token, channel)?;
+    let params = [("channel", id.clone()), ("ts", thread_ts.trim().to_string()), ("limit", limit.to_string())];
+    let page = call(&client, &token, "conversations.replies", &params)?;
+    let thread = conversation(app, &client, &token, "get_slack_thread", id, name, &page);
+    println!("[Slack] Thread {} in #{}: {} message(s)", thread_ts, thread.channel_name, thread.messages.len());
+    Ok(thread)
+}
+
+/// Messages of a channel (by name like "#incident-42" or id), scrubbed; `oldest` and `latest`
+/// narrow it to a time range
+#[tauri::command]
+pub async fn get_slack_channel_messages(
+    app: AppHandle,
+    channel: String,
+    oldest: Option<String>,
+    latest: Option<String>,
+    max_results: Option<usize>,
+) -> Result<SlackConversation, String> {
+    let limit = max_results.unwrap_or(DEFAULT_MAX_MESSAGES).clamp(1, MAX_MESSAGES_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        channel_messages(&app, &channel, oldest.as_deref(), latest.as_deref(), limit)
+            .map_err(|e| format!("Failed to get Slack messages: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Slack task failed: {}", e))?
+}
+
+/// A Slack thread, by its parent's `ts`, scrubbed
+#[tauri::command]
+pub async fn get_slack_thread(
+    app: AppHandle,
+    channel: String,
+    thread_ts: String,
+    max_results: Option<usize>,
+) -> Result<SlackConversation, String> {
+    let limit = max_results.unwrap_or(MAX_MESSAGES_LIMIT).clamp(1, MAX_MESSAGES_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        thread_messages(&app, &channel, &thread_ts, limit).map_err(|e| format!("Failed to get Slack thread: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Slack task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_markup_and_messages() {
+        let names = HashMap::from([("U1".to_string(), "anna".to_string())]);
+        assert_eq!(
+            plain_text("<@U1> see <#C9|incident-42> &amp; <https://status.example.com|status> <!here>", &names),
+            "@anna see #incident-42 & status @here"
+        );
+        assert_eq!(plain_text("<@U2> 1 &lt; 2", &names), "@U2 1 < 2");
+
+        let raw = json!({
+            "type": "message", "user": "U1", "text": "Rolled back, cc <@U2|sam>",
+            "ts": "1760600000.000100", "thread_ts": "1760600000.000100", "reply_count": 3
+        });
+        assert_eq!(user_ids(&raw), vec!["U1", "U2"]);
+        let message = parse_message(&raw, &names);
+        assert_eq!((message.user.as_str(), message.text.as_str(), message.reply_count), ("anna", "Rolled back, cc @sam", 3));
+        assert_eq!(message.time, "2025-10-16T07:33:20+00:00");
+        let bot = parse_message(&json!({ "bot_profile": { "name": "PagerDuty" }, "text": "Triggered", "ts": "1.0" }), &names);
+        assert_eq!(bot.user, "PagerDuty");
+
+        assert_eq!(rfc3339_to_ts("2025-10-16T07:33:20Z").unwrap(), "1760600000");
+        assert!(rfc3339_to_ts("yesterday").is_err());
+    }
+}
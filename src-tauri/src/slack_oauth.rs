@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use crate::connections::{rfc3339, ConnectionStatus};
+use crate::google_oauth::{load_dotenv, load_env, now_ms, open_in_browser, redirect_path, redirect_port, wait_for_redirect};
+use crate::keychain;
+
+// Keychain entry holding the token as JSON
+const KEYCHAIN_SERVICE: &str = "agi-slack-oauth";
+const KEYCHAIN_ACCOUNT: &str = "tokens";
+
+const AUTHORIZE_ENDPOINT: &str = "https://slack.com/oauth/v2/authorize";
+const TOKEN_ENDPOINT: &str = "https://slack.com/api/oauth.v2.access";
+const REVOKE_ENDPOINT: &str = "https://slack.com/api/auth.revoke";
+// Read-only user scopes: public and private channels the user is in, and member names
+const USER_SCOPES: &str = "channels:read channels:history groups:read groups:history users:read";
+// Slack matches redirect URIs against the app's list, so this one must be registered unless
+// SLACK_REDIRECT_URI names another
+const DEFAULT_REDIRECT_URI: &str = "http://localhost:53684/";
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// A user token; Slack issues them without expiry unless the app turns on token rotation
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SlackTokens {
+    access_token: String,
+    user_id: String,
+    team_id: String,
+    team_name: String,
+    scope: String,
+    obtained_at_ms: u128,
+}
+
+fn redirect_uri() -> String {
+    std::env::var("SLACK_REDIRECT_URI").ok().filter(|u| !u.trim().is_empty()).unwrap_or_else(|| DEFAULT_REDIRECT_URI.to_string())
+}
+
+fn authorize_url(client_id: &str, redirect_uri: &str, state: &str) -> String {
+    format!(
+        "{}?client_id={}&user_scope={}&redirect_uri={}&state={}",
+        AUTHORIZE_ENDPOINT,
+        urlencoding::encode(client_id),
+        urlencoding::encode(USER_SCOPES),
+        urlencoding::encode(redirect_uri),
+        state
+    )
+}
+
+// oauth.v2.access answers 200 either way; the user token sits under "authed_user"
+fn from_response(resp: &Value, now_ms: u128) -> Result<SlackTokens> {
+    if resp["ok"].as_bool() != Some(true) {
+        return Err(anyhow!("Slack returned {}", resp["error"].as_str().unwrap_or("an error")));
+    }
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let user = &resp["authed_user"];
+    let access_token = text(&user["access_token"]);
+    if access_token.is_empty() {
+        return Err(anyhow!("Slack didn't issue a user token"));
+    }
+    Ok(SlackTokens {
+        access_token,
+        user_id: text(&user["id"]),
+        team_id: text(&resp["team"]["id"]),
+        team_name: text(&resp["team"]["name"]),
+        scope: text(&user["scope"]),
+        obtained_at_ms: now_ms,
+    })
+}
+
+fn load_tokens() -> Result<Option<SlackTokens>> {
+    match keychain::get(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)? {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+fn store_tokens(tokens: &SlackTokens) -> Result<()> {
+    keychain::set(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT, &serde_json::to_string(tokens)?)
+}
+
+/// The connected user's Slack token
+pub(crate) fn access_token() -> Result<String> {
+    let tokens = load_tokens()?.ok_or_else(|| anyhow!("Slack is not connected"))?;
+    Ok(tokens.access_token)
+}
+
+fn connect() -> Result<()> {
+    load_dotenv("SlackConnect");
+    let client_id = load_env("SLACK_CLIENT_ID")?;
+    let client_secret = load_env("SLACK_CLIENT_SECRET")?;
+
+    let redirect_uri = redirect_uri();
+    let port = redirect_port(&redirect_uri).ok_or_else(|| anyhow!("SLACK_REDIRECT_URI needs a port: {}", redirect_uri))?;
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+
+    println!("[Slack][Connect] Opening browser for sign-in (redirect {})", redirect_uri);
+    open_in_browser(&authorize_url(&client_id, &redirect_uri, &state))?;
+    let code = wait_for_redirect(&listener, redirect_path(&redirect_uri), &state, AUTH_TIMEOUT)?;
+    drop(listener);
+
+    let form = [
+        ("client_id", client_id.as_str()),
+        ("client_secret", client_secret.as_str()),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+    ];
+    let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let resp: Value = client.post(TOKEN_ENDPOINT).form(&form).send()?.json()?;
+    let tokens = from_response(&resp, now_ms()).map_err(|e| anyhow!("Token exchange failed: {}", e))?;
+    println!(
+        "[Slack][Connect] Token received for workspace {} (access: {} chars, scopes: {})",
+        tokens.team_name,
+        tokens.access_token.len(),
+        tokens.scope
+    );
+    store_tokens(&tokens)
+}
+
+/// Sign in to Slack in the browser (loopback redirect) and keep the user token in the
+/// keychain. Needs SLACK_CLIENT_ID and SLACK_CLIENT_SECRET; SLACK_REDIRECT_URI is optional.
+#[tauri::command]
+pub async fn connect_slack() -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(|| connect().map_err(|e| format!("Failed to connect Slack: {}", e)))
+        .await
+        .map_err(|e| format!("Connect task failed: {}", e))??;
+    Ok("Slack connected successfully".to_string())
+}
+
+/// Revoke the Slack token and forget it
+#[tauri::command]
+pub async fn disconnect_slack() -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        if let Ok(token) = access_token() {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+            match client.post(REVOKE_ENDPOINT).bearer_auth(token).send().and_then(|r| r.json::<Value>()) {
+                Ok(r) if r["ok"].as_bool() == Some(true) => println!("[Slack][Disconnect] Token revoked"),
+                Ok(r) => eprintln!("[Slack][Disconnect] Revoke returned {}", r["error"]),
+                Err(e) => eprintln!("[Slack][Disconnect] Revoke failed: {}", e),
+            }
+        }
+        keychain::delete(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(|e| format!("Failed to remove Slack token: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Disconnect task failed: {}", e))??;
+    println!("[Slack][Disconnect] Removed stored token");
+    Ok("Disconnected from Slack".to_string())
+}
+
+/// The stored Slack connection, for get_connection_status. User tokens don't expire, and
+/// the email would need another scope, so both stay empty.
+pub(crate) fn connection_status() -> Result<ConnectionStatus> {
+    let Some(tokens) = load_tokens()? else { return Ok(ConnectionStatus::disconnected("slack")) };
+    Ok(ConnectionStatus {
+        connected: true,
+        scopes: tokens.scope.split(',').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect(),
+        last_refresh: rfc3339(tokens.obtained_at_ms),
+        ..ConnectionStatus::disconnected("slack")
+    })
+}
+
+#[tauri::command]
+pub fn is_slack_connected() -> Result<bool, String> {
+    match load_tokens() {
+        Ok(tokens) => Ok(tokens.is_some()),
+        Err(e) => {
+            eprintln!("[Slack][Status] Failed to load token: {}", e);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_authorize_url_and_user_token() {
+        let url = authorize_url("123.456", DEFAULT_REDIRECT_URI, "s1");
+        assert!(url.starts_with(AUTHORIZE_ENDPOINT));
+        assert!(url.contains("user_scope=channels%3Aread%20channels%3Ahistory"));
+        assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A53684%2F&state=s1"));
+
+        let tokens = from_response(
+            &json!({
+                "ok": true,
+                "team": { "id": "T1", "name": "Acme" },
+                "authed_user": { "id": "U1", "scope": USER_SCOPES, "access_token": "xoxp-1", "token_type": "user" }
+            }),
+            7,
+        )
+        .unwrap();
+        assert_eq!((tokens.access_token.as_str(), tokens.team_name.as_str(), tokens.user_id.as_str()), ("xoxp-1", "Acme", "U1"));
+        assert!(from_response(&json!({ "ok": false, "error": "invalid_code" }), 0).is_err());
+        assert!(from_response(&json!({ "ok": true, "access_token": "xoxb-bot" }), 0).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::{multipart, Client};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::file_storage::{FileInfo, FileStorage};
+use crate::notifications::{self, Notification};
+
+/// Video and audio types whose audio track can be transcribed
+pub const MEDIA_TYPES: &[&str] = &[
+    "mp4", "mov", "mkv", "webm", "avi", "wmv", "flv", "m4v", "mp3", "wav", "m4a", "flac", "aac",
+    "ogg",
+];
+
+/// Whisper accepts at most 25 MB per request; 10 min of 16 kHz mono PCM is ~19 MB
+const SEGMENT_SECS: u64 = 600;
+const WHISPER_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+
+/// ffmpeg binary; override with FFMPEG_CMD when it isn't on PATH
+pub fn ffmpeg_cmd() -> String {
+    std::env::var("FFMPEG_CMD").unwrap_or_else(|_| "ffmpeg".to_string())
+}
+
+/// Whether the audio decoder is installed
+pub fn is_available() -> bool {
+    // ffmpeg uses -version rather than --version
+    Command::new(ffmpeg_cmd())
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperResponse {
+    #[serde(default)]
+    segments: Vec<WhisperSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperSegment {
+    start: f64,                        // Seconds from the start of the submitted audio
+    text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct TranscriptionProgress {
+    file_id: String,
+    segment: usize,
+    total: usize,
+}
+
+/// Decode the audio track to 16 kHz mono WAV pieces of SEGMENT_SECS each
+fn extract_audio_segments(media: &Path, work_dir: &Path) -> Result<Vec<PathBuf>> {
+    let output = Command::new(ffmpeg_cmd())
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(media)
+        .args(["-vn", "-ac", "1", "-ar", "16000", "-c:a", "pcm_s16le"])
+        .args(["-f", "segment", "-segment_time", &SEGMENT_SECS.to_string()])
+        .arg(work_dir.join("part_%03d.wav"))
+        .stdin(Stdio::null())
+        .output()
+        .context("Audio decoder (ffmpeg) is not installed")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("does not contain any stream") {
+            return Err(anyhow!("File has no audio track"));
+        }
+        return Err(anyhow!("ffmpeg failed: {}", stderr.trim()));
+    }
+
+    let mut parts: Vec<PathBuf> = fs::read_dir(work_dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("wav"))
+        .collect();
+    parts.sort();
+    Ok(parts)
+}
+
+fn transcribe_segment(client: &Client, api_key: &str, wav: &Path) -> Result<Vec<WhisperSegment>> {
+    let form = multipart::Form::new()
+        .text("model", "whisper-1")
+        .text("response_format", "verbose_json")
+        .text("timestamp_granularities[]", "segment")
+        .file("file", wav)?;
+    let resp = client
+        .post(WHISPER_URL)
+        .bearer_auth(api_key)
+        .multipart(form)
+        .send()?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        return Err(anyhow!("Transcription API error {}: {}", status, resp.text().unwrap_or_default()));
+    }
+    Ok(resp.json::<WhisperResponse>()?.segments)
+}
+
+/// "[HH:MM:SS]" for an offset in seconds
+pub fn format_timecode(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    format!("[{:02}:{:02}:{:02}]", total / 3600, total % 3600 / 60, total % 60)
+}
+
+/// Transcribe a media file into timecoded lines, reporting progress per audio segment
+fn transcribe_media(
+    media: &Path,
+    api_key: &str,
+    mut on_segment: impl FnMut(usize, usize),
+) -> Result<String> {
+    let work_dir = std::env::temp_dir().join(format!("agi-transcribe-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work_dir)?;
+    let result = (|| {
+        let parts = extract_audio_segments(media, &work_dir)?;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .context("building http client")?;
+
+        let mut lines = Vec::new();
+        for (i, part) in parts.iter().enumerate() {
+            on_segment(i + 1, parts.len());
+            let offset = (i as u64 * SEGMENT_SECS) as f64;
+            for seg in transcribe_segment(&client, api_key, part)? {
+                let text = seg.text.trim();
+                if !text.is_empty() {
+                    lines.push(format!("{} {}", format_timecode(offset + seg.start), text));
+                }
+            }
+        }
+        Ok(lines.join("\n"))
+    })();
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+/// Transcribe the audio track of an uploaded video or audio file and store the
+/// timecoded transcript as the file's content. Emits "transcription:progress".
+#[tauri::command]
+pub async fn transcribe_uploaded_file(
+    app: tauri::AppHandle,
+    file_id: String,
+    api_key: Option<String>,
+) -> Result<FileInfo, String> {
+    let api_key = api_key
+        .filter(|k| !k.trim().is_empty())
+        .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+        .ok_or_else(|| "An OpenAI API key is required for transcription".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = FileStorage::new()
+            .map_err(|e| format!("Failed to initialize file storage: {}", e))?;
+        let file = storage
+            .get_file(&file_id)
+            .map_err(|e| format!("Failed to load file: {}", e))?;
+        if !MEDIA_TYPES.contains(&file.file_type.as_str()) {
+            return Err(format!("Cannot transcribe .{} files", file.file_type));
+        }
+
+        println!("[Transcription] Transcribing '{}' ({})", file.name, file_id);
+        let transcript = transcribe_media(&storage.blob_path(&file_id), &api_key, |segment, total| {
+            let _ = app.emit(
+                "transcription:progress",
+                TranscriptionProgress { file_id: file_id.clone(), segment, total },
+            );
+        })
+        .map_err(|e| format!("Failed to transcribe file: {}", e))?;
+
+        let file = storage
+            .set_transcript(&file_id, transcript)
+            .map_err(|e| format!("Failed to save transcript: {}", e))?;
+        notifications::notify(
+            &app,
+            Notification::new("Transcription complete", format!("{} is ready to search and chat about", file.name))
+                .action("Open transcript", &format!("files/{}", file.id)),
+        );
+        Ok(file)
+    })
+    .await
+    .map_err(|e| format!("Transcription task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timecode() {
+        assert_eq!(format_timecode(0.0), "[00:00:00]");
+        assert_eq!(format_timecode(83.6), "[00:01:23]");
+        assert_eq!(format_timecode(3725.0), "[01:02:05]");
+    }
+}
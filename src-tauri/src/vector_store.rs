@@ -0,0 +1,674 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use rusqlite::{ffi, params, Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, Once};
+use std::time::Duration;
+
+use crate::chunking::Provenance;
+use crate::embeddings::FileEmbeddings;
+
+type ExtensionInit =
+    unsafe extern "C" fn(*mut ffi::sqlite3, *mut *mut c_char, *const ffi::sqlite3_api_routines) -> c_int;
+
+static REGISTER_VEC: Once = Once::new();
+
+// Uploads, toggles and conversation exports index on background threads; one at a time,
+// so two of them can't interleave a document's rows or both act on a model switch
+static INDEXING: Mutex<()> = Mutex::new(());
+
+/// Hold while embedding documents and writing them to the index
+pub fn indexing() -> MutexGuard<'static, ()> {
+    INDEXING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Make sqlite-vec's `vec0` tables available on every connection opened afterwards
+fn register_sqlite_vec() {
+    REGISTER_VEC.call_once(|| unsafe {
+        // The extern declaration in sqlite-vec omits the entry point's arguments; the
+        // symbol is a regular SQLite extension init function
+        let init = std::mem::transmute::<*const (), ExtensionInit>(sqlite_vec::sqlite3_vec_init as *const ());
+        ffi::sqlite3_auto_extension(Some(init));
+    });
+}
+
+/// Separate sets of documents sharing one index (and embedding model)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namespace {
+    Files,                             // Uploaded files, by file id
+    Conversations,                     // Past conversation transcripts, by conversation id
+}
+
+impl Namespace {
+    fn as_str(self) -> &'static str {
+        match self {
+            Namespace::Files => "files",
+            Namespace::Conversations => "conversations",
+        }
+    }
+}
+
+/// A chunk as stored in the index
+#[derive(Debug, Serialize, Clone)]
+pub struct StoredChunk {
+    pub file_id: String,
+    pub chunk_index: usize,
+    pub heading: Option<String>,
+    pub text: String,
+    pub tokens: usize,
+    pub source: Provenance,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ScoredChunk {
+    pub chunk: StoredChunk,
+    pub score: f32,                    // Cosine similarity, -BM25, or fused RRF score depending on the search
+}
+
+/// What an upsert changed, in chunks
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct IndexUpdate {
+    pub added: usize,
+    pub kept: usize,                   // Unchanged content, vector reused
+    pub removed: usize,
+}
+
+/// Vectors kept in the embedding cache; the least recently used go first. About 150 MB
+/// for a 384-dimension model.
+const MAX_CACHED_VECTORS: i64 = 100_000;
+
+/// Rank constant of reciprocal rank fusion; 60 is the usual choice from the original paper
+const RRF_K: f32 = 60.0;
+
+/// Turn free text into an FTS5 query that matches any of its words. Each word is quoted,
+/// so identifiers like "INV-2024-0042" match as a phrase and FTS syntax in the question
+/// can't break the query.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+/// Merge ranked lists with reciprocal rank fusion: each chunk scores the sum of
+/// 1 / (RRF_K + rank) over the lists it appears in
+fn fuse(lists: &[Vec<ScoredChunk>], limit: usize) -> Vec<ScoredChunk> {
+    let mut fused: HashMap<(String, usize), ScoredChunk> = HashMap::new();
+    for list in lists {
+        for (rank, hit) in list.iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            fused
+                .entry((hit.chunk.file_id.clone(), hit.chunk.chunk_index))
+                .or_insert_with(|| ScoredChunk { chunk: hit.chunk.clone(), score: 0.0 })
+                .score += score;
+        }
+    }
+    let mut hits: Vec<ScoredChunk> = fused.into_values().collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    hits
+}
+
+/// Chunk embeddings in uploads/vectors.db: chunk rows in `chunks`, vectors in a sqlite-vec
+/// `vec0` table sharing their rowid and partitioned by namespace, so context-enabled files
+/// and conversation history never show up in each other's searches. All vectors come from
+/// one embedding model; switching models keeps the chunks, marked stale until
+/// `embeddings::reembed_stale` gives them vectors from the new one.
+pub struct VectorStore {
+    conn: Connection,
+}
+
+fn vector_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+fn blob_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()
+}
+
+impl VectorStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        register_sqlite_vec();
+        let conn = Connection::open(path)?;
+        // Uploads index in the background; let concurrent writers wait their turn
+        conn.busy_timeout(Duration::from_secs(30))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS chunks (
+                 id INTEGER PRIMARY KEY,
+                 file_id TEXT NOT NULL,
+                 chunk_index INTEGER NOT NULL,
+                 heading TEXT,
+                 text TEXT NOT NULL,
+                 tokens INTEGER NOT NULL,
+                 hash TEXT NOT NULL DEFAULT '',
+                 source TEXT NOT NULL DEFAULT '{}',
+                 namespace TEXT NOT NULL DEFAULT 'files',
+                 stale INTEGER NOT NULL DEFAULT 0
+             );
+             CREATE INDEX IF NOT EXISTS chunks_file ON chunks(file_id);
+             CREATE TABLE IF NOT EXISTS embedding_cache (
+                 hash TEXT NOT NULL,
+                 model TEXT NOT NULL,
+                 vector BLOB NOT NULL,
+                 used INTEGER NOT NULL,
+                 PRIMARY KEY (hash, model)
+             );",
+        )?;
+        // Columns added since the first index version. Old rows have no hash, so they never
+        // match and get replaced on the file's next indexing; their provenance is empty.
+        for (column, definition) in [
+            ("hash", "TEXT NOT NULL DEFAULT ''"),
+            ("source", "TEXT NOT NULL DEFAULT '{}'"),
+            ("namespace", "TEXT NOT NULL DEFAULT 'files'"),
+            ("stale", "INTEGER NOT NULL DEFAULT 0"),
+        ] {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info('chunks') WHERE name = ?1)",
+                [column],
+                |r| r.get(0),
+            )?;
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE chunks ADD COLUMN {} {}", column, definition))?;
+            }
+        }
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS chunks_namespace ON chunks(namespace, file_id)")?;
+
+        // Vector tables from before namespaces have no partition key; vec0 can't be altered,
+        // so the vectors (all of uploaded files then) move into a new table
+        let vec_sql: Option<String> = conn
+            .query_row("SELECT sql FROM sqlite_master WHERE name = 'vec_chunks'", [], |r| r.get(0))
+            .optional()?;
+        if vec_sql.is_some_and(|sql| !sql.contains("partition key")) {
+            let dimensions: Option<String> = conn
+                .query_row("SELECT value FROM meta WHERE key = 'dimensions'", [], |r| r.get(0))
+                .optional()?;
+            println!("[VectorStore] Adding namespaces to the vector table");
+            match dimensions {
+                Some(dimensions) => conn.execute_batch(&format!(
+                    "CREATE TEMP TABLE vec_backup AS SELECT rowid AS id, embedding FROM vec_chunks;
+                     DROP TABLE vec_chunks;
+                     {};
+                     INSERT INTO vec_chunks (rowid, namespace, embedding) SELECT id, 'files', embedding FROM vec_backup;
+                     DROP TABLE vec_backup;",
+                    Self::vec_table(&dimensions)
+                ))?,
+                // Unknown size: start over, files get indexed again on their next sync
+                None => conn.execute_batch("DROP TABLE vec_chunks; DELETE FROM chunks; DELETE FROM meta;")?,
+            }
+        }
+
+        // Keyword index over the same rows, kept in step by triggers
+        let has_fts: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'chunks_fts')",
+            [],
+            |r| r.get(0),
+        )?;
+        if !has_fts {
+            conn.execute_batch(
+                "CREATE VIRTUAL TABLE chunks_fts USING fts5(text, heading, content='chunks', content_rowid='id');
+                 CREATE TRIGGER chunks_fts_insert AFTER INSERT ON chunks BEGIN
+                     INSERT INTO chunks_fts (rowid, text, heading) VALUES (new.id, new.text, new.heading);
+                 END;
+                 CREATE TRIGGER chunks_fts_delete AFTER DELETE ON chunks BEGIN
+                     INSERT INTO chunks_fts (chunks_fts, rowid, text, heading) VALUES ('delete', old.id, old.text, old.heading);
+                 END;
+                 INSERT INTO chunks_fts (chunks_fts) VALUES ('rebuild');",
+            )?;
+        }
+        Ok(Self { conn })
+    }
+
+    fn vec_table(dimensions: &str) -> String {
+        format!(
+            "CREATE VIRTUAL TABLE vec_chunks USING vec0(namespace text partition key, embedding float[{}] distance_metric=cosine)",
+            dimensions
+        )
+    }
+
+    fn meta(conn: &Connection, key: &str) -> Result<Option<String>> {
+        Ok(conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get(0)).optional()?)
+    }
+
+    /// Embedding model whose vectors the index holds, e.g. "local:all-MiniLM-L6-v2"
+    pub fn model(&self) -> Result<Option<String>> {
+        Self::meta(&self.conn, "model")
+    }
+
+    /// Recreate the vector table when the model (and so the vector space) changes. The
+    /// chunks stay, for keyword search and to be embedded again, but are marked stale.
+    /// Runs in the caller's transaction, so the check and the switch happen together.
+    fn ensure_model(conn: &Connection, model: &str, dimensions: usize) -> Result<()> {
+        let current = (Self::meta(conn, "model")?, Self::meta(conn, "dimensions")?);
+        if current == (Some(model.to_string()), Some(dimensions.to_string())) {
+            return Ok(());
+        }
+        println!("[VectorStore] Switching index to {} ({} dimensions)", model, dimensions);
+        conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS vec_chunks;
+             {};
+             UPDATE chunks SET stale = 1;",
+            Self::vec_table(&dimensions.to_string())
+        ))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('model', ?1), ('dimensions', ?2)",
+            params![model, dimensions.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Chunks left without a vector by a model switch, as (id, namespace, text)
+    pub fn stale_chunks(&self) -> Result<Vec<(i64, String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT id, namespace, text FROM chunks WHERE stale = 1 ORDER BY id")?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Store new vectors for stale chunks; they must come from the index's model
+    pub fn restore_vectors(&mut self, model: &str, vectors: &[(i64, &str, &[f32])]) -> Result<()> {
+        let tx = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let indexed = Self::meta(&tx, "model")?;
+        if indexed.as_deref() != Some(model) {
+            return Err(anyhow!("Index now holds {:?} vectors, not {}", indexed, model));
+        }
+        for (id, namespace, vector) in vectors {
+            tx.execute(
+                "INSERT INTO vec_chunks (rowid, namespace, embedding) VALUES (?1, ?2, ?3)",
+                params![id, namespace, vector_blob(vector)],
+            )?;
+            tx.execute("UPDATE chunks SET stale = 0 WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stored vectors of a document's chunks by content hash, if they come from `model`
+    pub fn vectors_for(&self, namespace: Namespace, file_id: &str, model: &str) -> Result<HashMap<String, Vec<f32>>> {
+        if self.model()?.as_deref() != Some(model) {
+            return Ok(HashMap::new());
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT c.hash, v.embedding FROM chunks c JOIN vec_chunks v ON v.rowid = c.id
+             WHERE c.namespace = ?1 AND c.file_id = ?2 AND c.hash != ''",
+        )?;
+        let rows = stmt.query_map([namespace.as_str(), file_id], |r| {
+            Ok((r.get::<_, String>(0)?, blob_vector(&r.get::<_, Vec<u8>>(1)?)))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<HashMap<_, _>>>()?)
+    }
+
+    /// Vectors `model` produced earlier for any of `hashes`, whichever document they came
+    /// from. The cache outlives the chunks, so toggling a file off and on again or
+    /// re-linking it costs no embedding calls.
+    pub fn cached_vectors(&self, model: &str, hashes: &[&str]) -> Result<HashMap<String, Vec<f32>>> {
+        let mut found = HashMap::new();
+        if hashes.is_empty() {
+            return Ok(found);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut select = tx.prepare("SELECT vector FROM embedding_cache WHERE hash = ?1 AND model = ?2")?;
+            let mut touch = tx.prepare("UPDATE embedding_cache SET used = ?3 WHERE hash = ?1 AND model = ?2")?;
+            let now = Utc::now().timestamp();
+            for hash in hashes {
+                let blob: Option<Vec<u8>> = select.query_row([hash, model], |r| r.get(0)).optional()?;
+                if let Some(blob) = blob {
+                    touch.execute(params![hash, model, now])?;
+                    found.insert(hash.to_string(), blob_vector(&blob));
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(found)
+    }
+
+    /// Remember freshly computed vectors, evicting the least recently used beyond the cap
+    pub fn cache_vectors(&self, model: &str, vectors: &[(&str, &[f32])]) -> Result<()> {
+        if vectors.is_empty() {
+            return Ok(());
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO embedding_cache (hash, model, vector, used) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let now = Utc::now().timestamp();
+            for (hash, vector) in vectors {
+                insert.execute(params![hash, model, vector_blob(vector), now])?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM embedding_cache WHERE rowid IN (
+                 SELECT rowid FROM embedding_cache ORDER BY used
+                 LIMIT max(0, (SELECT COUNT(*) FROM embedding_cache) - ?1))",
+            [MAX_CACHED_VECTORS],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Empty the embedding cache; returns how many vectors were dropped
+    pub fn clear_cache(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM embedding_cache", [])?)
+    }
+
+    /// Bring a document's rows in line with freshly embedded chunks. Rows whose content hash
+    /// is unchanged stay as they are (at most renumbered); only new chunks are written and
+    /// only vanished ones deleted.
+    pub fn upsert_file(&mut self, namespace: Namespace, embeddings: &FileEmbeddings) -> Result<IndexUpdate> {
+        if embeddings.chunks.is_empty() {
+            let removed = self.remove_file(namespace, &embeddings.file_id)?;
+            return Ok(IndexUpdate { removed, ..Default::default() });
+        }
+        // Written at once, so no other connection switches the model in between
+        let tx = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        Self::ensure_model(&tx, &embeddings.model, embeddings.dimensions)?;
+
+        let mut existing: HashMap<String, Vec<(i64, usize, String, bool)>> = HashMap::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT id, chunk_index, hash, source, stale FROM chunks WHERE namespace = ?1 AND file_id = ?2",
+            )?;
+            let rows = stmt.query_map([namespace.as_str(), &embeddings.file_id], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, i64>(1)? as usize,
+                    r.get::<_, String>(2)?,
+                    r.get::<_, String>(3)?,
+                    r.get::<_, bool>(4)?,
+                ))
+            })?;
+            for row in rows {
+                let (id, index, hash, source, stale) = row?;
+                existing.entry(hash).or_default().push((id, index, source, stale));
+            }
+        }
+
+        let mut update = IndexUpdate::default();
+        for chunk in &embeddings.chunks {
+            let source = serde_json::to_string(&chunk.source)?;
+            if let Some((id, index, old_source, stale)) = existing.get_mut(&chunk.hash).and_then(|rows| rows.pop()) {
+                // Same text can move within the file (an edit above it shifts its lines)
+                if index != chunk.index || old_source != source {
+                    tx.execute(
+                        "UPDATE chunks SET chunk_index = ?1, source = ?2 WHERE id = ?3",
+                        params![chunk.index as i64, source, id],
+                    )?;
+                }
+                // Kept through a model switch; the new vector is at hand
+                if stale {
+                    tx.execute(
+                        "INSERT INTO vec_chunks (rowid, namespace, embedding) VALUES (?1, ?2, ?3)",
+                        params![id, namespace.as_str(), vector_blob(&chunk.vector)],
+                    )?;
+                    tx.execute("UPDATE chunks SET stale = 0 WHERE id = ?1", [id])?;
+                }
+                update.kept += 1;
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO chunks (file_id, chunk_index, heading, text, tokens, hash, source, namespace)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    embeddings.file_id,
+                    chunk.index as i64,
+                    chunk.heading,
+                    chunk.text,
+                    chunk.tokens as i64,
+                    chunk.hash,
+                    source,
+                    namespace.as_str()
+                ],
+            )?;
+            tx.execute(
+                "INSERT INTO vec_chunks (rowid, namespace, embedding) VALUES (?1, ?2, ?3)",
+                params![tx.last_insert_rowid(), namespace.as_str(), vector_blob(&chunk.vector)],
+            )?;
+            update.added += 1;
+        }
+        for (id, _, _, _) in existing.into_values().flatten() {
+            tx.execute("DELETE FROM vec_chunks WHERE rowid = ?1", [id])?;
+            tx.execute("DELETE FROM chunks WHERE id = ?1", [id])?;
+            update.removed += 1;
+        }
+        tx.commit()?;
+        Ok(update)
+    }
+
+    fn delete_rows(conn: &Connection, namespace: Namespace, file_id: &str) -> Result<usize> {
+        let has_vectors: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'vec_chunks')",
+            [],
+            |r| r.get(0),
+        )?;
+        if has_vectors {
+            conn.execute(
+                "DELETE FROM vec_chunks WHERE rowid IN (SELECT id FROM chunks WHERE namespace = ?1 AND file_id = ?2)",
+                [namespace.as_str(), file_id],
+            )?;
+        }
+        Ok(conn.execute("DELETE FROM chunks WHERE namespace = ?1 AND file_id = ?2", [namespace.as_str(), file_id])?)
+    }
+
+    /// Drop a document's chunks; returns how many were removed
+    pub fn remove_file(&self, namespace: Namespace, file_id: &str) -> Result<usize> {
+        Self::delete_rows(&self.conn, namespace, file_id)
+    }
+
+    /// IDs of documents that have chunks in the index
+    pub fn indexed_files(&self, namespace: Namespace) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT file_id FROM chunks WHERE namespace = ?1")?;
+        let ids = stmt.query_map([namespace.as_str()], |r| r.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(ids)
+    }
+
+    /// The `k` chunks nearest to `query`, which must come from the index's model
+    pub fn search(&self, namespace: Namespace, model: &str, query: &[f32], k: usize) -> Result<Vec<ScoredChunk>> {
+        match self.model()? {
+            Some(indexed) if indexed == model => {}
+            Some(indexed) => {
+                return Err(anyhow!("Index holds {} vectors, query is from {}", indexed, model))
+            }
+            None => return Ok(Vec::new()),
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT c.file_id, c.chunk_index, c.heading, c.text, c.tokens, c.source, v.distance
+             FROM (SELECT rowid, distance FROM vec_chunks WHERE embedding MATCH ?1 AND k = ?2 AND namespace = ?3) v
+             JOIN chunks c ON c.id = v.rowid
+             ORDER BY v.distance",
+        )?;
+        let rows = stmt.query_map(params![vector_blob(query), k as i64, namespace.as_str()], |r| {
+            Ok(ScoredChunk { chunk: Self::stored_chunk(r)?, score: 1.0 - r.get::<_, f64>(6)? as f32 })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// The `k` chunks best matching the words of `text` by BM25
+    pub fn keyword_search(&self, namespace: Namespace, text: &str, k: usize) -> Result<Vec<ScoredChunk>> {
+        let Some(query) = fts_query(text) else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT c.file_id, c.chunk_index, c.heading, c.text, c.tokens, c.source, bm25(chunks_fts)
+             FROM chunks_fts JOIN chunks c ON c.id = chunks_fts.rowid
+             WHERE chunks_fts MATCH ?1 AND c.namespace = ?3
+             ORDER BY bm25(chunks_fts)
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![query, k as i64, namespace.as_str()], |r| {
+            // bm25() is lower-is-better; flip it so higher scores mean better matches
+            Ok(ScoredChunk { chunk: Self::stored_chunk(r)?, score: -r.get::<_, f64>(6)? as f32 })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Keyword and vector search fused by reciprocal rank. Exact identifiers that embeddings
+    /// blur together (invoice numbers, function names) still surface through BM25; without a
+    /// query vector this is keyword search alone.
+    pub fn hybrid_search(
+        &self,
+        namespace: Namespace,
+        text: &str,
+        query: Option<(&str, &[f32])>,
+        k: usize,
+    ) -> Result<Vec<ScoredChunk>> {
+        // Look deeper than `k` in each list so chunks ranked moderately by both can win
+        let depth = (k * 4).max(20);
+        let mut lists = vec![self.keyword_search(namespace, text, depth)?];
+        if let Some((model, vector)) = query {
+            lists.push(self.search(namespace, model, vector, depth)?);
+        }
+        Ok(fuse(&lists, k))
+    }
+
+    fn stored_chunk(r: &rusqlite::Row) -> rusqlite::Result<StoredChunk> {
+        Ok(StoredChunk {
+            file_id: r.get(0)?,
+            chunk_index: r.get::<_, i64>(1)? as usize,
+            heading: r.get(2)?,
+            text: r.get(3)?,
+            tokens: r.get::<_, i64>(4)? as usize,
+            // Provenance is informational; an unreadable record shouldn't fail the search
+            source: serde_json::from_str(&r.get::<_, String>(5)?).unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::EmbeddedChunk;
+
+    fn file(id: &str, vectors: &[[f32; 3]]) -> FileEmbeddings {
+        FileEmbeddings {
+            file_id: id.to_string(),
+            model: "test".to_string(),
+            dimensions: 3,
+            created: String::new(),
+            chunks: vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| EmbeddedChunk {
+                    index: i,
+                    heading: None,
+                    text: format!("{} chunk {}", id, i),
+                    tokens: 2,
+                    hash: format!("{}-{}", id, i),
+                    source: Provenance { lines: (i + 1, i + 1), ..Default::default() },
+                    vector: v.to_vec(),
+                })
+                .collect(),
+            reused: 0,
+        }
+    }
+
+    #[test]
+    fn test_upsert_search_and_remove() {
+        let path = std::env::temp_dir().join(format!("agi-vectors-{}.db", uuid::Uuid::new_v4()));
+        let mut store = VectorStore::open(&path).unwrap();
+        store.upsert_file(Namespace::Files, &file("a", &[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])).unwrap();
+        store.upsert_file(Namespace::Files, &file("b", &[[0.0, 0.0, 1.0]])).unwrap();
+
+        let hits = store.search(Namespace::Files, "test", &[0.1, 0.9, 0.0], 2).unwrap();
+        assert_eq!(hits[0].chunk.text, "a chunk 1");
+        assert_eq!(hits[0].chunk.source.lines, (2, 2));
+        assert!(hits[0].score > hits[1].score);
+
+        // Re-indexing replaces rather than duplicates, touching only changed chunks
+        let update = store.upsert_file(Namespace::Files, &file("a", &[[1.0, 0.0, 0.0]])).unwrap();
+        assert_eq!(update, IndexUpdate { added: 0, kept: 1, removed: 1 });
+        assert_eq!(store.vectors_for(Namespace::Files, "a", "test").unwrap()["a-0"], vec![1.0, 0.0, 0.0]);
+        assert_eq!(store.remove_file(Namespace::Files, "a").unwrap(), 1);
+        assert_eq!(store.indexed_files(Namespace::Files).unwrap(), vec!["b".to_string()]);
+        assert!(store.search(Namespace::Files, "other", &[0.0, 0.0, 1.0], 1).is_err());
+
+        // Same id, other namespace: kept apart in both halves of the search
+        store.upsert_file(Namespace::Conversations, &file("b", &[[0.0, 0.0, 1.0]])).unwrap();
+        assert_eq!(store.search(Namespace::Files, "test", &[0.0, 0.0, 1.0], 5).unwrap().len(), 1);
+        assert_eq!(store.keyword_search(Namespace::Conversations, "chunk", 5).unwrap().len(), 1);
+        assert_eq!(store.remove_file(Namespace::Conversations, "b").unwrap(), 1);
+        assert_eq!(store.indexed_files(Namespace::Files).unwrap(), vec!["b".to_string()]);
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_hybrid_search_finds_exact_identifiers() {
+        let path = std::env::temp_dir().join(format!("agi-vectors-{}.db", uuid::Uuid::new_v4()));
+        let mut store = VectorStore::open(&path).unwrap();
+        let mut invoices = file("inv", &[[1.0, 0.0, 0.0], [0.9, 0.1, 0.0]]);
+        invoices.chunks[0].text = "Invoice INV-2024-0042 is overdue".to_string();
+        invoices.chunks[1].text = "Invoice INV-2024-0017 was paid".to_string();
+        store.upsert_file(Namespace::Files, &invoices).unwrap();
+
+        // The query vector prefers chunk 1, the identifier only appears in chunk 0
+        let query = [0.9, 0.1, 0.0];
+        let hits = store.hybrid_search(Namespace::Files, "status of INV-2024-0042?", Some(("test", &query)), 2).unwrap();
+        assert_eq!(hits[0].chunk.chunk_index, 0);
+
+        let keyword = store.hybrid_search(Namespace::Files, "0017", None, 5).unwrap();
+        assert_eq!(keyword.len(), 1);
+        assert_eq!(keyword[0].chunk.chunk_index, 1);
+
+        // Removing a file clears its keyword rows too
+        store.remove_file(Namespace::Files, "inv").unwrap();
+        assert!(store.keyword_search(Namespace::Files, "invoice", 5).unwrap().is_empty());
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_model_switch_keeps_chunks_of_both_namespaces() {
+        let path = std::env::temp_dir().join(format!("agi-vectors-{}.db", uuid::Uuid::new_v4()));
+        let mut store = VectorStore::open(&path).unwrap();
+        store.upsert_file(Namespace::Files, &file("a", &[[1.0, 0.0, 0.0]])).unwrap();
+        store.upsert_file(Namespace::Conversations, &file("c", &[[0.0, 1.0, 0.0]])).unwrap();
+
+        // Another model, other dimensions: the conversation waits for its new vector
+        let mut switched = file("a", &[[1.0, 0.0, 0.0, 0.0]]);
+        switched.model = "other".to_string();
+        switched.dimensions = 4;
+        store.upsert_file(Namespace::Files, &switched).unwrap();
+        let stale = store.stale_chunks().unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!((stale[0].1.as_str(), stale[0].2.as_str()), ("conversations", "c chunk 0"));
+        assert_eq!(store.keyword_search(Namespace::Conversations, "chunk", 5).unwrap().len(), 1);
+        assert_eq!(store.search(Namespace::Files, "other", &[1.0, 0.0, 0.0, 0.0], 1).unwrap().len(), 1);
+
+        store.restore_vectors("other", &[(stale[0].0, "conversations", &[0.0, 1.0, 0.0, 0.0])]).unwrap();
+        assert!(store.stale_chunks().unwrap().is_empty());
+        let hits = store.search(Namespace::Conversations, "other", &[0.0, 1.0, 0.0, 0.0], 1).unwrap();
+        assert_eq!(hits[0].chunk.file_id, "c");
+        assert!(store.restore_vectors("test", &[]).is_err());
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_embedding_cache_outlives_chunks() {
+        let path = std::env::temp_dir().join(format!("agi-vectors-{}.db", uuid::Uuid::new_v4()));
+        let mut store = VectorStore::open(&path).unwrap();
+        store.upsert_file(Namespace::Files, &file("a", &[[1.0, 0.0, 0.0]])).unwrap();
+        store.cache_vectors("test", &[("a-0", &[1.0, 0.0, 0.0])]).unwrap();
+        store.remove_file(Namespace::Files, "a").unwrap();
+
+        let cached = store.cached_vectors("test", &["a-0", "a-1"]).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached["a-0"], vec![1.0, 0.0, 0.0]);
+        assert!(store.cached_vectors("other", &["a-0"]).unwrap().is_empty());
+        assert_eq!(store.clear_cache().unwrap(), 1);
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
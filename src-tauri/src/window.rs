@@ -1,60 +1,485 @@
-use tauri::{Manager, App, WebviewWindow};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, App, LogicalSize, Monitor, PhysicalPosition, PhysicalSize, Runtime, Size, WebviewWindow, Window};
+
+use crate::pii_scrubber::ScrubPass;
+use crate::settings::{DockSide, MonitorPlacement, Settings, WindowGeometry, WindowSettings};
 
 // The offset from the top of the screen to the window
 const TOP_OFFSET: i32 = 54;
+// Moves and resizes arrive in bursts while dragging; the geometry is saved once they settle
+const GEOMETRY_SAVE_DELAY: Duration = Duration::from_millis(750);
+// Fainter than this and the overlay is easy to lose track of
+const MIN_OPACITY: f64 = 0.1;
+// A window dropped this close to a screen edge (physical pixels) snaps against it
+const SNAP_DISTANCE: i32 = 24;
+// The quick-question HUD: its label, size (logical pixels) and gap from the cursor
+pub const HUD_LABEL: &str = "hud";
+const HUD_WIDTH: f64 = 360.0;
+const HUD_HEIGHT: f64 = 120.0;
+const HUD_CURSOR_GAP: i32 = 16;
+// Clipboard text sent along with a HUD question is cut to this many characters
+const HUD_CONTEXT_CHARS: usize = 4000;
+// Height animations step about 60 times a second
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+// The latest geometry not yet written to settings, and whether a save is scheduled
+static PENDING_GEOMETRY: Mutex<Option<WindowGeometry>> = Mutex::new(None);
+static SAVE_SCHEDULED: AtomicBool = AtomicBool::new(false);
+// Whether the window was dragged since the last save
+static SNAP_PENDING: AtomicBool = AtomicBool::new(false);
+// Where we last moved the main window ourselves; a Moved landing there isn't a drag
+static PLACED_AT: Mutex<Option<PhysicalPosition<i32>>> = Mutex::new(None);
+// Bumped by every resize; an animation stops once it's no longer the latest
+static RESIZE_GENERATION: AtomicU64 = AtomicU64::new(0);
+// Bumped on every focus change, so a pending auto-hide is dropped once focus comes back
+static FOCUS_GENERATION: AtomicU64 = AtomicU64::new(0);
+// A response is streaming in; auto-hide waits for it to finish
+static STREAMING: AtomicBool = AtomicBool::new(false);
+
+/// A connected screen, as list_monitors reports it
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    pub index: usize,                  // What move_to_monitor takes
+    pub name: Option<String>,
+    pub x: i32,                        // Physical pixels, relative to the whole desktop
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+    pub current: bool,                 // The main window is on it
+}
 
 /// Sets up the main window with custom positioning
 pub fn setup_main_window(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+    let window = main_window(app).ok_or("No window found")?;
+
+    let settings = Settings::load().map(|s| s.window).unwrap_or_default();
+    if settings.content_protected {
+        window.set_content_protected(true)?;
+    }
+    // tauri.conf.json turns it on; the setting can turn it off
+    if !settings.all_workspaces {
+        window.set_visible_on_all_workspaces(false)?;
+    }
+    if !settings.show_in_dock {
+        apply_dock_visibility(app.handle(), false)?;
+    }
+    let monitors = window.available_monitors()?;
+    if let Some(geometry) = settings.geometry.as_ref().filter(|g| is_on_screen(g, &monitors)) {
+        // The height follows the chat at runtime (set_window_height), so only the width comes back
+        let height = window.outer_size()?.height;
+        window.set_size(tauri::Size::Physical(PhysicalSize { width: geometry.width, height }))?;
+        window.set_position(placing(PhysicalPosition { x: geometry.x, y: geometry.y }))?;
+        println!("[Window] Restored to {},{} ({} wide)", geometry.x, geometry.y, geometry.width);
+        return Ok(());
+    }
+    match (preferred_monitor(&window, &settings)?, settings.dock) {
+        (Some(monitor), Some(side)) => dock_on_monitor(&window, &monitor, side)?,
+        (Some(monitor), None) => position_on_monitor(&window, &monitor, TOP_OFFSET)?,
+        (None, _) => position_window_top_center(&window, TOP_OFFSET)?,
+    }
+
+    Ok(())
+}
+
+// Whether a saved geometry still lands on a connected screen: the middle of its top edge must
+// be on one, and on the same one when we know which it was
+fn is_on_screen(geometry: &WindowGeometry, monitors: &[Monitor]) -> bool {
+    let (x, y) = (geometry.x + geometry.width as i32 / 2, geometry.y);
+    monitors.iter().any(|m| {
+        let same_screen = geometry.monitor_name.is_none() || m.name() == geometry.monitor_name.as_ref();
+        same_screen && contains(*m.position(), *m.size(), x, y)
+    })
+}
+
+fn contains(position: PhysicalPosition<i32>, size: PhysicalSize<u32>, x: i32, y: i32) -> bool {
+    x >= position.x && x < position.x + size.width as i32 && y >= position.y && y < position.y + size.height as i32
+}
+
+/// Note where the main window is after it moved or resized; it's written to settings once
+/// the window has been still for a moment. A window dragged near a screen edge is then
+/// snapped against it.
+pub fn remember_geometry<R: Runtime>(window: &Window<R>, moved: bool) {
+    // Minimized windows report an off-screen parking spot on Windows
+    if !window.is_visible().unwrap_or(false) || window.is_minimized().unwrap_or(false) {
+        return;
+    }
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.outer_size()) else { return };
+    let monitor_name = window.current_monitor().ok().flatten().and_then(|m| m.name().cloned());
+    *PENDING_GEOMETRY.lock().unwrap_or_else(|e| e.into_inner()) = Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        monitor_name,
+    });
+    // Only drags snap; a window growing toward an edge, or one we moved, stays put
+    if moved && !was_placed(position) {
+        SNAP_PENDING.store(true, Ordering::SeqCst);
+    }
+    if SAVE_SCHEDULED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let window = window.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(GEOMETRY_SAVE_DELAY);
+        SAVE_SCHEDULED.store(false, Ordering::SeqCst);
+        if SNAP_PENDING.swap(false, Ordering::SeqCst) {
+            if let Err(e) = snap_to_edge(&window) {
+                eprintln!("[Window] Failed to snap to the screen edge: {}", e);
+            }
+        }
+        save_geometry();
+    });
+}
+
+// Note that we're moving the main window to `position`, so the Moved it fires doesn't snap it
+// or float it off its dock
+fn placing(position: PhysicalPosition<i32>) -> tauri::Position {
+    *PLACED_AT.lock().unwrap_or_else(|e| e.into_inner()) = Some(position);
+    tauri::Position::Physical(position)
+}
+
+fn was_placed(position: PhysicalPosition<i32>) -> bool {
+    *PLACED_AT.lock().unwrap_or_else(|e| e.into_inner()) == Some(position)
+}
+
+// Dock the window against the edge of its screen's work area it was dropped near, or mark it
+// floating when it's near none. Snapping moves it, which saves the geometry again.
+fn snap_to_edge<R: Runtime>(window: &Window<R>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(monitor) = window.current_monitor()? else { return Ok(()) };
+    let area = monitor.work_area();
+    let (position, size) = (window.outer_position()?, window.outer_size()?);
+    let side = nearest_edge(area.position, area.size, position, size);
+    if let Some(side) = side {
+        let docked = flush_to(area.position, area.size, position, size, side);
+        if docked != position {
+            window.set_position(placing(docked))?;
+        }
+    }
+    let saved = Settings::load().map(|s| s.window.dock).unwrap_or_default();
+    if saved != side {
+        Settings::update(|s| s.window.dock = side)?;
+        println!("[Window] Dock side: {:?}", side);
+    }
+    Ok(())
+}
+
+// The work-area edge a window is within SNAP_DISTANCE of, the closest when it's near two
+fn nearest_edge(area_position: PhysicalPosition<i32>, area_size: PhysicalSize<u32>, position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> Option<DockSide> {
+    let distances = [
+        (DockSide::Top, position.y - area_position.y),
+        (DockSide::Bottom, (area_position.y + area_size.height as i32) - (position.y + size.height as i32)),
+        (DockSide::Left, position.x - area_position.x),
+        (DockSide::Right, (area_position.x + area_size.width as i32) - (position.x + size.width as i32)),
+    ];
+    distances
+        .into_iter()
+        .filter(|(_, distance)| distance.abs() <= SNAP_DISTANCE)
+        .min_by_key(|(_, distance)| distance.abs())
+        .map(|(side, _)| side)
+}
+
+// Top-left corner that puts a window against one edge of an area, keeping it inside along
+// the other axis
+fn flush_to(area_position: PhysicalPosition<i32>, area_size: PhysicalSize<u32>, position: PhysicalPosition<i32>, size: PhysicalSize<u32>, side: DockSide) -> PhysicalPosition<i32> {
+    let inside = clamp_into(area_position, area_size, position, size);
+    match side {
+        DockSide::Top => PhysicalPosition { y: area_position.y, ..inside },
+        DockSide::Bottom => PhysicalPosition { y: area_position.y + area_size.height as i32 - size.height as i32, ..inside },
+        DockSide::Left => PhysicalPosition { x: area_position.x, ..inside },
+        DockSide::Right => PhysicalPosition { x: area_position.x + area_size.width as i32 - size.width as i32, ..inside },
+    }
+}
+
+/// Dock a window against `side` of a screen, centered along that edge
+pub fn dock_on_monitor<R: Runtime>(window: &WebviewWindow<R>, monitor: &Monitor, side: DockSide) -> Result<(), Box<dyn std::error::Error>> {
+    let area = monitor.work_area();
+    let size = window.outer_size()?;
+    let centered = PhysicalPosition {
+        x: area.position.x + (area.size.width as i32 - size.width as i32) / 2,
+        y: area.position.y + (area.size.height as i32 - size.height as i32) / 2,
+    };
+    window.set_position(placing(flush_to(area.position, area.size, centered, size, side)))?;
+    Ok(())
+}
+
+/// Track the main window's focus for auto-hide: losing it schedules a hide after the
+/// configured delay, which is called off when focus returns
+pub fn on_focus_changed<R: Runtime>(window: &Window<R>, focused: bool) {
+    let generation = FOCUS_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    if !focused {
+        schedule_auto_hide(window, generation);
+    }
+}
+
+fn schedule_auto_hide<R: Runtime>(window: &Window<R>, generation: u64) {
+    let settings = Settings::load().map(|s| s.window).unwrap_or_default();
+    if !settings.auto_hide || STREAMING.load(Ordering::SeqCst) {
+        return;
+    }
+    let window = window.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(settings.auto_hide_delay_ms));
+        if FOCUS_GENERATION.load(Ordering::SeqCst) != generation || STREAMING.load(Ordering::SeqCst) {
+            return;
+        }
+        // Focus moving to the settings or sign-in window isn't leaving the app
+        let app_focused = window.app_handle().webview_windows().values().any(|w| w.is_focused().unwrap_or(false));
+        if !app_focused && window.is_visible().unwrap_or(false) {
+            if let Err(e) = window.hide() {
+                eprintln!("[Window] Failed to auto-hide: {}", e);
+            }
+        }
+    });
+}
+
+/// Write the pending geometry to settings now, e.g. right before the app exits
+pub fn save_geometry() {
+    let Some(geometry) = PENDING_GEOMETRY.lock().unwrap_or_else(|e| e.into_inner()).take() else { return };
+    if let Err(e) = Settings::update(|s| s.window.geometry = Some(geometry)) {
+        eprintln!("[Window] Failed to save window position: {}", e);
+    }
+}
+
+/// The assistant's main window
+pub fn main_window<R: Runtime, M: Manager<R>>(manager: &M) -> Option<WebviewWindow<R>> {
     // Try different possible window labels
-    let window = app.get_webview_window("main")
-        .or_else(|| app.get_webview_window("pluely"))
+    manager.get_webview_window("main")
+        .or_else(|| manager.get_webview_window("pluely"))
         .or_else(|| {
             // Get the first window if specific labels don't work
-            app.webview_windows().values().next().cloned()
+            manager.webview_windows().values().next().cloned()
         })
-        .ok_or("No window found")?;
-    
-    position_window_top_center(&window, TOP_OFFSET)?;
-    
+}
+
+/// Show and focus the main window, or hide it when it's already in front. Once shown, the
+/// frontend gets "window:focus-input" to put the cursor in the chat box.
+pub fn toggle_main_window(app: &AppHandle) -> tauri::Result<()> {
+    let Some(window) = main_window(app) else { return Ok(()) };
+    if window.is_visible()? && window.is_focused()? && !window.is_minimized()? {
+        return window.hide();
+    }
+    // Follow the mouse to another screen when that's the chosen placement
+    let settings = Settings::load().map(|s| s.window).unwrap_or_default();
+    if settings.monitor == MonitorPlacement::Cursor {
+        if let Some(monitor) = preferred_monitor(&window, &settings)? {
+            if let Err(e) = position_on_monitor(&window, &monitor, TOP_OFFSET) {
+                eprintln!("Failed to move window to the cursor's screen: {}", e);
+            }
+        }
+    }
+    show_main_window(app)?;
+    window.emit_to(window.label(), "window:focus-input", ())
+}
+
+/// Bring the main window to the front, restoring it if it was hidden or minimized
+pub fn show_main_window<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<Option<WebviewWindow<R>>> {
+    let Some(window) = main_window(app) else { return Ok(None) };
+    window.show()?;
+    window.unminimize()?;
+    window.set_focus()?;
+    Ok(Some(window))
+}
+
+/// Show or hide the app's Dock icon (macOS, via the activation policy) or the main window's
+/// taskbar button (Windows, Linux)
+pub fn apply_dock_visibility<R: Runtime>(app: &AppHandle<R>, visible: bool) -> tauri::Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        let was_visible = main_window(app).is_some_and(|w| w.is_visible().unwrap_or(false));
+        let policy = if visible { tauri::ActivationPolicy::Regular } else { tauri::ActivationPolicy::Accessory };
+        app.set_activation_policy(policy)?;
+        // Switching policy can order the window out
+        if was_visible {
+            show_main_window(app)?;
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    if let Some(window) = main_window(app) {
+        window.set_skip_taskbar(!visible)?;
+    }
     Ok(())
 }
 
-/// Positions a window at the top center of the screen with a specified Y offset
+/// The screen `settings` place the window on: the one under the cursor, the remembered one,
+/// or the one it's on. Falls back to the primary screen.
+pub fn preferred_monitor<R: Runtime>(window: &WebviewWindow<R>, settings: &WindowSettings) -> tauri::Result<Option<Monitor>> {
+    let chosen = match settings.monitor {
+        MonitorPlacement::Active => None,
+        MonitorPlacement::Cursor => {
+            let cursor = window.cursor_position()?;
+            window.monitor_from_point(cursor.x, cursor.y)?
+        }
+        MonitorPlacement::Remembered => window
+            .available_monitors()?
+            .into_iter()
+            .find(|m| settings.monitor_name.is_some() && m.name() == settings.monitor_name.as_ref()),
+    };
+    match chosen {
+        Some(monitor) => Ok(Some(monitor)),
+        None => Ok(window.current_monitor()?.or(window.primary_monitor()?)),
+    }
+}
+
+// Top-left corner that centers a window horizontally on a screen, `y_offset` below its top
+fn top_center(monitor_position: PhysicalPosition<i32>, monitor_size: PhysicalSize<u32>, window_size: PhysicalSize<u32>, y_offset: i32) -> PhysicalPosition<i32> {
+    PhysicalPosition {
+        x: monitor_position.x + (monitor_size.width as i32 - window_size.width as i32) / 2,
+        y: monitor_position.y + y_offset,
+    }
+}
+
+/// Positions a window at the top center of a given screen
+pub fn position_on_monitor<R: Runtime>(window: &WebviewWindow<R>, monitor: &Monitor, y_offset: i32) -> Result<(), Box<dyn std::error::Error>> {
+    let position = top_center(*monitor.position(), *monitor.size(), window.outer_size()?, y_offset);
+    window.set_position(placing(position))?;
+    Ok(())
+}
+
+/// Positions a window at the top center of the screen it is on with a specified Y offset
 pub fn position_window_top_center(window: &WebviewWindow, y_offset: i32) -> Result<(), Box<dyn std::error::Error>> {
-    // Get the primary monitor
-    if let Some(monitor) = window.primary_monitor()? {
-        let monitor_size = monitor.size();
-        let window_size = window.outer_size()?;
-        
-        // Calculate center X position
-        let center_x = (monitor_size.width as i32 - window_size.width as i32) / 2;
-        
-        // Set the window position
-        window.set_position(tauri::Position::Physical(tauri::PhysicalPosition {
-            x: center_x,
-            y: y_offset,
-        }))?;
+    // The window's screen, or the primary one when the OS can't tell
+    if let Some(monitor) = window.current_monitor()?.or(window.primary_monitor()?) {
+        position_on_monitor(window, &monitor, y_offset)?;
     }
-    
+
     Ok(())
 }
 
+// Top-left corner that keeps a window inside an area, moving it as little as possible
+fn clamp_into(area_position: PhysicalPosition<i32>, area_size: PhysicalSize<u32>, position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> PhysicalPosition<i32> {
+    let max_x = area_position.x + area_size.width as i32 - size.width as i32;
+    let max_y = area_position.y + area_size.height as i32 - size.height as i32;
+    PhysicalPosition {
+        x: position.x.min(max_x).max(area_position.x),
+        y: position.y.min(max_y).max(area_position.y),
+    }
+}
+
+/// Nudge a window back inside its screen's work area (clear of the menu bar, Dock and
+/// taskbar), e.g. after it grew past the bottom
+pub fn keep_on_screen(window: &WebviewWindow) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(monitor) = window.current_monitor()? else { return Ok(()) };
+    let area = monitor.work_area();
+    let position = window.outer_position()?;
+    let clamped = clamp_into(area.position, area.size, position, window.outer_size()?);
+    if clamped != position {
+        window.set_position(placing(clamped))?;
+    }
+    Ok(())
+}
+
+/// Set how opaque the whole window is, from 0 (invisible) to 1, natively on each platform.
+/// Called from a sync command, so it runs on the main thread as AppKit and GTK require.
+#[cfg(target_os = "macos")]
+fn apply_opacity(window: &WebviewWindow, level: f64) -> Result<(), Box<dyn std::error::Error>> {
+    use objc2::{msg_send, runtime::AnyObject};
+    let ns_window = window.ns_window()? as *mut AnyObject;
+    unsafe {
+        let _: () = msg_send![ns_window, setAlphaValue: level];
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn apply_opacity(window: &WebviewWindow, level: f64) -> Result<(), Box<dyn std::error::Error>> {
+    use windows::Win32::Foundation::COLORREF;
+    use windows::Win32::UI::WindowsAndMessaging::{GetWindowLongPtrW, SetLayeredWindowAttributes, SetWindowLongPtrW, GWL_EXSTYLE, LWA_ALPHA, WS_EX_LAYERED};
+    let hwnd = window.hwnd()?;
+    unsafe {
+        // Only layered windows take a per-window alpha
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED.0 as isize);
+        SetLayeredWindowAttributes(hwnd, COLORREF(0), (level * 255.0).round() as u8, LWA_ALPHA)?;
+    }
+    Ok(())
+}
+
+// Needs a compositing window manager; without one GTK leaves the window opaque
+#[cfg(target_os = "linux")]
+fn apply_opacity(window: &WebviewWindow, level: f64) -> Result<(), Box<dyn std::error::Error>> {
+    use gtk::prelude::WidgetExt;
+    window.gtk_window()?.set_opacity(level);
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn apply_opacity(_window: &WebviewWindow, _level: f64) -> Result<(), Box<dyn std::error::Error>> {
+    Err("window opacity isn't supported on this platform".into())
+}
+
+// Fast start, gentle landing; `t` runs from 0 to 1
+fn ease_out_cubic(t: f64) -> f64 {
+    1.0 - (1.0 - t.clamp(0.0, 1.0)).powi(3)
+}
+
+/// Resize the window to `height` logical pixels over `duration`, keeping its width. A newer
+/// call takes over from wherever this one got to; the window is kept on screen only once the
+/// animation ends, so it doesn't jitter while growing.
+pub fn animate_height(window: &WebviewWindow, height: f64, duration: Duration) -> tauri::Result<()> {
+    let generation = RESIZE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let current = window.inner_size()?.to_logical::<f64>(window.scale_factor()?);
+    if duration.is_zero() || (current.height - height).abs() < 1.0 {
+        window.set_size(Size::Logical(LogicalSize::new(current.width, height)))?;
+        if let Err(e) = keep_on_screen(window) {
+            eprintln!("Failed to reposition window: {}", e);
+        }
+        return Ok(());
+    }
+
+    let window = window.clone();
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        loop {
+            if RESIZE_GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let t = start.elapsed().as_secs_f64() / duration.as_secs_f64();
+            let step = current.height + (height - current.height) * ease_out_cubic(t);
+            if let Err(e) = window.set_size(Size::Logical(LogicalSize::new(current.width, step))) {
+                eprintln!("Failed to resize window: {}", e);
+                return;
+            }
+            if t >= 1.0 {
+                break;
+            }
+            std::thread::sleep(FRAME_INTERVAL);
+        }
+        if let Err(e) = keep_on_screen(&window) {
+            eprintln!("Failed to reposition window: {}", e);
+        }
+    });
+    Ok(())
+}
+
+// Top-left corner of the HUD: just below and right of the cursor, pulled back inside the
+// screen's work area near its edges
+fn hud_position(cursor: PhysicalPosition<i32>, size: PhysicalSize<u32>, area_position: PhysicalPosition<i32>, area_size: PhysicalSize<u32>) -> PhysicalPosition<i32> {
+    let beside = PhysicalPosition { x: cursor.x + HUD_CURSOR_GAP, y: cursor.y + HUD_CURSOR_GAP };
+    clamp_into(area_position, area_size, beside, size)
+}
+
 /// Future function for centering window completely (both X and Y)
 #[allow(dead_code)]
 pub fn center_window_completely(window: &WebviewWindow) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(monitor) = window.primary_monitor()? {
         let monitor_size = monitor.size();
         let window_size = window.outer_size()?;
-        
+
         let center_x = (monitor_size.width as i32 - window_size.width as i32) / 2;
         let center_y = (monitor_size.height as i32 - window_size.height as i32) / 2;
-        
+
         window.set_position(tauri::Position::Physical(tauri::PhysicalPosition {
             x: center_x,
             y: center_y,
         }))?;
     }
-    
+
     Ok(())
 }
 
@@ -64,3 +489,288 @@ pub fn position_window_at(window: &WebviewWindow, x: i32, y: i32) -> Result<(),
     window.set_position(tauri::Position::Physical(tauri::PhysicalPosition { x, y }))?;
     Ok(())
 }
+
+/// The connected screens, in the order move_to_monitor numbers them
+#[tauri::command]
+pub fn list_monitors(window: WebviewWindow) -> Result<Vec<MonitorInfo>, String> {
+    let monitors = window.available_monitors().map_err(|e| format!("Failed to list screens: {}", e))?;
+    let primary = window.primary_monitor().ok().flatten();
+    let current = window.current_monitor().ok().flatten();
+    let same = |a: &Monitor, b: Option<&Monitor>| b.is_some_and(|b| a.name() == b.name() && a.position() == b.position());
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(index, m)| MonitorInfo {
+            index,
+            name: m.name().cloned(),
+            x: m.position().x,
+            y: m.position().y,
+            width: m.size().width,
+            height: m.size().height,
+            scale_factor: m.scale_factor(),
+            primary: same(m, primary.as_ref()),
+            current: same(m, current.as_ref()),
+        })
+        .collect())
+}
+
+/// Park the main window on screen `index` (see list_monitors) and open it there from now on
+#[tauri::command]
+pub fn move_to_monitor(window: WebviewWindow, index: usize) -> Result<MonitorInfo, String> {
+    let monitors = list_monitors(window.clone())?;
+    let target = monitors.get(index).cloned().ok_or_else(|| format!("No screen {}; there are {}", index, monitors.len()))?;
+    let monitor = window
+        .available_monitors()
+        .map_err(|e| format!("Failed to list screens: {}", e))?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("Screen {} was disconnected", index))?;
+    position_on_monitor(&window, &monitor, TOP_OFFSET).map_err(|e| format!("Failed to move window: {}", e))?;
+    Settings::update(|s| {
+        s.window.monitor = MonitorPlacement::Remembered;
+        s.window.monitor_name = target.name.clone();
+    })
+    .map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("[Window] Moved to screen {} ({:?})", index, target.name);
+    Ok(MonitorInfo { current: true, ..target })
+}
+
+/// Dock the window against an edge of its screen, centered along it: "top", "bottom", "left"
+/// or "right". Dragging it away undocks it.
+#[tauri::command]
+pub fn dock_window(window: WebviewWindow, side: DockSide) -> Result<(), String> {
+    let monitor = window
+        .current_monitor()
+        .and_then(|m| Ok(m.or(window.primary_monitor()?)))
+        .map_err(|e| format!("Failed to find the window's screen: {}", e))?
+        .ok_or("No screen found")?;
+    dock_on_monitor(&window, &monitor, side).map_err(|e| format!("Failed to dock window: {}", e))?;
+    Settings::update(|s| s.window.dock = Some(side)).map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("[Window] Docked to the {:?} edge", side);
+    Ok(())
+}
+
+/// Hide the window from screen shares, screenshots and recordings, or show it in them again;
+/// it stays visible on the screen itself. Saved for future launches. Linux has no way to do it.
+#[tauri::command]
+pub fn set_content_protection(window: WebviewWindow, enabled: bool) -> Result<bool, String> {
+    if cfg!(target_os = "linux") && enabled {
+        return Err("Hiding the window from screen capture isn't supported on Linux".to_string());
+    }
+    window.set_content_protected(enabled).map_err(|e| format!("Failed to set content protection: {}", e))?;
+    Settings::update(|s| s.window.content_protected = enabled).map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("[Window] Content protection {}", if enabled { "on" } else { "off" });
+    Ok(enabled)
+}
+
+/// Keep the assistant out of the Dock and taskbar, like a menu-bar utility summoned with the
+/// global shortcut, or put it back. Saved for future launches.
+#[tauri::command]
+pub fn set_dock_visibility(app: AppHandle, visible: bool) -> Result<bool, String> {
+    apply_dock_visibility(&app, visible).map_err(|e| format!("Failed to change Dock visibility: {}", e))?;
+    Settings::update(|s| s.window.show_in_dock = visible).map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("[Window] {} the Dock/taskbar", if visible { "Shown in" } else { "Hidden from" });
+    Ok(visible)
+}
+
+/// Keep the window on every macOS Space and Linux workspace, or only on the one it was
+/// opened on. Windows offers apps no way to pin a window to all virtual desktops.
+#[tauri::command]
+pub fn set_visible_on_all_workspaces(window: WebviewWindow, enabled: bool) -> Result<bool, String> {
+    if cfg!(target_os = "windows") && enabled {
+        return Err("Showing the window on every virtual desktop isn't supported on Windows".to_string());
+    }
+    window
+        .set_visible_on_all_workspaces(enabled)
+        .map_err(|e| format!("Failed to change workspace visibility: {}", e))?;
+    Settings::update(|s| s.window.all_workspaces = enabled).map_err(|e| format!("Failed to save settings: {}", e))?;
+    println!("[Window] Visible on all workspaces: {}", enabled);
+    Ok(enabled)
+}
+
+/// Hide the window when it loses focus, after `delay_ms` (default: the saved delay)
+#[tauri::command]
+pub fn set_auto_hide(enabled: bool, delay_ms: Option<u64>) -> Result<WindowSettings, String> {
+    Settings::update(|s| {
+        s.window.auto_hide = enabled;
+        if let Some(delay_ms) = delay_ms {
+            s.window.auto_hide_delay_ms = delay_ms;
+        }
+    })
+    .map(|s| s.window)
+    .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+/// The UI reports when a response starts and stops streaming; the window isn't auto-hidden
+/// meanwhile, and hides once it ends if focus went elsewhere
+#[tauri::command]
+pub fn set_response_streaming(app: AppHandle, streaming: bool) {
+    let was_streaming = STREAMING.swap(streaming, Ordering::SeqCst);
+    if was_streaming && !streaming {
+        if let Some(window) = main_window(&app) {
+            if !window.is_focused().unwrap_or(true) {
+                schedule_auto_hide(&window.as_ref().window(), FOCUS_GENERATION.load(Ordering::SeqCst));
+            }
+        }
+    }
+}
+
+/// "hud:open": where the HUD was summoned and the text the question is about
+#[derive(Serialize, Clone, Debug)]
+pub struct HudOpen {
+    pub cursor: PhysicalPosition<i32>, // Physical pixels
+    pub context: Option<String>,       // Clipboard text at summon time, scrubbed and capped
+}
+
+// What the question is about: the text on the clipboard when the HUD is summoned, e.g. a
+// selection just copied. It goes out with the question, so it is scrubbed first.
+fn hud_context(app: &AppHandle) -> Option<String> {
+    let text = crate::clipboard::read_text().ok()?;
+    let text: String = text.trim().chars().take(HUD_CONTEXT_CHARS).collect();
+    if text.is_empty() {
+        return None;
+    }
+    let mut pass = ScrubPass::new();
+    let text = pass.text(&text);
+    pass.finish(app, "hud_context");
+    Some(text)
+}
+
+/// Summon the mini HUD next to the mouse for a one-shot question about the text just copied:
+/// a small frameless window kept above others, apart from the main chat window. Emits
+/// "hud:open" with the cursor position and the clipboard text to it; it hides again on
+/// focus loss.
+#[tauri::command]
+pub async fn open_hud_at_cursor(app: AppHandle) -> Result<(), String> {
+    let cursor = app.cursor_position().map_err(|e| format!("Failed to read the cursor position: {}", e))?;
+    let cursor = PhysicalPosition { x: cursor.x.round() as i32, y: cursor.y.round() as i32 };
+    let monitor = app
+        .monitor_from_point(cursor.x as f64, cursor.y as f64)
+        .and_then(|m| Ok(m.or(app.primary_monitor()?)))
+        .map_err(|e| format!("Failed to find the cursor's screen: {}", e))?
+        .ok_or("No screen found")?;
+
+    let hud = match app.get_webview_window(HUD_LABEL) {
+        Some(hud) => hud,
+        None => tauri::WebviewWindowBuilder::new(&app, HUD_LABEL, tauri::WebviewUrl::App("/hud".into()))
+            .title("AGI - Quick Question")
+            .inner_size(HUD_WIDTH, HUD_HEIGHT)
+            .resizable(false)
+            .decorations(false)
+            .transparent(true)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .visible_on_all_workspaces(true)
+            .visible(false)
+            .build()
+            .map_err(|e| format!("Failed to create HUD window: {}", e))?,
+    };
+
+    let scale = monitor.scale_factor();
+    let size = PhysicalSize { width: (HUD_WIDTH * scale).round() as u32, height: (HUD_HEIGHT * scale).round() as u32 };
+    let area = monitor.work_area();
+    hud.set_position(tauri::Position::Physical(hud_position(cursor, size, area.position, area.size)))
+        .map_err(|e| format!("Failed to move HUD window: {}", e))?;
+    hud.show().map_err(|e| format!("Failed to show HUD window: {}", e))?;
+    hud.set_focus().map_err(|e| format!("Failed to focus HUD window: {}", e))?;
+    let _ = hud.emit_to(HUD_LABEL, "hud:open", HudOpen { cursor, context: hud_context(&app) });
+    Ok(())
+}
+
+/// Put the HUD away, e.g. once its answer was read
+#[tauri::command]
+pub async fn close_hud(app: AppHandle) -> Result<(), String> {
+    if let Some(hud) = app.get_webview_window(HUD_LABEL) {
+        hud.hide().map_err(|e| format!("Failed to hide HUD window: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Fade the window to `level`, 1 being opaque, e.g. while it isn't focused; levels below 0.1
+/// are raised to it. Returns the level applied.
+#[tauri::command]
+pub fn set_window_opacity(window: WebviewWindow, level: f64) -> Result<f64, String> {
+    if !level.is_finite() {
+        return Err(format!("Opacity must be a number between {} and 1", MIN_OPACITY));
+    }
+    let level = level.clamp(MIN_OPACITY, 1.0);
+    apply_opacity(&window, level).map_err(|e| format!("Failed to set window opacity: {}", e))?;
+    Ok(level)
+}
+
+/// Choose which screen the window opens on: "active", "cursor" or "remembered"
+#[tauri::command]
+pub fn set_monitor_placement(placement: MonitorPlacement) -> Result<WindowSettings, String> {
+    Settings::update(|s| s.window.monitor = placement)
+        .map(|s| s.window)
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_saved_position() {
+        let (origin, size) = (PhysicalPosition { x: -1920, y: 0 }, PhysicalSize { width: 1920, height: 1080 });
+        assert!(contains(origin, size, -960, 54));
+        assert!(contains(origin, size, -1920, 0));
+        // Past the right edge is the next screen's
+        assert!(!contains(origin, size, 0, 54));
+        assert!(!contains(origin, size, -960, 1080));
+
+        // A window that grew past the bottom moves up, not sideways
+        let window = PhysicalSize { width: 700, height: 600 };
+        assert_eq!(clamp_into(origin, size, PhysicalPosition { x: -1500, y: 700 }, window), PhysicalPosition { x: -1500, y: 480 });
+        assert_eq!(clamp_into(origin, size, PhysicalPosition { x: -400, y: -10 }, window), PhysicalPosition { x: -700, y: 0 });
+    }
+
+    #[test]
+    fn test_hud_position() {
+        let (origin, size) = (PhysicalPosition { x: -1920, y: 0 }, PhysicalSize { width: 1920, height: 1080 });
+        // The HUD opens beside the cursor, pulled back inside near the screen's corner
+        let hud = PhysicalSize { width: 360, height: 120 };
+        assert_eq!(hud_position(PhysicalPosition { x: -1000, y: 300 }, hud, origin, size), PhysicalPosition { x: -984, y: 316 });
+        assert_eq!(hud_position(PhysicalPosition { x: -10, y: 1075 }, hud, origin, size), PhysicalPosition { x: -360, y: 960 });
+    }
+
+    #[test]
+    fn test_snap_to_nearest_edge() {
+        let (origin, area) = (PhysicalPosition { x: 0, y: 25 }, PhysicalSize { width: 1920, height: 1000 });
+        let window = PhysicalSize { width: 700, height: 300 };
+        assert_eq!(nearest_edge(origin, area, PhysicalPosition { x: 600, y: 40 }, window), Some(DockSide::Top));
+        assert_eq!(nearest_edge(origin, area, PhysicalPosition { x: 1230, y: 400 }, window), Some(DockSide::Right));
+        // Near two edges, the closer one wins
+        assert_eq!(nearest_edge(origin, area, PhysicalPosition { x: 5, y: 710 }, window), Some(DockSide::Left));
+        assert_eq!(nearest_edge(origin, area, PhysicalPosition { x: 600, y: 400 }, window), None);
+
+        assert_eq!(flush_to(origin, area, PhysicalPosition { x: 600, y: 40 }, window, DockSide::Top), PhysicalPosition { x: 600, y: 25 });
+        assert_eq!(flush_to(origin, area, PhysicalPosition { x: 1300, y: 400 }, window, DockSide::Right), PhysicalPosition { x: 1220, y: 400 });
+        assert_eq!(flush_to(origin, area, PhysicalPosition { x: 600, y: 900 }, window, DockSide::Bottom), PhysicalPosition { x: 600, y: 725 });
+
+        // Our own moves don't count as drags; a drag away from them does
+        let docked = PhysicalPosition { x: 600, y: 25 };
+        let _ = placing(docked);
+        assert!(was_placed(docked));
+        assert!(!was_placed(PhysicalPosition { x: 610, y: 30 }));
+    }
+
+    #[test]
+    fn test_ease_out_cubic() {
+        assert_eq!(ease_out_cubic(0.0), 0.0);
+        assert_eq!(ease_out_cubic(1.0), 1.0);
+        assert_eq!(ease_out_cubic(0.5), 0.875);
+        // Overshooting the duration lands exactly on the target
+        assert_eq!(ease_out_cubic(1.3), 1.0);
+    }
+
+    #[test]
+    fn test_top_center_on_secondary_screen() {
+        let window = PhysicalSize { width: 700, height: 54 };
+        let primary = top_center(PhysicalPosition { x: 0, y: 0 }, PhysicalSize { width: 1920, height: 1080 }, window, TOP_OFFSET);
+        assert_eq!(primary, PhysicalPosition { x: 610, y: 54 });
+        // A screen left of and above the primary one
+        let secondary = top_center(PhysicalPosition { x: -2560, y: -200 }, PhysicalSize { width: 2560, height: 1440 }, window, TOP_OFFSET);
+        assert_eq!(secondary, PhysicalPosition { x: -2130, y: -146 });
+    }
+}
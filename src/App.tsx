@@ -2,7 +2,10 @@ import { useEffect, useState } from "react";
 import { Card, Settings, Completion, ChatHistory, FullChatHistory, Integrations } from "./components";
 import { ChatConversation } from "./types";
 import { check } from "@tauri-apps/plugin-updater";
+import { listen } from "@tauri-apps/api/event";
+import { Toaster } from "react-hot-toast";
 import { startRealTimeExport } from "./lib/storage";
+import { AppNotification, openAppLink, showAppNotification } from "./lib/notifications";
 
 const App = () => {
   const [isFullChatViewOpen, setIsFullChatViewOpen] = useState(false);
@@ -33,6 +36,20 @@ const App = () => {
     };
   }, []);
 
+  // Background notifications, with their buttons, and the links those buttons open
+  useEffect(() => {
+    const unlistenNotification = listen<AppNotification>("notification", (event) => {
+      showAppNotification(event.payload);
+    });
+    const unlistenOpen = listen<{ link: string }>("notification:open", (event) => {
+      openAppLink(event.payload.link);
+    });
+
+    return () => {
+      unlistenNotification.then((f) => f());
+      unlistenOpen.then((f) => f());
+    };
+  }, []);
 
   const handleNewConversation = () => {
     // Clear any selected conversation and trigger new conversation
@@ -79,6 +96,7 @@ const App = () => {
       />
 
       <Integrations isOpen={isIntegrationsOpen} onClose={handleCloseIntegrations} />
+      <Toaster />
     </div>
   );
 };
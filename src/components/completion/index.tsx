@@ -10,6 +10,7 @@ import {
 import { useCompletion, useWindowFocus } from "@/hooks";
 import { useWindowResize } from "@/hooks";
 import { useRef, useEffect, useMemo } from "react";
+import { listen } from "@tauri-apps/api/event";
 import ReactMarkdown from "react-markdown";
 import remarkGfm from "remark-gfm";
 import { Speech } from "./Speech";
@@ -47,6 +48,7 @@ export const Completion = () => {
   const { resizeWindow } = useWindowResize();
   const fileInputRef = useRef<HTMLInputElement>(null);
   const scrollAreaRef = useRef<HTMLDivElement>(null);
+  const inputRef = useRef<HTMLInputElement>(null);
 
   const handleFileSelect = (e: React.ChangeEvent<HTMLInputElement>) => {
     const files = Array.from(e.target.files || []);
@@ -89,6 +91,17 @@ export const Completion = () => {
     }
   }, [response, toolActivities.length]);
 
+  // The show/hide shortcut brings the window up ready to type
+  useEffect(() => {
+    const unlisten = listen("window:focus-input", () => {
+      inputRef.current?.focus();
+    });
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
   useWindowFocus({
     onFocusLost: () => {
       setMicOpen(false);
@@ -183,6 +196,7 @@ export const Completion = () => {
           <PopoverTrigger asChild className="!border-none">
             <div className="relative select-none">
               <Input
+                ref={inputRef}
                 placeholder="Ask me anything..."
                 value={input}
                 onChange={(e) => setInput(e.target.value)}
@@ -52,8 +52,8 @@ export const createGoogleSuiteIntegration = (): Integration => ({
   },
   disconnect: async () => {
     try {
-      await invoke<string>("disconnect_google_suite");
-      console.log('[Integrations][Google] Disconnect successful');
+      const summary = await invoke<Record<string, unknown>>("disconnect_google_suite");
+      console.log('[Integrations][Google] Disconnect successful:', summary);
     } catch (e: any) {
       console.error('[Integrations][Google] Disconnect error:', e);
       throw e;
@@ -84,7 +84,7 @@ export const TauriFileUpload = () => {
         ref={fileInputRef}
         type="file"
         onChange={handleFileSelect}
-        accept=".pdf,.txt,.md,.json,.csv,.xml,.yaml,.yml,.log,.rtf,.py,.js,.ts,.jsx,.tsx,.java,.cpp,.c,.go,.rs,.php,.rb,.swift,.kt,.scala,.html,.htm,.css,.scss,.sass,.less,.sql,.sh,.bash,.zsh,.fish,.ps1,.bat,.cmd,.jpg,.jpeg,.png,.gif,.bmp,.webp,.svg"
+        accept=".pdf,.tar,.gz,.tgz,.tex,.bib,.txt,.md,.markdown,.json,.csv,.tsv,.srt,.vtt,.eml,.msg,.xml,.yaml,.yml,.log,.rtf,.py,.js,.ts,.jsx,.tsx,.java,.cpp,.c,.go,.rs,.php,.rb,.swift,.kt,.scala,.html,.htm,.css,.scss,.sass,.less,.sql,.sh,.bash,.zsh,.fish,.ps1,.bat,.cmd,.jpg,.jpeg,.png,.gif,.bmp,.webp,.tif,.tiff,.heic,.svg,.mp4,.mov,.mkv,.webm,.avi,.wmv,.flv,.m4v,.mp3,.wav,.m4a,.flac,.aac,.ogg"
         style={{ display: 'none' }}
       />
 
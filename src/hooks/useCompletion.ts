@@ -14,6 +14,16 @@ import {
   ToolActivity,
 } from "@/types";
 
+// The window doesn't auto-hide on focus loss while a response streams in
+const setResponseStreaming = async (streaming: boolean) => {
+  try {
+    const { invoke } = await import('@tauri-apps/api/core');
+    await invoke('set_response_streaming', { streaming });
+  } catch (error) {
+    console.warn('Failed to report response streaming:', error);
+  }
+};
+
 export const useCompletion = () => {
   const [state, setState] = useState<CompletionState>({
     input: "",
@@ -89,7 +99,8 @@ export const useCompletion = () => {
       if (abortControllerRef.current) {
         abortControllerRef.current.abort();
       }
-      abortControllerRef.current = new AbortController();
+      const controller = new AbortController();
+      abortControllerRef.current = controller;
 
       setState((prev) => ({
         ...prev,
@@ -99,15 +110,21 @@ export const useCompletion = () => {
         toolActivities: [],
       }));
 
+      await setResponseStreaming(true);
       try {
         let fullResponse = "";
 
-        // Gather optimized context from enabled files with smart chunking
+        // Gather the file chunks most relevant to this question, within a token budget
         let fileContext: string[] | undefined = undefined;
         try {
           const { invoke } = await import('@tauri-apps/api/core');
-          // Use the new optimized context system with smart chunking
-          fileContext = await invoke<string[]>('get_optimized_file_context');
+          const chunks = await invoke<{ file_id: string; text: string; tokens: number }[]>('get_context_for_query', {
+            query: input,
+            maxTokens: 8000,
+            conversationId: state.currentConversationId ?? undefined,
+            model: getSettings()?.selectedModel || getSettings()?.customModel || undefined,
+          });
+          fileContext = chunks.map((c) => c.text);
           console.log(`[useCompletion] Loaded ${fileContext?.length || 0} context chunks from uploaded files`);
         } catch (error) {
           console.warn("Failed to load optimized file context, falling back to summaries:", error);
@@ -148,7 +165,7 @@ export const useCompletion = () => {
             fileContext,
             // Note: files are processed with smart chunking and included in the system prompt.
           }),
-          signal: abortControllerRef.current.signal,
+          signal: controller.signal,
         });
 
         console.log("[ui] Sidecar response status:", res.status, res.statusText);
@@ -298,9 +315,14 @@ export const useCompletion = () => {
           error: error instanceof Error ? error.message : "An error occurred",
           isLoading: false,
         }));
+      } finally {
+        // Done, failed or cancelled; a newer request that replaced this one still streams
+        if (abortControllerRef.current === controller || abortControllerRef.current === null) {
+          await setResponseStreaming(false);
+        }
       }
     },
-    [state.input, state.attachedFiles, state.isLoading]
+    [state.input, state.attachedFiles, state.isLoading, state.currentConversationId]
   );
 
   const cancel = useCallback(() => {
@@ -1,4 +1,5 @@
 import toast from 'react-hot-toast';
+import { invoke } from '@tauri-apps/api/core';
 import { openUrl } from '@tauri-apps/plugin-opener';
 import { ToolActivity } from '@/types';
 
@@ -345,3 +346,68 @@ export const openExternalLink = async (url: string) => {
     toast.error('Failed to open link in browser');
   }
 };
+
+// "notification" event from the backend: a background job finished or failed
+export interface AppNotification {
+  title: string;
+  body: string;
+  actions: { id: string; label: string; link: string }[];
+}
+
+// Render a backend notification with its buttons; macOS and Windows banners have none.
+// Choosing one goes back through open_notification_link, like a Linux notification button.
+export const showAppNotification = ({ title, body, actions }: AppNotification) => {
+  toast.custom(
+    (t: { id: string; visible: boolean }) => (
+      <div
+        className={`${
+          t.visible ? 'animate-enter' : 'animate-leave'
+        } max-w-md w-full bg-card shadow-lg rounded-lg pointer-events-auto border border-border`}
+      >
+        <div className="p-2">
+          <div className="font-medium text-sm">{title}</div>
+          {body && <div className="text-xs text-muted-foreground mt-1 line-clamp-2">{body}</div>}
+          {actions.length > 0 && (
+            <div className="mt-2 flex gap-3">
+              {actions.map((action) => (
+                <button
+                  key={action.id}
+                  onClick={async () => {
+                    toast.dismiss(t.id);
+                    try {
+                      await invoke('open_notification_link', { link: action.link });
+                    } catch (error) {
+                      console.error('Failed to open notification link:', error);
+                    }
+                  }}
+                  className="text-xs text-primary hover:text-primary/80 font-medium transition-colors"
+                >
+                  {action.label} →
+                </button>
+              ))}
+            </div>
+          )}
+        </div>
+      </div>
+    ),
+    {
+      duration: actions.length > 0 ? 8000 : 4000,
+      position: 'top-right',
+    }
+  );
+};
+
+// Follow an "agi://" link from a notification button. Uploaded files and diagnostics
+// both live in the advanced settings window.
+export const openAppLink = async (link: string) => {
+  const path = link.replace(/^agi:\/\//, '');
+  if (path.startsWith('files') || path.startsWith('settings')) {
+    try {
+      await invoke('open_settings_window');
+    } catch (error) {
+      console.error('Failed to open settings window:', error);
+    }
+  } else {
+    console.warn('Unknown notification link:', link);
+  }
+};
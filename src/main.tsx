@@ -8,6 +8,7 @@ import { AdvancedSettingsPage } from "./components/advanced/AdvancedSettingsPage
 import { ProfilePage } from "./components/profile/ProfilePage";
 import { LoginPage } from "./components/auth/LoginPage";
 import { Auth } from "./routes/Auth";
+import { Hud } from "./routes/Hud";
 
 const isSettingsRoute = () => {
   const path = typeof window !== "undefined" ? window.location.pathname : "";
@@ -33,6 +34,12 @@ const isLoginRoute = () => {
   return path.startsWith("/login") || hash.startsWith("#/login");
 };
 
+const isHudRoute = () => {
+  const path = typeof window !== "undefined" ? window.location.pathname : "";
+  const hash = typeof window !== "undefined" ? window.location.hash : "";
+  return path.startsWith("/hud") || hash.startsWith("#/hud");
+};
+
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
     <ThemeProvider>
@@ -45,6 +52,8 @@ ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
           <ProfilePage />
         ) : isLoginRoute() ? (
           <LoginPage />
+        ) : isHudRoute() ? (
+          <Hud />
         ) : (
           <App />
         )}
@@ -0,0 +1,139 @@
+import React, { useEffect, useRef, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { Loader2 } from 'lucide-react';
+import { Input } from '@/components';
+import { getSettings } from '@/lib';
+
+// "hud:open" payload from open_hud_at_cursor
+interface HudOpen {
+  cursor: { x: number; y: number };
+  context: string | null;
+}
+
+// The quick-question HUD opened by open_hud_at_cursor: one question about the copied text,
+// one streamed answer
+export const Hud: React.FC = () => {
+  const [question, setQuestion] = useState('');
+  const [context, setContext] = useState<string | null>(null);
+  const [answer, setAnswer] = useState('');
+  const [error, setError] = useState<string | null>(null);
+  const [isLoading, setIsLoading] = useState(false);
+  const inputRef = useRef<HTMLInputElement>(null);
+  const abortControllerRef = useRef<AbortController | null>(null);
+
+  // Each summon starts over at the cursor
+  useEffect(() => {
+    const unlisten = listen<HudOpen>('hud:open', (event) => {
+      abortControllerRef.current?.abort();
+      setContext(event.payload.context);
+      setQuestion('');
+      setAnswer('');
+      setError(null);
+      setIsLoading(false);
+      inputRef.current?.focus();
+    });
+
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, []);
+
+  const close = () => {
+    abortControllerRef.current?.abort();
+    invoke('close_hud').catch(console.error);
+  };
+
+  const ask = async () => {
+    const message = question.trim();
+    if (!message || isLoading) return;
+    const controller = new AbortController();
+    abortControllerRef.current = controller;
+    setAnswer('');
+    setError(null);
+    setIsLoading(true);
+
+    try {
+      const settings = getSettings();
+      const activePersona = settings?.personas?.find((p: any) => p.id === settings?.currentPersonaId);
+      const res = await fetch('http://127.0.0.1:8765/api/chat/stream', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+          message: context ? `About this text:\n"""\n${context}\n"""\n\n${message}` : message,
+          systemPrompt: activePersona?.prompt || settings?.systemPrompt || undefined,
+          apiKey: settings?.openAiApiKey || settings?.apiKey || undefined,
+          model: settings?.selectedModel || settings?.customModel || 'gpt-4o-mini',
+          providerId: settings?.selectedProvider || 'openai',
+        }),
+        signal: controller.signal,
+      });
+      if (!res.ok || !res.body) {
+        throw new Error(`Sidecar error: ${res.status} ${res.statusText}`);
+      }
+
+      const reader = res.body.getReader();
+      const decoder = new TextDecoder();
+      let buffer = '';
+      while (true) {
+        const { done, value } = await reader.read();
+        if (done) break;
+        buffer += decoder.decode(value, { stream: true });
+        const lines = buffer.split('\n');
+        buffer = lines.pop() || '';
+        for (const line of lines) {
+          if (!line.startsWith('data:')) continue;
+          try {
+            const evt = JSON.parse(line.slice(5).trim());
+            if (evt?.type === 'token' && evt.content) {
+              setAnswer((prev) => prev + evt.content);
+            } else if (evt?.type === 'error') {
+              setError(evt.content || 'An error occurred');
+            }
+          } catch {
+            // Keep-alives and partial lines
+          }
+        }
+      }
+    } catch (e) {
+      if (!controller.signal.aborted) {
+        setError(e instanceof Error ? e.message : 'An error occurred');
+      }
+    } finally {
+      if (abortControllerRef.current === controller) {
+        setIsLoading(false);
+      }
+    }
+  };
+
+  return (
+    <div
+      className="h-screen w-screen flex flex-col gap-2 p-2 rounded-lg border bg-background/95 text-sm"
+      onKeyDown={(e) => {
+        if (e.key === 'Escape') close();
+      }}
+    >
+      <div className="flex items-center gap-2">
+        <Input
+          ref={inputRef}
+          autoFocus
+          placeholder={context ? 'Ask about the copied text...' : 'Ask a quick question...'}
+          value={question}
+          onChange={(e) => setQuestion(e.target.value)}
+          onKeyDown={(e) => {
+            if (e.key === 'Enter') ask();
+          }}
+        />
+        {isLoading && <Loader2 className="h-4 w-4 animate-spin shrink-0" />}
+      </div>
+      {context && !answer && !error && (
+        <div className="truncate text-xs text-muted-foreground">About: {context}</div>
+      )}
+      {(answer || error) && (
+        <div className="flex-1 overflow-y-auto whitespace-pre-wrap">
+          {error ? <span className="text-destructive">{error}</span> : answer}
+        </div>
+      )}
+    </div>
+  );
+};